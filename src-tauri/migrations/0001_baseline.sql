@@ -0,0 +1,125 @@
+-- Baseline schema. Every statement is idempotent so databases created
+-- before versioned migrations existed adopt this version in place.
+
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    email VARCHAR NOT NULL UNIQUE,
+    hashed_password VARCHAR NOT NULL,
+    full_name VARCHAR NOT NULL,
+    role VARCHAR NOT NULL,
+    is_active BOOLEAN NOT NULL,
+    is_superuser BOOLEAN NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS roles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT UNIQUE NOT NULL,
+    description TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS permissions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT UNIQUE NOT NULL,
+    description TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role_id INTEGER NOT NULL,
+    permission_id INTEGER NOT NULL,
+    PRIMARY KEY (role_id, permission_id),
+    FOREIGN KEY (role_id) REFERENCES roles (id) ON DELETE CASCADE,
+    FOREIGN KEY (permission_id) REFERENCES permissions (id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS products (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    name VARCHAR NOT NULL,
+    description VARCHAR NOT NULL,
+    sku VARCHAR NOT NULL UNIQUE,
+    category VARCHAR(13) NOT NULL,
+    price FLOAT NOT NULL,
+    cost FLOAT NOT NULL,
+    quantity INTEGER NOT NULL,
+    reorder_level INTEGER NOT NULL,
+    supplier_id INTEGER,
+    FOREIGN KEY(supplier_id) REFERENCES suppliers (id)
+);
+
+CREATE TABLE IF NOT EXISTS suppliers (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    name VARCHAR NOT NULL,
+    contact_name VARCHAR NOT NULL,
+    email VARCHAR NOT NULL,
+    phone VARCHAR NOT NULL,
+    address VARCHAR NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS orders (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    customer_name VARCHAR NOT NULL,
+    total_amount FLOAT NOT NULL,
+    payment_method VARCHAR(14) NOT NULL,
+    status VARCHAR(9) NOT NULL,
+    cashier_id INTEGER,
+    FOREIGN KEY(cashier_id) REFERENCES users (id)
+);
+
+CREATE TABLE IF NOT EXISTS order_items (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    order_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    unit_price FLOAT NOT NULL,
+    FOREIGN KEY(order_id) REFERENCES orders (id),
+    FOREIGN KEY(product_id) REFERENCES products (id)
+);
+
+CREATE TABLE IF NOT EXISTS stock_movements (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    product_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    movement_type VARCHAR(10) NOT NULL,
+    notes VARCHAR NOT NULL,
+    FOREIGN KEY(product_id) REFERENCES products (id)
+);
+
+CREATE TABLE IF NOT EXISTS notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER,
+    title TEXT NOT NULL,
+    message TEXT NOT NULL,
+    type TEXT NOT NULL,
+    priority TEXT NOT NULL DEFAULT 'medium',
+    is_read BOOLEAN NOT NULL DEFAULT FALSE,
+    product_id INTEGER,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (product_id) REFERENCES products (id)
+);
+
+-- Sync queue for offline operations
+CREATE TABLE IF NOT EXISTS sync_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    created_at DATETIME NOT NULL,
+    operation_type VARCHAR NOT NULL,
+    endpoint VARCHAR NOT NULL,
+    method VARCHAR NOT NULL,
+    payload TEXT NOT NULL,
+    retries INTEGER NOT NULL DEFAULT 0,
+    last_attempt_at DATETIME,
+    error_message TEXT
+);
@@ -0,0 +1,34 @@
+-- Lot/batch tracking. Each batch holds the remaining quantity of one
+-- delivery; products.quantity stays the total across all batches.
+CREATE TABLE product_batches (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    product_id INTEGER NOT NULL,
+    batch_number VARCHAR NOT NULL,
+    received_date DATE NOT NULL,
+    expiry_date DATE,
+    initial_quantity INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    FOREIGN KEY(product_id) REFERENCES products (id) ON DELETE CASCADE,
+    UNIQUE (product_id, batch_number)
+);
+
+CREATE INDEX idx_product_batches_fefo ON product_batches (product_id, expiry_date, received_date);
+
+-- Which batches each sold line was drawn from, so cancellations and
+-- returns put stock back into the batch it came from
+CREATE TABLE order_item_batches (
+    order_item_id INTEGER NOT NULL,
+    batch_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    PRIMARY KEY (order_item_id, batch_id),
+    FOREIGN KEY(order_item_id) REFERENCES order_items (id) ON DELETE CASCADE,
+    FOREIGN KEY(batch_id) REFERENCES product_batches (id)
+);
+
+-- Existing stock becomes an opening batch with no known expiry
+INSERT INTO product_batches (created_at, updated_at, product_id, batch_number, received_date, expiry_date, initial_quantity, quantity)
+SELECT CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, id, 'OPENING', date('now'), NULL, quantity, quantity
+FROM products
+WHERE quantity > 0;
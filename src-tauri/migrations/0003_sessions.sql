@@ -0,0 +1,15 @@
+-- Login sessions. Only a SHA-256 hash of the bearer token is stored so a
+-- copied database file cannot be used to hijack a session.
+CREATE TABLE sessions (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    user_id INTEGER NOT NULL,
+    user_info TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    last_seen_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    revoked_at DATETIME,
+    FOREIGN KEY(user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_sessions_user ON sessions (user_id);
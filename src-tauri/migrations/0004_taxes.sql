@@ -0,0 +1,62 @@
+-- Tax rates. A compound rate is charged on the price plus every rate
+-- stacked before it in the class; a simple rate only on the price.
+CREATE TABLE tax_rates (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    name VARCHAR NOT NULL,
+    rate FLOAT NOT NULL CHECK (rate >= 0),
+    is_compound BOOLEAN NOT NULL DEFAULT 0,
+    is_active BOOLEAN NOT NULL DEFAULT 1
+);
+
+-- A tax class groups the rates that apply to a product and says whether
+-- the product's shelf price already includes them
+CREATE TABLE tax_classes (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    name VARCHAR NOT NULL UNIQUE,
+    description VARCHAR NOT NULL DEFAULT '',
+    prices_include_tax BOOLEAN NOT NULL DEFAULT 0
+);
+
+CREATE TABLE tax_class_rates (
+    tax_class_id INTEGER NOT NULL,
+    tax_rate_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (tax_class_id, tax_rate_id),
+    FOREIGN KEY(tax_class_id) REFERENCES tax_classes (id) ON DELETE CASCADE,
+    FOREIGN KEY(tax_rate_id) REFERENCES tax_rates (id)
+);
+
+ALTER TABLE products ADD COLUMN tax_class_id INTEGER REFERENCES tax_classes (id);
+
+-- Per-line amounts; unit_price stays the price as charged at the till
+ALTER TABLE order_items ADD COLUMN net_amount FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE order_items ADD COLUMN tax_amount FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE order_items ADD COLUMN line_total FLOAT NOT NULL DEFAULT 0;
+
+UPDATE order_items SET net_amount = quantity * unit_price, line_total = quantity * unit_price;
+
+-- Snapshot of every rate applied to a line, so editing a rate never
+-- changes the tax shown on receipts that were already issued
+CREATE TABLE order_item_taxes (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    order_item_id INTEGER NOT NULL,
+    tax_rate_id INTEGER,
+    name VARCHAR NOT NULL,
+    rate FLOAT NOT NULL,
+    is_compound BOOLEAN NOT NULL,
+    taxable_amount FLOAT NOT NULL,
+    tax_amount FLOAT NOT NULL,
+    FOREIGN KEY(order_item_id) REFERENCES order_items (id),
+    FOREIGN KEY(tax_rate_id) REFERENCES tax_rates (id)
+);
+
+CREATE INDEX idx_order_item_taxes_item ON order_item_taxes (order_item_id);
+
+ALTER TABLE orders ADD COLUMN subtotal FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE orders ADD COLUMN tax_total FLOAT NOT NULL DEFAULT 0;
+
+UPDATE orders SET subtotal = total_amount;
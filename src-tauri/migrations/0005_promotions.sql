@@ -0,0 +1,46 @@
+-- Promotions. `value` is a percentage for percent_off and buy_x_get_y
+-- (the discount on the free items), an amount for amount_off and the
+-- bundle price for multi_buy. Line promotions may target one product or
+-- one category; order promotions apply to the discounted subtotal.
+CREATE TABLE promotions (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    name VARCHAR NOT NULL,
+    description VARCHAR NOT NULL DEFAULT '',
+    promotion_type VARCHAR(11) NOT NULL,
+    scope VARCHAR(5) NOT NULL,
+    value FLOAT NOT NULL CHECK (value >= 0),
+    product_id INTEGER,
+    category VARCHAR(13),
+    buy_quantity INTEGER,
+    get_quantity INTEGER,
+    min_order_amount FLOAT,
+    coupon_code VARCHAR UNIQUE,
+    starts_at DATETIME,
+    ends_at DATETIME,
+    priority INTEGER NOT NULL DEFAULT 0,
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    FOREIGN KEY(product_id) REFERENCES products (id)
+);
+
+-- Every promotion applied to an order; order_item_id is NULL for
+-- order-level discounts, which are spread over the lines for tax
+CREATE TABLE order_promotions (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    order_id INTEGER NOT NULL,
+    order_item_id INTEGER,
+    promotion_id INTEGER NOT NULL,
+    name VARCHAR NOT NULL,
+    coupon_code VARCHAR,
+    discount_amount FLOAT NOT NULL,
+    FOREIGN KEY(order_id) REFERENCES orders (id),
+    FOREIGN KEY(order_item_id) REFERENCES order_items (id),
+    FOREIGN KEY(promotion_id) REFERENCES promotions (id)
+);
+
+CREATE INDEX idx_order_promotions_order ON order_promotions (order_id);
+CREATE INDEX idx_order_promotions_promotion ON order_promotions (promotion_id);
+
+ALTER TABLE order_items ADD COLUMN discount_amount FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE orders ADD COLUMN discount_total FLOAT NOT NULL DEFAULT 0;
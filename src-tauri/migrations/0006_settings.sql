@@ -0,0 +1,6 @@
+-- Application settings, one JSON document per section (printer, ...)
+CREATE TABLE app_settings (
+    key VARCHAR NOT NULL PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at DATETIME NOT NULL
+);
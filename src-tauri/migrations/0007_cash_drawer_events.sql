@@ -0,0 +1,16 @@
+-- Audit trail of every cash drawer open. The row is written before the
+-- drawer is kicked and `opened` set once the pulse was delivered, so a
+-- failed kick is still visible and no open goes unrecorded.
+CREATE TABLE cash_drawer_events (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    reason VARCHAR(7) NOT NULL,
+    order_id INTEGER,
+    notes VARCHAR NOT NULL DEFAULT '',
+    opened BOOLEAN NOT NULL DEFAULT 0,
+    FOREIGN KEY(order_id) REFERENCES orders (id)
+);
+
+CREATE INDEX idx_cash_drawer_events_created ON cash_drawer_events (created_at);
@@ -0,0 +1,24 @@
+-- Tenders taken against an order. `amount` is what the tender paid toward
+-- the order; `tendered` is what the customer handed over, and the
+-- difference for cash is the change given back.
+CREATE TABLE order_payments (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    order_id INTEGER NOT NULL,
+    tender_type VARCHAR(14) NOT NULL,
+    amount FLOAT NOT NULL CHECK (amount > 0),
+    tendered FLOAT NOT NULL,
+    change_due FLOAT NOT NULL DEFAULT 0,
+    reference VARCHAR NOT NULL DEFAULT '',
+    FOREIGN KEY(order_id) REFERENCES orders (id)
+);
+
+CREATE INDEX idx_order_payments_order ON order_payments (order_id);
+
+-- Orders completed before split tenders were paid in full by their single payment method
+INSERT INTO order_payments (created_at, order_id, tender_type, amount, tendered, change_due, reference)
+SELECT updated_at, id,
+       CASE WHEN payment_method IN ('cash', 'credit_card', 'debit_card', 'mobile_payment') THEN payment_method ELSE 'other' END,
+       total_amount, total_amount, 0, ''
+FROM orders
+WHERE status = 'completed' AND total_amount > 0;
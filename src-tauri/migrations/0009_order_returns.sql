@@ -0,0 +1,46 @@
+-- Returns against completed orders. Each returned line keeps the share of
+-- the line total and tax it refunded so later returns of the same line
+-- never refund more than was paid; refunds record the original tender
+-- they went back to.
+CREATE TABLE order_returns (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    order_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    reason VARCHAR(12) NOT NULL,
+    notes VARCHAR NOT NULL DEFAULT '',
+    refund_total FLOAT NOT NULL,
+    tax_total FLOAT NOT NULL,
+    FOREIGN KEY(order_id) REFERENCES orders (id)
+);
+
+CREATE INDEX idx_order_returns_order ON order_returns (order_id);
+
+CREATE TABLE order_return_items (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    return_id INTEGER NOT NULL,
+    order_item_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    disposition VARCHAR(9) NOT NULL,
+    refund_amount FLOAT NOT NULL,
+    tax_amount FLOAT NOT NULL,
+    FOREIGN KEY(return_id) REFERENCES order_returns (id) ON DELETE CASCADE,
+    FOREIGN KEY(order_item_id) REFERENCES order_items (id),
+    FOREIGN KEY(product_id) REFERENCES products (id)
+);
+
+CREATE INDEX idx_order_return_items_item ON order_return_items (order_item_id);
+
+CREATE TABLE order_refunds (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    return_id INTEGER NOT NULL,
+    order_payment_id INTEGER,
+    tender_type VARCHAR(14) NOT NULL,
+    amount FLOAT NOT NULL CHECK (amount > 0),
+    FOREIGN KEY(return_id) REFERENCES order_returns (id) ON DELETE CASCADE,
+    FOREIGN KEY(order_payment_id) REFERENCES order_payments (id)
+);
+
+CREATE INDEX idx_order_refunds_payment ON order_refunds (order_payment_id);
@@ -0,0 +1,32 @@
+-- Order lifecycle. Cancelled orders become voided, completed orders that
+-- have been returned take the refund status their returns imply, and every
+-- status change from here on is kept in order_events with the acting user.
+UPDATE orders SET status = 'voided' WHERE status = 'cancelled';
+
+UPDATE orders
+SET status = CASE
+    WHEN (SELECT TOTAL(ri.quantity) FROM order_return_items ri JOIN order_items oi ON oi.id = ri.order_item_id WHERE oi.order_id = orders.id)
+         >= (SELECT TOTAL(quantity) FROM order_items WHERE order_id = orders.id)
+    THEN 'refunded'
+    ELSE 'partially_refunded'
+END
+WHERE status = 'completed' AND id IN (SELECT order_id FROM order_returns);
+
+CREATE TABLE order_events (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    order_id INTEGER NOT NULL,
+    from_status VARCHAR(18),
+    to_status VARCHAR(18) NOT NULL,
+    user_id INTEGER,
+    user_name VARCHAR NOT NULL DEFAULT '',
+    notes VARCHAR NOT NULL DEFAULT '',
+    FOREIGN KEY(order_id) REFERENCES orders (id)
+);
+
+CREATE INDEX idx_order_events_order ON order_events (order_id);
+
+-- Orders from before the history start with their current status
+INSERT INTO order_events (created_at, order_id, from_status, to_status, user_id, user_name, notes)
+SELECT updated_at, id, NULL, status, NULL, '', 'Status when order history began'
+FROM orders;
@@ -0,0 +1,5 @@
+-- Who moved stock. Orders already carry cashier_id and returns their
+-- user_id; earlier movements stay unattributed.
+ALTER TABLE stock_movements ADD COLUMN user_id INTEGER REFERENCES users (id);
+
+CREATE INDEX idx_orders_cashier ON orders (cashier_id);
@@ -0,0 +1,53 @@
+-- Till sessions. One shift is open at a time; tenders and refunds taken
+-- while it is open are stamped with it, and cash paid out of or dropped
+-- from the drawer is recorded against it. X and Z reports are stored as
+-- JSON snapshots so they can be reprinted exactly as first produced.
+CREATE TABLE shifts (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    opened_at DATETIME NOT NULL,
+    closed_at DATETIME,
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    opening_float FLOAT NOT NULL CHECK (opening_float >= 0),
+    status VARCHAR(6) NOT NULL DEFAULT 'open',
+    closed_by_user_id INTEGER,
+    notes VARCHAR NOT NULL DEFAULT '',
+    FOREIGN KEY(user_id) REFERENCES users (id),
+    FOREIGN KEY(closed_by_user_id) REFERENCES users (id)
+);
+
+CREATE UNIQUE INDEX idx_shifts_one_open ON shifts (status) WHERE status = 'open';
+
+CREATE TABLE shift_cash_movements (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    shift_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    kind VARCHAR(6) NOT NULL,
+    amount FLOAT NOT NULL CHECK (amount > 0),
+    notes VARCHAR NOT NULL DEFAULT '',
+    FOREIGN KEY(shift_id) REFERENCES shifts (id),
+    FOREIGN KEY(user_id) REFERENCES users (id)
+);
+
+CREATE INDEX idx_shift_cash_movements_shift ON shift_cash_movements (shift_id);
+
+CREATE TABLE shift_reports (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    shift_id INTEGER NOT NULL,
+    report_type VARCHAR(1) NOT NULL,
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    body TEXT NOT NULL,
+    FOREIGN KEY(shift_id) REFERENCES shifts (id)
+);
+
+CREATE INDEX idx_shift_reports_shift ON shift_reports (shift_id);
+
+ALTER TABLE order_payments ADD COLUMN shift_id INTEGER REFERENCES shifts (id);
+ALTER TABLE order_refunds ADD COLUMN shift_id INTEGER REFERENCES shifts (id);
+
+CREATE INDEX idx_order_payments_shift ON order_payments (shift_id);
+CREATE INDEX idx_order_refunds_shift ON order_refunds (shift_id);
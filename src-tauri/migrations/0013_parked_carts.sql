@@ -0,0 +1,27 @@
+-- Carts set aside at the till to serve another customer. A parked cart can
+-- hold its stock until reserved_until; after that the cart is kept but its
+-- reservation no longer counts against other carts.
+CREATE TABLE parked_carts (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    label VARCHAR NOT NULL,
+    customer_name VARCHAR NOT NULL DEFAULT '',
+    payment_method VARCHAR NOT NULL DEFAULT 'cash',
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    reserved_until DATETIME,
+    FOREIGN KEY(user_id) REFERENCES users (id)
+);
+
+CREATE TABLE parked_cart_items (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    cart_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    price_at_sale FLOAT NOT NULL,
+    FOREIGN KEY(cart_id) REFERENCES parked_carts (id) ON DELETE CASCADE,
+    FOREIGN KEY(product_id) REFERENCES products (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_parked_cart_items_cart ON parked_cart_items (cart_id);
+CREATE INDEX idx_parked_cart_items_product ON parked_cart_items (product_id);
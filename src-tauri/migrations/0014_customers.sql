@@ -0,0 +1,21 @@
+-- Customer accounts. Phone numbers are stored as digits (keeping a leading
+-- +) and emails lowercased so lookups at the counter match however they
+-- are typed; either may be left blank but neither can belong to two
+-- customers. Orders keep their free-text customer_name for receipts.
+CREATE TABLE customers (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    name VARCHAR NOT NULL,
+    email VARCHAR NOT NULL DEFAULT '',
+    phone VARCHAR NOT NULL DEFAULT '',
+    address VARCHAR NOT NULL DEFAULT '',
+    notes VARCHAR NOT NULL DEFAULT ''
+);
+
+CREATE UNIQUE INDEX idx_customers_email ON customers (email) WHERE email != '';
+CREATE UNIQUE INDEX idx_customers_phone ON customers (phone) WHERE phone != '';
+
+ALTER TABLE orders ADD COLUMN customer_id INTEGER REFERENCES customers (id);
+
+CREATE INDEX idx_orders_customer ON orders (customer_id);
@@ -0,0 +1,28 @@
+-- Loyalty points. Every change to a customer's points is a ledger row:
+-- positive rows (earned or given back) are lots that keep what is left of
+-- them in remaining and lapse at expires_at; negative rows (redeemed,
+-- reversed or expired) record what was drawn from those lots. A
+-- customer's balance is the remaining points of their unexpired lots.
+CREATE TABLE loyalty_ledger (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    customer_id INTEGER NOT NULL,
+    kind VARCHAR(7) NOT NULL,
+    points INTEGER NOT NULL,
+    remaining INTEGER NOT NULL DEFAULT 0 CHECK (remaining >= 0),
+    expires_at DATETIME,
+    order_id INTEGER,
+    order_payment_id INTEGER,
+    return_id INTEGER,
+    user_id INTEGER,
+    notes VARCHAR NOT NULL DEFAULT '',
+    FOREIGN KEY(customer_id) REFERENCES customers (id),
+    FOREIGN KEY(order_id) REFERENCES orders (id),
+    FOREIGN KEY(order_payment_id) REFERENCES order_payments (id) ON DELETE SET NULL,
+    FOREIGN KEY(return_id) REFERENCES order_returns (id),
+    FOREIGN KEY(user_id) REFERENCES users (id)
+);
+
+CREATE INDEX idx_loyalty_ledger_customer ON loyalty_ledger (customer_id);
+CREATE INDEX idx_loyalty_ledger_order ON loyalty_ledger (order_id);
+CREATE INDEX idx_loyalty_ledger_lots ON loyalty_ledger (customer_id, expires_at) WHERE remaining > 0;
@@ -0,0 +1,48 @@
+-- Gift cards and store credit. A gift card is sold on an order and only
+-- becomes usable when that order completes; store credit is issued by a
+-- return or a voided order in place of cash. Either is spent as a tender
+-- quoting its code. The balance is the sum of the card's ledger.
+CREATE TABLE gift_cards (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    code VARCHAR NOT NULL UNIQUE,
+    kind VARCHAR(12) NOT NULL,
+    status VARCHAR(7) NOT NULL DEFAULT 'pending',
+    customer_id INTEGER,
+    initial_amount FLOAT NOT NULL CHECK (initial_amount > 0),
+    expires_on DATE,
+    issued_order_id INTEGER,
+    issued_return_id INTEGER,
+    FOREIGN KEY(customer_id) REFERENCES customers (id),
+    FOREIGN KEY(issued_order_id) REFERENCES orders (id),
+    FOREIGN KEY(issued_return_id) REFERENCES order_returns (id)
+);
+
+CREATE INDEX idx_gift_cards_customer ON gift_cards (customer_id);
+CREATE INDEX idx_gift_cards_order ON gift_cards (issued_order_id);
+
+CREATE TABLE gift_card_ledger (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    gift_card_id INTEGER NOT NULL,
+    kind VARCHAR(7) NOT NULL,
+    amount FLOAT NOT NULL,
+    order_id INTEGER,
+    order_payment_id INTEGER,
+    return_id INTEGER,
+    user_id INTEGER,
+    notes VARCHAR NOT NULL DEFAULT '',
+    FOREIGN KEY(gift_card_id) REFERENCES gift_cards (id),
+    FOREIGN KEY(order_id) REFERENCES orders (id),
+    FOREIGN KEY(order_payment_id) REFERENCES order_payments (id) ON DELETE SET NULL,
+    FOREIGN KEY(return_id) REFERENCES order_returns (id),
+    FOREIGN KEY(user_id) REFERENCES users (id)
+);
+
+CREATE INDEX idx_gift_card_ledger_card ON gift_card_ledger (gift_card_id);
+CREATE INDEX idx_gift_card_ledger_order ON gift_card_ledger (order_id);
+
+CREATE VIEW gift_card_balances AS
+SELECT gc.*,
+       ROUND(COALESCE((SELECT SUM(l.amount) FROM gift_card_ledger l WHERE l.gift_card_id = gc.id), 0), 2) AS balance
+FROM gift_cards gc;
@@ -0,0 +1,139 @@
+-- Money is kept as INTEGER minor units (cents) instead of FLOAT, so totals
+-- add up exactly. Each amount column is swapped for an integer copy rounded
+-- to the cent; the swap drops the old column-level CHECKs, which are now
+-- enforced by the application. Orders record the currency they were taken in.
+DROP VIEW gift_card_balances;
+
+ALTER TABLE products ADD COLUMN price_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE products SET price_minor = CAST(ROUND(price * 100) AS INTEGER);
+ALTER TABLE products DROP COLUMN price;
+ALTER TABLE products RENAME COLUMN price_minor TO price;
+ALTER TABLE products ADD COLUMN cost_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE products SET cost_minor = CAST(ROUND(cost * 100) AS INTEGER);
+ALTER TABLE products DROP COLUMN cost;
+ALTER TABLE products RENAME COLUMN cost_minor TO cost;
+
+ALTER TABLE orders ADD COLUMN total_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE orders SET total_amount_minor = CAST(ROUND(total_amount * 100) AS INTEGER);
+ALTER TABLE orders DROP COLUMN total_amount;
+ALTER TABLE orders RENAME COLUMN total_amount_minor TO total_amount;
+ALTER TABLE orders ADD COLUMN subtotal_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE orders SET subtotal_minor = CAST(ROUND(subtotal * 100) AS INTEGER);
+ALTER TABLE orders DROP COLUMN subtotal;
+ALTER TABLE orders RENAME COLUMN subtotal_minor TO subtotal;
+ALTER TABLE orders ADD COLUMN tax_total_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE orders SET tax_total_minor = CAST(ROUND(tax_total * 100) AS INTEGER);
+ALTER TABLE orders DROP COLUMN tax_total;
+ALTER TABLE orders RENAME COLUMN tax_total_minor TO tax_total;
+ALTER TABLE orders ADD COLUMN discount_total_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE orders SET discount_total_minor = CAST(ROUND(discount_total * 100) AS INTEGER);
+ALTER TABLE orders DROP COLUMN discount_total;
+ALTER TABLE orders RENAME COLUMN discount_total_minor TO discount_total;
+
+ALTER TABLE order_items ADD COLUMN unit_price_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_items SET unit_price_minor = CAST(ROUND(unit_price * 100) AS INTEGER);
+ALTER TABLE order_items DROP COLUMN unit_price;
+ALTER TABLE order_items RENAME COLUMN unit_price_minor TO unit_price;
+ALTER TABLE order_items ADD COLUMN net_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_items SET net_amount_minor = CAST(ROUND(net_amount * 100) AS INTEGER);
+ALTER TABLE order_items DROP COLUMN net_amount;
+ALTER TABLE order_items RENAME COLUMN net_amount_minor TO net_amount;
+ALTER TABLE order_items ADD COLUMN tax_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_items SET tax_amount_minor = CAST(ROUND(tax_amount * 100) AS INTEGER);
+ALTER TABLE order_items DROP COLUMN tax_amount;
+ALTER TABLE order_items RENAME COLUMN tax_amount_minor TO tax_amount;
+ALTER TABLE order_items ADD COLUMN line_total_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_items SET line_total_minor = CAST(ROUND(line_total * 100) AS INTEGER);
+ALTER TABLE order_items DROP COLUMN line_total;
+ALTER TABLE order_items RENAME COLUMN line_total_minor TO line_total;
+ALTER TABLE order_items ADD COLUMN discount_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_items SET discount_amount_minor = CAST(ROUND(discount_amount * 100) AS INTEGER);
+ALTER TABLE order_items DROP COLUMN discount_amount;
+ALTER TABLE order_items RENAME COLUMN discount_amount_minor TO discount_amount;
+
+ALTER TABLE order_item_taxes ADD COLUMN taxable_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_item_taxes SET taxable_amount_minor = CAST(ROUND(taxable_amount * 100) AS INTEGER);
+ALTER TABLE order_item_taxes DROP COLUMN taxable_amount;
+ALTER TABLE order_item_taxes RENAME COLUMN taxable_amount_minor TO taxable_amount;
+ALTER TABLE order_item_taxes ADD COLUMN tax_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_item_taxes SET tax_amount_minor = CAST(ROUND(tax_amount * 100) AS INTEGER);
+ALTER TABLE order_item_taxes DROP COLUMN tax_amount;
+ALTER TABLE order_item_taxes RENAME COLUMN tax_amount_minor TO tax_amount;
+
+ALTER TABLE promotions ADD COLUMN min_order_amount_minor INTEGER;
+UPDATE promotions SET min_order_amount_minor = CAST(ROUND(min_order_amount * 100) AS INTEGER);
+ALTER TABLE promotions DROP COLUMN min_order_amount;
+ALTER TABLE promotions RENAME COLUMN min_order_amount_minor TO min_order_amount;
+
+ALTER TABLE order_promotions ADD COLUMN discount_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_promotions SET discount_amount_minor = CAST(ROUND(discount_amount * 100) AS INTEGER);
+ALTER TABLE order_promotions DROP COLUMN discount_amount;
+ALTER TABLE order_promotions RENAME COLUMN discount_amount_minor TO discount_amount;
+
+ALTER TABLE order_payments ADD COLUMN amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_payments SET amount_minor = CAST(ROUND(amount * 100) AS INTEGER);
+ALTER TABLE order_payments DROP COLUMN amount;
+ALTER TABLE order_payments RENAME COLUMN amount_minor TO amount;
+ALTER TABLE order_payments ADD COLUMN tendered_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_payments SET tendered_minor = CAST(ROUND(tendered * 100) AS INTEGER);
+ALTER TABLE order_payments DROP COLUMN tendered;
+ALTER TABLE order_payments RENAME COLUMN tendered_minor TO tendered;
+ALTER TABLE order_payments ADD COLUMN change_due_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_payments SET change_due_minor = CAST(ROUND(change_due * 100) AS INTEGER);
+ALTER TABLE order_payments DROP COLUMN change_due;
+ALTER TABLE order_payments RENAME COLUMN change_due_minor TO change_due;
+
+ALTER TABLE order_returns ADD COLUMN refund_total_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_returns SET refund_total_minor = CAST(ROUND(refund_total * 100) AS INTEGER);
+ALTER TABLE order_returns DROP COLUMN refund_total;
+ALTER TABLE order_returns RENAME COLUMN refund_total_minor TO refund_total;
+ALTER TABLE order_returns ADD COLUMN tax_total_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_returns SET tax_total_minor = CAST(ROUND(tax_total * 100) AS INTEGER);
+ALTER TABLE order_returns DROP COLUMN tax_total;
+ALTER TABLE order_returns RENAME COLUMN tax_total_minor TO tax_total;
+
+ALTER TABLE order_return_items ADD COLUMN refund_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_return_items SET refund_amount_minor = CAST(ROUND(refund_amount * 100) AS INTEGER);
+ALTER TABLE order_return_items DROP COLUMN refund_amount;
+ALTER TABLE order_return_items RENAME COLUMN refund_amount_minor TO refund_amount;
+ALTER TABLE order_return_items ADD COLUMN tax_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_return_items SET tax_amount_minor = CAST(ROUND(tax_amount * 100) AS INTEGER);
+ALTER TABLE order_return_items DROP COLUMN tax_amount;
+ALTER TABLE order_return_items RENAME COLUMN tax_amount_minor TO tax_amount;
+
+ALTER TABLE order_refunds ADD COLUMN amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE order_refunds SET amount_minor = CAST(ROUND(amount * 100) AS INTEGER);
+ALTER TABLE order_refunds DROP COLUMN amount;
+ALTER TABLE order_refunds RENAME COLUMN amount_minor TO amount;
+
+ALTER TABLE shifts ADD COLUMN opening_float_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE shifts SET opening_float_minor = CAST(ROUND(opening_float * 100) AS INTEGER);
+ALTER TABLE shifts DROP COLUMN opening_float;
+ALTER TABLE shifts RENAME COLUMN opening_float_minor TO opening_float;
+
+ALTER TABLE shift_cash_movements ADD COLUMN amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE shift_cash_movements SET amount_minor = CAST(ROUND(amount * 100) AS INTEGER);
+ALTER TABLE shift_cash_movements DROP COLUMN amount;
+ALTER TABLE shift_cash_movements RENAME COLUMN amount_minor TO amount;
+
+ALTER TABLE parked_cart_items ADD COLUMN price_at_sale_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE parked_cart_items SET price_at_sale_minor = CAST(ROUND(price_at_sale * 100) AS INTEGER);
+ALTER TABLE parked_cart_items DROP COLUMN price_at_sale;
+ALTER TABLE parked_cart_items RENAME COLUMN price_at_sale_minor TO price_at_sale;
+
+ALTER TABLE gift_cards ADD COLUMN initial_amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE gift_cards SET initial_amount_minor = CAST(ROUND(initial_amount * 100) AS INTEGER);
+ALTER TABLE gift_cards DROP COLUMN initial_amount;
+ALTER TABLE gift_cards RENAME COLUMN initial_amount_minor TO initial_amount;
+
+ALTER TABLE gift_card_ledger ADD COLUMN amount_minor INTEGER NOT NULL DEFAULT 0;
+UPDATE gift_card_ledger SET amount_minor = CAST(ROUND(amount * 100) AS INTEGER);
+ALTER TABLE gift_card_ledger DROP COLUMN amount;
+ALTER TABLE gift_card_ledger RENAME COLUMN amount_minor TO amount;
+
+ALTER TABLE orders ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'USD';
+
+CREATE VIEW gift_card_balances AS
+SELECT gc.*,
+       COALESCE((SELECT SUM(l.amount) FROM gift_card_ledger l WHERE l.gift_card_id = gc.id), 0) AS balance
+FROM gift_cards gc;
@@ -0,0 +1,27 @@
+-- Lines are priced from the product's shelf price. A cashier may only sell
+-- at another price with a reason and a manager's approval, and every such
+-- override is recorded here. The permission is created ungranted; give it
+-- to the roles that may approve overrides.
+CREATE TABLE price_overrides (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    order_id INTEGER NOT NULL,
+    order_item_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    shelf_price INTEGER NOT NULL,
+    override_price INTEGER NOT NULL,
+    reason VARCHAR NOT NULL,
+    cashier_id INTEGER NOT NULL,
+    cashier_name VARCHAR NOT NULL,
+    approved_by INTEGER NOT NULL,
+    approver_name VARCHAR NOT NULL,
+    FOREIGN KEY(order_id) REFERENCES orders (id),
+    FOREIGN KEY(order_item_id) REFERENCES order_items (id),
+    FOREIGN KEY(product_id) REFERENCES products (id)
+);
+
+CREATE INDEX idx_price_overrides_created ON price_overrides (created_at);
+CREATE INDEX idx_price_overrides_order ON price_overrides (order_id);
+
+INSERT OR IGNORE INTO permissions (name, description) VALUES ('price_override', 'Approve price overrides');
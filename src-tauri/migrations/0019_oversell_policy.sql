@@ -0,0 +1,25 @@
+-- What a sale may do when it asks for more than is in stock: block, warn or
+-- allow a backorder. A product's own policy wins over its category's, which
+-- wins over the store default; both of those live in app_settings. Sales
+-- allowed to oversell are recorded here until a manager has reviewed them.
+ALTER TABLE products ADD COLUMN stock_policy VARCHAR;
+
+CREATE TABLE oversold_lines (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    order_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    policy VARCHAR NOT NULL,
+    requested INTEGER NOT NULL,
+    available INTEGER NOT NULL,
+    reviewed_at DATETIME,
+    reviewed_by INTEGER,
+    reviewer_name VARCHAR NOT NULL DEFAULT '',
+    review_notes VARCHAR NOT NULL DEFAULT '',
+    FOREIGN KEY(order_id) REFERENCES orders (id),
+    FOREIGN KEY(product_id) REFERENCES products (id),
+    FOREIGN KEY(reviewed_by) REFERENCES users (id)
+);
+
+CREATE INDEX idx_oversold_lines_order ON oversold_lines (order_id);
+CREATE INDEX idx_oversold_lines_reviewed ON oversold_lines (reviewed_at);
@@ -0,0 +1,63 @@
+-- Orders placed with suppliers. A purchase order is drafted, sent, received
+-- in one or more deliveries and finally closed; closing a partly received
+-- order gives up on what is still outstanding. Every delivery is recorded
+-- against its line, with the batch it became and what it actually cost.
+CREATE TABLE purchase_orders (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    supplier_id INTEGER NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'draft',
+    expected_date DATE,
+    notes VARCHAR NOT NULL DEFAULT '',
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    created_by INTEGER NOT NULL,
+    created_by_name VARCHAR NOT NULL,
+    sent_at DATETIME,
+    closed_at DATETIME,
+    FOREIGN KEY(supplier_id) REFERENCES suppliers (id),
+    FOREIGN KEY(created_by) REFERENCES users (id)
+);
+
+CREATE TABLE purchase_order_lines (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    purchase_order_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    quantity_ordered INTEGER NOT NULL CHECK (quantity_ordered > 0),
+    quantity_received INTEGER NOT NULL DEFAULT 0,
+    -- Expected cost per unit
+    unit_cost INTEGER NOT NULL,
+    FOREIGN KEY(purchase_order_id) REFERENCES purchase_orders (id) ON DELETE CASCADE,
+    FOREIGN KEY(product_id) REFERENCES products (id)
+);
+
+CREATE TABLE purchase_order_receipts (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    purchase_order_id INTEGER NOT NULL,
+    line_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    batch_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    -- Actual cost per unit of this delivery
+    unit_cost INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    FOREIGN KEY(purchase_order_id) REFERENCES purchase_orders (id) ON DELETE CASCADE,
+    FOREIGN KEY(line_id) REFERENCES purchase_order_lines (id) ON DELETE CASCADE,
+    FOREIGN KEY(product_id) REFERENCES products (id),
+    FOREIGN KEY(batch_id) REFERENCES product_batches (id)
+);
+
+CREATE INDEX idx_purchase_orders_supplier ON purchase_orders (supplier_id);
+CREATE INDEX idx_purchase_orders_status ON purchase_orders (status);
+CREATE INDEX idx_purchase_order_lines_order ON purchase_order_lines (purchase_order_id);
+CREATE INDEX idx_purchase_order_receipts_order ON purchase_order_receipts (purchase_order_id);
+
+-- Purchase orders with their supplier's name and expected cost
+CREATE VIEW purchase_order_summaries AS
+SELECT po.*,
+       s.name AS supplier_name,
+       COALESCE((SELECT SUM(l.quantity_ordered * l.unit_cost) FROM purchase_order_lines l WHERE l.purchase_order_id = po.id), 0) AS total_cost
+FROM purchase_orders po
+JOIN suppliers s ON s.id = po.supplier_id;
@@ -0,0 +1,5 @@
+-- Days a supplier usually takes to deliver, used to time reorders. NULL
+-- falls back to the default lead time in the reorder settings.
+ALTER TABLE suppliers ADD COLUMN lead_time_days INTEGER;
+
+CREATE INDEX idx_purchase_order_lines_product ON purchase_order_lines (product_id);
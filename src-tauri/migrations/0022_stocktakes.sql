@@ -0,0 +1,76 @@
+-- Stocktakes (cycle counts). Starting one freezes the expected quantity and
+-- cost of every product in scope; counts are entered against it from any
+-- number of devices, each entry adding to the tally for its product. Posting
+-- books the variance between the tally and the frozen quantity as stock
+-- adjustments, so sales made while counting are not counted twice.
+ALTER TABLE products ADD COLUMN location VARCHAR;
+
+CREATE TABLE stocktakes (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'counting',
+    -- Scope of a partial count; NULL covers everything
+    category VARCHAR,
+    location VARCHAR,
+    notes VARCHAR NOT NULL DEFAULT '',
+    started_by INTEGER NOT NULL,
+    started_by_name VARCHAR NOT NULL,
+    posted_at DATETIME,
+    posted_by INTEGER,
+    FOREIGN KEY(started_by) REFERENCES users (id),
+    FOREIGN KEY(posted_by) REFERENCES users (id)
+);
+
+CREATE TABLE stocktake_lines (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    stocktake_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    -- On hand and cost per unit when the stocktake started
+    expected_quantity INTEGER NOT NULL,
+    unit_cost INTEGER NOT NULL,
+    FOREIGN KEY(stocktake_id) REFERENCES stocktakes (id) ON DELETE CASCADE,
+    FOREIGN KEY(product_id) REFERENCES products (id),
+    UNIQUE(stocktake_id, product_id)
+);
+
+CREATE TABLE stocktake_counts (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    stocktake_id INTEGER NOT NULL,
+    line_id INTEGER NOT NULL,
+    -- Negative to correct an earlier entry
+    quantity INTEGER NOT NULL,
+    method VARCHAR NOT NULL,
+    device_id VARCHAR NOT NULL DEFAULT '',
+    user_id INTEGER NOT NULL,
+    user_name VARCHAR NOT NULL,
+    FOREIGN KEY(stocktake_id) REFERENCES stocktakes (id) ON DELETE CASCADE,
+    FOREIGN KEY(line_id) REFERENCES stocktake_lines (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_stocktakes_status ON stocktakes (status);
+CREATE INDEX idx_stocktake_counts_line ON stocktake_counts (line_id);
+
+-- Lines with their product and tally; a line nobody has counted has NULL
+-- for its counted quantity and variance
+CREATE VIEW stocktake_line_details AS
+SELECT l.id,
+       l.stocktake_id,
+       l.product_id,
+       p.name AS product_name,
+       p.sku,
+       p.category,
+       p.location,
+       l.expected_quantity,
+       l.unit_cost,
+       c.counted_quantity,
+       c.counted_quantity - l.expected_quantity AS variance,
+       (c.counted_quantity - l.expected_quantity) * l.unit_cost AS variance_value
+FROM stocktake_lines l
+JOIN products p ON p.id = l.product_id
+LEFT JOIN (
+    SELECT line_id, SUM(quantity) AS counted_quantity
+    FROM stocktake_counts
+    GROUP BY line_id
+) c ON c.line_id = l.id;
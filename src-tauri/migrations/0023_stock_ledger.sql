@@ -0,0 +1,41 @@
+-- stock_movements becomes the stock ledger: every change to a product's
+-- quantity is an entry with a typed kind, the running balance after it, the
+-- document that made it and who made it. Free-text movement types from
+-- manual stock updates are mapped onto the kinds, keeping the old label in
+-- the notes when it is folded into an adjustment.
+ALTER TABLE stock_movements ADD COLUMN user_name VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE stock_movements ADD COLUMN balance_after INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE stock_movements ADD COLUMN document_type VARCHAR;
+ALTER TABLE stock_movements ADD COLUMN document_id INTEGER;
+
+UPDATE stock_movements
+SET notes = CASE WHEN notes = '' THEN movement_type ELSE movement_type || ': ' || notes END
+WHERE lower(trim(movement_type)) NOT IN ('sale', 'return', 'receipt', 'adjustment', 'transfer', 'write_off', 'write-off', 'writeoff', 'damage', 'damaged', 'expired');
+
+UPDATE stock_movements
+SET movement_type = CASE
+    WHEN lower(trim(movement_type)) IN ('sale', 'return', 'receipt', 'adjustment', 'transfer') THEN lower(trim(movement_type))
+    WHEN lower(trim(movement_type)) IN ('write_off', 'write-off', 'writeoff', 'damage', 'damaged', 'expired') THEN 'write_off'
+    ELSE 'adjustment'
+END;
+
+UPDATE stock_movements
+SET user_name = COALESCE((SELECT full_name FROM users WHERE users.id = stock_movements.user_id), '');
+
+UPDATE stock_movements
+SET balance_after = (
+    SELECT SUM(m.quantity) FROM stock_movements m
+    WHERE m.product_id = stock_movements.product_id AND m.id <= stock_movements.id
+);
+
+-- Opening stock was never recorded, so bring each product's ledger up to its
+-- quantity with one adjustment
+INSERT INTO stock_movements (created_at, updated_at, product_id, quantity, movement_type, notes, user_name, balance_after)
+SELECT CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, p.id,
+       p.quantity - COALESCE((SELECT SUM(m.quantity) FROM stock_movements m WHERE m.product_id = p.id), 0),
+       'adjustment', 'Balance brought forward into the stock ledger', '', p.quantity
+FROM products p
+WHERE p.quantity <> COALESCE((SELECT SUM(m.quantity) FROM stock_movements m WHERE m.product_id = p.id), 0);
+
+CREATE INDEX idx_stock_movements_product ON stock_movements (product_id, id);
+CREATE INDEX idx_stock_movements_document ON stock_movements (document_type, document_id);
@@ -0,0 +1,20 @@
+-- A manager approves a price override by signing in at the till, which
+-- issues a short-lived token for one product at one price. Only a hash of
+-- the token is kept; the order that sells the line uses it up.
+CREATE TABLE price_override_approvals (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at DATETIME NOT NULL,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    cashier_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    price INTEGER NOT NULL,
+    approved_by INTEGER NOT NULL,
+    approver_name VARCHAR NOT NULL,
+    expires_at DATETIME NOT NULL,
+    used_at DATETIME,
+    order_id INTEGER,
+    FOREIGN KEY(cashier_id) REFERENCES users (id),
+    FOREIGN KEY(product_id) REFERENCES products (id),
+    FOREIGN KEY(approved_by) REFERENCES users (id),
+    FOREIGN KEY(order_id) REFERENCES orders (id)
+);
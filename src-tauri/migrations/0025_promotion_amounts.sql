@@ -0,0 +1,9 @@
+-- Amount-off discounts and multi-buy bundle prices are money, so they move
+-- out of the FLOAT value into an INTEGER amount in minor units. value keeps
+-- only the percentages of percent_off and buy_x_get_y promotions.
+ALTER TABLE promotions ADD COLUMN amount INTEGER NOT NULL DEFAULT 0 CHECK (amount >= 0);
+
+UPDATE promotions
+SET amount = CAST(ROUND(value * 100) AS INTEGER),
+    value = 0
+WHERE promotion_type IN ('amount_off', 'multi_buy');
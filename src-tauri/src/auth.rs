@@ -1,15 +1,48 @@
 use tauri::State;
 use crate::{AppState, models::*};
+use crate::error::AppError;
 use anyhow::Result;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
 
-// Simple in-memory session store for demo purposes
-// In production, you'd want to use a more robust solution
-lazy_static::lazy_static! {
-    static ref SESSIONS: Arc<Mutex<HashMap<String, UserInfo>>> = Arc::new(Mutex::new(HashMap::new()));
+/// A session is dropped after this long without any authenticated call
+const SESSION_IDLE_TIMEOUT_MINUTES: i64 = 120;
+
+/// A session never outlives this, however active it is (one long shift)
+const SESSION_ABSOLUTE_TIMEOUT_HOURS: i64 = 12;
+
+/// Only persist `last_seen_at` when it is at least this stale, so that
+/// every command doesn't turn into a database write
+const SESSION_TOUCH_INTERVAL_SECONDS: i64 = 60;
+
+/// Sessions and price override approvals are looked up by the SHA-256 of
+/// their token
+pub(crate) fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn idle_expires_at(session: &Session) -> DateTime<Utc> {
+    session.last_seen_at + Duration::minutes(SESSION_IDLE_TIMEOUT_MINUTES)
+}
+
+/// Whether a session may still be used at `now`
+fn is_session_active(session: &Session, now: DateTime<Utc>) -> bool {
+    session.revoked_at.is_none() && now < session.expires_at && now < idle_expires_at(session)
+}
+
+fn session_info(session: &Session, current_session_id: i64) -> SessionInfo {
+    SessionInfo {
+        id: session.id,
+        user_id: session.user_id,
+        created_at: session.created_at,
+        last_seen_at: session.last_seen_at,
+        idle_expires_at: idle_expires_at(session).min(session.expires_at),
+        expires_at: session.expires_at,
+        is_current: session.id == current_session_id,
+    }
 }
 
 #[tauri::command]
@@ -17,7 +50,7 @@ pub async fn login(
     email: String,
     password: String,
     state: State<'_, AppState>,
-) -> Result<LoginResponse, String> {
+) -> Result<LoginResponse, AppError> {
     println!("Login attempt for email: {}", email);
 
     let db = state.db.lock().await;
@@ -27,11 +60,11 @@ pub async fn login(
     let user = db.get_user_by_email(&email).await
         .map_err(|e| {
             println!("Database error: {}", e);
-            "Internal server error".to_string() // Generic error for security
+            AppError::internal("Internal server error") // Generic error for security
         })?
         .ok_or_else(|| {
             println!("User not found for email: {}", email);
-            "Invalid email or password".to_string()
+            AppError::unauthorized("Invalid email or password")
         })?;
 
     println!("User found: {} ({})", user.full_name, user.email);
@@ -41,21 +74,21 @@ pub async fn login(
     let is_valid = bcrypt::verify(&password, &user.password_hash)
         .map_err(|e| {
             println!("Password verification error: {}", e);
-            "Internal server error".to_string() // Generic error for security
+            AppError::internal("Internal server error") // Generic error for security
         })?;
 
     if !is_valid {
         println!("Password verification failed");
-        return Err("Invalid email or password".to_string());
+        return Err(AppError::unauthorized("Invalid email or password"));
     }
 
     println!("Password verification successful");
 
     // Get user permissions
     let permissions = db.get_user_permissions(user.id).await
-        .map_err(|e| format!("Failed to get user permissions: {}", e))?;
+        .map_err(|e| AppError::from(e).context("Failed to get user permissions"))?;
 
-    // Create session token (in production, use JWT or similar)
+    // Create session token; random and opaque, only its hash is stored
     let session_token = uuid::Uuid::new_v4().to_string();
 
     let user_info = UserInfo {
@@ -66,9 +99,12 @@ pub async fn login(
         permissions,
     };
 
-    // Store session
-    let mut sessions = SESSIONS.lock().await;
-    sessions.insert(session_token.clone(), user_info.clone());
+    // Store session, clearing out any that can no longer be used
+    let now = Utc::now();
+    db.purge_sessions(now - Duration::minutes(SESSION_IDLE_TIMEOUT_MINUTES)).await
+        .map_err(|e| AppError::from(e).context("Failed to clean up sessions"))?;
+    db.create_session(&hash_token(&session_token), &user_info, now + Duration::hours(SESSION_ABSOLUTE_TIMEOUT_HOURS)).await
+        .map_err(|e| AppError::from(e).context("Failed to create session"))?;
 
     Ok(LoginResponse {
         access_token: session_token,
@@ -77,49 +113,154 @@ pub async fn login(
 }
 
 #[tauri::command]
-pub async fn logout(token: String) -> Result<String, String> {
-    let mut sessions = SESSIONS.lock().await;
-    sessions.remove(&token);
+pub async fn logout(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let db = state.db.lock().await;
+    if let Some(session) = db.get_session_by_token_hash(&hash_token(&token)).await
+        .map_err(|e| AppError::from(e).context("Failed to log out"))?
+    {
+        db.revoke_session(session.id).await
+            .map_err(|e| AppError::from(e).context("Failed to log out"))?;
+    }
     Ok("Logged out successfully".to_string())
 }
 
 #[tauri::command]
-pub async fn get_current_user(token: String) -> Result<UserInfo, String> {
-    let sessions = SESSIONS.lock().await;
-    let user_info = sessions.get(&token)
-        .ok_or_else(|| "Invalid or expired session".to_string())?;
-    Ok(user_info.clone())
+pub async fn get_current_user(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<UserInfo, AppError> {
+    validate_session(&state, &token).await
+}
+
+// Look up a live session for a token, renewing its idle timeout
+async fn find_active_session(state: &AppState, token: &str) -> Result<Session, AppError> {
+    let db = state.db.lock().await;
+    let session = db.get_session_by_token_hash(&hash_token(token)).await
+        .map_err(|e| AppError::from(e).context("Failed to validate session"))?
+        .ok_or_else(|| AppError::unauthorized("Invalid or expired session"))?;
+
+    let now = Utc::now();
+    if !is_session_active(&session, now) {
+        return Err(AppError::unauthorized("Invalid or expired session"));
+    }
+
+    // Sliding renewal: activity pushes the idle timeout back
+    if now - session.last_seen_at >= Duration::seconds(SESSION_TOUCH_INTERVAL_SECONDS) {
+        db.touch_session(session.id, now).await
+            .map_err(|e| AppError::from(e).context("Failed to validate session"))?;
+    }
+
+    Ok(session)
 }
 
-// Helper function to validate session (for use in other commands)
-pub async fn validate_session(token: &str) -> Result<UserInfo, String> {
-    let sessions = SESSIONS.lock().await;
-    let user_info = sessions.get(token)
-        .ok_or_else(|| "Invalid or expired session".to_string())?;
-    Ok(user_info.clone())
+// Helper function to validate session (for use in other commands).
+// Permissions are read fresh rather than from the login snapshot, so an
+// edited role applies to sessions that are already open
+pub async fn validate_session(state: &AppState, token: &str) -> Result<UserInfo, AppError> {
+    let session = find_active_session(state, token).await?;
+    let mut user_info: UserInfo = serde_json::from_str(&session.user_info)
+        .map_err(|e| AppError::internal(format!("Corrupt session data: {}", e)))?;
+    user_info.permissions = state.db.lock().await.get_user_permissions(user_info.id).await
+        .map_err(|e| AppError::from(e).context("Failed to validate session"))?;
+    Ok(user_info)
 }
 
 // Helper function to check if user has permission
-pub async fn check_permission(token: &str, required_permission: &str) -> Result<UserInfo, String> {
-    let user_info = validate_session(token).await?;
+pub async fn check_permission(state: &AppState, token: &str, required_permission: &str) -> Result<UserInfo, AppError> {
+    let user_info = validate_session(state, token).await?;
 
     if user_info.permissions.contains(&required_permission.to_string()) {
         Ok(user_info)
     } else {
-        Err("Insufficient permissions".to_string())
+        Err(AppError::forbidden(required_permission))
     }
 }
 
 // Tauri command wrapper for validate_session
 #[tauri::command]
-pub async fn validate_user_session(token: String) -> Result<UserInfo, String> {
-    validate_session(&token).await
+pub async fn validate_user_session(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<UserInfo, AppError> {
+    validate_session(&state, &token).await
 }
 
 // Tauri command wrapper for check_permission
 #[tauri::command]
-pub async fn check_user_permission(token: String, permission: String) -> Result<UserInfo, String> {
-    check_permission(&token, &permission).await
+pub async fn check_user_permission(
+    token: String,
+    permission: String,
+    state: State<'_, AppState>,
+) -> Result<UserInfo, AppError> {
+    check_permission(&state, &token, &permission).await
+}
+
+// List a user's live sessions; other users' sessions need user management rights
+#[tauri::command]
+pub async fn get_active_sessions(
+    token: String,
+    user_id: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SessionInfo>, AppError> {
+    let current = find_active_session(&state, &token).await?;
+    let user_id = user_id.unwrap_or(current.user_id);
+    if user_id != current.user_id {
+        check_permission(&state, &token, "user_management").await?;
+    }
+
+    let db = state.db.lock().await;
+    let sessions = db.get_user_sessions(user_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get sessions"))?;
+
+    let now = Utc::now();
+    Ok(sessions.iter()
+        .filter(|session| is_session_active(session, now))
+        .map(|session| session_info(session, current.id))
+        .collect())
+}
+
+// Revoke a single session, e.g. one left open on another till
+#[tauri::command]
+pub async fn revoke_session(
+    token: String,
+    session_id: i64,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let current = find_active_session(&state, &token).await?;
+
+    let target = {
+        let db = state.db.lock().await;
+        db.get_session(session_id).await
+            .map_err(|e| AppError::from(e).context("Failed to revoke session"))?
+            .ok_or_else(|| AppError::not_found("Session not found"))?
+    };
+    if target.user_id != current.user_id {
+        check_permission(&state, &token, "user_management").await?;
+    }
+
+    let db = state.db.lock().await;
+    db.revoke_session(session_id).await
+        .map_err(|e| AppError::from(e).context("Failed to revoke session"))
+}
+
+// Revoke all of a user's sessions; the caller's own session is kept
+#[tauri::command]
+pub async fn revoke_user_sessions(
+    token: String,
+    user_id: i64,
+    state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let current = find_active_session(&state, &token).await?;
+    if user_id != current.user_id {
+        check_permission(&state, &token, "user_management").await?;
+    }
+
+    let db = state.db.lock().await;
+    db.revoke_user_sessions(user_id, Some(current.id)).await
+        .map_err(|e| AppError::from(e).context("Failed to revoke sessions"))
 }
 
 // Save user data to local database after successful online login
@@ -127,7 +268,7 @@ pub async fn check_user_permission(token: String, permission: String) -> Result<
 pub async fn save_user_to_local(
     user: User,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db = state.db.lock().await;
 
     if let Some(pool) = &db.pool {
@@ -150,10 +291,172 @@ pub async fn save_user_to_local(
         .bind(user.is_superuser)
         .execute(pool)
         .await
-        .map_err(|e| format!("Failed to save user to local database: {}", e))?;
+        .map_err(|e| AppError::from(e).context("Failed to save user to local database"))?;
 
         println!("User {} saved to local database", user.email);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_at(created_at: DateTime<Utc>) -> Session {
+        Session {
+            id: 1,
+            token_hash: hash_token("token"),
+            user_id: 1,
+            user_info: String::new(),
+            created_at,
+            last_seen_at: created_at,
+            expires_at: created_at + Duration::hours(SESSION_ABSOLUTE_TIMEOUT_HOURS),
+            revoked_at: None,
+        }
+    }
+
+    async fn grant(db: &crate::database::Database, role: &str, permission: &str) {
+        let pool = db.pool.as_ref().unwrap();
+        sqlx::query("INSERT OR IGNORE INTO roles (name) VALUES (?)").bind(role).execute(pool).await.unwrap();
+        sqlx::query("INSERT OR IGNORE INTO permissions (name) VALUES (?)").bind(permission).execute(pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO role_permissions (role_id, permission_id) SELECT r.id, p.id FROM roles r, permissions p WHERE r.name = ? AND p.name = ?"
+        )
+        .bind(role)
+        .bind(permission)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_idle_sessions_expire() {
+        let now = Utc::now();
+        let session = session_at(now);
+        assert!(is_session_active(&session, now + Duration::minutes(SESSION_IDLE_TIMEOUT_MINUTES - 1)));
+        assert!(!is_session_active(&session, now + Duration::minutes(SESSION_IDLE_TIMEOUT_MINUTES)));
+    }
+
+    #[test]
+    fn test_activity_cannot_extend_past_absolute_timeout() {
+        let now = Utc::now();
+        let mut session = session_at(now);
+        let late = now + Duration::hours(SESSION_ABSOLUTE_TIMEOUT_HOURS);
+        session.last_seen_at = late - Duration::minutes(1);
+        assert!(!is_session_active(&session, late));
+    }
+
+    #[test]
+    fn test_revoked_sessions_are_inactive() {
+        let now = Utc::now();
+        let mut session = session_at(now);
+        session.revoked_at = Some(now);
+        assert!(!is_session_active(&session, now));
+    }
+
+    #[tokio::test]
+    async fn test_sessions_survive_reopening_the_database() {
+        let db = crate::database::Database::in_memory().await.unwrap();
+        let user = UserInfo {
+            id: 7,
+            email: "cashier@example.com".to_string(),
+            full_name: "Cashier".to_string(),
+            role: "cashier".to_string(),
+            permissions: vec!["sales_management".to_string()],
+        };
+        sqlx::query(
+            "INSERT INTO users (id, created_at, updated_at, email, hashed_password, full_name, role, is_active, is_superuser) VALUES (7, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, '', ?, ?, TRUE, FALSE)"
+        )
+        .bind(&user.email)
+        .bind(&user.full_name)
+        .bind(&user.role)
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .unwrap();
+        grant(&db, "cashier", "sales_management").await;
+        db.create_session(&hash_token("abc"), &user, Utc::now() + Duration::hours(1)).await.unwrap();
+
+        let state = AppState { db: std::sync::Arc::new(tokio::sync::Mutex::new(db)) };
+        let found = check_permission(&state, "abc", "sales_management").await.unwrap();
+        assert_eq!(found.id, 7);
+        assert_eq!(check_permission(&state, "abc", "user_management").await.unwrap_err(), AppError::forbidden("user_management"));
+        assert_eq!(validate_session(&state, "other").await.unwrap_err(), AppError::unauthorized("Invalid or expired session"));
+
+        let session = find_active_session(&state, "abc").await.unwrap();
+        state.db.lock().await.revoke_session(session.id).await.unwrap();
+        assert!(validate_session(&state, "abc").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_changing_or_deleting_a_user_ends_their_sessions() {
+        let db = crate::database::Database::in_memory().await.unwrap();
+        let user = UserInfo {
+            id: 7,
+            email: "manager@example.com".to_string(),
+            full_name: "Manager".to_string(),
+            role: "manager".to_string(),
+            permissions: vec!["user_management".to_string()],
+        };
+        sqlx::query(
+            "INSERT INTO users (id, created_at, updated_at, email, hashed_password, full_name, role, is_active, is_superuser) VALUES (7, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, '', ?, ?, TRUE, FALSE)"
+        )
+        .bind(&user.email)
+        .bind(&user.full_name)
+        .bind(&user.role)
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .unwrap();
+        grant(&db, "manager", "user_management").await;
+        db.create_session(&hash_token("abc"), &user, Utc::now() + Duration::hours(1)).await.unwrap();
+        let state = AppState { db: std::sync::Arc::new(tokio::sync::Mutex::new(db)) };
+
+        // Renaming keeps the session; a demotion ends it
+        state.db.lock().await.update_user(7, &user.email, "Shift Manager", "manager").await.unwrap();
+        assert!(check_permission(&state, "abc", "user_management").await.is_ok());
+        state.db.lock().await.update_user(7, &user.email, "Shift Manager", "cashier").await.unwrap();
+        assert_eq!(check_permission(&state, "abc", "user_management").await.unwrap_err(), AppError::unauthorized("Invalid or expired session"));
+
+        let db = state.db.lock().await;
+        db.create_session(&hash_token("def"), &user, Utc::now() + Duration::hours(1)).await.unwrap();
+        db.delete_user(7).await.unwrap();
+        assert!(db.get_session_by_token_hash(&hash_token("def")).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_role_changes_reach_open_sessions() {
+        let db = crate::database::Database::in_memory().await.unwrap();
+        let user = UserInfo {
+            id: 7,
+            email: "cashier@example.com".to_string(),
+            full_name: "Cashier".to_string(),
+            role: "cashier".to_string(),
+            permissions: vec!["sales_management".to_string()],
+        };
+        sqlx::query(
+            "INSERT INTO users (id, created_at, updated_at, email, hashed_password, full_name, role, is_active, is_superuser) VALUES (7, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, '', ?, ?, TRUE, FALSE)"
+        )
+        .bind(&user.email)
+        .bind(&user.full_name)
+        .bind(&user.role)
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .unwrap();
+        grant(&db, "cashier", "sales_management").await;
+        db.create_session(&hash_token("abc"), &user, Utc::now() + Duration::hours(1)).await.unwrap();
+        let state = AppState { db: std::sync::Arc::new(tokio::sync::Mutex::new(db)) };
+
+        // Granting a permission to the role applies without logging in again
+        assert_eq!(check_permission(&state, "abc", "inventory_management").await.unwrap_err(), AppError::forbidden("inventory_management"));
+        grant(&*state.db.lock().await, "cashier", "inventory_management").await;
+        assert!(check_permission(&state, "abc", "inventory_management").await.is_ok());
+
+        // and so does taking one away
+        sqlx::query("DELETE FROM role_permissions WHERE permission_id = (SELECT id FROM permissions WHERE name = 'sales_management')")
+            .execute(state.db.lock().await.pool.as_ref().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(check_permission(&state, "abc", "sales_management").await.unwrap_err(), AppError::forbidden("sales_management"));
+        assert_eq!(validate_session(&state, "abc").await.unwrap().permissions, vec!["inventory_management".to_string()]);
+    }
+}
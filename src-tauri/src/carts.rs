@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// Key of the parked cart section in `app_settings`
+pub const PARKED_CART_SETTINGS_KEY: &str = "parked_carts";
+
+const MAX_RESERVATION_MINUTES: u32 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParkedCartSettings {
+    /// How long stock reserved for a parked cart is held before others can take it
+    pub reservation_minutes: u32,
+}
+
+impl Default for ParkedCartSettings {
+    fn default() -> Self {
+        ParkedCartSettings { reservation_minutes: 30 }
+    }
+}
+
+fn validate_cart(cart: &ParkCartRequest) -> Result<(), AppError> {
+    if cart.label.trim().is_empty() {
+        return Err(AppError::validation("label", "Give the parked cart a label"));
+    }
+    if cart.items.is_empty() {
+        return Err(AppError::validation("items", "An empty cart cannot be parked"));
+    }
+    if cart.items.iter().any(|item| item.quantity <= 0) {
+        return Err(AppError::validation("items", "Quantities must be positive"));
+    }
+    Ok(())
+}
+
+// Parked cart commands
+#[tauri::command]
+pub async fn park_cart(
+    token: String,
+    cart_data: ParkCartRequest,
+    state: State<'_, AppState>,
+) -> Result<ParkedCartDetails, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+    validate_cart(&cart_data)?;
+
+    let db = state.db.lock().await;
+    let reserve_for = if cart_data.reserve_stock {
+        let settings = db.get_setting::<ParkedCartSettings>(PARKED_CART_SETTINGS_KEY).await
+            .map_err(|e| AppError::from(e).context("Failed to get parked cart settings"))?
+            .unwrap_or_default();
+        Some(chrono::Duration::minutes(settings.reservation_minutes as i64))
+    } else {
+        None
+    };
+
+    let cart_id = db.park_cart(&user, &cart_data, reserve_for).await
+        .map_err(|e| AppError::from(e).context("Failed to park cart"))?;
+
+    db.get_parked_cart(cart_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get parked cart"))
+}
+
+#[tauri::command]
+pub async fn get_parked_carts(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ParkedCart>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_parked_carts().await
+        .map_err(|e| AppError::from(e).context("Failed to get parked carts"))
+}
+
+/// Load a parked cart back into the till, removing it from the parked list
+#[tauri::command]
+pub async fn resume_parked_cart(
+    token: String,
+    cart_id: i64,
+    state: State<'_, AppState>,
+) -> Result<ParkedCartDetails, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.resume_parked_cart(cart_id).await
+        .map_err(|e| AppError::from(e).context("Failed to resume parked cart"))
+}
+
+#[tauri::command]
+pub async fn discard_parked_cart(
+    token: String,
+    cart_id: i64,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.discard_parked_cart(cart_id).await
+        .map_err(|e| AppError::from(e).context("Failed to discard parked cart"))
+}
+
+#[tauri::command]
+pub async fn get_parked_cart_settings(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<ParkedCartSettings, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    let settings = db.get_setting::<ParkedCartSettings>(PARKED_CART_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get parked cart settings"))?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn update_parked_cart_settings(
+    token: String,
+    settings: ParkedCartSettings,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+
+    if settings.reservation_minutes == 0 || settings.reservation_minutes > MAX_RESERVATION_MINUTES {
+        return Err(AppError::validation(
+            "reservation_minutes",
+            format!("Reservations must last between 1 and {} minutes", MAX_RESERVATION_MINUTES),
+        ));
+    }
+
+    let db = state.db.lock().await;
+    db.set_setting(PARKED_CART_SETTINGS_KEY, &settings).await
+        .map_err(|e| AppError::from(e).context("Failed to save parked cart settings"))
+}
@@ -0,0 +1,149 @@
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// Phone numbers are kept as their digits, with a leading `+` if one was
+/// typed, so "0803 123 4567" and "0803-123-4567" are the same number
+pub fn normalize_phone(phone: &str) -> String {
+    let phone = phone.trim();
+    let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+    if phone.starts_with('+') && !digits.is_empty() {
+        format!("+{}", digits)
+    } else {
+        digits
+    }
+}
+
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn validate_customer(customer: &CustomerRequest) -> Result<(), AppError> {
+    if customer.name.trim().is_empty() {
+        return Err(AppError::validation("name", "Customer name is required"));
+    }
+    let email = normalize_email(customer.email.as_deref().unwrap_or(""));
+    if !email.is_empty() && (!email.contains('@') || email.contains(char::is_whitespace)) {
+        return Err(AppError::validation("email", "Enter a valid email address"));
+    }
+    let phone = customer.phone.as_deref().unwrap_or("");
+    if !phone.trim().is_empty() && normalize_phone(phone).trim_start_matches('+').len() < 6 {
+        return Err(AppError::validation("phone", "Enter a valid phone number"));
+    }
+    Ok(())
+}
+
+// Customer commands
+#[tauri::command]
+pub async fn get_customers(
+    token: String,
+    search: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Customer>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_customers(search.as_deref()).await
+        .map_err(|e| AppError::from(e).context("Failed to get customers"))
+}
+
+#[tauri::command]
+pub async fn get_customer(
+    token: String,
+    customer_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Customer, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_customer(customer_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get customer"))
+}
+
+/// Find a customer at the counter by phone number or email address
+#[tauri::command]
+pub async fn lookup_customer(
+    token: String,
+    contact: String,
+    state: State<'_, AppState>,
+) -> Result<Option<Customer>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.lookup_customer(&contact).await
+        .map_err(|e| AppError::from(e).context("Failed to look up customer"))
+}
+
+#[tauri::command]
+pub async fn create_customer(
+    token: String,
+    customer: CustomerRequest,
+    state: State<'_, AppState>,
+) -> Result<Customer, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+    validate_customer(&customer)?;
+
+    let db = state.db.lock().await;
+    let customer_id = db.create_customer(&customer).await
+        .map_err(|e| AppError::from(e).context("Failed to create customer"))?;
+
+    db.get_customer(customer_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get customer"))
+}
+
+#[tauri::command]
+pub async fn update_customer(
+    token: String,
+    customer_id: i64,
+    customer: CustomerRequest,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+    validate_customer(&customer)?;
+
+    let db = state.db.lock().await;
+    db.update_customer(customer_id, &customer).await
+        .map_err(|e| AppError::from(e).context("Failed to update customer"))
+}
+
+#[tauri::command]
+pub async fn delete_customer(
+    token: String,
+    customer_id: i64,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.delete_customer(customer_id).await
+        .map_err(|e| AppError::from(e).context("Failed to delete customer"))
+}
+
+/// Lifetime spend and every line the customer has bought, newest first
+#[tauri::command]
+pub async fn get_customer_history(
+    token: String,
+    customer_id: i64,
+    state: State<'_, AppState>,
+) -> Result<CustomerHistory, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_customer_history(customer_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get customer history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contact_details_are_normalized() {
+        assert_eq!(normalize_phone(" 0803-123 4567 "), "08031234567");
+        assert_eq!(normalize_phone("+234 (803) 123 4567"), "+2348031234567");
+        assert_eq!(normalize_phone(""), "");
+        assert_eq!(normalize_email("  Ada@Example.COM "), "ada@example.com");
+    }
+}
@@ -1,6 +1,24 @@
-use sqlx::{SqlitePool, Row};
+use sqlx::{SqliteConnection, SqlitePool, Row};
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{de::DeserializeOwned, Serialize};
 use crate::models::*;
+use crate::customers::{normalize_email, normalize_phone};
+use crate::error::{AppError, StockShortage};
+use crate::giftcards::{check_redeemable, generate_card_code, normalize_card_code, validate_card_sale, CardMovement};
+use crate::ledger::StockEntry;
+use crate::loyalty::{earn_reversal, LoyaltySettings, PointsMovement, LOYALTY_SETTINGS_KEY};
+use crate::money::{CurrencySettings, CURRENCY_SETTINGS_KEY};
+use crate::payments::apply_tender;
+use crate::pos::{check_line_price, OverrideApproval};
+use crate::purchasing::{average_cost, po_number};
+use crate::reorder::ReorderCandidate;
+use crate::promotions::{apply_promotions, normalize_coupon_code, PromotionLine};
+use crate::returns::{allocate_refund, refund_share, RefundableTender};
+use crate::shifts::tender_lines;
+use crate::stock::{check_stock, StockCheck, StockPolicySettings, STOCK_POLICY_SETTINGS_KEY};
+use crate::stocktake::{stocktake_number, summarize_variances};
+use crate::tax::{calculate_line, TaxRule};
 
 #[derive(Clone)]
 pub struct Database {
@@ -28,241 +46,164 @@ impl Database {
     }
 
     pub async fn migrate(&mut self) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
         
-        // Create tables - Updated to match online API schema exactly
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS users (
-                id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL,
-                email VARCHAR NOT NULL UNIQUE,
-                hashed_password VARCHAR NOT NULL,
-                full_name VARCHAR NOT NULL,
-                role VARCHAR NOT NULL,
-                is_active BOOLEAN NOT NULL,
-                is_superuser BOOLEAN NOT NULL
-            )
-            "#,
-        )
-        .execute(pool)
-        .await?;
+        crate::migrations::run(pool, crate::migrations::MIGRATIONS).await?;
+        Ok(())
+    }
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS roles (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT UNIQUE NOT NULL,
-                description TEXT,
-                created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
-            )
-            "#,
-        )
-        .execute(pool)
-        .await?;
+    // Database initialization complete - no mock data seeded
+    // All data will be fetched from the online API after authentication
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS permissions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT UNIQUE NOT NULL,
-                description TEXT,
-                created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
-            )
-            "#,
+    pub async fn get_user_by_email(&self, email: &str) -> Result<Option<User>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+        
+        let user = sqlx::query_as::<_, User>(
+            "SELECT * FROM users WHERE email = ?"
         )
-        .execute(pool)
+        .bind(email)
+        .fetch_optional(pool)
         .await?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS role_permissions (
-                role_id INTEGER NOT NULL,
-                permission_id INTEGER NOT NULL,
-                PRIMARY KEY (role_id, permission_id),
-                FOREIGN KEY (role_id) REFERENCES roles (id) ON DELETE CASCADE,
-                FOREIGN KEY (permission_id) REFERENCES permissions (id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(pool)
-        .await?;
+        Ok(user)
+    }
 
-        sqlx::query(
+    pub async fn get_user_permissions(&self, user_id: i64) -> Result<Vec<String>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+        
+        let permissions = sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS products (
-                id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL,
-                name VARCHAR NOT NULL,
-                description VARCHAR NOT NULL,
-                sku VARCHAR NOT NULL UNIQUE,
-                category VARCHAR(13) NOT NULL,
-                price FLOAT NOT NULL,
-                cost FLOAT NOT NULL,
-                quantity INTEGER NOT NULL,
-                reorder_level INTEGER NOT NULL,
-                supplier_id INTEGER,
-                FOREIGN KEY(supplier_id) REFERENCES suppliers (id)
-            )
-            "#,
+            SELECT p.name 
+            FROM permissions p
+            JOIN role_permissions rp ON p.id = rp.permission_id
+            JOIN roles r ON rp.role_id = r.id
+            JOIN users u ON u.role = r.name
+            WHERE u.id = ?
+            "#
         )
-        .execute(pool)
+        .bind(user_id)
+        .fetch_all(pool)
         .await?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS suppliers (
-                id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL,
-                name VARCHAR NOT NULL,
-                contact_name VARCHAR NOT NULL,
-                email VARCHAR NOT NULL,
-                phone VARCHAR NOT NULL,
-                address VARCHAR NOT NULL
-            )
-            "#,
-        )
-        .execute(pool)
-        .await?;
+        Ok(permissions.into_iter().map(|row| row.get::<String, _>(0)).collect())
+    }
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS orders (
-                id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL,
-                customer_name VARCHAR NOT NULL,
-                total_amount FLOAT NOT NULL,
-                payment_method VARCHAR(14) NOT NULL,
-                status VARCHAR(9) NOT NULL,
-                cashier_id INTEGER,
-                FOREIGN KEY(cashier_id) REFERENCES users (id)
-            )
-            "#,
-        )
-        .execute(pool)
-        .await?;
+    // Session methods
+    pub async fn create_session(&self, token_hash: &str, user_info: &UserInfo, expires_at: DateTime<Utc>) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS order_items (
-                id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL,
-                order_id INTEGER NOT NULL,
-                product_id INTEGER NOT NULL,
-                quantity INTEGER NOT NULL,
-                unit_price FLOAT NOT NULL,
-                FOREIGN KEY(order_id) REFERENCES orders (id),
-                FOREIGN KEY(product_id) REFERENCES products (id)
-            )
-            "#,
+        let now = Utc::now();
+        let result = sqlx::query(
+            "INSERT INTO sessions (token_hash, user_id, user_info, created_at, last_seen_at, expires_at) VALUES (?, ?, ?, ?, ?, ?)"
         )
+        .bind(token_hash)
+        .bind(user_info.id)
+        .bind(serde_json::to_string(user_info)?)
+        .bind(now)
+        .bind(now)
+        .bind(expires_at)
         .execute(pool)
         .await?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS stock_movements (
-                id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL,
-                product_id INTEGER NOT NULL,
-                quantity INTEGER NOT NULL,
-                movement_type VARCHAR(10) NOT NULL,
-                notes VARCHAR NOT NULL,
-                FOREIGN KEY(product_id) REFERENCES products (id)
-            )
-            "#,
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_session_by_token_hash(&self, token_hash: &str) -> Result<Option<Session>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let session = sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions WHERE token_hash = ?"
         )
-        .execute(pool)
+        .bind(token_hash)
+        .fetch_optional(pool)
         .await?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS notifications (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                user_id INTEGER,
-                title TEXT NOT NULL,
-                message TEXT NOT NULL,
-                type TEXT NOT NULL,
-                priority TEXT NOT NULL DEFAULT 'medium',
-                is_read BOOLEAN NOT NULL DEFAULT FALSE,
-                product_id INTEGER,
-                created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (user_id) REFERENCES users (id),
-                FOREIGN KEY (product_id) REFERENCES products (id)
-            )
-            "#,
+        Ok(session)
+    }
+
+    pub async fn get_session(&self, session_id: i64) -> Result<Option<Session>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let session = sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions WHERE id = ?"
         )
-        .execute(pool)
+        .bind(session_id)
+        .fetch_optional(pool)
         .await?;
 
-        // Create sync queue table for offline operations
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS sync_queue (
-                id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
-                created_at DATETIME NOT NULL,
-                operation_type VARCHAR NOT NULL,
-                endpoint VARCHAR NOT NULL,
-                method VARCHAR NOT NULL,
-                payload TEXT NOT NULL,
-                retries INTEGER NOT NULL DEFAULT 0,
-                last_attempt_at DATETIME,
-                error_message TEXT
-            )
-            "#,
+        Ok(session)
+    }
+
+    pub async fn get_user_sessions(&self, user_id: i64) -> Result<Vec<Session>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let sessions = sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions WHERE user_id = ? AND revoked_at IS NULL ORDER BY last_seen_at DESC"
         )
-        .execute(pool)
+        .bind(user_id)
+        .fetch_all(pool)
         .await?;
 
-        println!("Database tables created successfully");
+        Ok(sessions)
+    }
+
+    pub async fn touch_session(&self, session_id: i64, last_seen_at: DateTime<Utc>) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        sqlx::query("UPDATE sessions SET last_seen_at = ? WHERE id = ?")
+            .bind(last_seen_at)
+            .bind(session_id)
+            .execute(pool)
+            .await?;
+
         Ok(())
     }
 
-    // Database initialization complete - no mock data seeded
-    // All data will be fetched from the online API after authentication
+    pub async fn revoke_session(&self, session_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-    pub async fn get_user_by_email(&self, email: &str) -> Result<Option<User>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
-        
-        let user = sqlx::query_as::<_, User>(
-            "SELECT * FROM users WHERE email = ?"
+        sqlx::query("UPDATE sessions SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(session_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Revoke every live session of a user, optionally keeping one (the caller's own)
+    pub async fn revoke_user_sessions(&self, user_id: i64, except_session_id: Option<i64>) -> Result<u64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let result = sqlx::query(
+            "UPDATE sessions SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL AND id IS NOT ?"
         )
-        .bind(email)
-        .fetch_optional(pool)
+        .bind(Utc::now())
+        .bind(user_id)
+        .bind(except_session_id)
+        .execute(pool)
         .await?;
 
-        Ok(user)
+        Ok(result.rows_affected())
     }
 
-    pub async fn get_user_permissions(&self, user_id: i64) -> Result<Vec<String>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
-        
-        let permissions = sqlx::query(
-            r#"
-            SELECT p.name 
-            FROM permissions p
-            JOIN role_permissions rp ON p.id = rp.permission_id
-            JOIN roles r ON rp.role_id = r.id
-            JOIN users u ON u.role = r.name
-            WHERE u.id = ?
-            "#
+    /// Drop sessions that can never be used again
+    pub async fn purge_sessions(&self, idle_cutoff: DateTime<Utc>) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        sqlx::query(
+            "DELETE FROM sessions WHERE revoked_at IS NOT NULL OR datetime(expires_at) <= datetime('now') OR datetime(last_seen_at) <= datetime(?)"
         )
-        .bind(user_id)
-        .fetch_all(pool)
+        .bind(idle_cutoff)
+        .execute(pool)
         .await?;
 
-        Ok(permissions.into_iter().map(|row| row.get::<String, _>(0)).collect())
+        Ok(())
     }
 
     // User management methods
     pub async fn get_all_users(&self) -> Result<Vec<User>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         let users = sqlx::query_as::<_, User>(
             "SELECT * FROM users ORDER BY created_at DESC"
@@ -274,7 +215,7 @@ impl Database {
     }
 
     pub async fn create_user(&self, email: &str, full_name: &str, role: &str, password: &str) -> Result<i64> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)?;
 
@@ -291,8 +232,18 @@ impl Database {
         Ok(result.last_insert_rowid())
     }
 
+    /// A new role ends the user's sessions, as each holds the role they
+    /// logged in with
     pub async fn update_user(&self, user_id: i64, email: &str, full_name: &str, role: &str) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+
+        let current_role: String = sqlx::query_scalar("SELECT role FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("User #{} not found", user_id)))?;
 
         sqlx::query(
             "UPDATE users SET email = ?, full_name = ?, role = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
@@ -301,25 +252,40 @@ impl Database {
         .bind(full_name)
         .bind(role)
         .bind(user_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
 
+        if current_role != role {
+            sqlx::query("UPDATE sessions SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL")
+                .bind(Utc::now())
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
+    /// The user's sessions go with them
     pub async fn delete_user(&self, user_id: i64) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         sqlx::query("DELETE FROM users WHERE id = ?")
             .bind(user_id)
             .execute(pool)
-            .await?;
+            .await
+            .map_err(|e| match AppError::from(e) {
+                // Orders, stock movements and shifts keep pointing at who made them
+                AppError::Conflict { .. } => AppError::conflict("User has recorded sales or stock movements and cannot be deleted"),
+                e => e,
+            })?;
 
         Ok(())
     }
 
     pub async fn get_all_roles(&self) -> Result<Vec<Role>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         let roles = sqlx::query_as::<_, Role>(
             "SELECT * FROM roles ORDER BY name"
@@ -331,7 +297,7 @@ impl Database {
     }
 
     pub async fn get_all_permissions(&self) -> Result<Vec<Permission>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         let permissions = sqlx::query_as::<_, Permission>(
             "SELECT * FROM permissions ORDER BY name"
@@ -344,7 +310,7 @@ impl Database {
 
     // Product management methods
     pub async fn get_all_products(&self) -> Result<Vec<Product>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         let products = sqlx::query_as::<_, Product>(
             "SELECT * FROM products ORDER BY name"
@@ -355,363 +321,3640 @@ impl Database {
         Ok(products)
     }
 
-    pub async fn create_product(&self, product: CreateProductRequest) -> Result<i64> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    pub async fn create_product(&self, user: &UserInfo, product: CreateProductRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let now = Utc::now();
 
         let result = sqlx::query(
-            "INSERT INTO products (name, description, sku, category, price, cost, quantity, reorder_level, expiry_date, supplier_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO products (created_at, updated_at, name, description, sku, category, price, cost, quantity, reorder_level, supplier_id, tax_class_id, stock_policy, location) VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?, ?, ?, ?)"
         )
+        .bind(now)
+        .bind(now)
         .bind(&product.name)
-        .bind(&product.description)
+        .bind(product.description.as_deref().unwrap_or(""))
         .bind(&product.sku)
         .bind(&product.category)
         .bind(product.price)
         .bind(product.cost)
-        .bind(product.quantity)
         .bind(product.reorder_level)
-        .bind(&product.expiry_date)
         .bind(product.supplier_id)
-        .execute(pool)
+        .bind(product.tax_class_id)
+        .bind(product.stock_policy)
+        .bind(product.location.as_deref().map(str::trim).filter(|location| !location.is_empty()))
+        .execute(&mut *tx)
         .await?;
 
-        Ok(result.last_insert_rowid())
+        let product_id = result.last_insert_rowid();
+
+        // Initial stock becomes the product's opening batch and first ledger entry
+        if product.quantity != 0 {
+            let batch_id = if product.quantity > 0 {
+                Some(Self::insert_batch(&mut tx, product_id, "OPENING", now.date_naive(), product.expiry_date, product.quantity).await?)
+            } else {
+                None
+            };
+            Self::move_stock(&mut tx, user, StockEntry {
+                product_id,
+                kind: StockMovementKind::Adjustment,
+                quantity: product.quantity,
+                document: batch_id.map(|batch_id| (StockDocument::Batch, batch_id)),
+                notes: "Opening stock",
+            }).await?;
+        }
+
+        tx.commit().await?;
+        Ok(product_id)
     }
 
-    pub async fn update_product(&self, product_id: i64, product: CreateProductRequest) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    /// Save a product's details. A changed quantity is booked to the ledger
+    /// as an adjustment rather than overwritten.
+    pub async fn update_product(&self, user: &UserInfo, product_id: i64, product: CreateProductRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let on_hand: i32 = sqlx::query_scalar("SELECT quantity FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Product #{} not found", product_id)))?;
 
         sqlx::query(
-            "UPDATE products SET name = ?, description = ?, sku = ?, category = ?, price = ?, cost = ?, quantity = ?, reorder_level = ?, expiry_date = ?, supplier_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+            "UPDATE products SET name = ?, description = ?, sku = ?, category = ?, price = ?, cost = ?, reorder_level = ?, supplier_id = ?, tax_class_id = ?, stock_policy = ?, location = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
         )
         .bind(&product.name)
-        .bind(&product.description)
+        .bind(product.description.as_deref().unwrap_or(""))
         .bind(&product.sku)
         .bind(&product.category)
         .bind(product.price)
         .bind(product.cost)
-        .bind(product.quantity)
         .bind(product.reorder_level)
-        .bind(&product.expiry_date)
         .bind(product.supplier_id)
+        .bind(product.tax_class_id)
+        .bind(product.stock_policy)
+        .bind(product.location.as_deref().map(str::trim).filter(|location| !location.is_empty()))
         .bind(product_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
 
+        let change = product.quantity - on_hand;
+        if change != 0 {
+            if change < 0 {
+                Self::deplete_batches_fefo(&mut tx, product_id, -change).await?;
+            }
+            Self::move_stock(&mut tx, user, StockEntry {
+                product_id,
+                kind: StockMovementKind::Adjustment,
+                quantity: change,
+                document: None,
+                notes: "Quantity edited with the product",
+            }).await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
+    /// Only a product that has never been sold or had stock can be deleted;
+    /// anything else would take its lines and ledger with it
     pub async fn delete_product(&self, product_id: i64) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let (sold, stocked): (bool, bool) = sqlx::query_as(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM order_items WHERE product_id = ?),
+                   EXISTS(SELECT 1 FROM stock_movements WHERE product_id = ?)
+                   OR EXISTS(SELECT 1 FROM product_batches WHERE product_id = ?)
+            "#
+        )
+        .bind(product_id)
+        .bind(product_id)
+        .bind(product_id)
+        .fetch_one(pool)
+        .await?;
+
+        if sold {
+            return Err(AppError::conflict("Product has been sold and cannot be deleted").into());
+        }
+        if stocked {
+            return Err(AppError::conflict("Product has stock in the ledger and cannot be deleted").into());
+        }
 
         sqlx::query("DELETE FROM products WHERE id = ?")
             .bind(product_id)
             .execute(pool)
-            .await?;
+            .await
+            .map_err(|e| match AppError::from(e) {
+                // Purchase orders, stocktakes and promotions still list it
+                AppError::Conflict { .. } => AppError::conflict("Product is on a purchase order, stocktake or promotion and cannot be deleted"),
+                e => e,
+            })?;
 
         Ok(())
     }
 
-    pub async fn update_stock(&self, product_id: i64, stock_update: UpdateStockRequest) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    pub async fn update_stock(&self, user: &UserInfo, product_id: i64, stock_update: UpdateStockRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         // Start a transaction
         let mut tx = pool.begin().await?;
 
-        // Update product quantity
-        sqlx::query(
-            "UPDATE products SET quantity = quantity + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
-        )
-        .bind(stock_update.quantity_change)
-        .bind(product_id)
-        .execute(&mut *tx)
-        .await?;
-
-        // Record inventory movement
-        sqlx::query(
-            "INSERT INTO inventory_movements (product_id, quantity_change, movement_type, notes) VALUES (?, ?, ?, ?)"
-        )
-        .bind(product_id)
-        .bind(stock_update.quantity_change)
-        .bind(&stock_update.movement_type)
-        .bind(&stock_update.notes)
-        .execute(&mut *tx)
-        .await?;
+        Self::move_stock(&mut tx, user, StockEntry {
+            product_id,
+            kind: stock_update.movement_type,
+            quantity: stock_update.quantity_change,
+            document: None,
+            notes: stock_update.notes.as_deref().unwrap_or(""),
+        }).await?;
+
+        // Stock taken out comes from the batches that expire first
+        if stock_update.quantity_change < 0 {
+            Self::deplete_batches_fefo(&mut tx, product_id, -stock_update.quantity_change).await?;
+        }
 
         tx.commit().await?;
         Ok(())
     }
 
-    pub async fn get_low_stock_products(&self) -> Result<Vec<Product>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    // Batch (lot) tracking
+    pub async fn get_product_batches(&self, product_id: i64, include_empty: bool) -> Result<Vec<ProductBatch>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        let products = sqlx::query_as::<_, Product>(
-            "SELECT * FROM products WHERE quantity <= reorder_level ORDER BY quantity ASC"
+        let batches = sqlx::query_as::<_, ProductBatch>(
+            r#"
+            SELECT * FROM product_batches
+            WHERE product_id = ? AND (? OR quantity > 0)
+            ORDER BY expiry_date IS NULL, expiry_date ASC, received_date ASC, id ASC
+            "#
         )
+        .bind(product_id)
+        .bind(include_empty)
         .fetch_all(pool)
         .await?;
 
-        Ok(products)
+        Ok(batches)
     }
 
-    // Supplier management methods
-    pub async fn get_all_suppliers(&self) -> Result<Vec<Supplier>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    pub async fn receive_batch(&self, user: &UserInfo, product_id: i64, batch: ReceiveBatchRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        let suppliers = sqlx::query_as::<_, Supplier>(
-            "SELECT * FROM suppliers ORDER BY name"
-        )
-        .fetch_all(pool)
-        .await?;
+        if batch.quantity <= 0 {
+            return Err(AppError::validation("quantity", "Batch quantity must be positive").into());
+        }
 
-        Ok(suppliers)
-    }
+        let mut tx = pool.begin().await?;
+        let now = Utc::now();
+        let received_date = batch.received_date.unwrap_or_else(|| now.date_naive());
+        let batch_number = batch.batch_number
+            .filter(|number| !number.trim().is_empty())
+            .unwrap_or_else(|| format!("B{}", now.format("%Y%m%d%H%M%S")));
+
+        let batch_id = Self::insert_batch(&mut tx, product_id, &batch_number, received_date, batch.expiry_date, batch.quantity).await?;
+
+        Self::move_stock(&mut tx, user, StockEntry {
+            product_id,
+            kind: StockMovementKind::Receipt,
+            quantity: batch.quantity,
+            document: Some((StockDocument::Batch, batch_id)),
+            notes: &batch.notes.unwrap_or_else(|| format!("Received batch {}", batch_number)),
+        }).await?;
 
-    pub async fn create_supplier(&self, name: &str, contact_name: Option<&str>, email: Option<&str>, phone: Option<&str>, address: Option<&str>) -> Result<i64> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        tx.commit().await?;
+        Ok(batch_id)
+    }
 
+    async fn insert_batch(conn: &mut SqliteConnection, product_id: i64, batch_number: &str, received_date: NaiveDate, expiry_date: Option<NaiveDate>, quantity: i32) -> Result<i64> {
+        let now = Utc::now();
         let result = sqlx::query(
-            "INSERT INTO suppliers (name, contact_name, email, phone, address) VALUES (?, ?, ?, ?, ?)"
+            "INSERT INTO product_batches (created_at, updated_at, product_id, batch_number, received_date, expiry_date, initial_quantity, quantity) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
         )
-        .bind(name)
-        .bind(contact_name)
-        .bind(email)
-        .bind(phone)
-        .bind(address)
-        .execute(pool)
+        .bind(now)
+        .bind(now)
+        .bind(product_id)
+        .bind(batch_number)
+        .bind(received_date)
+        .bind(expiry_date)
+        .bind(quantity)
+        .bind(quantity)
+        .execute(&mut *conn)
         .await?;
 
         Ok(result.last_insert_rowid())
     }
 
-    pub async fn update_supplier(&self, supplier_id: i64, name: &str, contact_name: Option<&str>, email: Option<&str>, phone: Option<&str>, address: Option<&str>) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
-
-        sqlx::query(
-            "UPDATE suppliers SET name = ?, contact_name = ?, email = ?, phone = ?, address = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+    /// Take `quantity` units out of a product's batches, earliest expiry first
+    /// (batches without an expiry date go last), and return how much was drawn
+    /// from each batch. Stock not held in any batch is left untracked.
+    async fn deplete_batches_fefo(conn: &mut SqliteConnection, product_id: i64, quantity: i32) -> Result<Vec<(i64, i32)>> {
+        let batches = sqlx::query_as::<_, ProductBatch>(
+            r#"
+            SELECT * FROM product_batches
+            WHERE product_id = ? AND quantity > 0
+            ORDER BY expiry_date IS NULL, expiry_date ASC, received_date ASC, id ASC
+            "#
         )
-        .bind(name)
-        .bind(contact_name)
-        .bind(email)
-        .bind(phone)
-        .bind(address)
-        .bind(supplier_id)
-        .execute(pool)
+        .bind(product_id)
+        .fetch_all(&mut *conn)
         .await?;
 
-        Ok(())
-    }
+        let mut remaining = quantity;
+        let mut drawn = Vec::new();
 
-    pub async fn delete_supplier(&self, supplier_id: i64) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        for batch in batches {
+            if remaining <= 0 {
+                break;
+            }
 
-        sqlx::query("DELETE FROM suppliers WHERE id = ?")
-            .bind(supplier_id)
-            .execute(pool)
+            let take = remaining.min(batch.quantity);
+            sqlx::query(
+                "UPDATE product_batches SET quantity = quantity - ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+            )
+            .bind(take)
+            .bind(batch.id)
+            .execute(&mut *conn)
             .await?;
 
-        Ok(())
+            drawn.push((batch.id, take));
+            remaining -= take;
+        }
+
+        Ok(drawn)
     }
 
-    // Inventory movements
-    pub async fn get_inventory_movements(&self, product_id: Option<i64>) -> Result<Vec<InventoryMovement>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    /// Change a product's quantity and write the change to the stock ledger
+    /// with the balance it leaves, returning that balance. Every change to
+    /// `products.quantity` goes through here, so the ledger always adds up.
+    async fn move_stock(conn: &mut SqliteConnection, user: &UserInfo, entry: StockEntry<'_>) -> Result<i32> {
+        if !entry.kind.allows(entry.quantity) {
+            return Err(AppError::validation("quantity", format!("A {} of {} does not fit the ledger", entry.kind.as_str(), entry.quantity)).into());
+        }
 
-        let movements = if let Some(pid) = product_id {
-            sqlx::query_as::<_, InventoryMovement>(
-                "SELECT * FROM inventory_movements WHERE product_id = ? ORDER BY timestamp DESC"
-            )
+        let now = Utc::now();
+        let balance: i32 = sqlx::query_scalar(
+            "UPDATE products SET quantity = quantity + ?, updated_at = ? WHERE id = ? RETURNING quantity"
+        )
+        .bind(entry.quantity)
+        .bind(now)
+        .bind(entry.product_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("Product #{} not found", entry.product_id)))?;
+
+        sqlx::query(
+            "INSERT INTO stock_movements (created_at, updated_at, product_id, quantity, movement_type, notes, user_id, user_name, balance_after, document_type, document_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(entry.product_id)
+        .bind(entry.quantity)
+        .bind(entry.kind)
+        .bind(entry.notes)
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(balance)
+        .bind(entry.document.map(|(document_type, _)| document_type))
+        .bind(entry.document.map(|(_, document_id)| document_id))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(balance)
+    }
+
+    pub async fn get_low_stock_products(&self) -> Result<Vec<Product>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let products = sqlx::query_as::<_, Product>(
+            "SELECT * FROM products WHERE quantity <= reorder_level ORDER BY quantity ASC"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(products)
+    }
+
+    // Settings
+    pub async fn get_setting<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await?;
+
+        match value {
+            Some(value) => Ok(Some(serde_json::from_str(&value)
+                .map_err(|e| AppError::internal(format!("Corrupt {} settings: {}", key, e)))?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_setting<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        sqlx::query(
+            "INSERT INTO app_settings (key, value, updated_at) VALUES (?, ?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at"
+        )
+        .bind(key)
+        .bind(serde_json::to_string(value)?)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Tax configuration
+    pub async fn get_tax_rates(&self) -> Result<Vec<TaxRate>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let rates = sqlx::query_as::<_, TaxRate>("SELECT * FROM tax_rates ORDER BY name")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rates)
+    }
+
+    pub async fn create_tax_rate(&self, rate: &TaxRateRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let now = Utc::now();
+        let result = sqlx::query(
+            "INSERT INTO tax_rates (created_at, updated_at, name, rate, is_compound, is_active) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(rate.name.trim())
+        .bind(rate.rate)
+        .bind(rate.is_compound)
+        .bind(rate.is_active)
+        .execute(pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Changes only affect future orders; sold lines keep their own tax snapshot
+    pub async fn update_tax_rate(&self, rate_id: i64, rate: &TaxRateRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let result = sqlx::query(
+            "UPDATE tax_rates SET name = ?, rate = ?, is_compound = ?, is_active = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(rate.name.trim())
+        .bind(rate.rate)
+        .bind(rate.is_compound)
+        .bind(rate.is_active)
+        .bind(Utc::now())
+        .bind(rate_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Tax rate #{} not found", rate_id)).into());
+        }
+        Ok(())
+    }
+
+    pub async fn get_tax_classes(&self) -> Result<Vec<TaxClass>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut classes = sqlx::query_as::<_, TaxClass>("SELECT * FROM tax_classes ORDER BY name")
+            .fetch_all(pool)
+            .await?;
+
+        for class in &mut classes {
+            class.rates = sqlx::query_as::<_, TaxRate>(
+                r#"
+                SELECT tr.* FROM tax_class_rates tcr
+                JOIN tax_rates tr ON tr.id = tcr.tax_rate_id
+                WHERE tcr.tax_class_id = ?
+                ORDER BY tcr.position
+                "#
+            )
+            .bind(class.id)
+            .fetch_all(pool)
+            .await?;
+        }
+
+        Ok(classes)
+    }
+
+    pub async fn create_tax_class(&self, class: &TaxClassRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let now = Utc::now();
+
+        let result = sqlx::query(
+            "INSERT INTO tax_classes (created_at, updated_at, name, description, prices_include_tax) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(class.name.trim())
+        .bind(class.description.as_deref().unwrap_or(""))
+        .bind(class.prices_include_tax)
+        .execute(&mut *tx)
+        .await?;
+
+        let class_id = result.last_insert_rowid();
+        Self::set_tax_class_rates(&mut tx, class_id, &class.rate_ids).await?;
+
+        tx.commit().await?;
+        Ok(class_id)
+    }
+
+    pub async fn update_tax_class(&self, class_id: i64, class: &TaxClassRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE tax_classes SET name = ?, description = ?, prices_include_tax = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(class.name.trim())
+        .bind(class.description.as_deref().unwrap_or(""))
+        .bind(class.prices_include_tax)
+        .bind(Utc::now())
+        .bind(class_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Tax class #{} not found", class_id)).into());
+        }
+
+        Self::set_tax_class_rates(&mut tx, class_id, &class.rate_ids).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn set_tax_class_rates(conn: &mut SqliteConnection, class_id: i64, rate_ids: &[i64]) -> Result<()> {
+        sqlx::query("DELETE FROM tax_class_rates WHERE tax_class_id = ?")
+            .bind(class_id)
+            .execute(&mut *conn)
+            .await?;
+
+        for (position, rate_id) in rate_ids.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO tax_class_rates (tax_class_id, tax_rate_id, position) VALUES (?, ?, ?)"
+            )
+            .bind(class_id)
+            .bind(rate_id)
+            .bind(position as i64)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// The active rates of a product's tax class, in stacking order, and
+    /// whether its price includes them. Products without a class are untaxed.
+    async fn product_tax_rules(conn: &mut SqliteConnection, product_id: i64) -> Result<(Vec<TaxRule>, bool)> {
+        let class = sqlx::query(
+            r#"
+            SELECT tc.id, tc.prices_include_tax FROM products p
+            JOIN tax_classes tc ON tc.id = p.tax_class_id
+            WHERE p.id = ?
+            "#
+        )
+        .bind(product_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(class) = class else {
+            return Ok((Vec::new(), false));
+        };
+
+        let rates = sqlx::query_as::<_, TaxRate>(
+            r#"
+            SELECT tr.* FROM tax_class_rates tcr
+            JOIN tax_rates tr ON tr.id = tcr.tax_rate_id
+            WHERE tcr.tax_class_id = ? AND tr.is_active = 1
+            ORDER BY tcr.position
+            "#
+        )
+        .bind(class.get::<i64, _>("id"))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok((rates.iter().map(TaxRule::from).collect(), class.get("prices_include_tax")))
+    }
+
+    // Promotions
+    pub async fn get_promotions(&self) -> Result<Vec<Promotion>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let promotions = sqlx::query_as::<_, Promotion>(
+            "SELECT * FROM promotions ORDER BY is_active DESC, priority DESC, id ASC"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(promotions)
+    }
+
+    pub async fn create_promotion(&self, promotion: &PromotionRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let now = Utc::now();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO promotions (created_at, updated_at, name, description, promotion_type, scope, value, amount, product_id, category,
+                                    buy_quantity, get_quantity, min_order_amount, coupon_code, starts_at, ends_at, priority, is_active)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(now)
+        .bind(now)
+        .bind(promotion.name.trim())
+        .bind(promotion.description.as_deref().unwrap_or(""))
+        .bind(promotion.promotion_type)
+        .bind(promotion.scope)
+        .bind(promotion.value)
+        .bind(promotion.amount)
+        .bind(promotion.product_id)
+        .bind(&promotion.category)
+        .bind(promotion.buy_quantity)
+        .bind(promotion.get_quantity)
+        .bind(promotion.min_order_amount)
+        .bind(promotion.coupon_code.as_deref().map(normalize_coupon_code))
+        .bind(promotion.starts_at)
+        .bind(promotion.ends_at)
+        .bind(promotion.priority)
+        .bind(promotion.is_active)
+        .execute(pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Orders already priced keep the discount recorded for them
+    pub async fn update_promotion(&self, promotion_id: i64, promotion: &PromotionRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE promotions SET name = ?, description = ?, promotion_type = ?, scope = ?, value = ?, amount = ?, product_id = ?, category = ?,
+                   buy_quantity = ?, get_quantity = ?, min_order_amount = ?, coupon_code = ?, starts_at = ?, ends_at = ?,
+                   priority = ?, is_active = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(promotion.name.trim())
+        .bind(promotion.description.as_deref().unwrap_or(""))
+        .bind(promotion.promotion_type)
+        .bind(promotion.scope)
+        .bind(promotion.value)
+        .bind(promotion.amount)
+        .bind(promotion.product_id)
+        .bind(&promotion.category)
+        .bind(promotion.buy_quantity)
+        .bind(promotion.get_quantity)
+        .bind(promotion.min_order_amount)
+        .bind(promotion.coupon_code.as_deref().map(normalize_coupon_code))
+        .bind(promotion.starts_at)
+        .bind(promotion.ends_at)
+        .bind(promotion.priority)
+        .bind(promotion.is_active)
+        .bind(Utc::now())
+        .bind(promotion_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Promotion #{} not found", promotion_id)).into());
+        }
+        Ok(())
+    }
+
+    // Customers
+    pub async fn get_customers(&self, search: Option<&str>) -> Result<Vec<Customer>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let search = search.map(str::trim).unwrap_or("");
+        let digits = normalize_phone(search);
+        let customers = sqlx::query_as::<_, Customer>(
+            r#"
+            SELECT * FROM customers
+            WHERE ?1 = ''
+               OR name LIKE '%' || ?1 || '%'
+               OR email LIKE '%' || ?1 || '%'
+               OR (?2 != '' AND phone LIKE '%' || ?2 || '%')
+            ORDER BY name
+            "#
+        )
+        .bind(search)
+        .bind(digits)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(customers)
+    }
+
+    pub async fn get_customer(&self, customer_id: i64) -> Result<Customer> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let customer = sqlx::query_as::<_, Customer>("SELECT * FROM customers WHERE id = ?")
+            .bind(customer_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Customer #{} not found", customer_id)))?;
+
+        Ok(customer)
+    }
+
+    /// Find a customer by an email address or phone number, however it was typed
+    pub async fn lookup_customer(&self, contact: &str) -> Result<Option<Customer>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let query = if contact.contains('@') {
+            sqlx::query_as::<_, Customer>("SELECT * FROM customers WHERE email = ?").bind(normalize_email(contact))
+        } else {
+            sqlx::query_as::<_, Customer>("SELECT * FROM customers WHERE phone = ?").bind(normalize_phone(contact))
+        };
+        let customer = query.fetch_optional(pool).await?;
+
+        Ok(customer)
+    }
+
+    pub async fn create_customer(&self, customer: &CustomerRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let now = Utc::now();
+        let result = sqlx::query(
+            "INSERT INTO customers (created_at, updated_at, name, email, phone, address, notes) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(customer.name.trim())
+        .bind(normalize_email(customer.email.as_deref().unwrap_or("")))
+        .bind(normalize_phone(customer.phone.as_deref().unwrap_or("")))
+        .bind(customer.address.as_deref().unwrap_or("").trim())
+        .bind(customer.notes.as_deref().unwrap_or("").trim())
+        .execute(pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn update_customer(&self, customer_id: i64, customer: &CustomerRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let result = sqlx::query(
+            "UPDATE customers SET name = ?, email = ?, phone = ?, address = ?, notes = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(customer.name.trim())
+        .bind(normalize_email(customer.email.as_deref().unwrap_or("")))
+        .bind(normalize_phone(customer.phone.as_deref().unwrap_or("")))
+        .bind(customer.address.as_deref().unwrap_or("").trim())
+        .bind(customer.notes.as_deref().unwrap_or("").trim())
+        .bind(Utc::now())
+        .bind(customer_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Customer #{} not found", customer_id)).into());
+        }
+        Ok(())
+    }
+
+    /// Customers with orders cannot be deleted, so their history is kept
+    pub async fn delete_customer(&self, customer_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE customer_id = ?")
+            .bind(customer_id)
+            .fetch_one(pool)
+            .await?;
+        if orders > 0 {
+            return Err(AppError::conflict(format!("Customer #{} has {} orders on record", customer_id, orders)).into());
+        }
+
+        sqlx::query("DELETE FROM customers WHERE id = ?")
+            .bind(customer_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_customer_history(&self, customer_id: i64) -> Result<CustomerHistory> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let customer = self.get_customer(customer_id).await?;
+
+        let summary = sqlx::query_as::<_, CustomerSummary>(
+            r#"
+            SELECT COUNT(*) AS orders,
+                   COALESCE(SUM(o.total_amount), 0) - COALESCE(SUM(r.refunded), 0) AS lifetime_spend,
+                   COALESCE(SUM(r.refunded), 0) AS refunded,
+                   MIN(o.created_at) AS first_purchase_at,
+                   MAX(o.created_at) AS last_purchase_at
+            FROM orders o
+            LEFT JOIN (
+                SELECT order_id, SUM(refund_total) AS refunded FROM order_returns GROUP BY order_id
+            ) r ON r.order_id = o.id
+            WHERE o.customer_id = ?
+              AND o.status IN ('completed', 'partially_refunded', 'refunded')
+            "#
+        )
+        .bind(customer_id)
+        .fetch_one(pool)
+        .await?;
+
+        let purchases = sqlx::query_as::<_, CustomerPurchaseLine>(
+            r#"
+            SELECT o.id AS order_id,
+                   o.created_at,
+                   o.status,
+                   oi.id AS order_item_id,
+                   oi.product_id,
+                   p.name AS product_name,
+                   p.sku,
+                   oi.quantity,
+                   CAST(COALESCE((SELECT SUM(ri.quantity) FROM order_return_items ri WHERE ri.order_item_id = oi.id), 0) AS INTEGER) AS returned_quantity,
+                   oi.line_total
+            FROM orders o
+            JOIN order_items oi ON oi.order_id = o.id
+            JOIN products p ON p.id = oi.product_id
+            WHERE o.customer_id = ?
+              AND o.status IN ('completed', 'partially_refunded', 'refunded')
+            ORDER BY o.created_at DESC, oi.id
+            "#
+        )
+        .bind(customer_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(CustomerHistory { customer, summary, purchases })
+    }
+
+    // Loyalty points
+    pub async fn get_loyalty_account(&self, customer_id: i64) -> Result<LoyaltyAccount> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let settings = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+        self.get_customer(customer_id).await?;
+
+        let now = Utc::now();
+        let mut conn = pool.acquire().await?;
+        let balance = Self::loyalty_balance(&mut conn, customer_id, now).await?;
+
+        let next_expiry = sqlx::query_scalar(
+            "SELECT expires_at FROM loyalty_ledger WHERE customer_id = ? AND remaining > 0 AND expires_at > ? ORDER BY expires_at LIMIT 1"
+        )
+        .bind(customer_id)
+        .bind(now)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let entries = sqlx::query_as::<_, LoyaltyEntry>(
+            "SELECT * FROM loyalty_ledger WHERE customer_id = ? ORDER BY id DESC"
+        )
+        .bind(customer_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(LoyaltyAccount {
+            customer_id,
+            balance,
+            balance_value: settings.value_of(balance),
+            next_expiry,
+            entries,
+        })
+    }
+
+    /// Points a customer can spend: what is left of their unexpired lots
+    async fn loyalty_balance(conn: &mut SqliteConnection, customer_id: i64, now: DateTime<Utc>) -> Result<i64> {
+        let balance = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(remaining), 0) FROM loyalty_ledger WHERE customer_id = ? AND remaining > 0 AND (expires_at IS NULL OR expires_at > ?)"
+        )
+        .bind(customer_id)
+        .bind(now)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(balance)
+    }
+
+    async fn insert_loyalty_entry(conn: &mut SqliteConnection, movement: &PointsMovement<'_>, points: i64, remaining: i64, expires_at: Option<DateTime<Utc>>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO loyalty_ledger (created_at, customer_id, kind, points, remaining, expires_at, order_id, order_payment_id, return_id, user_id, notes) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(Utc::now())
+        .bind(movement.customer_id)
+        .bind(movement.kind)
+        .bind(points)
+        .bind(remaining)
+        .bind(expires_at)
+        .bind(movement.order_id)
+        .bind(movement.order_payment_id)
+        .bind(movement.return_id)
+        .bind(movement.user_id)
+        .bind(movement.notes)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Add a lot of points to a customer's balance
+    async fn credit_points(conn: &mut SqliteConnection, movement: &PointsMovement<'_>, expires_at: Option<DateTime<Utc>>) -> Result<()> {
+        if movement.points > 0 {
+            Self::insert_loyalty_entry(conn, movement, movement.points, movement.points, expires_at).await?;
+        }
+        Ok(())
+    }
+
+    /// Draw up to `movement.points` from a customer's unexpired lots, soonest
+    /// expiry first. The balance never goes below zero, so fewer points may
+    /// be taken; returns how many were.
+    async fn debit_points(conn: &mut SqliteConnection, movement: &PointsMovement<'_>, now: DateTime<Utc>) -> Result<i64> {
+        let lots: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT id, remaining FROM loyalty_ledger
+            WHERE customer_id = ? AND remaining > 0 AND (expires_at IS NULL OR expires_at > ?)
+            ORDER BY expires_at IS NULL, expires_at, id
+            "#
+        )
+        .bind(movement.customer_id)
+        .bind(now)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut left = movement.points;
+        for (lot_id, remaining) in lots {
+            if left <= 0 {
+                break;
+            }
+
+            let take = left.min(remaining);
+            sqlx::query("UPDATE loyalty_ledger SET remaining = remaining - ? WHERE id = ?")
+                .bind(take)
+                .bind(lot_id)
+                .execute(&mut *conn)
+                .await?;
+
+            left -= take;
+        }
+
+        let taken = movement.points - left.max(0);
+        if taken > 0 {
+            Self::insert_loyalty_entry(conn, movement, -taken, 0, None).await?;
+        }
+        Ok(taken)
+    }
+
+    /// Give back points redeemed on an order that have not been given back
+    /// already, for one of its payments or all of them
+    async fn restore_redeemed_points(conn: &mut SqliteConnection, user: &UserInfo, order_id: i64, payment_id: Option<i64>, expires_at: Option<DateTime<Utc>>, notes: &str) -> Result<()> {
+        let outstanding: Vec<(i64, i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT customer_id, order_payment_id, -SUM(points) AS outstanding
+            FROM loyalty_ledger
+            WHERE order_id = ? AND kind IN ('redeem', 'restore') AND order_payment_id IS NOT NULL
+              AND (? IS NULL OR order_payment_id = ?)
+            GROUP BY customer_id, order_payment_id
+            HAVING outstanding > 0
+            "#
+        )
+        .bind(order_id)
+        .bind(payment_id)
+        .bind(payment_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for (customer_id, order_payment_id, points) in outstanding {
+            Self::credit_points(conn, &PointsMovement {
+                customer_id,
+                kind: LoyaltyKind::Restore,
+                points,
+                order_id: Some(order_id),
+                order_payment_id: Some(order_payment_id),
+                return_id: None,
+                user_id: Some(user.id),
+                notes,
+            }, expires_at).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write off every lot whose expiry has passed
+    pub async fn expire_loyalty_points(&self) -> Result<u64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let now = Utc::now();
+        let mut tx = pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO loyalty_ledger (created_at, customer_id, kind, points, order_id, notes)
+            SELECT ?, customer_id, 'expire', -remaining, order_id, 'Lot #' || id || ' expired'
+            FROM loyalty_ledger
+            WHERE remaining > 0 AND expires_at <= ?
+            "#
+        )
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE loyalty_ledger SET remaining = 0 WHERE remaining > 0 AND expires_at <= ?")
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    // Gift cards and store credit
+    pub async fn lookup_gift_card(&self, code: &str) -> Result<GiftCardDetails> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut conn = pool.acquire().await?;
+        let card = Self::gift_card_by_code(&mut conn, code).await?;
+
+        let entries = sqlx::query_as::<_, GiftCardEntry>(
+            "SELECT * FROM gift_card_ledger WHERE gift_card_id = ? ORDER BY id DESC"
+        )
+        .bind(card.id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(GiftCardDetails { card, entries })
+    }
+
+    pub async fn get_customer_gift_cards(&self, customer_id: i64) -> Result<Vec<GiftCard>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let cards = sqlx::query_as::<_, GiftCard>(
+            "SELECT * FROM gift_card_balances WHERE customer_id = ? ORDER BY id DESC"
+        )
+        .bind(customer_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(cards)
+    }
+
+    pub async fn get_gift_card(&self, gift_card_id: i64) -> Result<GiftCard> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let card = sqlx::query_as::<_, GiftCard>("SELECT * FROM gift_card_balances WHERE id = ?")
+            .bind(gift_card_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Gift card #{} not found", gift_card_id)))?;
+
+        Ok(card)
+    }
+
+    async fn gift_card_by_code(conn: &mut SqliteConnection, code: &str) -> Result<GiftCard> {
+        let code = normalize_card_code(code);
+        let card = sqlx::query_as::<_, GiftCard>("SELECT * FROM gift_card_balances WHERE code = ?")
+            .bind(&code)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("No gift card with code {}", code)))?;
+
+        Ok(card)
+    }
+
+    async fn record_card_entry(conn: &mut SqliteConnection, movement: &CardMovement<'_>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO gift_card_ledger (created_at, gift_card_id, kind, amount, order_id, order_payment_id, return_id, user_id, notes) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(Utc::now())
+        .bind(movement.gift_card_id)
+        .bind(movement.kind)
+        .bind(movement.amount)
+        .bind(movement.order_id)
+        .bind(movement.order_payment_id)
+        .bind(movement.return_id)
+        .bind(movement.user_id)
+        .bind(movement.notes)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Issue store credit for `amount` in place of a cash refund on an order
+    /// or one of its returns
+    async fn issue_store_credit(conn: &mut SqliteConnection, user: &UserInfo, order_id: i64, return_id: Option<i64>, amount: Money) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO gift_cards (created_at, code, kind, status, customer_id, initial_amount, issued_order_id, issued_return_id)
+            VALUES (?, ?, 'store_credit', 'active', (SELECT customer_id FROM orders WHERE id = ?), ?, ?, ?)
+            "#
+        )
+        .bind(Utc::now())
+        .bind(generate_card_code())
+        .bind(order_id)
+        .bind(amount)
+        .bind(order_id)
+        .bind(return_id)
+        .execute(&mut *conn)
+        .await?;
+
+        let gift_card_id = result.last_insert_rowid();
+        let notes = match return_id {
+            Some(return_id) => format!("Return #{} - Order #{}", return_id, order_id),
+            None => format!("Order #{} voided", order_id),
+        };
+        Self::record_card_entry(conn, &CardMovement {
+            gift_card_id,
+            kind: GiftCardEntryKind::Issue,
+            amount,
+            order_id: Some(order_id),
+            order_payment_id: None,
+            return_id,
+            user_id: Some(user.id),
+            notes: &notes,
+        }).await?;
+
+        Ok(gift_card_id)
+    }
+
+    /// Put back what was drawn from cards on an order and not put back
+    /// already, for one of its payments or all of them
+    async fn refund_card_redemptions(conn: &mut SqliteConnection, user: &UserInfo, order_id: i64, payment_id: Option<i64>, notes: &str) -> Result<()> {
+        let outstanding: Vec<(i64, i64, Money)> = sqlx::query_as(
+            r#"
+            SELECT gift_card_id, order_payment_id, -SUM(amount) AS outstanding
+            FROM gift_card_ledger
+            WHERE order_id = ? AND kind IN ('redeem', 'refund') AND order_payment_id IS NOT NULL
+              AND (? IS NULL OR order_payment_id = ?)
+            GROUP BY gift_card_id, order_payment_id
+            HAVING outstanding > 0
+            "#
+        )
+        .bind(order_id)
+        .bind(payment_id)
+        .bind(payment_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for (gift_card_id, order_payment_id, amount) in outstanding {
+            Self::record_card_entry(conn, &CardMovement {
+                gift_card_id,
+                kind: GiftCardEntryKind::Refund,
+                amount,
+                order_id: Some(order_id),
+                order_payment_id: Some(order_payment_id),
+                return_id: None,
+                user_id: Some(user.id),
+                notes,
+            }).await?;
+        }
+
+        Ok(())
+    }
+
+    // Supplier management methods
+    pub async fn get_all_suppliers(&self) -> Result<Vec<Supplier>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let suppliers = sqlx::query_as::<_, Supplier>(
+            "SELECT * FROM suppliers ORDER BY name"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(suppliers)
+    }
+
+    pub async fn create_supplier(&self, name: &str, contact_name: Option<&str>, email: Option<&str>, phone: Option<&str>, address: Option<&str>) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let now = Utc::now();
+        let result = sqlx::query(
+            "INSERT INTO suppliers (created_at, updated_at, name, contact_name, email, phone, address) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(name)
+        .bind(contact_name.unwrap_or(""))
+        .bind(email.unwrap_or(""))
+        .bind(phone.unwrap_or(""))
+        .bind(address.unwrap_or(""))
+        .execute(pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn update_supplier(&self, supplier_id: i64, name: &str, contact_name: Option<&str>, email: Option<&str>, phone: Option<&str>, address: Option<&str>) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        sqlx::query(
+            "UPDATE suppliers SET name = ?, contact_name = ?, email = ?, phone = ?, address = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(name)
+        .bind(contact_name.unwrap_or(""))
+        .bind(email.unwrap_or(""))
+        .bind(phone.unwrap_or(""))
+        .bind(address.unwrap_or(""))
+        .bind(supplier_id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_supplier(&self, supplier_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        sqlx::query("DELETE FROM suppliers WHERE id = ?")
+            .bind(supplier_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// `None` falls back to the default lead time in the reorder settings
+    pub async fn set_supplier_lead_time(&self, supplier_id: i64, lead_time_days: Option<u32>) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let result = sqlx::query("UPDATE suppliers SET lead_time_days = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(lead_time_days)
+            .bind(supplier_id)
+            .execute(pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Supplier #{} not found", supplier_id)).into());
+        }
+
+        Ok(())
+    }
+
+    /// Every product with its sales since `since` and the stock still due on
+    /// open purchase orders, drafts included
+    pub async fn get_reorder_candidates(&self, since: DateTime<Utc>) -> Result<Vec<ReorderCandidate>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let candidates = sqlx::query_as::<_, ReorderCandidate>(
+            r#"
+            SELECT p.id AS product_id, p.name AS product_name, p.sku, p.supplier_id,
+                   s.name AS supplier_name, s.lead_time_days,
+                   p.quantity, p.reorder_level, p.cost,
+                   COALESCE((
+                       SELECT SUM(oi.quantity)
+                       FROM order_items oi
+                       JOIN orders o ON o.id = oi.order_id
+                       WHERE oi.product_id = p.id
+                         AND o.status IN ('completed', 'partially_refunded', 'refunded')
+                         AND datetime(o.created_at) >= ?
+                   ), 0) AS units_sold,
+                   COALESCE((
+                       SELECT SUM(l.quantity_ordered - l.quantity_received)
+                       FROM purchase_order_lines l
+                       JOIN purchase_orders po ON po.id = l.purchase_order_id
+                       WHERE l.product_id = p.id
+                         AND po.status IN ('draft', 'sent', 'partially_received')
+                   ), 0) AS on_order
+            FROM products p
+            LEFT JOIN suppliers s ON s.id = p.supplier_id
+            ORDER BY p.name
+            "#
+        )
+        .bind(since.format("%Y-%m-%d %H:%M:%S").to_string())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(candidates)
+    }
+
+    // Purchase orders
+    async fn purchase_order_status(conn: &mut SqliteConnection, purchase_order_id: i64) -> Result<PurchaseOrderStatus> {
+        let status = sqlx::query_scalar("SELECT status FROM purchase_orders WHERE id = ?")
+            .bind(purchase_order_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Purchase order #{} not found", purchase_order_id)))?;
+
+        Ok(status)
+    }
+
+    async fn insert_purchase_order_lines(conn: &mut SqliteConnection, purchase_order_id: i64, order: &PurchaseOrderRequest) -> Result<()> {
+        let supplier_exists: Option<i64> = sqlx::query_scalar("SELECT id FROM suppliers WHERE id = ?")
+            .bind(order.supplier_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if supplier_exists.is_none() {
+            return Err(AppError::not_found(format!("Supplier #{} not found", order.supplier_id)).into());
+        }
+
+        for line in &order.lines {
+            let product_exists: Option<i64> = sqlx::query_scalar("SELECT id FROM products WHERE id = ?")
+                .bind(line.product_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+            if product_exists.is_none() {
+                return Err(AppError::not_found(format!("Product #{} not found", line.product_id)).into());
+            }
+
+            sqlx::query(
+                "INSERT INTO purchase_order_lines (purchase_order_id, product_id, quantity_ordered, unit_cost) VALUES (?, ?, ?, ?)"
+            )
+            .bind(purchase_order_id)
+            .bind(line.product_id)
+            .bind(line.quantity)
+            .bind(line.unit_cost)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_purchase_order(&self, user: &UserInfo, order: &PurchaseOrderRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let currency = self.get_setting::<CurrencySettings>(CURRENCY_SETTINGS_KEY).await?.unwrap_or_default();
+        let mut tx = pool.begin().await?;
+        let now = Utc::now();
+
+        let result = sqlx::query(
+            "INSERT INTO purchase_orders (created_at, updated_at, supplier_id, status, expected_date, notes, currency, created_by, created_by_name) VALUES (?, ?, ?, 'draft', ?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(order.supplier_id)
+        .bind(order.expected_date)
+        .bind(order.notes.as_deref().unwrap_or("").trim())
+        .bind(&currency.code)
+        .bind(user.id)
+        .bind(&user.full_name)
+        .execute(&mut *tx)
+        .await?;
+
+        let purchase_order_id = result.last_insert_rowid();
+        Self::insert_purchase_order_lines(&mut tx, purchase_order_id, order).await?;
+
+        tx.commit().await?;
+        Ok(purchase_order_id)
+    }
+
+    pub async fn update_purchase_order(&self, purchase_order_id: i64, order: &PurchaseOrderRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        if Self::purchase_order_status(&mut tx, purchase_order_id).await? != PurchaseOrderStatus::Draft {
+            return Err(AppError::conflict(format!("{} has been sent and can no longer be changed", po_number(purchase_order_id))).into());
+        }
+
+        sqlx::query(
+            "UPDATE purchase_orders SET supplier_id = ?, expected_date = ?, notes = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(order.supplier_id)
+        .bind(order.expected_date)
+        .bind(order.notes.as_deref().unwrap_or("").trim())
+        .bind(Utc::now())
+        .bind(purchase_order_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM purchase_order_lines WHERE purchase_order_id = ?")
+            .bind(purchase_order_id)
+            .execute(&mut *tx)
+            .await?;
+        Self::insert_purchase_order_lines(&mut tx, purchase_order_id, order).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn send_purchase_order(&self, purchase_order_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let status = Self::purchase_order_status(&mut tx, purchase_order_id).await?;
+        status.check_transition(purchase_order_id, PurchaseOrderStatus::Sent)?;
+
+        let now = Utc::now();
+        sqlx::query("UPDATE purchase_orders SET status = ?, sent_at = ?, updated_at = ? WHERE id = ?")
+            .bind(PurchaseOrderStatus::Sent)
+            .bind(now)
+            .bind(now)
+            .bind(purchase_order_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Book received quantities into stock as new batches, moving each
+    /// product's cost towards what the delivery cost
+    pub async fn receive_purchase_order(&self, user: &UserInfo, purchase_order_id: i64, receipt: &ReceivePurchaseOrderRequest) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let status = Self::purchase_order_status(&mut tx, purchase_order_id).await?;
+        status.check_transition(purchase_order_id, PurchaseOrderStatus::PartiallyReceived)?;
+
+        let now = Utc::now();
+        let number = po_number(purchase_order_id);
+        for received in &receipt.lines {
+            let (product_id, quantity_ordered, quantity_received, expected_cost): (i64, i32, i32, Money) = sqlx::query_as(
+                "SELECT product_id, quantity_ordered, quantity_received, unit_cost FROM purchase_order_lines WHERE id = ? AND purchase_order_id = ?"
+            )
+            .bind(received.line_id)
+            .bind(purchase_order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Line #{} is not on {}", received.line_id, number)))?;
+
+            let outstanding = quantity_ordered - quantity_received;
+            if received.quantity > outstanding {
+                return Err(AppError::validation("lines", format!("Only {} more of line #{} are on order", outstanding, received.line_id)).into());
+            }
+
+            let unit_cost = received.unit_cost.unwrap_or(expected_cost);
+            let (on_hand, cost): (i32, Money) = sqlx::query_as("SELECT quantity, cost FROM products WHERE id = ?")
+                .bind(product_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+            // Batches are named after the order, numbering later deliveries of a line
+            let deliveries: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM purchase_order_receipts WHERE line_id = ?")
+                .bind(received.line_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            let batch_number = match received.batch_number.as_deref().map(str::trim) {
+                Some(batch_number) if !batch_number.is_empty() => batch_number.to_string(),
+                _ if deliveries == 0 => number.clone(),
+                _ => format!("{}/{}", number, deliveries + 1),
+            };
+            let batch_id = Self::insert_batch(&mut tx, product_id, &batch_number, now.date_naive(), received.expiry_date, received.quantity).await?;
+
+            sqlx::query("UPDATE products SET cost = ? WHERE id = ?")
+                .bind(average_cost(on_hand, cost, received.quantity, unit_cost))
+                .bind(product_id)
+                .execute(&mut *tx)
+                .await?;
+
+            Self::move_stock(&mut tx, user, StockEntry {
+                product_id,
+                kind: StockMovementKind::Receipt,
+                quantity: received.quantity,
+                document: Some((StockDocument::PurchaseOrder, purchase_order_id)),
+                notes: &format!("Received on {}", number),
+            }).await?;
+
+            sqlx::query("UPDATE purchase_order_lines SET quantity_received = quantity_received + ? WHERE id = ?")
+                .bind(received.quantity)
+                .bind(received.line_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(
+                "INSERT INTO purchase_order_receipts (created_at, purchase_order_id, line_id, product_id, batch_id, quantity, unit_cost, user_id, user_name) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(now)
+            .bind(purchase_order_id)
+            .bind(received.line_id)
+            .bind(product_id)
+            .bind(batch_id)
+            .bind(received.quantity)
+            .bind(unit_cost)
+            .bind(user.id)
+            .bind(&user.full_name)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let outstanding: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(quantity_ordered - quantity_received), 0) FROM purchase_order_lines WHERE purchase_order_id = ?"
+        )
+        .bind(purchase_order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let status = if outstanding > 0 { PurchaseOrderStatus::PartiallyReceived } else { PurchaseOrderStatus::Received };
+
+        sqlx::query("UPDATE purchase_orders SET status = ?, updated_at = ? WHERE id = ?")
+            .bind(status)
+            .bind(now)
+            .bind(purchase_order_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn close_purchase_order(&self, purchase_order_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let status = Self::purchase_order_status(&mut tx, purchase_order_id).await?;
+        status.check_transition(purchase_order_id, PurchaseOrderStatus::Closed)?;
+
+        let now = Utc::now();
+        sqlx::query("UPDATE purchase_orders SET status = ?, closed_at = ?, updated_at = ? WHERE id = ?")
+            .bind(PurchaseOrderStatus::Closed)
+            .bind(now)
+            .bind(now)
+            .bind(purchase_order_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Newest first
+    pub async fn get_purchase_orders(&self, status: Option<PurchaseOrderStatus>, supplier_id: Option<i64>) -> Result<Vec<PurchaseOrder>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let orders = sqlx::query_as::<_, PurchaseOrder>(
+            r#"
+            SELECT * FROM purchase_order_summaries
+            WHERE (? IS NULL OR status = ?)
+              AND (? IS NULL OR supplier_id = ?)
+            ORDER BY created_at DESC, id DESC
+            "#
+        )
+        .bind(status)
+        .bind(status)
+        .bind(supplier_id)
+        .bind(supplier_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(orders)
+    }
+
+    pub async fn get_purchase_order(&self, purchase_order_id: i64) -> Result<PurchaseOrderDetails> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let purchase_order = sqlx::query_as::<_, PurchaseOrder>("SELECT * FROM purchase_order_summaries WHERE id = ?")
+            .bind(purchase_order_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Purchase order #{} not found", purchase_order_id)))?;
+
+        let supplier = sqlx::query_as::<_, Supplier>("SELECT * FROM suppliers WHERE id = ?")
+            .bind(purchase_order.supplier_id)
+            .fetch_one(pool)
+            .await?;
+
+        let lines = sqlx::query_as::<_, PurchaseOrderLine>(
+            r#"
+            SELECT l.id, l.purchase_order_id, l.product_id, p.name AS product_name, p.sku,
+                   l.quantity_ordered, l.quantity_received, l.unit_cost
+            FROM purchase_order_lines l
+            JOIN products p ON p.id = l.product_id
+            WHERE l.purchase_order_id = ?
+            ORDER BY l.id
+            "#
+        )
+        .bind(purchase_order_id)
+        .fetch_all(pool)
+        .await?;
+
+        let receipts = sqlx::query_as::<_, PurchaseOrderReceipt>("SELECT * FROM purchase_order_receipts WHERE purchase_order_id = ? ORDER BY id")
+            .bind(purchase_order_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(PurchaseOrderDetails { purchase_order, supplier, lines, receipts })
+    }
+
+    // Stocktakes
+    async fn stocktake_status(conn: &mut SqliteConnection, stocktake_id: i64) -> Result<StocktakeStatus> {
+        let status = sqlx::query_scalar("SELECT status FROM stocktakes WHERE id = ?")
+            .bind(stocktake_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Stocktake #{} not found", stocktake_id)))?;
+
+        Ok(status)
+    }
+
+    /// Freeze the quantity and cost of every product in scope. A product can
+    /// only be in one stocktake at a time, or its variance would be posted twice.
+    pub async fn create_stocktake(&self, user: &UserInfo, request: &StocktakeRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let category = request.category.as_deref().map(str::trim).filter(|category| !category.is_empty());
+        let location = request.location.as_deref().map(str::trim).filter(|location| !location.is_empty());
+        let mut tx = pool.begin().await?;
+
+        let overlapping: Option<(i64, String)> = sqlx::query_as(
+            r#"
+            SELECT s.id, p.name
+            FROM stocktake_lines l
+            JOIN stocktakes s ON s.id = l.stocktake_id
+            JOIN products p ON p.id = l.product_id
+            WHERE s.status = 'counting'
+              AND (? IS NULL OR p.category = ?)
+              AND (? IS NULL OR p.location = ?)
+            LIMIT 1
+            "#
+        )
+        .bind(category)
+        .bind(category)
+        .bind(location)
+        .bind(location)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some((stocktake_id, product_name)) = overlapping {
+            return Err(AppError::conflict(format!("{} is already being counted in {}", product_name, stocktake_number(stocktake_id))).into());
+        }
+
+        let now = Utc::now();
+        let result = sqlx::query(
+            "INSERT INTO stocktakes (created_at, updated_at, status, category, location, notes, started_by, started_by_name) VALUES (?, ?, 'counting', ?, ?, ?, ?, ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(category)
+        .bind(location)
+        .bind(request.notes.as_deref().unwrap_or("").trim())
+        .bind(user.id)
+        .bind(&user.full_name)
+        .execute(&mut *tx)
+        .await?;
+        let stocktake_id = result.last_insert_rowid();
+
+        let frozen = sqlx::query(
+            r#"
+            INSERT INTO stocktake_lines (stocktake_id, product_id, expected_quantity, unit_cost)
+            SELECT ?, id, quantity, cost FROM products
+            WHERE (? IS NULL OR category = ?)
+              AND (? IS NULL OR location = ?)
+            "#
+        )
+        .bind(stocktake_id)
+        .bind(category)
+        .bind(category)
+        .bind(location)
+        .bind(location)
+        .execute(&mut *tx)
+        .await?;
+        if frozen.rows_affected() == 0 {
+            return Err(AppError::validation("category", "No products match this category and location").into());
+        }
+
+        tx.commit().await?;
+        Ok(stocktake_id)
+    }
+
+    /// Add `quantity` to the tally for a product in an open stocktake; a
+    /// negative entry corrects a miscount but cannot take the tally below zero
+    pub async fn record_stocktake_count(&self, user: &UserInfo, stocktake_id: i64, product_id: i64, quantity: i32, method: CountMethod, device_id: &str) -> Result<StocktakeLine> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        Self::stocktake_status(&mut tx, stocktake_id).await?.check_open(stocktake_id)?;
+
+        let line = sqlx::query_as::<_, StocktakeLine>("SELECT * FROM stocktake_line_details WHERE stocktake_id = ? AND product_id = ?")
+            .bind(stocktake_id)
+            .bind(product_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::validation("product_id", format!("This product is not part of {}", stocktake_number(stocktake_id))))?;
+        if line.counted_quantity.unwrap_or(0) + quantity < 0 {
+            return Err(AppError::validation("quantity", format!("Only {} of {} have been counted", line.counted_quantity.unwrap_or(0), line.product_name)).into());
+        }
+
+        sqlx::query(
+            "INSERT INTO stocktake_counts (created_at, stocktake_id, line_id, quantity, method, device_id, user_id, user_name) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(Utc::now())
+        .bind(stocktake_id)
+        .bind(line.id)
+        .bind(quantity)
+        .bind(method)
+        .bind(device_id)
+        .bind(user.id)
+        .bind(&user.full_name)
+        .execute(&mut *tx)
+        .await?;
+
+        let line = sqlx::query_as::<_, StocktakeLine>("SELECT * FROM stocktake_line_details WHERE id = ?")
+            .bind(line.id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(line)
+    }
+
+    /// Book every counted line's variance from the frozen quantity as an
+    /// adjustment, all or nothing. Lines nobody counted are left as they are.
+    pub async fn post_stocktake(&self, user: &UserInfo, stocktake_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let status = Self::stocktake_status(&mut tx, stocktake_id).await?;
+        status.check_transition(stocktake_id, StocktakeStatus::Posted)?;
+
+        let lines = sqlx::query_as::<_, StocktakeLine>(
+            "SELECT * FROM stocktake_line_details WHERE stocktake_id = ? AND variance <> 0 ORDER BY id"
+        )
+        .bind(stocktake_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let number = stocktake_number(stocktake_id);
+        for line in &lines {
+            let (Some(counted), Some(variance)) = (line.counted_quantity, line.variance) else { continue };
+
+            Self::move_stock(&mut tx, user, StockEntry {
+                product_id: line.product_id,
+                kind: StockMovementKind::Adjustment,
+                quantity: variance,
+                document: Some((StockDocument::Stocktake, stocktake_id)),
+                notes: &format!("{}: counted {}, expected {}", number, counted, line.expected_quantity),
+            }).await?;
+            if variance < 0 {
+                Self::deplete_batches_fefo(&mut tx, line.product_id, -variance).await?;
+            }
+        }
+
+        let now = Utc::now();
+        sqlx::query("UPDATE stocktakes SET status = ?, posted_at = ?, posted_by = ?, updated_at = ? WHERE id = ?")
+            .bind(StocktakeStatus::Posted)
+            .bind(now)
+            .bind(user.id)
+            .bind(now)
+            .bind(stocktake_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn cancel_stocktake(&self, stocktake_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let status = Self::stocktake_status(&mut tx, stocktake_id).await?;
+        status.check_transition(stocktake_id, StocktakeStatus::Cancelled)?;
+
+        sqlx::query("UPDATE stocktakes SET status = ?, updated_at = ? WHERE id = ?")
+            .bind(StocktakeStatus::Cancelled)
+            .bind(Utc::now())
+            .bind(stocktake_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_stocktakes(&self, status: Option<StocktakeStatus>) -> Result<Vec<Stocktake>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let stocktakes = sqlx::query_as::<_, Stocktake>(
+            "SELECT * FROM stocktakes WHERE (? IS NULL OR status = ?) ORDER BY created_at DESC, id DESC"
+        )
+        .bind(status)
+        .bind(status)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(stocktakes)
+    }
+
+    /// The stocktake with its lines in shelf order and the variances so far
+    pub async fn get_stocktake(&self, stocktake_id: i64) -> Result<StocktakeDetails> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let stocktake = sqlx::query_as::<_, Stocktake>("SELECT * FROM stocktakes WHERE id = ?")
+            .bind(stocktake_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Stocktake #{} not found", stocktake_id)))?;
+
+        let lines = sqlx::query_as::<_, StocktakeLine>(
+            "SELECT * FROM stocktake_line_details WHERE stocktake_id = ? ORDER BY location IS NULL, location, category, product_name, id"
+        )
+        .bind(stocktake_id)
+        .fetch_all(pool)
+        .await?;
+
+        let summary = summarize_variances(&lines);
+        Ok(StocktakeDetails { stocktake, lines, summary })
+    }
+
+    /// Count entries, newest first, for the whole stocktake or one product
+    pub async fn get_stocktake_counts(&self, stocktake_id: i64, product_id: Option<i64>) -> Result<Vec<StocktakeCount>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let counts = sqlx::query_as::<_, StocktakeCount>(
+            r#"
+            SELECT c.*, l.product_id
+            FROM stocktake_counts c
+            JOIN stocktake_lines l ON l.id = c.line_id
+            WHERE c.stocktake_id = ? AND (? IS NULL OR l.product_id = ?)
+            ORDER BY c.id DESC
+            "#
+        )
+        .bind(stocktake_id)
+        .bind(product_id)
+        .bind(product_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    // Inventory movements
+    pub async fn get_inventory_movements(&self, product_id: Option<i64>) -> Result<Vec<InventoryMovement>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let movements = if let Some(pid) = product_id {
+            sqlx::query_as::<_, InventoryMovement>(
+                "SELECT * FROM stock_movements WHERE product_id = ? ORDER BY id DESC"
+            )
             .bind(pid)
             .fetch_all(pool)
             .await?
-        } else {
-            sqlx::query_as::<_, InventoryMovement>(
-                "SELECT * FROM inventory_movements ORDER BY timestamp DESC LIMIT 100"
+        } else {
+            sqlx::query_as::<_, InventoryMovement>(
+                "SELECT * FROM stock_movements ORDER BY id DESC LIMIT 100"
+            )
+            .fetch_all(pool)
+            .await?
+        };
+
+        Ok(movements)
+    }
+
+    async fn ledger_discrepancies(conn: &mut SqliteConnection) -> Result<Vec<LedgerDiscrepancy>> {
+        let discrepancies = sqlx::query_as::<_, LedgerDiscrepancy>(
+            r#"
+            SELECT p.id AS product_id, p.name AS product_name, p.sku, p.quantity,
+                   COALESCE(SUM(m.quantity), 0) AS ledger_quantity,
+                   (SELECT l.balance_after FROM stock_movements l WHERE l.product_id = p.id ORDER BY l.id DESC LIMIT 1) AS last_balance
+            FROM products p
+            LEFT JOIN stock_movements m ON m.product_id = p.id
+            GROUP BY p.id
+            HAVING p.quantity <> ledger_quantity OR last_balance <> ledger_quantity
+            ORDER BY p.name, p.id
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(discrepancies)
+    }
+
+    /// Products whose quantity, or latest running balance, is not the sum of
+    /// their stock ledger
+    pub async fn get_ledger_discrepancies(&self) -> Result<Vec<LedgerDiscrepancy>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut conn = pool.acquire().await?;
+        Self::ledger_discrepancies(&mut conn).await
+    }
+
+    /// Recompute the quantity and running balances of every product that is
+    /// out of line from its ledger, which is taken as the truth. Returns the
+    /// discrepancies as they were before the repair.
+    pub async fn repair_stock_ledger(&self) -> Result<Vec<LedgerDiscrepancy>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let discrepancies = Self::ledger_discrepancies(&mut tx).await?;
+
+        for discrepancy in &discrepancies {
+            sqlx::query(
+                r#"
+                UPDATE stock_movements
+                SET balance_after = (
+                    SELECT SUM(m.quantity) FROM stock_movements m
+                    WHERE m.product_id = stock_movements.product_id AND m.id <= stock_movements.id
+                )
+                WHERE product_id = ?
+                "#
+            )
+            .bind(discrepancy.product_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query("UPDATE products SET quantity = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(discrepancy.ledger_quantity)
+                .bind(discrepancy.product_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(discrepancies)
+    }
+
+    // POS-related methods
+    pub async fn get_product_by_sku(&self, sku: &str) -> Result<Option<Product>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let product = sqlx::query_as::<_, Product>(
+            "SELECT * FROM products WHERE sku = ?"
+        )
+        .bind(sku)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(product)
+    }
+
+    pub async fn search_products_by_name(&self, query: &str) -> Result<Vec<Product>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let search_pattern = format!("%{}%", query);
+        let products = sqlx::query_as::<_, Product>(
+            "SELECT * FROM products WHERE name LIKE ? OR sku LIKE ? ORDER BY name LIMIT 20"
+        )
+        .bind(&search_pattern)
+        .bind(&search_pattern)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(products)
+    }
+
+    /// `approvals` are the token hashes of the cashier's price override
+    /// approvals; each line sold away from its shelf price uses up one for
+    /// its product and price.
+    pub async fn create_order(&self, user: &UserInfo, approvals: &[String], order_data: CreateOrderRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        if order_data.items.is_empty() && order_data.gift_cards.is_empty() {
+            return Err(AppError::validation("items", "An order needs at least one line").into());
+        }
+        // A line for nothing, or for less than nothing, would be a refund
+        // passed off as a sale
+        if order_data.items.iter().any(|item| item.quantity <= 0) {
+            return Err(AppError::validation("items", "Quantities must be positive").into());
+        }
+
+        let loyalty = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+        let currency = self.get_setting::<CurrencySettings>(CURRENCY_SETTINGS_KEY).await?.unwrap_or_default();
+        let stock_policies = self.get_setting::<StockPolicySettings>(STOCK_POLICY_SETTINGS_KEY).await?.unwrap_or_default();
+        for sale in &order_data.gift_cards {
+            validate_card_sale(sale)?;
+        }
+
+        // Start a transaction
+        let mut tx = pool.begin().await?;
+
+        let now = Utc::now();
+
+        let mut held = Vec::with_capacity(approvals.len());
+        for token_hash in approvals {
+            let approval = sqlx::query_as::<_, OverrideApproval>(
+                "SELECT id, product_id, price, approved_by, approver_name FROM price_override_approvals WHERE token_hash = ? AND cashier_id = ? AND used_at IS NULL AND datetime(expires_at) > datetime('now')"
+            )
+            .bind(token_hash)
+            .bind(user.id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::validation("approval_tokens", "A price override approval has expired or already been used"))?;
+            held.push(approval);
+        }
+
+        // Price every line from the catalogue, then apply promotions, then
+        // tax what is left of every line
+        let mut promotion_lines = Vec::with_capacity(order_data.items.len());
+        let mut overrides = Vec::with_capacity(order_data.items.len());
+        for item in &order_data.items {
+            let (name, category, shelf_price): (String, String, Money) = sqlx::query_as("SELECT name, category, price FROM products WHERE id = ?")
+                .bind(item.product_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::not_found(format!("Product #{} not found", item.product_id)))?;
+            let approval = held.iter()
+                .position(|a| item.price_at_sale != shelf_price && a.product_id == item.product_id && a.price == item.price_at_sale)
+                .map(|index| held.swap_remove(index));
+            let reason = check_line_price(&name, shelf_price, item, approval.as_ref())?;
+            overrides.push(reason.and_then(|reason| approval.map(|approval| (shelf_price, reason, approval))));
+
+            promotion_lines.push(PromotionLine {
+                product_id: item.product_id,
+                category,
+                quantity: item.quantity,
+                unit_price: item.price_at_sale,
+            });
+        }
+
+        // Check stock per product, as one may be on several lines. Stock held
+        // for parked carts is not free to sell.
+        let mut requested: Vec<(i64, i32)> = Vec::new();
+        for item in &order_data.items {
+            match requested.iter_mut().find(|(product_id, _)| *product_id == item.product_id) {
+                Some((_, quantity)) => *quantity += item.quantity,
+                None => requested.push((item.product_id, item.quantity)),
+            }
+        }
+
+        let mut shortages = Vec::new();
+        let mut oversold = Vec::new();
+        for (product_id, quantity) in requested {
+            let (product_name, category, on_hand, product_policy): (String, String, i32, Option<StockPolicy>) =
+                sqlx::query_as("SELECT name, category, quantity, stock_policy FROM products WHERE id = ?")
+                    .bind(product_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            let available = on_hand - Self::reserved_quantity(&mut tx, product_id, now).await?;
+            let policy = stock_policies.policy_for(product_policy, &category);
+
+            match check_stock(policy, quantity, available) {
+                StockCheck::InStock => {}
+                StockCheck::Short => shortages.push(StockShortage { product_id, product_name, requested: quantity, available: available.max(0) }),
+                StockCheck::Oversold => oversold.push((product_id, policy, quantity, available)),
+            }
+        }
+        if !shortages.is_empty() {
+            return Err(AppError::insufficient_stock(shortages).into());
+        }
+
+        let promotions = sqlx::query_as::<_, Promotion>("SELECT * FROM promotions WHERE is_active = 1")
+            .fetch_all(&mut *tx)
+            .await?;
+        let priced = apply_promotions(&promotion_lines, &promotions, &order_data.coupon_codes, now)?;
+
+        let mut lines = Vec::with_capacity(order_data.items.len());
+        for (item, discount) in order_data.items.iter().zip(&priced.line_discounts) {
+            let (rules, prices_include_tax) = Self::product_tax_rules(&mut tx, item.product_id).await?;
+            lines.push(calculate_line(item.price_at_sale * item.quantity - *discount, &rules, prices_include_tax));
+        }
+
+        // Gift cards are sold at face value, untaxed and outside promotions
+        let gift_card_total: Money = order_data.gift_cards.iter().map(|sale| sale.amount).sum();
+        let subtotal = lines.iter().map(|l| l.net_amount).sum::<Money>() + gift_card_total;
+        let tax_total: Money = lines.iter().map(|l| l.tax_amount).sum();
+        let discount_total: Money = priced.applied.iter().map(|a| a.discount_amount).sum();
+        let total_amount = subtotal + tax_total;
+
+        // A registered customer's name goes on the receipt unless another was typed
+        let mut customer_name = order_data.customer_name.as_deref().unwrap_or("").trim().to_string();
+        if let Some(customer_id) = order_data.customer_id {
+            let name: String = sqlx::query_scalar("SELECT name FROM customers WHERE id = ?")
+                .bind(customer_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::not_found(format!("Customer #{} not found", customer_id)))?;
+            if customer_name.is_empty() {
+                customer_name = name;
+            }
+        }
+
+        // Create order
+        let order_result = sqlx::query(
+            "INSERT INTO orders (created_at, updated_at, customer_name, customer_id, payment_method, subtotal, tax_total, discount_total, total_amount, currency, status, cashier_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'pending', ?)"
+        )
+        .bind(now)
+        .bind(now)
+        .bind(&customer_name)
+        .bind(order_data.customer_id)
+        .bind(&order_data.payment_method)
+        .bind(subtotal)
+        .bind(tax_total)
+        .bind(discount_total)
+        .bind(total_amount)
+        .bind(&currency.code)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+
+        let order_id = order_result.last_insert_rowid();
+        Self::record_order_event(&mut tx, order_id, None, OrderStatus::Pending, user, "").await?;
+        let mut order_item_ids = Vec::with_capacity(lines.len());
+
+        // Flag what was sold beyond stock for a manager to review
+        for (product_id, policy, quantity, available) in oversold {
+            sqlx::query(
+                "INSERT INTO oversold_lines (created_at, order_id, product_id, policy, requested, available) VALUES (?, ?, ?, ?, ?, ?)"
+            )
+            .bind(now)
+            .bind(order_id)
+            .bind(product_id)
+            .bind(policy)
+            .bind(quantity)
+            .bind(available)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Create order items and update stock
+        for (((item, line), discount), price_override) in order_data.items.into_iter().zip(lines).zip(priced.line_discounts).zip(overrides) {
+            // Insert order item
+            let item_result = sqlx::query(
+                "INSERT INTO order_items (created_at, updated_at, order_id, product_id, quantity, unit_price, discount_amount, net_amount, tax_amount, line_total) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(now)
+            .bind(now)
+            .bind(order_id)
+            .bind(item.product_id)
+            .bind(item.quantity)
+            .bind(item.price_at_sale)
+            .bind(discount)
+            .bind(line.net_amount)
+            .bind(line.tax_amount)
+            .bind(line.line_total)
+            .execute(&mut *tx)
+            .await?;
+
+            let order_item_id = item_result.last_insert_rowid();
+            order_item_ids.push(order_item_id);
+
+            if let Some((shelf_price, reason, approval)) = price_override {
+                sqlx::query(
+                    "INSERT INTO price_overrides (created_at, order_id, order_item_id, product_id, quantity, shelf_price, override_price, reason, cashier_id, cashier_name, approved_by, approver_name) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                )
+                .bind(now)
+                .bind(order_id)
+                .bind(order_item_id)
+                .bind(item.product_id)
+                .bind(item.quantity)
+                .bind(shelf_price)
+                .bind(item.price_at_sale)
+                .bind(&reason)
+                .bind(user.id)
+                .bind(&user.full_name)
+                .bind(approval.approved_by)
+                .bind(&approval.approver_name)
+                .execute(&mut *tx)
+                .await?;
+
+                sqlx::query("UPDATE price_override_approvals SET used_at = ?, order_id = ? WHERE id = ?")
+                    .bind(now)
+                    .bind(order_id)
+                    .bind(approval.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            for tax in &line.taxes {
+                sqlx::query(
+                    "INSERT INTO order_item_taxes (order_item_id, tax_rate_id, name, rate, is_compound, taxable_amount, tax_amount) VALUES (?, ?, ?, ?, ?, ?, ?)"
+                )
+                .bind(order_item_id)
+                .bind(tax.tax_rate_id)
+                .bind(&tax.name)
+                .bind(tax.rate)
+                .bind(tax.is_compound)
+                .bind(tax.taxable_amount)
+                .bind(tax.tax_amount)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            // Update product stock
+            Self::move_stock(&mut tx, user, StockEntry {
+                product_id: item.product_id,
+                kind: StockMovementKind::Sale,
+                quantity: -item.quantity,
+                document: Some((StockDocument::Order, order_id)),
+                notes: &format!("Sale - Order #{}", order_id),
+            }).await?;
+
+            // Sell from the batches that expire first and remember where stock came from
+            for (batch_id, quantity) in Self::deplete_batches_fefo(&mut tx, item.product_id, item.quantity).await? {
+                sqlx::query(
+                    "INSERT INTO order_item_batches (order_item_id, batch_id, quantity) VALUES (?, ?, ?)"
+                )
+                .bind(order_item_id)
+                .bind(batch_id)
+                .bind(quantity)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for applied in &priced.applied {
+            sqlx::query(
+                "INSERT INTO order_promotions (order_id, order_item_id, promotion_id, name, coupon_code, discount_amount) VALUES (?, ?, ?, ?, ?, ?)"
+            )
+            .bind(order_id)
+            .bind(applied.line_index.map(|index| order_item_ids[index]))
+            .bind(applied.promotion_id)
+            .bind(&applied.name)
+            .bind(&applied.coupon_code)
+            .bind(applied.discount_amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for sale in &order_data.gift_cards {
+            let code = sale.code.as_deref().map(normalize_card_code).unwrap_or_else(generate_card_code);
+            sqlx::query(
+                "INSERT INTO gift_cards (created_at, code, kind, status, customer_id, initial_amount, expires_on, issued_order_id) VALUES (?, ?, 'gift_card', 'pending', ?, ?, ?, ?)"
+            )
+            .bind(now)
+            .bind(&code)
+            .bind(order_data.customer_id)
+            .bind(sale.amount)
+            .bind(sale.expires_on)
+            .bind(order_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for payment in &order_data.payments {
+            Self::record_payment(&mut tx, user, order_id, total_amount, payment, &loyalty).await?;
+        }
+
+        tx.commit().await?;
+        Ok(order_id)
+    }
+
+    /// Take a tender against an order whose total is `total_amount`,
+    /// marking the order paid once its tenders cover the total. Loyalty
+    /// points are drawn from the order's customer and gift cards and store
+    /// credit from the card whose code is the payment's reference.
+    async fn record_payment(conn: &mut SqliteConnection, user: &UserInfo, order_id: i64, total_amount: Money, payment: &OrderPaymentRequest, loyalty: &LoyaltySettings) -> Result<OrderPaymentResult> {
+        let paid: Money = sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM order_payments WHERE order_id = ?")
+            .bind(order_id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        let balance_due = total_amount - paid;
+        let (amount, change_due) = apply_tender(balance_due, payment.tender_type, payment.amount)?;
+
+        let now = Utc::now();
+        let redemption = match payment.tender_type {
+            TenderType::LoyaltyPoints => {
+                if !loyalty.enabled {
+                    return Err(AppError::validation("tender_type", "Loyalty points are not accepted").into());
+                }
+                let customer_id: Option<i64> = sqlx::query_scalar("SELECT customer_id FROM orders WHERE id = ?")
+                    .bind(order_id)
+                    .fetch_one(&mut *conn)
+                    .await?;
+                let customer_id = customer_id
+                    .ok_or_else(|| AppError::validation("tender_type", "Only an order with a customer can be paid with points"))?;
+
+                let points = loyalty.points_for(amount);
+                let balance = Self::loyalty_balance(conn, customer_id, now).await?;
+                if points > balance {
+                    return Err(AppError::validation(
+                        "amount",
+                        format!("Paying {} takes {} points but the customer has {}", amount, points, balance),
+                    ).into());
+                }
+                Some((customer_id, points))
+            }
+            _ => None,
+        };
+
+        let card = match payment.tender_type {
+            TenderType::GiftCard | TenderType::StoreCredit => {
+                let code = payment.reference.as_deref().unwrap_or("");
+                if normalize_card_code(code).is_empty() {
+                    return Err(AppError::validation("reference", "Enter the card's code").into());
+                }
+                let card = Self::gift_card_by_code(conn, code).await?;
+                check_redeemable(&card, payment.tender_type, amount, now.date_naive())?;
+                Some(card)
+            }
+            _ => None,
+        };
+
+        let result = sqlx::query(
+            "INSERT INTO order_payments (created_at, order_id, tender_type, amount, tendered, change_due, reference, shift_id) VALUES (?, ?, ?, ?, ?, ?, ?, (SELECT id FROM shifts WHERE status = 'open'))"
+        )
+        .bind(Utc::now())
+        .bind(order_id)
+        .bind(payment.tender_type)
+        .bind(amount)
+        .bind(payment.amount)
+        .bind(change_due)
+        .bind(payment.reference.as_deref().unwrap_or(""))
+        .execute(&mut *conn)
+        .await?;
+        let payment_id = result.last_insert_rowid();
+
+        if let Some((customer_id, points)) = redemption {
+            Self::debit_points(conn, &PointsMovement {
+                customer_id,
+                kind: LoyaltyKind::Redeem,
+                points,
+                order_id: Some(order_id),
+                order_payment_id: Some(payment_id),
+                return_id: None,
+                user_id: Some(user.id),
+                notes: &format!("Order #{}", order_id),
+            }, now).await?;
+        }
+
+        if let Some(card) = card {
+            Self::record_card_entry(conn, &CardMovement {
+                gift_card_id: card.id,
+                kind: GiftCardEntryKind::Redeem,
+                amount: -amount,
+                order_id: Some(order_id),
+                order_payment_id: Some(payment_id),
+                return_id: None,
+                user_id: Some(user.id),
+                notes: &format!("Order #{}", order_id),
+            }).await?;
+        }
+
+        let remaining = balance_due - amount;
+        if !remaining.is_positive() {
+            Self::transition_order(conn, order_id, OrderStatus::Paid, user, "").await?;
+        }
+
+        Ok(OrderPaymentResult {
+            payment_id,
+            amount_paid: paid + amount,
+            balance_due: remaining,
+            change_due,
+        })
+    }
+
+    pub async fn add_order_payment(&self, user: &UserInfo, order_id: i64, payment: &OrderPaymentRequest) -> Result<OrderPaymentResult> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let loyalty = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+        let mut tx = pool.begin().await?;
+
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Order #{} not found", order_id)))?;
+
+        if !matches!(order.status, OrderStatus::Pending | OrderStatus::Paid) {
+            return Err(AppError::conflict(format!("Order #{} is {} and cannot take payments", order_id, order.status.as_str())).into());
+        }
+
+        let result = Self::record_payment(&mut tx, user, order_id, order.total_amount, payment, &loyalty).await?;
+
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    pub async fn get_order_payments(&self, order_id: i64) -> Result<Vec<OrderPayment>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let payments = sqlx::query_as::<_, OrderPayment>(
+            "SELECT * FROM order_payments WHERE order_id = ? ORDER BY id"
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(payments)
+    }
+
+    /// Take back a tender from an order that is not yet completed; a paid
+    /// order goes back to pending and redeemed points or card balance go back
+    pub async fn remove_order_payment(&self, user: &UserInfo, payment_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let loyalty = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+        let mut tx = pool.begin().await?;
+
+        let (order_id, status, tender_type): (i64, OrderStatus, TenderType) = sqlx::query_as(
+            "SELECT o.id, o.status, op.tender_type FROM order_payments op JOIN orders o ON o.id = op.order_id WHERE op.id = ?"
+        )
+        .bind(payment_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("Payment #{} not found", payment_id)))?;
+
+        match status {
+            OrderStatus::Pending => {}
+            OrderStatus::Paid => {
+                Self::transition_order(&mut tx, order_id, OrderStatus::Pending, user, &format!("Payment #{} removed", payment_id)).await?;
+            }
+            status => return Err(AppError::conflict(format!("Payments on a {} order cannot be removed", status.as_str())).into()),
+        }
+
+        let notes = format!("Payment #{} removed", payment_id);
+        match tender_type {
+            TenderType::LoyaltyPoints => {
+                Self::restore_redeemed_points(&mut tx, user, order_id, Some(payment_id), loyalty.expires_at(Utc::now()), &notes).await?;
+            }
+            TenderType::GiftCard | TenderType::StoreCredit => {
+                Self::refund_card_redemptions(&mut tx, user, order_id, Some(payment_id), &notes).await?;
+            }
+            _ => {}
+        }
+
+        sqlx::query("DELETE FROM order_payments WHERE id = ?")
+            .bind(payment_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Fails unless the order's tenders cover its total. Gift cards sold on
+    /// the order become usable and a customer's order earns points on what
+    /// was not paid with points.
+    pub async fn complete_order(&self, user: &UserInfo, order_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let loyalty = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+        let mut tx = pool.begin().await?;
+
+        Self::transition_order(&mut tx, order_id, OrderStatus::Completed, user, "").await?;
+
+        let total_amount: Money = sqlx::query_scalar("SELECT total_amount FROM orders WHERE id = ?")
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Order #{} not found", order_id)))?;
+
+        let paid: Money = sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM order_payments WHERE order_id = ?")
+            .bind(order_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        if paid < total_amount {
+            return Err(AppError::validation(
+                "payments",
+                format!("Payments of {} do not cover the order total of {}", paid, total_amount),
+            ).into());
+        }
+
+        // A single tender type is kept as the order's payment method; several become 'split'
+        sqlx::query(
+            r#"
+            UPDATE orders SET payment_method = COALESCE((
+                    SELECT CASE WHEN COUNT(DISTINCT tender_type) > 1 THEN 'split' ELSE MIN(tender_type) END
+                    FROM order_payments WHERE order_id = orders.id HAVING COUNT(*) > 0
+                ), payment_method)
+            WHERE id = ?
+            "#
+        )
+        .bind(order_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let sold: Vec<(i64, Money)> = sqlx::query_as(
+            "SELECT id, initial_amount FROM gift_cards WHERE issued_order_id = ? AND status = 'pending'"
+        )
+        .bind(order_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (gift_card_id, amount) in sold {
+            sqlx::query("UPDATE gift_cards SET status = 'active' WHERE id = ?")
+                .bind(gift_card_id)
+                .execute(&mut *tx)
+                .await?;
+
+            Self::record_card_entry(&mut tx, &CardMovement {
+                gift_card_id,
+                kind: GiftCardEntryKind::Issue,
+                amount,
+                order_id: Some(order_id),
+                order_payment_id: None,
+                return_id: None,
+                user_id: Some(user.id),
+                notes: &format!("Sold on order #{}", order_id),
+            }).await?;
+        }
+
+        if loyalty.enabled {
+            let customer_id: Option<i64> = sqlx::query_scalar("SELECT customer_id FROM orders WHERE id = ?")
+                .bind(order_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+            if let Some(customer_id) = customer_id {
+                let paid_with_points: Money = sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM order_payments WHERE order_id = ? AND tender_type = 'loyalty_points'")
+                    .bind(order_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+                let now = Utc::now();
+                Self::credit_points(&mut tx, &PointsMovement {
+                    customer_id,
+                    kind: LoyaltyKind::Earn,
+                    points: loyalty.points_earned(total_amount - paid_with_points),
+                    order_id: Some(order_id),
+                    order_payment_id: None,
+                    return_id: None,
+                    user_id: Some(user.id),
+                    notes: &format!("Order #{}", order_id),
+                }, loyalty.expires_at(now)).await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Void an order that has not been paid and put its stock, redeemed
+    /// points and card balances back. Cash taken can be kept as store credit,
+    /// whose card is returned. Completed orders are taken back through
+    /// returns instead.
+    pub async fn cancel_order(&self, user: &UserInfo, order_id: i64, store_credit: bool) -> Result<Option<i64>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let loyalty = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+
+        // Start a transaction
+        let mut tx = pool.begin().await?;
+
+        Self::transition_order(&mut tx, order_id, OrderStatus::Voided, user, "").await?;
+
+        let notes = format!("Order #{} voided", order_id);
+        Self::restore_redeemed_points(&mut tx, user, order_id, None, loyalty.expires_at(Utc::now()), &notes).await?;
+        Self::refund_card_redemptions(&mut tx, user, order_id, None, &notes).await?;
+
+        sqlx::query("UPDATE gift_cards SET status = 'void' WHERE issued_order_id = ? AND status = 'pending'")
+            .bind(order_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut store_credit_id = None;
+        if store_credit {
+            let cash: Money = sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM order_payments WHERE order_id = ? AND tender_type = 'cash'")
+                .bind(order_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            if cash.is_positive() {
+                store_credit_id = Some(Self::issue_store_credit(&mut tx, user, order_id, None, cash).await?);
+            }
+        }
+
+        // Every tender has gone back to the customer, so drop its payment;
+        // cash kept against store credit stays in the till and on the order
+        sqlx::query("DELETE FROM order_payments WHERE order_id = ? AND NOT (? AND tender_type = 'cash')")
+            .bind(order_id)
+            .bind(store_credit)
+            .execute(&mut *tx)
+            .await?;
+
+        // Get order items to restore stock
+        let order_items = sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM order_items WHERE order_id = ?"
+        )
+        .bind(order_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // Restore stock for each item
+        for item in order_items {
+            Self::move_stock(&mut tx, user, StockEntry {
+                product_id: item.product_id,
+                kind: StockMovementKind::Return,
+                quantity: item.quantity,
+                document: Some((StockDocument::Order, order_id)),
+                notes: &format!("Order cancellation - Order #{}", order_id),
+            }).await?;
+
+            // Put stock back into the batches it was sold from
+            sqlx::query(
+                r#"
+                UPDATE product_batches
+                SET quantity = quantity + (SELECT oib.quantity FROM order_item_batches oib WHERE oib.batch_id = product_batches.id AND oib.order_item_id = ?),
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE id IN (SELECT batch_id FROM order_item_batches WHERE order_item_id = ?)
+                "#
+            )
+            .bind(item.id)
+            .bind(item.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(store_credit_id)
+    }
+
+    /// Move an order to `to` if its lifecycle allows it and record the step
+    /// in its history. Returns the status it moved from.
+    async fn transition_order(conn: &mut SqliteConnection, order_id: i64, to: OrderStatus, user: &UserInfo, notes: &str) -> Result<OrderStatus> {
+        let from: OrderStatus = sqlx::query_scalar("SELECT status FROM orders WHERE id = ?")
+            .bind(order_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Order #{} not found", order_id)))?;
+
+        from.check_transition(order_id, to)?;
+
+        sqlx::query("UPDATE orders SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(to)
+            .bind(order_id)
+            .execute(&mut *conn)
+            .await?;
+
+        Self::record_order_event(conn, order_id, Some(from), to, user, notes).await?;
+        Ok(from)
+    }
+
+    async fn record_order_event(conn: &mut SqliteConnection, order_id: i64, from: Option<OrderStatus>, to: OrderStatus, user: &UserInfo, notes: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO order_events (created_at, order_id, from_status, to_status, user_id, user_name, notes) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(Utc::now())
+        .bind(order_id)
+        .bind(from)
+        .bind(to)
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(notes)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_order_events(&self, order_id: i64) -> Result<Vec<OrderEvent>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let events = sqlx::query_as::<_, OrderEvent>(
+            "SELECT * FROM order_events WHERE order_id = ? ORDER BY id"
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Take back part or all of a completed order: refund the returned
+    /// lines' share of what was paid to the original tenders, take back the
+    /// points it earned in proportion and restock or write off the goods
+    pub async fn create_return(&self, user: &UserInfo, request: &CreateReturnRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let loyalty = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+
+        if request.items.is_empty() {
+            return Err(AppError::validation("items", "Select at least one item to return").into());
+        }
+        let mut seen = std::collections::HashSet::new();
+        if !request.items.iter().all(|item| seen.insert(item.order_item_id)) {
+            return Err(AppError::validation("items", "An order line can only appear once in a return").into());
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let status: OrderStatus = sqlx::query_scalar("SELECT status FROM orders WHERE id = ?")
+            .bind(request.order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Order #{} not found", request.order_id)))?;
+
+        // Completed and partially refunded orders are the ones that can be refunded further
+        status.check_transition(request.order_id, OrderStatus::PartiallyRefunded)?;
+
+        // Work out each line's refund before writing anything
+        let mut lines = Vec::new();
+        for item in &request.items {
+            let order_item = sqlx::query_as::<_, OrderItem>("SELECT * FROM order_items WHERE id = ? AND order_id = ?")
+                .bind(item.order_item_id)
+                .bind(request.order_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::validation("items", format!("Order #{} has no line #{}", request.order_id, item.order_item_id)))?;
+
+            let returned: i32 = sqlx::query_scalar("SELECT COALESCE(SUM(quantity), 0) FROM order_return_items WHERE order_item_id = ?")
+                .bind(order_item.id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+            if item.quantity <= 0 {
+                return Err(AppError::validation("items", "Return quantities must be positive").into());
+            }
+            if item.quantity > order_item.quantity - returned {
+                return Err(AppError::validation(
+                    "items",
+                    format!("Only {} of line #{} can still be returned", order_item.quantity - returned, order_item.id),
+                ).into());
+            }
+
+            let (refund_amount, tax_amount) = refund_share(order_item.line_total, order_item.tax_amount, order_item.quantity, returned, item.quantity);
+            lines.push((order_item, item, refund_amount, tax_amount));
+        }
+
+        let refund_total: Money = lines.iter().map(|(_, _, refund, _)| *refund).sum();
+        let tax_total: Money = lines.iter().map(|(_, _, _, tax)| *tax).sum();
+
+        let result = sqlx::query(
+            "INSERT INTO order_returns (created_at, order_id, user_id, user_name, reason, notes, refund_total, tax_total) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(Utc::now())
+        .bind(request.order_id)
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(request.reason)
+        .bind(request.notes.as_deref().unwrap_or("").trim())
+        .bind(refund_total)
+        .bind(tax_total)
+        .execute(&mut *tx)
+        .await?;
+
+        let return_id = result.last_insert_rowid();
+
+        for (order_item, item, refund_amount, tax_amount) in &lines {
+            sqlx::query(
+                "INSERT INTO order_return_items (return_id, order_item_id, product_id, quantity, disposition, refund_amount, tax_amount) VALUES (?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(return_id)
+            .bind(order_item.id)
+            .bind(order_item.product_id)
+            .bind(item.quantity)
+            .bind(item.disposition)
+            .bind(refund_amount)
+            .bind(tax_amount)
+            .execute(&mut *tx)
+            .await?;
+
+            // Written-off goods come back only to go straight out again, which
+            // leaves stock as the sale left it
+            let notes = format!("Return #{} - Order #{}", return_id, request.order_id);
+            Self::move_stock(&mut tx, user, StockEntry {
+                product_id: order_item.product_id,
+                kind: StockMovementKind::Return,
+                quantity: item.quantity,
+                document: Some((StockDocument::Return, return_id)),
+                notes: &notes,
+            }).await?;
+            match item.disposition {
+                ReturnDisposition::Restock => Self::restock_returned_batches(&mut tx, order_item, item.quantity).await?,
+                ReturnDisposition::WriteOff => {
+                    Self::move_stock(&mut tx, user, StockEntry {
+                        product_id: order_item.product_id,
+                        kind: StockMovementKind::WriteOff,
+                        quantity: -item.quantity,
+                        document: Some((StockDocument::Return, return_id)),
+                        notes: &notes,
+                    }).await?;
+                }
+            }
+        }
+
+        let tenders = sqlx::query(
+            r#"
+            SELECT op.id, op.tender_type,
+                   op.amount - COALESCE((SELECT SUM(r.amount) FROM order_refunds r WHERE r.order_payment_id = op.id), 0) AS refundable
+            FROM order_payments op
+            WHERE op.order_id = ?
+            "#
+        )
+        .bind(request.order_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| RefundableTender {
+            payment_id: row.get("id"),
+            tender_type: row.get("tender_type"),
+            refundable: row.get("refundable"),
+        })
+        .collect::<Vec<_>>();
+
+        // Store credit replaces only what would have been paid back in cash
+        let mut refunds = allocate_refund(refund_total, &tenders);
+        if request.store_credit {
+            let credit: Money = refunds.iter().filter(|(_, tender_type, _)| *tender_type == TenderType::Cash).map(|(_, _, amount)| *amount).sum();
+            if credit.is_positive() {
+                for refund in refunds.iter_mut().filter(|(_, tender_type, _)| *tender_type == TenderType::Cash) {
+                    refund.1 = TenderType::StoreCredit;
+                }
+                Self::issue_store_credit(&mut tx, user, request.order_id, Some(return_id), credit).await?;
+            }
+        }
+
+        for (order_payment_id, tender_type, amount) in &refunds {
+            sqlx::query(
+                "INSERT INTO order_refunds (return_id, order_payment_id, tender_type, amount, shift_id) VALUES (?, ?, ?, ?, (SELECT id FROM shifts WHERE status = 'open'))"
+            )
+            .bind(return_id)
+            .bind(order_payment_id)
+            .bind(tender_type)
+            .bind(amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        Self::reverse_return_points(&mut tx, user, request.order_id, return_id, &refunds, &loyalty).await?;
+
+        // What was paid from a card goes back onto it
+        for (order_payment_id, tender_type, amount) in &refunds {
+            let Some(order_payment_id) = order_payment_id.filter(|_| matches!(tender_type, TenderType::GiftCard | TenderType::StoreCredit)) else {
+                continue;
+            };
+
+            let gift_card_id: Option<i64> = sqlx::query_scalar("SELECT gift_card_id FROM gift_card_ledger WHERE order_payment_id = ? AND kind = 'redeem'")
+                .bind(order_payment_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            if let Some(gift_card_id) = gift_card_id {
+                Self::record_card_entry(&mut tx, &CardMovement {
+                    gift_card_id,
+                    kind: GiftCardEntryKind::Refund,
+                    amount: *amount,
+                    order_id: Some(request.order_id),
+                    order_payment_id: Some(order_payment_id),
+                    return_id: Some(return_id),
+                    user_id: Some(user.id),
+                    notes: &format!("Return #{}", return_id),
+                }).await?;
+            }
+        }
+
+        let outstanding: i32 = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(oi.quantity), 0)
+                 - COALESCE((SELECT SUM(ri.quantity) FROM order_return_items ri JOIN order_items r ON r.id = ri.order_item_id WHERE r.order_id = ?), 0)
+            FROM order_items oi
+            WHERE oi.order_id = ?
+            "#
+        )
+        .bind(request.order_id)
+        .bind(request.order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let status = if outstanding > 0 { OrderStatus::PartiallyRefunded } else { OrderStatus::Refunded };
+        Self::transition_order(&mut tx, request.order_id, status, user, &format!("Return #{}", return_id)).await?;
+
+        tx.commit().await?;
+        Ok(return_id)
+    }
+
+    /// Refunds to a points tender go back as points; of the rest, the
+    /// customer loses the share of the points the sale earned
+    async fn reverse_return_points(conn: &mut SqliteConnection, user: &UserInfo, order_id: i64, return_id: i64, refunds: &[(Option<i64>, TenderType, Money)], loyalty: &LoyaltySettings) -> Result<()> {
+        let now = Utc::now();
+        let notes = format!("Return #{} - Order #{}", return_id, order_id);
+
+        for (order_payment_id, tender_type, amount) in refunds {
+            let Some(order_payment_id) = order_payment_id.filter(|_| *tender_type == TenderType::LoyaltyPoints) else {
+                continue;
+            };
+
+            let redeemed: Option<(i64, i64)> = sqlx::query_as(
+                "SELECT customer_id, -SUM(points) FROM loyalty_ledger WHERE order_payment_id = ? AND kind IN ('redeem', 'restore') GROUP BY customer_id"
+            )
+            .bind(order_payment_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            if let Some((customer_id, outstanding)) = redeemed {
+                Self::credit_points(conn, &PointsMovement {
+                    customer_id,
+                    kind: LoyaltyKind::Restore,
+                    points: loyalty.points_for(*amount).min(outstanding),
+                    order_id: Some(order_id),
+                    order_payment_id: Some(order_payment_id),
+                    return_id: Some(return_id),
+                    user_id: Some(user.id),
+                    notes: &notes,
+                }, loyalty.expires_at(now)).await?;
+            }
+        }
+
+        let earned: Option<(i64, i64)> = sqlx::query_as(
+            "SELECT customer_id, SUM(points) FROM loyalty_ledger WHERE order_id = ? AND kind = 'earn' GROUP BY customer_id"
+        )
+        .bind(order_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some((customer_id, earned)) = earned else {
+            return Ok(());
+        };
+
+        let earn_base: Money = sqlx::query_scalar(
+            "SELECT total_amount - (SELECT COALESCE(SUM(amount), 0) FROM order_payments WHERE order_id = orders.id AND tender_type = 'loyalty_points') FROM orders WHERE id = ?"
+        )
+        .bind(order_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let refunded_before: Money = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(r.amount), 0) FROM order_refunds r
+            JOIN order_returns rt ON rt.id = r.return_id
+            WHERE rt.order_id = ? AND rt.id != ? AND r.tender_type != 'loyalty_points'
+            "#
+        )
+        .bind(order_id)
+        .bind(return_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let refunded: Money = refunds.iter()
+            .filter(|(_, tender_type, _)| *tender_type != TenderType::LoyaltyPoints)
+            .map(|(_, _, amount)| *amount)
+            .sum();
+
+        Self::debit_points(conn, &PointsMovement {
+            customer_id,
+            kind: LoyaltyKind::Reverse,
+            points: earn_reversal(earned, earn_base, refunded_before, refunded),
+            order_id: Some(order_id),
+            order_payment_id: None,
+            return_id: Some(return_id),
+            user_id: Some(user.id),
+            notes: &notes,
+        }, now).await?;
+
+        Ok(())
+    }
+
+    /// Put returned units back into the batches they were sold from, latest
+    /// expiry first
+    async fn restock_returned_batches(conn: &mut SqliteConnection, order_item: &OrderItem, quantity: i32) -> Result<()> {
+        let drawn: Vec<(i64, i32)> = sqlx::query_as(
+            "SELECT batch_id, quantity FROM order_item_batches WHERE order_item_id = ? AND quantity > 0 ORDER BY rowid DESC"
+        )
+        .bind(order_item.id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut remaining = quantity;
+        for (batch_id, sold) in drawn {
+            if remaining <= 0 {
+                break;
+            }
+
+            let take = remaining.min(sold);
+            sqlx::query(
+                "UPDATE product_batches SET quantity = quantity + ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+            )
+            .bind(take)
+            .bind(batch_id)
+            .execute(&mut *conn)
+            .await?;
+
+            // Only what is still with the customer stays recorded against the sale
+            sqlx::query(
+                "UPDATE order_item_batches SET quantity = quantity - ? WHERE order_item_id = ? AND batch_id = ?"
             )
+            .bind(take)
+            .bind(order_item.id)
+            .bind(batch_id)
+            .execute(&mut *conn)
+            .await?;
+
+            remaining -= take;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_return(&self, return_id: i64) -> Result<ReturnDetails> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let order_return = sqlx::query_as::<_, OrderReturn>("SELECT * FROM order_returns WHERE id = ?")
+            .bind(return_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Return #{} not found", return_id)))?;
+
+        let items = sqlx::query_as::<_, OrderReturnItem>(
+            r#"
+            SELECT ri.id, ri.return_id, ri.order_item_id, ri.product_id,
+                   COALESCE(p.name, 'Product #' || ri.product_id) AS product_name,
+                   ri.quantity, ri.disposition, ri.refund_amount, ri.tax_amount
+            FROM order_return_items ri
+            LEFT JOIN products p ON p.id = ri.product_id
+            WHERE ri.return_id = ?
+            ORDER BY ri.id
+            "#
+        )
+        .bind(return_id)
+        .fetch_all(pool)
+        .await?;
+
+        let refunds = sqlx::query_as::<_, OrderRefund>("SELECT * FROM order_refunds WHERE return_id = ? ORDER BY id")
+            .bind(return_id)
+            .fetch_all(pool)
+            .await?;
+
+        let store_credit = sqlx::query_as::<_, GiftCard>("SELECT * FROM gift_card_balances WHERE issued_return_id = ?")
+            .bind(return_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(ReturnDetails { order_return, items, refunds, store_credit })
+    }
+
+    pub async fn get_order_returns(&self, order_id: i64) -> Result<Vec<ReturnDetails>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let return_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM order_returns WHERE order_id = ? ORDER BY id")
+            .bind(order_id)
             .fetch_all(pool)
+            .await?;
+
+        let mut returns = Vec::new();
+        for return_id in return_ids {
+            returns.push(self.get_return(return_id).await?);
+        }
+
+        Ok(returns)
+    }
+
+    // Shifts
+    pub async fn open_shift(&self, user: &UserInfo, shift: &OpenShiftRequest) -> Result<Shift> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        if let Some(open) = self.get_current_shift().await? {
+            return Err(AppError::conflict(format!("Shift #{} opened by {} is still open", open.id, open.user_name)).into());
+        }
+
+        let result = sqlx::query(
+            "INSERT INTO shifts (opened_at, user_id, user_name, opening_float, status, notes) VALUES (?, ?, ?, ?, 'open', ?)"
+        )
+        .bind(Utc::now())
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(shift.opening_float)
+        .bind(shift.notes.as_deref().unwrap_or("").trim())
+        .execute(pool)
+        .await?;
+
+        let shift = sqlx::query_as::<_, Shift>("SELECT * FROM shifts WHERE id = ?")
+            .bind(result.last_insert_rowid())
+            .fetch_one(pool)
+            .await?;
+
+        Ok(shift)
+    }
+
+    pub async fn get_current_shift(&self) -> Result<Option<Shift>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let shift = sqlx::query_as::<_, Shift>("SELECT * FROM shifts WHERE status = 'open'")
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(shift)
+    }
+
+    async fn open_shift_in(conn: &mut SqliteConnection) -> Result<Shift> {
+        let shift = sqlx::query_as::<_, Shift>("SELECT * FROM shifts WHERE status = 'open'")
+            .fetch_optional(&mut *conn)
             .await?
-        };
+            .ok_or_else(|| AppError::conflict("No shift is open"))?;
 
-        Ok(movements)
+        Ok(shift)
     }
 
-    // POS-related methods
-    pub async fn get_product_by_sku(&self, sku: &str) -> Result<Option<Product>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    pub async fn record_cash_movement(&self, user: &UserInfo, movement: &CashMovementRequest) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        let product = sqlx::query_as::<_, Product>(
-            "SELECT * FROM products WHERE sku = ?"
+        let mut tx = pool.begin().await?;
+        let shift = Self::open_shift_in(&mut tx).await?;
+
+        let result = sqlx::query(
+            "INSERT INTO shift_cash_movements (created_at, shift_id, user_id, user_name, kind, amount, notes) VALUES (?, ?, ?, ?, ?, ?, ?)"
         )
-        .bind(sku)
-        .fetch_optional(pool)
+        .bind(Utc::now())
+        .bind(shift.id)
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(movement.kind)
+        .bind(movement.amount)
+        .bind(movement.notes.as_deref().unwrap_or("").trim())
+        .execute(&mut *tx)
         .await?;
 
-        Ok(product)
+        tx.commit().await?;
+        Ok(result.last_insert_rowid())
     }
 
-    pub async fn search_products_by_name(&self, query: &str) -> Result<Vec<Product>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    pub async fn get_shift_cash_movements(&self, shift_id: i64) -> Result<Vec<ShiftCashMovement>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        let search_pattern = format!("%{}%", query);
-        let products = sqlx::query_as::<_, Product>(
-            "SELECT * FROM products WHERE name LIKE ? OR sku LIKE ? ORDER BY name LIMIT 20"
+        let movements = sqlx::query_as::<_, ShiftCashMovement>(
+            "SELECT * FROM shift_cash_movements WHERE shift_id = ? ORDER BY id"
         )
-        .bind(&search_pattern)
-        .bind(&search_pattern)
+        .bind(shift_id)
         .fetch_all(pool)
         .await?;
 
-        Ok(products)
+        Ok(movements)
     }
 
-    pub async fn create_order(&self, order_data: CreateOrderRequest) -> Result<i64> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    /// Read a shift's takings as they stand
+    async fn shift_report(conn: &mut SqliteConnection, shift: &Shift, report_type: ShiftReportType, counts: Option<&[TenderCount]>, closed_at: Option<DateTime<Utc>>) -> Result<ShiftReport> {
+        let payments: Vec<(TenderType, Money)> = sqlx::query_as("SELECT tender_type, amount FROM order_payments WHERE shift_id = ?")
+            .bind(shift.id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let refunds: Vec<(TenderType, Money)> = sqlx::query_as("SELECT tender_type, amount FROM order_refunds WHERE shift_id = ?")
+            .bind(shift.id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        // A voided order keeps only cash taken for store credit, which is
+        // takings but not a sale
+        let orders: i64 = sqlx::query_scalar("SELECT COUNT(DISTINCT p.order_id) FROM order_payments p JOIN orders o ON o.id = p.order_id WHERE p.shift_id = ? AND o.status <> 'voided'")
+            .bind(shift.id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        let cash_out = |kind: CashMovementKind| {
+            sqlx::query_scalar::<_, Money>("SELECT COALESCE(SUM(amount), 0) FROM shift_cash_movements WHERE shift_id = ? AND kind = ?")
+                .bind(shift.id)
+                .bind(kind)
+        };
+        let payouts = cash_out(CashMovementKind::Payout).fetch_one(&mut *conn).await?;
+        let drops = cash_out(CashMovementKind::Drop).fetch_one(&mut *conn).await?;
+
+        Ok(ShiftReport {
+            report_type,
+            shift_id: shift.id,
+            opened_at: shift.opened_at,
+            closed_at,
+            opened_by: shift.user_name.clone(),
+            opening_float: shift.opening_float,
+            orders,
+            gross_sales: payments.iter().map(|(_, amount)| *amount).sum(),
+            refunds: refunds.iter().map(|(_, amount)| *amount).sum(),
+            payouts,
+            drops,
+            tenders: tender_lines(shift.opening_float, &payments, &refunds, payouts + drops, counts),
+        })
+    }
+
+    async fn store_shift_report(conn: &mut SqliteConnection, user: &UserInfo, report: ShiftReport) -> Result<ShiftReportRecord> {
+        let created_at = Utc::now();
+        let body = serde_json::to_string(&report)
+            .map_err(|e| AppError::internal(format!("Could not store shift report: {}", e)))?;
+
+        let result = sqlx::query(
+            "INSERT INTO shift_reports (created_at, shift_id, report_type, user_id, user_name, body) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(created_at)
+        .bind(report.shift_id)
+        .bind(report.report_type)
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(body)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(ShiftReportRecord {
+            id: result.last_insert_rowid(),
+            created_at,
+            shift_id: report.shift_id,
+            user_id: user.id,
+            user_name: user.full_name.clone(),
+            report,
+        })
+    }
+
+    pub async fn take_x_report(&self, user: &UserInfo) -> Result<ShiftReportRecord> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        // Start a transaction
         let mut tx = pool.begin().await?;
+        let shift = Self::open_shift_in(&mut tx).await?;
+        let report = Self::shift_report(&mut tx, &shift, ShiftReportType::X, None, None).await?;
+        let record = Self::store_shift_report(&mut tx, user, report).await?;
 
-        // Calculate total amount
-        let total_amount: f64 = order_data.items.iter()
-            .map(|item| item.price_at_sale * item.quantity as f64)
-            .sum();
+        tx.commit().await?;
+        Ok(record)
+    }
 
-        // Create order
-        let order_result = sqlx::query(
-            "INSERT INTO orders (customer_name, payment_method, total_amount, status) VALUES (?, ?, ?, 'pending')"
+    /// Close the open shift and store its Z report
+    pub async fn close_shift(&self, user: &UserInfo, close: &CloseShiftRequest) -> Result<ShiftReportRecord> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let shift = Self::open_shift_in(&mut tx).await?;
+        let closed_at = Utc::now();
+
+        let report = Self::shift_report(&mut tx, &shift, ShiftReportType::Z, Some(&close.counts), Some(closed_at)).await?;
+        let record = Self::store_shift_report(&mut tx, user, report).await?;
+
+        let notes = close.notes.as_deref().unwrap_or("").trim();
+        sqlx::query(
+            "UPDATE shifts SET status = 'closed', closed_at = ?, closed_by_user_id = ?, notes = CASE WHEN ? = '' THEN notes ELSE ? END WHERE id = ?"
         )
-        .bind(&order_data.customer_name)
-        .bind(&order_data.payment_method)
-        .bind(total_amount)
+        .bind(closed_at)
+        .bind(user.id)
+        .bind(notes)
+        .bind(notes)
+        .bind(shift.id)
         .execute(&mut *tx)
         .await?;
 
-        let order_id = order_result.last_insert_rowid();
+        tx.commit().await?;
+        Ok(record)
+    }
 
-        // Create order items and update stock
-        for item in order_data.items {
-            // Insert order item
-            sqlx::query(
-                "INSERT INTO order_items (order_id, product_id, quantity, price_at_sale) VALUES (?, ?, ?, ?)"
-            )
-            .bind(order_id)
-            .bind(item.product_id)
-            .bind(item.quantity)
-            .bind(item.price_at_sale)
-            .execute(&mut *tx)
-            .await?;
+    fn shift_report_record(row: &sqlx::sqlite::SqliteRow) -> Result<ShiftReportRecord> {
+        let id: i64 = row.get("id");
+        let body: String = row.get("body");
+
+        Ok(ShiftReportRecord {
+            id,
+            created_at: row.get("created_at"),
+            shift_id: row.get("shift_id"),
+            user_id: row.get("user_id"),
+            user_name: row.get("user_name"),
+            report: serde_json::from_str(&body)
+                .map_err(|e| AppError::internal(format!("Corrupt shift report #{}: {}", id, e)))?,
+        })
+    }
 
-            // Update product stock
-            sqlx::query(
-                "UPDATE products SET quantity = quantity - ? WHERE id = ?"
-            )
-            .bind(item.quantity)
-            .bind(item.product_id)
-            .execute(&mut *tx)
+    pub async fn get_shift_reports(&self, shift_id: i64) -> Result<Vec<ShiftReportRecord>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let rows = sqlx::query("SELECT * FROM shift_reports WHERE shift_id = ? ORDER BY id")
+            .bind(shift_id)
+            .fetch_all(pool)
             .await?;
 
-            // Record inventory movement
-            sqlx::query(
-                "INSERT INTO inventory_movements (product_id, quantity_change, movement_type, notes) VALUES (?, ?, 'sale', ?)"
-            )
-            .bind(item.product_id)
-            .bind(-item.quantity)
-            .bind(format!("Sale - Order #{}", order_id))
-            .execute(&mut *tx)
+        rows.iter().map(Self::shift_report_record).collect()
+    }
+
+    pub async fn get_shift_report(&self, report_id: i64) -> Result<ShiftReportRecord> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let row = sqlx::query("SELECT * FROM shift_reports WHERE id = ?")
+            .bind(report_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Shift report #{} not found", report_id)))?;
+
+        Self::shift_report_record(&row)
+    }
+
+    pub async fn get_recent_orders(&self, limit: i64) -> Result<Vec<Order>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let orders = sqlx::query_as::<_, Order>(
+            "SELECT * FROM orders ORDER BY created_at DESC LIMIT ?"
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(orders)
+    }
+
+    pub async fn get_order(&self, order_id: i64) -> Result<Order> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
+            .bind(order_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Order #{} not found", order_id)))?;
+
+        Ok(order)
+    }
+
+    pub async fn get_order_tax_summary(&self, order_id: i64) -> Result<OrderTaxSummary> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let order = self.get_order(order_id).await?;
+
+        let taxes = sqlx::query_as::<_, OrderTaxLine>(
+            r#"
+            SELECT t.tax_rate_id, t.name, t.rate,
+                   SUM(t.taxable_amount) AS taxable_amount,
+                   SUM(t.tax_amount) AS tax_amount
+            FROM order_item_taxes t
+            JOIN order_items oi ON oi.id = t.order_item_id
+            WHERE oi.order_id = ?
+            GROUP BY t.tax_rate_id, t.name, t.rate
+            ORDER BY MIN(t.id)
+            "#
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(OrderTaxSummary {
+            order_id,
+            subtotal: order.subtotal,
+            tax_total: order.tax_total,
+            total_amount: order.total_amount,
+            taxes,
+        })
+    }
+
+    pub async fn get_order_promotions(&self, order_id: i64) -> Result<Vec<OrderPromotion>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let promotions = sqlx::query_as::<_, OrderPromotion>(
+            "SELECT * FROM order_promotions WHERE order_id = ? ORDER BY id"
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(promotions)
+    }
+
+    pub async fn get_receipt_data(&self, order_id: i64) -> Result<super::receipt::ReceiptData> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let order = self.get_order(order_id).await?;
+        let lines = sqlx::query_as::<_, super::receipt::ReceiptLine>(
+            r#"
+            SELECT oi.id AS order_item_id,
+                   COALESCE(p.name, 'Product #' || oi.product_id) AS product_name,
+                   oi.quantity, oi.unit_price, oi.discount_amount, oi.line_total
+            FROM order_items oi
+            LEFT JOIN products p ON p.id = oi.product_id
+            WHERE oi.order_id = ?
+            ORDER BY oi.id
+            "#
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        let cashier_name = sqlx::query_scalar("SELECT full_name FROM users WHERE id = ?")
+            .bind(order.cashier_id)
+            .fetch_optional(pool)
             .await?;
-        }
 
-        tx.commit().await?;
-        Ok(order_id)
+        let loyalty_settings = self.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await?.unwrap_or_default();
+        let loyalty = match order.customer_id {
+            Some(customer_id) if loyalty_settings.enabled => {
+                let (earned, redeemed): (i64, i64) = sqlx::query_as(
+                    r#"
+                    SELECT COALESCE(SUM(CASE WHEN kind = 'earn' THEN points END), 0),
+                           COALESCE(-SUM(CASE WHEN kind IN ('redeem', 'restore') THEN points END), 0)
+                    FROM loyalty_ledger
+                    WHERE order_id = ? AND return_id IS NULL
+                    "#
+                )
+                .bind(order_id)
+                .fetch_one(pool)
+                .await?;
+
+                let mut conn = pool.acquire().await?;
+                let balance = Self::loyalty_balance(&mut conn, customer_id, Utc::now()).await?;
+                Some(super::receipt::ReceiptLoyalty { earned, redeemed, balance })
+            }
+            _ => None,
+        };
+
+        let gift_cards = sqlx::query_as::<_, GiftCard>(
+            "SELECT * FROM gift_card_balances WHERE issued_order_id = ? AND kind = 'gift_card' ORDER BY id"
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(super::receipt::ReceiptData {
+            order,
+            cashier_name,
+            lines,
+            gift_cards,
+            taxes: self.get_order_tax_summary(order_id).await?.taxes,
+            promotions: self.get_order_promotions(order_id).await?,
+            payments: self.get_order_payments(order_id).await?,
+            loyalty,
+        })
     }
 
-    pub async fn complete_order(&self, order_id: i64) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    pub async fn get_order_items(&self, order_id: i64) -> Result<Vec<OrderItem>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        sqlx::query(
-            "UPDATE orders SET status = 'completed', updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        let items = sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM order_items WHERE order_id = ?"
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn get_oversold_lines(&self, order_id: Option<i64>, include_reviewed: bool) -> Result<Vec<OversoldLine>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let lines = sqlx::query_as::<_, OversoldLine>(
+            r#"
+            SELECT l.*, p.name AS product_name
+            FROM oversold_lines l
+            JOIN products p ON p.id = l.product_id
+            WHERE (? IS NULL OR l.order_id = ?)
+              AND (? OR l.reviewed_at IS NULL)
+            ORDER BY l.created_at, l.id
+            "#
         )
         .bind(order_id)
+        .bind(order_id)
+        .bind(include_reviewed)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(lines)
+    }
+
+    pub async fn review_oversold_line(&self, user: &UserInfo, line_id: i64, notes: &str) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let reviewed_at: Option<DateTime<Utc>> = sqlx::query_scalar("SELECT reviewed_at FROM oversold_lines WHERE id = ?")
+            .bind(line_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Oversold line #{} not found", line_id)))?;
+        if reviewed_at.is_some() {
+            return Err(AppError::conflict("This oversold line has already been reviewed").into());
+        }
+
+        sqlx::query(
+            "UPDATE oversold_lines SET reviewed_at = ?, reviewed_by = ?, reviewer_name = ?, review_notes = ? WHERE id = ?"
+        )
+        .bind(Utc::now())
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(notes)
+        .bind(line_id)
         .execute(pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn cancel_order(&self, order_id: i64) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    // Parked carts
+    /// Stock of a product held by parked carts whose reservation has not run out
+    async fn reserved_quantity(conn: &mut SqliteConnection, product_id: i64, now: DateTime<Utc>) -> Result<i32> {
+        let reserved: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(i.quantity), 0) FROM parked_cart_items i JOIN parked_carts c ON c.id = i.cart_id WHERE i.product_id = ? AND c.reserved_until > ?"
+        )
+        .bind(product_id)
+        .bind(now)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(reserved as i32)
+    }
+
+    pub async fn park_cart(&self, user: &UserInfo, cart: &ParkCartRequest, reserve_for: Option<chrono::Duration>) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        // Start a transaction
         let mut tx = pool.begin().await?;
+        let now = Utc::now();
+        let reserved_until = reserve_for.map(|duration| now + duration);
+
+        // A product may be on several lines; reservations are checked per product
+        let mut requested: Vec<(i64, i32)> = Vec::new();
+        for item in &cart.items {
+            match requested.iter_mut().find(|(product_id, _)| *product_id == item.product_id) {
+                Some((_, quantity)) => *quantity += item.quantity,
+                None => requested.push((item.product_id, item.quantity)),
+            }
+        }
 
-        // Get order items to restore stock
-        let order_items = sqlx::query_as::<_, OrderItem>(
-            "SELECT * FROM order_items WHERE order_id = ?"
+        let mut shortages = Vec::new();
+        for (product_id, quantity) in requested {
+            let (product_name, on_hand): (String, i32) = sqlx::query_as("SELECT name, quantity FROM products WHERE id = ?")
+                .bind(product_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::not_found(format!("Product #{} not found", product_id)))?;
+
+            if reserved_until.is_some() {
+                let available = on_hand - Self::reserved_quantity(&mut tx, product_id, now).await?;
+                if quantity > available {
+                    shortages.push(StockShortage { product_id, product_name, requested: quantity, available: available.max(0) });
+                }
+            }
+        }
+        if !shortages.is_empty() {
+            return Err(AppError::insufficient_stock(shortages).into());
+        }
+
+        let result = sqlx::query(
+            "INSERT INTO parked_carts (created_at, label, customer_name, payment_method, user_id, user_name, reserved_until) VALUES (?, ?, ?, ?, ?, ?, ?)"
         )
-        .bind(order_id)
-        .fetch_all(&mut *tx)
+        .bind(now)
+        .bind(cart.label.trim())
+        .bind(cart.customer_name.as_deref().unwrap_or("").trim())
+        .bind(cart.payment_method.as_deref().unwrap_or("cash"))
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(reserved_until)
+        .execute(&mut *tx)
         .await?;
 
-        // Restore stock for each item
-        for item in order_items {
+        let cart_id = result.last_insert_rowid();
+        for item in &cart.items {
             sqlx::query(
-                "UPDATE products SET quantity = quantity + ? WHERE id = ?"
+                "INSERT INTO parked_cart_items (cart_id, product_id, quantity, price_at_sale) VALUES (?, ?, ?, ?)"
             )
-            .bind(item.quantity)
+            .bind(cart_id)
             .bind(item.product_id)
+            .bind(item.quantity)
+            .bind(item.price_at_sale)
             .execute(&mut *tx)
             .await?;
+        }
 
-            // Record inventory movement
-            sqlx::query(
-                "INSERT INTO inventory_movements (product_id, quantity_change, movement_type, notes) VALUES (?, ?, 'return', ?)"
-            )
-            .bind(item.product_id)
-            .bind(item.quantity)
-            .bind(format!("Order cancellation - Order #{}", order_id))
+        tx.commit().await?;
+        Ok(cart_id)
+    }
+
+    pub async fn get_parked_carts(&self) -> Result<Vec<ParkedCart>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let carts = sqlx::query_as::<_, ParkedCart>(
+            r#"
+            SELECT c.*,
+                   COALESCE(SUM(i.quantity), 0) AS item_count,
+                   COALESCE(SUM(i.quantity * i.price_at_sale), 0) AS subtotal
+            FROM parked_carts c
+            LEFT JOIN parked_cart_items i ON i.cart_id = c.id
+            GROUP BY c.id
+            ORDER BY c.created_at
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(carts)
+    }
+
+    async fn parked_cart_in(conn: &mut SqliteConnection, cart_id: i64) -> Result<ParkedCartDetails> {
+        let cart = sqlx::query_as::<_, ParkedCart>(
+            r#"
+            SELECT c.*,
+                   COALESCE(SUM(i.quantity), 0) AS item_count,
+                   COALESCE(SUM(i.quantity * i.price_at_sale), 0) AS subtotal
+            FROM parked_carts c
+            LEFT JOIN parked_cart_items i ON i.cart_id = c.id
+            WHERE c.id = ?
+            GROUP BY c.id
+            "#
+        )
+        .bind(cart_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("Parked cart #{} not found", cart_id)))?;
+
+        let items = sqlx::query_as::<_, ParkedCartItem>(
+            r#"
+            SELECT i.*, p.name AS product_name, p.sku
+            FROM parked_cart_items i
+            JOIN products p ON p.id = i.product_id
+            WHERE i.cart_id = ?
+            ORDER BY i.id
+            "#
+        )
+        .bind(cart_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ParkedCartDetails { cart, items })
+    }
+
+    pub async fn get_parked_cart(&self, cart_id: i64) -> Result<ParkedCartDetails> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut conn = pool.acquire().await?;
+        Self::parked_cart_in(&mut conn, cart_id).await
+    }
+
+    /// Take a parked cart back to the till. The cart is removed, releasing
+    /// any stock it held.
+    pub async fn resume_parked_cart(&self, cart_id: i64) -> Result<ParkedCartDetails> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let mut tx = pool.begin().await?;
+        let details = Self::parked_cart_in(&mut tx, cart_id).await?;
+
+        sqlx::query("DELETE FROM parked_carts WHERE id = ?")
+            .bind(cart_id)
             .execute(&mut *tx)
             .await?;
+
+        tx.commit().await?;
+        Ok(details)
+    }
+
+    pub async fn discard_parked_cart(&self, cart_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let result = sqlx::query("DELETE FROM parked_carts WHERE id = ?")
+            .bind(cart_id)
+            .execute(pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Parked cart #{} not found", cart_id)).into());
         }
+        Ok(())
+    }
 
-        // Update order status
-        sqlx::query(
-            "UPDATE orders SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+    // Cash drawer audit log
+    pub async fn create_cash_drawer_event(&self, user: &UserInfo, reason: CashDrawerReason, order_id: Option<i64>, notes: &str) -> Result<i64> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let result = sqlx::query(
+            "INSERT INTO cash_drawer_events (created_at, user_id, user_name, reason, order_id, notes, opened) VALUES (?, ?, ?, ?, ?, ?, 0)"
         )
+        .bind(Utc::now())
+        .bind(user.id)
+        .bind(&user.full_name)
+        .bind(reason)
         .bind(order_id)
-        .execute(&mut *tx)
+        .bind(notes)
+        .execute(pool)
         .await?;
 
-        tx.commit().await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn mark_cash_drawer_opened(&self, event_id: i64) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        sqlx::query("UPDATE cash_drawer_events SET opened = 1 WHERE id = ?")
+            .bind(event_id)
+            .execute(pool)
+            .await?;
+
         Ok(())
     }
 
-    pub async fn get_recent_orders(&self, limit: i64) -> Result<Vec<Order>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    pub async fn get_cash_drawer_events(&self, range: &super::reports::ReportRange, reason: Option<CashDrawerReason>) -> Result<Vec<CashDrawerEvent>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        let orders = sqlx::query_as::<_, Order>(
-            "SELECT * FROM orders ORDER BY created_at DESC LIMIT ?"
+        let events = sqlx::query_as::<_, CashDrawerEvent>(
+            r#"
+            SELECT * FROM cash_drawer_events
+            WHERE datetime(created_at) >= ? AND datetime(created_at) < ?
+              AND (? IS NULL OR reason = ?)
+            ORDER BY created_at DESC, id DESC
+            "#
         )
-        .bind(limit)
+        .bind(range.start_sql())
+        .bind(range.end_sql())
+        .bind(reason)
+        .bind(reason)
         .fetch_all(pool)
         .await?;
 
-        Ok(orders)
+        Ok(events)
     }
 
-    pub async fn get_order_items(&self, order_id: i64) -> Result<Vec<OrderItem>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    /// Record a manager's approval for `user` to sell a product at `price`,
+    /// kept under the hash of its single-use token
+    pub async fn create_price_override_approval(&self, token_hash: &str, user: &UserInfo, approver: &User, product_id: i64, price: Money, expires_at: DateTime<Utc>) -> Result<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        let items = sqlx::query_as::<_, OrderItem>(
-            "SELECT * FROM order_items WHERE order_id = ?"
+        let result = sqlx::query(
+            r#"
+            INSERT INTO price_override_approvals (created_at, token_hash, cashier_id, product_id, price, approved_by, approver_name, expires_at)
+            SELECT ?, ?, ?, id, ?, ?, ?, ? FROM products WHERE id = ?
+            "#
         )
-        .bind(order_id)
+        .bind(Utc::now())
+        .bind(token_hash)
+        .bind(user.id)
+        .bind(price)
+        .bind(approver.id)
+        .bind(&approver.full_name)
+        .bind(expires_at)
+        .bind(product_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(format!("Product #{} not found", product_id)).into());
+        }
+        Ok(())
+    }
+
+    /// Lines sold away from their shelf price in a period, newest first
+    pub async fn get_price_overrides(&self, range: &super::reports::ReportRange) -> Result<Vec<PriceOverride>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let overrides = sqlx::query_as::<_, PriceOverride>(
+            r#"
+            SELECT * FROM price_overrides
+            WHERE datetime(created_at) >= ? AND datetime(created_at) < ?
+            ORDER BY created_at DESC, id DESC
+            "#
+        )
+        .bind(range.start_sql())
+        .bind(range.end_sql())
         .fetch_all(pool)
         .await?;
 
-        Ok(items)
+        Ok(overrides)
     }
 
     // Notification management methods
     pub async fn create_notification(&self, user_id: Option<i64>, title: &str, message: &str, notification_type: &str, priority: &str, product_id: Option<i64>) -> Result<i64> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         let result = sqlx::query(
             "INSERT INTO notifications (user_id, title, message, type, priority, product_id) VALUES (?, ?, ?, ?, ?, ?)"
@@ -729,7 +3972,7 @@ impl Database {
     }
 
     pub async fn get_notifications(&self, user_id: Option<i64>, unread_only: bool) -> Result<Vec<Notification>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         let notifications = if let Some(uid) = user_id {
             if unread_only {
@@ -767,7 +4010,7 @@ impl Database {
     }
 
     pub async fn mark_notification_read(&self, notification_id: i64) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
         sqlx::query("UPDATE notifications SET is_read = TRUE WHERE id = ?")
             .bind(notification_id)
@@ -777,21 +4020,40 @@ impl Database {
         Ok(())
     }
 
-    pub async fn get_expiring_products(&self, days_ahead: i32) -> Result<Vec<Product>> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    /// Batches with stock left that expire within `days_ahead` local days,
+    /// including batches that have already expired
+    pub async fn get_expiring_products(&self, days_ahead: i32) -> Result<Vec<ExpiringBatch>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
 
-        let products = sqlx::query_as::<_, Product>(
-            "SELECT * FROM products WHERE expiry_date IS NOT NULL AND expiry_date <= date('now', '+' || ? || ' days') ORDER BY expiry_date ASC"
+        let batches = sqlx::query_as::<_, ExpiringBatch>(
+            r#"
+            SELECT b.id AS batch_id,
+                   b.batch_number,
+                   p.id AS product_id,
+                   p.name AS product_name,
+                   p.sku,
+                   b.expiry_date,
+                   b.quantity,
+                   CAST(julianday(b.expiry_date) - julianday(date('now', 'localtime')) AS INTEGER) AS days_until_expiry
+            FROM product_batches b
+            JOIN products p ON p.id = b.product_id
+            WHERE b.quantity > 0
+              AND b.expiry_date IS NOT NULL
+              AND b.expiry_date <= date('now', 'localtime', '+' || ? || ' days')
+            ORDER BY b.expiry_date ASC, p.name ASC
+            "#
         )
         .bind(days_ahead)
         .fetch_all(pool)
         .await?;
 
-        Ok(products)
+        Ok(batches)
     }
 
     pub async fn check_and_create_alerts(&self) -> Result<()> {
-        let pool = self.pool.as_ref().ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        self.expire_loyalty_points().await?;
 
         // Check for low stock products
         let low_stock_products = self.get_low_stock_products().await?;
@@ -817,28 +4079,44 @@ impl Database {
             }
         }
 
-        // Check for expiring products (within 7 days)
-        let expiring_products = self.get_expiring_products(7).await?;
-        for product in expiring_products {
+        // Check for expiring batches (within 7 days), one alert per product
+        let expiring_batches = self.get_expiring_products(7).await?;
+        let mut alerted_products = Vec::new();
+        for batch in &expiring_batches {
+            if alerted_products.contains(&batch.product_id) {
+                continue;
+            }
+            alerted_products.push(batch.product_id);
+
             // Check if we already have a recent notification for this product
             let existing = sqlx::query_scalar::<_, i64>(
                 "SELECT COUNT(*) FROM notifications WHERE product_id = ? AND type = 'expiry_warning' AND created_at > datetime('now', '-1 day')"
             )
-            .bind(product.id)
+            .bind(batch.product_id)
             .fetch_one(pool)
             .await?;
 
             if existing == 0 {
-                // Note: Expiry date functionality removed to match online API schema
-                // This notification is now for low stock only
+                let product_batches: Vec<&ExpiringBatch> = expiring_batches.iter()
+                    .filter(|b| b.product_id == batch.product_id)
+                    .collect();
+                let units: i32 = product_batches.iter().map(|b| b.quantity).sum();
+
+                // Batches are ordered by expiry, so the first one is the most urgent
+                let (title, priority) = if batch.days_until_expiry < 0 {
+                    ("Expired Stock", "high")
+                } else {
+                    ("Expiry Warning", "medium")
+                };
+
                 self.create_notification(
                     None, // Send to all managers/admins
-                    "Low Stock Alert",
-                    &format!("Product '{}' is running low. Current stock: {}, Reorder level: {}",
-                            product.name, product.quantity, product.reorder_level),
-                    "low_stock",
-                    "medium",
-                    Some(product.id)
+                    title,
+                    &format!("Product '{}' has {} unit(s) in {} batch(es) expiring soon. Earliest: batch '{}' on {}",
+                            batch.product_name, units, product_batches.len(), batch.batch_number, batch.expiry_date),
+                    "expiry_warning",
+                    priority,
+                    Some(batch.product_id)
                 ).await?;
             }
         }
@@ -846,82 +4124,1513 @@ impl Database {
         Ok(())
     }
 
-    // Report methods - using simple mock data for now
-    pub async fn get_sales_by_date_range(&self, _start_date: &str, _end_date: &str) -> Result<Vec<super::reports::SalesReport>> {
-        // Return mock data for now
-        Ok(vec![
-            super::reports::SalesReport {
-                date: "2024-01-01".to_string(),
-                total_sales: 1250.50,
-                total_orders: 15,
-                average_order_value: 83.37,
-            },
+    // Report methods - only completed orders count as sales, net of what
+    // has since been refunded against them
+    pub async fn get_sales_by_date_range(&self, range: &super::reports::ReportRange, period: super::reports::ReportPeriod) -> Result<Vec<super::reports::SalesReport>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let query = format!(
+            r#"
+            SELECT {bucket} AS period_start,
+                   SUM(o.total_amount) - COALESCE(SUM(r.refunded), 0) AS total_sales,
+                   COUNT(*) AS total_orders
+            FROM orders o
+            LEFT JOIN (
+                SELECT order_id, SUM(refund_total) AS refunded FROM order_returns GROUP BY order_id
+            ) r ON r.order_id = o.id
+            WHERE o.status IN ('completed', 'partially_refunded', 'refunded')
+              AND datetime(o.created_at) >= ?
+              AND datetime(o.created_at) < ?
+            GROUP BY period_start
+            ORDER BY period_start ASC
+            "#,
+            bucket = period.bucket_sql()
+        );
+
+        let rows = sqlx::query(&query)
+            .bind(range.offset_modifier())
+            .bind(range.start_sql())
+            .bind(range.end_sql())
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| {
+            let total_sales: Money = row.get("total_sales");
+            let total_orders: i64 = row.get("total_orders");
             super::reports::SalesReport {
-                date: "2024-01-02".to_string(),
-                total_sales: 980.25,
-                total_orders: 12,
-                average_order_value: 81.69,
-            },
-        ])
-    }
-
-    pub async fn get_product_sales_by_date_range(&self, _start_date: &str, _end_date: &str) -> Result<Vec<super::reports::ProductSalesReport>> {
-        // Return mock data for now
-        Ok(vec![
-            super::reports::ProductSalesReport {
-                product_id: 1,
-                product_name: "Coca Cola 500ml".to_string(),
-                quantity_sold: 25,
-                total_revenue: 62.50,
-            },
-            super::reports::ProductSalesReport {
-                product_id: 2,
-                product_name: "Bread Loaf".to_string(),
-                quantity_sold: 15,
-                total_revenue: 59.85,
-            },
-        ])
+                date: row.get("period_start"),
+                total_sales,
+                total_orders,
+                average_order_value: total_sales.share(1, total_orders),
+            }
+        }).collect())
+    }
+
+    pub async fn get_product_sales_by_date_range(&self, range: &super::reports::ReportRange) -> Result<Vec<super::reports::ProductSalesReport>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT oi.product_id AS product_id,
+                   COALESCE(p.name, 'Deleted product #' || oi.product_id) AS product_name,
+                   CAST(TOTAL(oi.quantity) - TOTAL(ri.quantity) AS INTEGER) AS quantity_sold,
+                   SUM(oi.line_total) - COALESCE(SUM(ri.refunded), 0) AS total_revenue
+            FROM order_items oi
+            JOIN orders o ON o.id = oi.order_id
+            LEFT JOIN (
+                SELECT order_item_id, SUM(quantity) AS quantity, SUM(refund_amount) AS refunded
+                FROM order_return_items GROUP BY order_item_id
+            ) ri ON ri.order_item_id = oi.id
+            LEFT JOIN products p ON p.id = oi.product_id
+            WHERE o.status IN ('completed', 'partially_refunded', 'refunded')
+              AND datetime(o.created_at) >= ?
+              AND datetime(o.created_at) < ?
+            GROUP BY oi.product_id
+            ORDER BY total_revenue DESC, quantity_sold DESC
+            "#
+        )
+        .bind(range.start_sql())
+        .bind(range.end_sql())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| super::reports::ProductSalesReport {
+            product_id: row.get("product_id"),
+            product_name: row.get("product_name"),
+            quantity_sold: row.get("quantity_sold"),
+            total_revenue: row.get("total_revenue"),
+        }).collect())
+    }
+
+    /// Discount given by each promotion on completed orders
+    pub async fn get_promotion_sales_by_date_range(&self, range: &super::reports::ReportRange) -> Result<Vec<super::reports::PromotionReport>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT op.promotion_id AS promotion_id,
+                   COALESCE(p.name, MAX(op.name)) AS promotion_name,
+                   COUNT(DISTINCT op.order_id) AS orders,
+                   SUM(op.discount_amount) AS total_discount
+            FROM order_promotions op
+            JOIN orders o ON o.id = op.order_id
+            LEFT JOIN promotions p ON p.id = op.promotion_id
+            WHERE o.status IN ('completed', 'partially_refunded', 'refunded')
+              AND datetime(o.created_at) >= ?
+              AND datetime(o.created_at) < ?
+            GROUP BY op.promotion_id
+            ORDER BY total_discount DESC
+            "#
+        )
+        .bind(range.start_sql())
+        .bind(range.end_sql())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| super::reports::PromotionReport {
+            promotion_id: row.get("promotion_id"),
+            promotion_name: row.get("promotion_name"),
+            orders: row.get("orders"),
+            total_discount: row.get("total_discount"),
+        }).collect())
+    }
+
+    /// Revenue taken per tender type on completed orders
+    pub async fn get_tender_sales_by_date_range(&self, range: &super::reports::ReportRange) -> Result<Vec<super::reports::TenderReport>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT op.tender_type AS tender_type,
+                   COUNT(*) AS payments,
+                   SUM(op.amount) AS total_amount
+            FROM order_payments op
+            JOIN orders o ON o.id = op.order_id
+            WHERE o.status IN ('completed', 'partially_refunded', 'refunded')
+              AND datetime(o.created_at) >= ?
+              AND datetime(o.created_at) < ?
+            GROUP BY op.tender_type
+            ORDER BY total_amount DESC
+            "#
+        )
+        .bind(range.start_sql())
+        .bind(range.end_sql())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| super::reports::TenderReport {
+            tender_type: row.get("tender_type"),
+            payments: row.get("payments"),
+            total_amount: row.get("total_amount"),
+        }).collect())
+    }
+
+    /// Sales rung up and returns processed per user. Refunds are counted
+    /// against whoever processed the return, not the original cashier.
+    pub async fn get_cashier_sales_by_date_range(&self, range: &super::reports::ReportRange) -> Result<Vec<super::reports::CashierSalesReport>> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let rows = sqlx::query(
+            r#"
+            WITH sales AS (
+                SELECT cashier_id AS user_id, COUNT(*) AS orders, SUM(total_amount) AS total_sales
+                FROM orders
+                WHERE status IN ('completed', 'partially_refunded', 'refunded')
+                  AND cashier_id IS NOT NULL
+                  AND datetime(created_at) >= ?
+                  AND datetime(created_at) < ?
+                GROUP BY cashier_id
+            ),
+            refunds AS (
+                SELECT user_id, COUNT(*) AS returns, SUM(refund_total) AS total_refunds
+                FROM order_returns
+                WHERE datetime(created_at) >= ?
+                  AND datetime(created_at) < ?
+                GROUP BY user_id
+            ),
+            cashiers AS (
+                SELECT user_id FROM sales UNION SELECT user_id FROM refunds
+            )
+            SELECT c.user_id AS cashier_id,
+                   COALESCE(u.full_name, 'User #' || c.user_id) AS cashier_name,
+                   COALESCE(s.orders, 0) AS orders,
+                   COALESCE(s.total_sales, 0) AS total_sales,
+                   COALESCE(r.returns, 0) AS returns,
+                   COALESCE(r.total_refunds, 0) AS total_refunds
+            FROM cashiers c
+            LEFT JOIN users u ON u.id = c.user_id
+            LEFT JOIN sales s ON s.user_id = c.user_id
+            LEFT JOIN refunds r ON r.user_id = c.user_id
+            ORDER BY total_sales DESC, cashier_name
+            "#
+        )
+        .bind(range.start_sql())
+        .bind(range.end_sql())
+        .bind(range.start_sql())
+        .bind(range.end_sql())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| {
+            let orders: i64 = row.get("orders");
+            let total_sales: Money = row.get("total_sales");
+            super::reports::CashierSalesReport {
+                cashier_id: row.get("cashier_id"),
+                cashier_name: row.get("cashier_name"),
+                orders,
+                total_sales,
+                average_order: total_sales.share(1, orders),
+                returns: row.get("returns"),
+                total_refunds: row.get("total_refunds"),
+            }
+        }).collect())
     }
 
     pub async fn get_inventory_report(&self) -> Result<super::reports::InventoryReport> {
-        // Return mock data for now
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let totals = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS total_products,
+                   COALESCE(SUM(price * quantity), 0) AS total_value,
+                   COUNT(CASE WHEN quantity <= reorder_level THEN 1 END) AS low_stock_count
+            FROM products
+            "#
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let expiring_soon_count = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(DISTINCT product_id) FROM product_batches
+            WHERE quantity > 0
+              AND expiry_date IS NOT NULL
+              AND expiry_date <= date('now', 'localtime', '+7 days')
+            "#
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let categories = sqlx::query(
+            r#"
+            SELECT category,
+                   COUNT(*) AS product_count,
+                   COALESCE(SUM(price * quantity), 0) AS total_value
+            FROM products
+            GROUP BY category
+            ORDER BY total_value DESC
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
         Ok(super::reports::InventoryReport {
-            total_products: 5,
-            total_value: 1250.75,
-            low_stock_count: 2,
-            expiring_soon_count: 2,
-            categories: vec![
-                super::reports::CategoryReport {
-                    category: "Beverages".to_string(),
-                    product_count: 2,
-                    total_value: 245.00,
-                },
-                super::reports::CategoryReport {
-                    category: "Bakery".to_string(),
-                    product_count: 1,
-                    total_value: 99.75,
-                },
-                super::reports::CategoryReport {
-                    category: "Dairy".to_string(),
-                    product_count: 1,
-                    total_value: 135.00,
-                },
-            ],
+            total_products: totals.get("total_products"),
+            total_value: totals.get("total_value"),
+            low_stock_count: totals.get("low_stock_count"),
+            expiring_soon_count,
+            categories: categories.into_iter().map(|row| super::reports::CategoryReport {
+                category: row.get("category"),
+                product_count: row.get("product_count"),
+                total_value: row.get("total_value"),
+            }).collect(),
         })
     }
 
-    pub async fn get_dashboard_stats(&self) -> Result<super::reports::DashboardStats> {
-        // Get recent orders using existing method
+    pub async fn get_dashboard_stats(&self, today: &super::reports::ReportRange) -> Result<super::reports::DashboardStats> {
+        let pool = self.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+        let sales = sqlx::query(
+            r#"
+            SELECT COALESCE(SUM(o.total_amount), 0) - COALESCE(SUM(r.refunded), 0) AS today_sales,
+                   COUNT(*) AS today_orders
+            FROM orders o
+            LEFT JOIN (
+                SELECT order_id, SUM(refund_total) AS refunded FROM order_returns GROUP BY order_id
+            ) r ON r.order_id = o.id
+            WHERE o.status IN ('completed', 'partially_refunded', 'refunded')
+              AND datetime(o.created_at) >= ?
+              AND datetime(o.created_at) < ?
+            "#
+        )
+        .bind(today.start_sql())
+        .bind(today.end_sql())
+        .fetch_one(pool)
+        .await?;
+
+        let inventory = self.get_inventory_report().await?;
         let recent_orders = self.get_recent_orders(5).await?;
 
-        // Return mock data for now
         Ok(super::reports::DashboardStats {
-            today_sales: 450.75,
-            today_orders: 8,
-            total_products: 5,
-            low_stock_count: 2,
-            total_inventory_value: 1250.75,
+            today_sales: sales.get("today_sales"),
+            today_orders: sales.get("today_orders"),
+            total_products: inventory.total_products,
+            low_stock_count: inventory.low_stock_count,
+            total_inventory_value: inventory.total_value,
             recent_orders,
         })
     }
 }
+
+#[cfg(test)]
+impl Database {
+    /// A fresh, fully migrated in-memory database. A single connection is
+    /// used because every SQLite `:memory:` connection is its own database.
+    pub async fn in_memory() -> Result<Self> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+
+        let mut database = Self { pool: Some(pool) };
+        database.migrate().await?;
+        Ok(database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cashier() -> UserInfo {
+        UserInfo {
+            id: 3,
+            email: "cashier@example.com".to_string(),
+            full_name: "Till Operator".to_string(),
+            role: "cashier".to_string(),
+            permissions: vec![],
+        }
+    }
+
+    /// An in-memory database with the `cashier()` user on file
+    async fn test_db() -> Database {
+        let db = Database::in_memory().await.unwrap();
+        sqlx::query(
+            "INSERT INTO users (id, created_at, updated_at, email, hashed_password, full_name, role, is_active, is_superuser) VALUES (3, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, 'cashier@example.com', '', 'Till Operator', 'cashier', TRUE, FALSE)"
+        )
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .unwrap();
+        db
+    }
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    fn product_request(sku: &str, quantity: i32, expiry_date: Option<NaiveDate>) -> CreateProductRequest {
+        CreateProductRequest {
+            name: format!("Product {}", sku),
+            description: None,
+            sku: sku.to_string(),
+            category: "grocery".to_string(),
+            price: money(2.5),
+            cost: money(1.0),
+            quantity,
+            reorder_level: 0,
+            expiry_date,
+            supplier_id: None,
+            tax_class_id: None,
+            stock_policy: None,
+            location: None,
+        }
+    }
+
+    fn batch_request(number: &str, expiry_date: &str, quantity: i32) -> ReceiveBatchRequest {
+        ReceiveBatchRequest {
+            batch_number: Some(number.to_string()),
+            received_date: None,
+            expiry_date: Some(expiry_date.parse().unwrap()),
+            quantity,
+            notes: None,
+        }
+    }
+
+    fn remaining(batches: &[ProductBatch]) -> Vec<(&str, i32)> {
+        batches.iter().map(|b| (b.batch_number.as_str(), b.quantity)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sales_deplete_batches_first_expired_first_out() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("MILK", 2, None)).await.unwrap();
+        db.receive_batch(&cashier(), product_id, batch_request("LATE", "2030-03-01", 4)).await.unwrap();
+        db.receive_batch(&cashier(), product_id, batch_request("EARLY", "2030-01-01", 3)).await.unwrap();
+
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 5, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+
+        let batches = db.get_product_batches(product_id, true).await.unwrap();
+        assert_eq!(remaining(&batches), vec![("EARLY", 0), ("LATE", 2), ("OPENING", 2)]);
+
+        db.cancel_order(&cashier(), order_id, false).await.unwrap();
+
+        let batches = db.get_product_batches(product_id, true).await.unwrap();
+        assert_eq!(remaining(&batches), vec![("EARLY", 3), ("LATE", 4), ("OPENING", 2)]);
+        assert_eq!(db.get_product_by_sku("MILK").await.unwrap().unwrap().quantity, 9);
+    }
+
+    #[tokio::test]
+    async fn test_expiring_products_only_include_batches_with_stock() {
+        let db = test_db().await;
+        let soon = Utc::now().date_naive() + chrono::Duration::days(2);
+        let product_id = db.create_product(&cashier(), product_request("BREAD", 3, Some(soon))).await.unwrap();
+        db.receive_batch(&cashier(), product_id, batch_request("FAR", "2099-01-01", 5)).await.unwrap();
+
+        let expiring = db.get_expiring_products(7).await.unwrap();
+        assert_eq!(expiring.len(), 1);
+        assert_eq!(expiring[0].batch_number, "OPENING");
+        assert_eq!(expiring[0].quantity, 3);
+
+        db.update_stock(&cashier(), product_id, UpdateStockRequest {
+            quantity_change: -3,
+            movement_type: StockMovementKind::WriteOff,
+            notes: None,
+        }).await.unwrap();
+
+        assert!(db.get_expiring_products(7).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_orders_store_per_line_tax_and_summary() {
+        let db = test_db().await;
+        let vat = db.create_tax_rate(&TaxRateRequest { name: "VAT".to_string(), rate: 20.0, is_compound: false, is_active: true }).await.unwrap();
+        let levy = db.create_tax_rate(&TaxRateRequest { name: "Levy".to_string(), rate: 5.0, is_compound: true, is_active: true }).await.unwrap();
+        let standard = db.create_tax_class(&TaxClassRequest {
+            name: "Standard".to_string(),
+            description: None,
+            prices_include_tax: true,
+            rate_ids: vec![vat],
+        }).await.unwrap();
+        let sugar = db.create_tax_class(&TaxClassRequest {
+            name: "Sugar".to_string(),
+            description: None,
+            prices_include_tax: false,
+            rate_ids: vec![vat, levy],
+        }).await.unwrap();
+
+        let soap = db.create_product(&cashier(), CreateProductRequest { price: money(6.0), tax_class_id: Some(standard), ..product_request("SOAP", 5, None) }).await.unwrap();
+        let cola = db.create_product(&cashier(), CreateProductRequest { price: money(10.0), tax_class_id: Some(sugar), ..product_request("COLA", 5, None) }).await.unwrap();
+        let rice = db.create_product(&cashier(), product_request("RICE", 5, None)).await.unwrap();
+
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: vec![
+                OrderItemRequest { product_id: soap, quantity: 2, price_at_sale: money(6.0), override_reason: None },
+                OrderItemRequest { product_id: cola, quantity: 1, price_at_sale: money(10.0), override_reason: None },
+                OrderItemRequest { product_id: rice, quantity: 1, price_at_sale: money(2.5), override_reason: None },
+            ],
+        }).await.unwrap();
+
+        let items = db.get_order_items(order_id).await.unwrap();
+        let amounts: Vec<(Money, Money, Money)> = items.iter().map(|i| (i.net_amount, i.tax_amount, i.line_total)).collect();
+        assert_eq!(amounts, vec![(money(10.0), money(2.0), money(12.0)), (money(10.0), money(2.6), money(12.6)), (money(2.5), Money::ZERO, money(2.5))]);
+
+        let summary = db.get_order_tax_summary(order_id).await.unwrap();
+        assert_eq!((summary.subtotal, summary.tax_total, summary.total_amount), (money(22.5), money(4.6), money(27.1)));
+        let taxes: Vec<(&str, Money, Money)> = summary.taxes.iter().map(|t| (t.name.as_str(), t.taxable_amount, t.tax_amount)).collect();
+        assert_eq!(taxes, vec![("VAT", money(20.0), money(4.0)), ("Levy", money(12.0), money(0.6))]);
+
+        // Rate changes must not rewrite tax already charged
+        db.update_tax_rate(vat, &TaxRateRequest { name: "VAT".to_string(), rate: 25.0, is_compound: false, is_active: true }).await.unwrap();
+        assert_eq!(db.get_order_tax_summary(order_id).await.unwrap().taxes[0].tax_amount, money(4.0));
+    }
+
+    #[tokio::test]
+    async fn test_promotions_are_recorded_and_reduce_taxable_amount() {
+        let db = test_db().await;
+        let vat = db.create_tax_rate(&TaxRateRequest { name: "VAT".to_string(), rate: 20.0, is_compound: false, is_active: true }).await.unwrap();
+        let standard = db.create_tax_class(&TaxClassRequest {
+            name: "Standard".to_string(),
+            description: None,
+            prices_include_tax: true,
+            rate_ids: vec![vat],
+        }).await.unwrap();
+        let juice = db.create_product(&cashier(), CreateProductRequest { price: money(2.4), tax_class_id: Some(standard), ..product_request("JUICE", 10, None) }).await.unwrap();
+
+        let promotion = |name: &str, promotion_type, scope, value, amount, coupon_code: Option<&str>| PromotionRequest {
+            name: name.to_string(),
+            description: None,
+            promotion_type,
+            scope,
+            value,
+            amount,
+            product_id: None,
+            category: None,
+            buy_quantity: Some(2),
+            get_quantity: Some(1),
+            min_order_amount: None,
+            coupon_code: coupon_code.map(str::to_string),
+            starts_at: None,
+            ends_at: None,
+            priority: 0,
+            is_active: true,
+        };
+        let bogof = db.create_promotion(&promotion("3 for 2", PromotionType::BuyXGetY, PromotionScope::Line, 100.0, Money::ZERO, None)).await.unwrap();
+        db.create_promotion(&promotion("Coupon", PromotionType::AmountOff, PromotionScope::Order, 0.0, money(1.2), Some("take1"))).await.unwrap();
+
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec!["TAKE1".to_string()],
+            payments: vec![OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(5.0), reference: None }],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id: juice, quantity: 3, price_at_sale: money(2.4), override_reason: None }],
+        }).await.unwrap();
+
+        let items = db.get_order_items(order_id).await.unwrap();
+        assert_eq!((items[0].discount_amount, items[0].net_amount, items[0].tax_amount, items[0].line_total), (money(3.6), money(3.0), money(0.6), money(3.6)));
+
+        let applied = db.get_order_promotions(order_id).await.unwrap();
+        let applied: Vec<(i64, Option<i64>, Option<&str>, Money)> = applied.iter()
+            .map(|p| (p.promotion_id, p.order_item_id, p.coupon_code.as_deref(), p.discount_amount))
+            .collect();
+        assert_eq!(applied, vec![(bogof, Some(items[0].id), None, money(2.4)), (bogof + 1, None, Some("TAKE1"), money(1.2))]);
+
+        db.complete_order(&cashier(), order_id).await.unwrap();
+        let range = super::super::reports::ReportRange::today(Some(0)).unwrap();
+        let report = db.get_promotion_sales_by_date_range(&range).await.unwrap();
+        assert_eq!(report.iter().map(|r| r.total_discount).sum::<Money>(), money(3.6));
+
+        let order = db.get_recent_orders(1).await.unwrap().remove(0);
+        assert_eq!((order.discount_total, order.subtotal, order.tax_total, order.total_amount), (money(3.6), money(3.0), money(0.6), money(3.6)));
+    }
+
+    #[tokio::test]
+    async fn test_split_tenders_must_cover_the_total_before_completion() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("BREAD", 10, None)).await.unwrap();
+        let tender = |tender_type, amount| OrderPaymentRequest { tender_type, amount, reference: None };
+
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![tender(TenderType::CreditCard, money(6.0))],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 4, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+
+        let err = db.complete_order(&cashier(), order_id).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+
+        let err = db.add_order_payment(&cashier(), order_id, &tender(TenderType::DebitCard, money(5.0))).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+
+        let paid = db.add_order_payment(&cashier(), order_id, &tender(TenderType::Cash, money(20.0))).await.unwrap();
+        assert_eq!((paid.amount_paid, paid.balance_due, paid.change_due), (money(10.0), Money::ZERO, money(16.0)));
+
+        db.complete_order(&cashier(), order_id).await.unwrap();
+        let order = db.get_order(order_id).await.unwrap();
+        assert_eq!(order.payment_method, "split");
+
+        let err = db.remove_order_payment(&cashier(), paid.payment_id).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+
+        let range = super::super::reports::ReportRange::today(Some(0)).unwrap();
+        let report = db.get_tender_sales_by_date_range(&range).await.unwrap();
+        let report: Vec<(TenderType, i64, Money)> = report.iter().map(|r| (r.tender_type, r.payments, r.total_amount)).collect();
+        assert_eq!(report, vec![(TenderType::CreditCard, 1, money(6.0)), (TenderType::Cash, 1, money(4.0))]);
+    }
+
+    #[tokio::test]
+    async fn test_returns_refund_original_tenders_and_never_exceed_the_sale() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("EGGS", 0, None)).await.unwrap();
+        db.receive_batch(&cashier(), product_id, batch_request("E1", "2030-01-01", 5)).await.unwrap();
+
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![
+                OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(3.0), reference: None },
+                OrderPaymentRequest { tender_type: TenderType::DebitCard, amount: money(4.5), reference: None },
+            ],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 3, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        let item_id = db.get_order_items(order_id).await.unwrap()[0].id;
+        let request = |quantity, disposition| CreateReturnRequest {
+            order_id,
+            reason: ReturnReason::Damaged,
+            notes: None,
+            items: vec![ReturnItemRequest { order_item_id: item_id, quantity, disposition }],
+            store_credit: false,
+        };
+
+        // Only completed orders can be returned, and completed ones never cancelled
+        let err = db.create_return(&cashier(), &request(1, ReturnDisposition::Restock)).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::InvalidTransition { .. }));
+        db.complete_order(&cashier(), order_id).await.unwrap();
+        let err = db.cancel_order(&cashier(), order_id, false).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::InvalidTransition { .. }));
+
+        let first = db.create_return(&cashier(), &request(2, ReturnDisposition::Restock)).await.unwrap();
+        let first = db.get_return(first).await.unwrap();
+        assert_eq!(first.order_return.refund_total, money(5.0));
+        let refunds: Vec<(TenderType, Money)> = first.refunds.iter().map(|r| (r.tender_type, r.amount)).collect();
+        assert_eq!(refunds, vec![(TenderType::DebitCard, money(4.5)), (TenderType::Cash, money(0.5))]);
+        assert_eq!(db.get_product_by_sku("EGGS").await.unwrap().unwrap().quantity, 4);
+        assert_eq!(remaining(&db.get_product_batches(product_id, true).await.unwrap()), vec![("E1", 4)]);
+
+        let err = db.create_return(&cashier(), &request(2, ReturnDisposition::WriteOff)).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+
+        db.create_return(&cashier(), &request(1, ReturnDisposition::WriteOff)).await.unwrap();
+        assert_eq!(db.get_product_by_sku("EGGS").await.unwrap().unwrap().quantity, 4);
+
+        let returns = db.get_order_returns(order_id).await.unwrap();
+        let refunded: Money = returns.iter().flat_map(|r| &r.refunds).map(|r| r.amount).sum();
+        assert_eq!(refunded, money(7.5));
+        assert_eq!(returns[1].refunds[0].tender_type, TenderType::Cash);
+        assert_eq!(db.get_order(order_id).await.unwrap().status, OrderStatus::Refunded);
+    }
+
+    #[tokio::test]
+    async fn test_reports_net_out_refunds() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("JAM", 10, None)).await.unwrap();
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(7.5), reference: None }],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 3, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        db.complete_order(&cashier(), order_id).await.unwrap();
+        let item_id = db.get_order_items(order_id).await.unwrap()[0].id;
+        let give_back = |quantity| CreateReturnRequest {
+            order_id,
+            reason: ReturnReason::ChangedMind,
+            notes: None,
+            items: vec![ReturnItemRequest { order_item_id: item_id, quantity, disposition: ReturnDisposition::Restock }],
+            store_credit: false,
+        };
+        async fn totals(db: &Database) -> (Money, i64, i64, Money, Money) {
+            let range = super::super::reports::ReportRange::today(Some(0)).unwrap();
+            let sales = db.get_sales_by_date_range(&range, super::super::reports::ReportPeriod::Daily).await.unwrap();
+            let products = db.get_product_sales_by_date_range(&range).await.unwrap();
+            let dashboard = db.get_dashboard_stats(&range).await.unwrap();
+            (sales[0].total_sales, sales[0].total_orders, products[0].quantity_sold, products[0].total_revenue, dashboard.today_sales)
+        }
+
+        db.create_return(&cashier(), &give_back(1)).await.unwrap();
+        assert_eq!(totals(&db).await, (money(5.0), 1, 2, money(5.0), money(5.0)));
+
+        // A fully refunded order still counts as an order, but for nothing
+        db.create_return(&cashier(), &give_back(2)).await.unwrap();
+        assert_eq!(totals(&db).await, (Money::ZERO, 1, 0, Money::ZERO, Money::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_order_lifecycle_is_enforced_and_recorded() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("TEA", 5, None)).await.unwrap();
+        let order = || CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 2, price_at_sale: money(2.5), override_reason: None }],
+        };
+
+        // A second cancel is rejected instead of restocking again
+        let voided = db.create_order(&cashier(), &[], order()).await.unwrap();
+        db.cancel_order(&cashier(), voided, false).await.unwrap();
+        let err = db.cancel_order(&cashier(), voided, false).await.unwrap_err();
+        assert_eq!(AppError::from(err), AppError::invalid_transition(format!("Order #{} cannot go from voided to voided", voided), "voided", "voided"));
+        assert_eq!(db.get_product_by_sku("TEA").await.unwrap().unwrap().quantity, 5);
+
+        // Paying in full marks the order paid; taking a tender back reopens it
+        let order_id = db.create_order(&cashier(), &[], order()).await.unwrap();
+        let cash = OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(5.0), reference: None };
+        let payment = db.add_order_payment(&cashier(), order_id, &cash).await.unwrap();
+        assert_eq!(db.get_order(order_id).await.unwrap().status, OrderStatus::Paid);
+        let err = db.cancel_order(&cashier(), order_id, false).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::InvalidTransition { .. }));
+        db.remove_order_payment(&cashier(), payment.payment_id).await.unwrap();
+        db.add_order_payment(&cashier(), order_id, &cash).await.unwrap();
+        db.complete_order(&cashier(), order_id).await.unwrap();
+
+        let events = db.get_order_events(order_id).await.unwrap();
+        let steps: Vec<(Option<OrderStatus>, OrderStatus, &str)> = events.iter()
+            .map(|e| (e.from_status, e.to_status, e.user_name.as_str()))
+            .collect();
+        assert_eq!(steps, vec![
+            (None, OrderStatus::Pending, "Till Operator"),
+            (Some(OrderStatus::Pending), OrderStatus::Paid, "Till Operator"),
+            (Some(OrderStatus::Paid), OrderStatus::Pending, "Till Operator"),
+            (Some(OrderStatus::Pending), OrderStatus::Paid, "Till Operator"),
+            (Some(OrderStatus::Paid), OrderStatus::Completed, "Till Operator"),
+        ]);
+        assert!(events.iter().all(|e| e.user_id == Some(3)));
+    }
+
+    #[tokio::test]
+    async fn test_sales_and_stock_changes_are_attributed_to_the_cashier() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("SOAP", 10, None)).await.unwrap();
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(5.0), reference: None }],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 2, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        db.complete_order(&cashier(), order_id).await.unwrap();
+        let item_id = db.get_order_items(order_id).await.unwrap()[0].id;
+        db.create_return(&cashier(), &CreateReturnRequest {
+            order_id,
+            reason: ReturnReason::ChangedMind,
+            notes: None,
+            items: vec![ReturnItemRequest { order_item_id: item_id, quantity: 1, disposition: ReturnDisposition::Restock }],
+            store_credit: false,
+        }).await.unwrap();
+
+        assert_eq!(db.get_order(order_id).await.unwrap().cashier_id, Some(3));
+        // Opening stock, the sale and the return
+        let movements = db.get_inventory_movements(Some(product_id)).await.unwrap();
+        assert_eq!(movements.len(), 3);
+        assert!(movements.iter().all(|m| m.user_id == Some(3)));
+
+        let range = super::super::reports::ReportRange::today(Some(0)).unwrap();
+        let report = db.get_cashier_sales_by_date_range(&range).await.unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!((report[0].cashier_name.as_str(), report[0].orders, report[0].total_sales), ("Till Operator", 1, money(5.0)));
+        assert_eq!((report[0].returns, report[0].total_refunds), (1, money(2.5)));
+    }
+
+    #[tokio::test]
+    async fn test_users_with_a_history_cannot_be_deleted() {
+        let db = test_db().await;
+        db.create_product(&cashier(), product_request("SOAP", 10, None)).await.unwrap();
+
+        let err = db.delete_user(3).await.unwrap_err();
+        assert_eq!(AppError::from(err), AppError::conflict("User has recorded sales or stock movements and cannot be deleted"));
+        assert!(db.get_all_users().await.unwrap().iter().any(|u| u.id == 3));
+    }
+
+    #[tokio::test]
+    async fn test_shift_close_reconciles_counted_takings() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("RICE", 10, None)).await.unwrap();
+
+        let err = db.record_cash_movement(&cashier(), &CashMovementRequest { kind: CashMovementKind::Drop, amount: money(5.0), notes: None }).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+
+        let shift = db.open_shift(&cashier(), &OpenShiftRequest { opening_float: money(100.0), notes: None }).await.unwrap();
+        let err = db.open_shift(&cashier(), &OpenShiftRequest { opening_float: money(50.0), notes: None }).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+
+        let order_id = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![
+                OrderPaymentRequest { tender_type: TenderType::CreditCard, amount: money(5.0), reference: None },
+                OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(20.0), reference: None },
+            ],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 4, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        db.complete_order(&cashier(), order_id).await.unwrap();
+        db.record_cash_movement(&cashier(), &CashMovementRequest { kind: CashMovementKind::Payout, amount: money(3.5), notes: Some("Milk for staff room".to_string()) }).await.unwrap();
+
+        let x = db.take_x_report(&cashier()).await.unwrap();
+        assert_eq!((x.report.orders, x.report.gross_sales), (1, money(10.0)));
+        assert_eq!(x.report.tenders[0].expected, money(101.5));
+        assert_eq!(x.report.tenders[0].counted, None);
+
+        let item_id = db.get_order_items(order_id).await.unwrap()[0].id;
+        db.create_return(&cashier(), &CreateReturnRequest {
+            order_id,
+            reason: ReturnReason::Defective,
+            notes: None,
+            items: vec![ReturnItemRequest { order_item_id: item_id, quantity: 3, disposition: ReturnDisposition::WriteOff }],
+            store_credit: false,
+        }).await.unwrap();
+        db.record_cash_movement(&cashier(), &CashMovementRequest { kind: CashMovementKind::Drop, amount: money(50.0), notes: None }).await.unwrap();
+
+        let z = db.close_shift(&cashier(), &CloseShiftRequest {
+            counts: vec![
+                TenderCount { tender_type: TenderType::Cash, counted: money(48.0) },
+                TenderCount { tender_type: TenderType::CreditCard, counted: Money::ZERO },
+            ],
+            notes: None,
+        }).await.unwrap();
+        let lines: Vec<(TenderType, Money, Money, Money, Option<Money>)> = z.report.tenders.iter()
+            .map(|t| (t.tender_type, t.payments, t.refunds, t.expected, t.variance))
+            .collect();
+        assert_eq!(lines, vec![
+            (TenderType::Cash, money(5.0), money(2.5), money(49.0), Some(money(-1.0))),
+            (TenderType::CreditCard, money(5.0), money(5.0), Money::ZERO, Some(Money::ZERO)),
+        ]);
+        assert!(db.get_current_shift().await.unwrap().is_none());
+
+        // Both readings are stored as produced
+        let stored = db.get_shift_reports(shift.id).await.unwrap();
+        let types: Vec<ShiftReportType> = stored.iter().map(|r| r.report.report_type).collect();
+        assert_eq!(types, vec![ShiftReportType::X, ShiftReportType::Z]);
+        assert_eq!(stored[1].report.tenders, z.report.tenders);
+
+        let movements = db.get_shift_cash_movements(shift.id).await.unwrap();
+        let movements: Vec<(CashMovementKind, Money)> = movements.iter().map(|m| (m.kind, m.amount)).collect();
+        assert_eq!(movements, vec![(CashMovementKind::Payout, money(3.5)), (CashMovementKind::Drop, money(50.0))]);
+
+        db.open_shift(&cashier(), &OpenShiftRequest { opening_float: money(100.0), notes: None }).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_voided_orders_leave_the_shift_takings() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("RICE", 10, None)).await.unwrap();
+        let partly_paid = || CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![
+                OrderPaymentRequest { tender_type: TenderType::CreditCard, amount: money(2.0), reference: None },
+                OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(6.0), reference: None },
+            ],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 4, price_at_sale: money(2.5), override_reason: None }],
+        };
+        db.open_shift(&cashier(), &OpenShiftRequest { opening_float: money(100.0), notes: None }).await.unwrap();
+
+        let handed_back = db.create_order(&cashier(), &[], partly_paid()).await.unwrap();
+        db.cancel_order(&cashier(), handed_back, false).await.unwrap();
+        let x = db.take_x_report(&cashier()).await.unwrap();
+        assert_eq!((x.report.orders, x.report.gross_sales), (0, Money::ZERO));
+        let lines: Vec<(TenderType, Money)> = x.report.tenders.iter().map(|t| (t.tender_type, t.expected)).collect();
+        assert_eq!(lines, vec![(TenderType::Cash, money(100.0))]);
+
+        // Cash turned into store credit is still in the drawer
+        let credited = db.create_order(&cashier(), &[], partly_paid()).await.unwrap();
+        db.cancel_order(&cashier(), credited, true).await.unwrap();
+        let x = db.take_x_report(&cashier()).await.unwrap();
+        assert_eq!((x.report.orders, x.report.gross_sales), (0, money(6.0)));
+        let lines: Vec<(TenderType, Money)> = x.report.tenders.iter().map(|t| (t.tender_type, t.expected)).collect();
+        assert_eq!(lines, vec![(TenderType::Cash, money(106.0))]);
+    }
+
+    #[tokio::test]
+    async fn test_parked_cart_reservations_expire() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("TEA", 5, None)).await.unwrap();
+        let cart = |label: &str, quantity: i32, reserve_stock: bool| ParkCartRequest {
+            label: label.to_string(),
+            customer_name: Some("Ada".to_string()),
+            payment_method: None,
+            items: vec![
+                OrderItemRequest { product_id, quantity, price_at_sale: money(1.5), override_reason: None },
+                OrderItemRequest { product_id, quantity: 1, price_at_sale: money(1.5), override_reason: None },
+            ],
+            reserve_stock,
+        };
+        let hold = Some(chrono::Duration::minutes(30));
+
+        let first = db.park_cart(&cashier(), &cart("Lane 1", 2, true), hold).await.unwrap();
+        let err = db.park_cart(&cashier(), &cart("Lane 2", 2, true), hold).await.unwrap_err();
+        match AppError::from(err) {
+            AppError::InsufficientStock { lines, .. } => assert_eq!((lines[0].requested, lines[0].available), (3, 2)),
+            other => panic!("expected insufficient stock, got {:?}", other),
+        }
+
+        // Without a reservation the cart is kept regardless of stock
+        let second = db.park_cart(&cashier(), &cart("Lane 2", 9, false), None).await.unwrap();
+        let carts = db.get_parked_carts().await.unwrap();
+        let summary: Vec<(i64, i64, Money, bool)> = carts.iter().map(|c| (c.id, c.item_count, c.subtotal, c.reserved_until.is_some())).collect();
+        assert_eq!(summary, vec![(first, 3, money(4.5), true), (second, 10, money(15.0), false)]);
+
+        // Once the first cart's reservation runs out its stock is free again
+        sqlx::query("UPDATE parked_carts SET reserved_until = ? WHERE id = ?")
+            .bind(Utc::now() - chrono::Duration::minutes(1))
+            .bind(first)
+            .execute(db.pool.as_ref().unwrap())
+            .await
+            .unwrap();
+        let third = db.park_cart(&cashier(), &cart("Lane 3", 4, true), hold).await.unwrap();
+
+        let resumed = db.resume_parked_cart(first).await.unwrap();
+        assert_eq!(resumed.cart.label, "Lane 1");
+        assert_eq!(resumed.items.iter().map(|i| (i.sku.as_str(), i.quantity)).collect::<Vec<_>>(), vec![("TEA", 2), ("TEA", 1)]);
+        assert!(matches!(AppError::from(db.resume_parked_cart(first).await.unwrap_err()), AppError::NotFound { .. }));
+
+        db.discard_parked_cart(third).await.unwrap();
+        assert!(matches!(AppError::from(db.discard_parked_cart(third).await.unwrap_err()), AppError::NotFound { .. }));
+        assert_eq!(db.get_parked_carts().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_customer_history_tracks_spend_and_returns() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), CreateProductRequest { price: money(20.0), ..product_request("KETTLE", 10, None) }).await.unwrap();
+        let customer = |phone: &str, email: &str| CustomerRequest {
+            name: "Ada Obi".to_string(),
+            email: Some(email.to_string()),
+            phone: Some(phone.to_string()),
+            address: None,
+            notes: None,
+        };
+
+        let customer_id = db.create_customer(&customer("0803 123 4567", "Ada@Example.com")).await.unwrap();
+        let err = db.create_customer(&customer("0803-123-4567", "")).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+        assert_eq!(db.lookup_customer("(0803) 1234567").await.unwrap().map(|c| c.id), Some(customer_id));
+        assert_eq!(db.lookup_customer(" ada@example.COM").await.unwrap().map(|c| c.id), Some(customer_id));
+        assert!(db.lookup_customer("0800000000").await.unwrap().is_none());
+        assert_eq!(db.get_customers(Some("4567")).await.unwrap().len(), 1);
+
+        let order = |quantity: i32| CreateOrderRequest {
+            customer_name: None,
+            customer_id: Some(customer_id),
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(20.0) * quantity, reference: None }],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity, price_at_sale: money(20.0), override_reason: None }],
+        };
+        let first = db.create_order(&cashier(), &[], order(2)).await.unwrap();
+        db.complete_order(&cashier(), first).await.unwrap();
+        assert_eq!(db.get_order(first).await.unwrap().customer_name, "Ada Obi");
+
+        // Orders not yet completed are not purchases
+        db.create_order(&cashier(), &[], order(1)).await.unwrap();
+
+        let item_id = db.get_order_items(first).await.unwrap()[0].id;
+        db.create_return(&cashier(), &CreateReturnRequest {
+            order_id: first,
+            reason: ReturnReason::Defective,
+            notes: None,
+            items: vec![ReturnItemRequest { order_item_id: item_id, quantity: 1, disposition: ReturnDisposition::WriteOff }],
+            store_credit: false,
+        }).await.unwrap();
+
+        let history = db.get_customer_history(customer_id).await.unwrap();
+        assert_eq!((history.summary.orders, history.summary.lifetime_spend, history.summary.refunded), (1, money(20.0), money(20.0)));
+        assert!(history.summary.first_purchase_at.is_some());
+        let lines: Vec<(i64, &str, i32, i32)> = history.purchases.iter()
+            .map(|l| (l.order_id, l.sku.as_str(), l.quantity, l.returned_quantity))
+            .collect();
+        assert_eq!(lines, vec![(first, "KETTLE", 2, 1)]);
+
+        let err = db.delete_customer(customer_id).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+
+        let mut unknown = order(1);
+        unknown.customer_id = Some(999);
+        let err = db.create_order(&cashier(), &[], unknown).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_loyalty_points_are_earned_redeemed_and_reversed() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), CreateProductRequest { price: money(20.0), ..product_request("KETTLE", 10, None) }).await.unwrap();
+        let customer_id = db.create_customer(&CustomerRequest {
+            name: "Ada Obi".to_string(),
+            email: None,
+            phone: Some("08031234567".to_string()),
+            address: None,
+            notes: None,
+        }).await.unwrap();
+        db.set_setting(LOYALTY_SETTINGS_KEY, &LoyaltySettings {
+            enabled: true,
+            points_per_unit: 1.0,
+            point_value: 0.1,
+            expiry_days: Some(30),
+        }).await.unwrap();
+
+        let order = |quantity: i32, payments: Vec<OrderPaymentRequest>| CreateOrderRequest {
+            customer_name: None,
+            customer_id: Some(customer_id),
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments,
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity, price_at_sale: money(20.0), override_reason: None }],
+        };
+        let tender = |tender_type, amount| OrderPaymentRequest { tender_type, amount, reference: None };
+        let balance = || async { db.get_loyalty_account(customer_id).await.unwrap().balance };
+
+        let first = db.create_order(&cashier(), &[], order(2, vec![tender(TenderType::Cash, money(40.0))])).await.unwrap();
+        db.complete_order(&cashier(), first).await.unwrap();
+        assert_eq!(balance().await, 40);
+
+        // Points pay for part of the sale and only the rest earns more
+        let err = db.create_order(&cashier(), &[], order(1, vec![tender(TenderType::LoyaltyPoints, money(5.0))])).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+        let second = db.create_order(&cashier(), &[], order(1, vec![
+            tender(TenderType::LoyaltyPoints, money(2.0)),
+            tender(TenderType::Cash, money(18.0)),
+        ])).await.unwrap();
+        db.complete_order(&cashier(), second).await.unwrap();
+        assert_eq!(balance().await, 38);
+        let receipt = db.get_receipt_data(second).await.unwrap().loyalty.unwrap();
+        assert_eq!((receipt.earned, receipt.redeemed, receipt.balance), (18, 20, 38));
+
+        // Voiding an order gives its redeemed points back
+        let third = db.create_order(&cashier(), &[], order(1, vec![tender(TenderType::LoyaltyPoints, money(1.0))])).await.unwrap();
+        assert_eq!(balance().await, 28);
+        db.cancel_order(&cashier(), third, false).await.unwrap();
+        assert_eq!(balance().await, 38);
+
+        // Refunds take back what the refunded spend earned and return points paid with
+        let return_all = |order_id: i64, quantity: i32| {
+            let db = &db;
+            async move {
+                let item_id = db.get_order_items(order_id).await.unwrap()[0].id;
+                db.create_return(&cashier(), &CreateReturnRequest {
+                    order_id,
+                    reason: ReturnReason::Defective,
+                    notes: None,
+                    items: vec![ReturnItemRequest { order_item_id: item_id, quantity, disposition: ReturnDisposition::Restock }],
+                    store_credit: false,
+                }).await.unwrap()
+            }
+        };
+        return_all(first, 1).await;
+        assert_eq!(balance().await, 18);
+        return_all(second, 1).await;
+        assert_eq!(balance().await, 20);
+
+        let anonymous = CreateOrderRequest { customer_id: None, ..order(1, vec![tender(TenderType::LoyaltyPoints, money(1.0))]) };
+        let err = db.create_order(&cashier(), &[], anonymous).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+
+        // Lapsed lots are written off by the alert pass
+        sqlx::query("UPDATE loyalty_ledger SET expires_at = ? WHERE remaining > 0")
+            .bind(Utc::now() - chrono::Duration::days(1))
+            .execute(db.pool.as_ref().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(balance().await, 0);
+        db.check_and_create_alerts().await.unwrap();
+        let account = db.get_loyalty_account(customer_id).await.unwrap();
+        assert_eq!(account.entries[0].kind, LoyaltyKind::Expire);
+        let total: i64 = account.entries.iter().map(|e| e.points).sum();
+        assert_eq!(total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_gift_cards_are_sold_redeemed_and_refunded_as_store_credit() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), CreateProductRequest { price: money(20.0), ..product_request("KETTLE", 10, None) }).await.unwrap();
+        let tender = |tender_type, amount, reference: Option<&str>| OrderPaymentRequest {
+            tender_type,
+            amount,
+            reference: reference.map(str::to_string),
+        };
+        let order = |quantity: i32, payments: Vec<OrderPaymentRequest>| CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments,
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity, price_at_sale: money(20.0), override_reason: None }],
+        };
+        let balance = |code: &'static str| {
+            let db = &db;
+            async move { db.lookup_gift_card(code).await.unwrap().card.balance }
+        };
+
+        // A card sold on an order can only be spent once the order completes
+        let sale = db.create_order(&cashier(), &[], CreateOrderRequest {
+            gift_cards: vec![GiftCardSaleRequest { amount: money(50.0), code: Some("gift-0001-ab".to_string()), expires_on: None }],
+            ..order(1, vec![tender(TenderType::Cash, money(70.0), None)])
+        }).await.unwrap();
+        assert_eq!(db.get_order(sale).await.unwrap().total_amount, money(70.0));
+        let err = db.create_order(&cashier(), &[], order(1, vec![tender(TenderType::GiftCard, money(5.0), Some("GIFT0001AB"))])).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+        db.complete_order(&cashier(), sale).await.unwrap();
+        assert_eq!(balance("GIFT0001AB").await, money(50.0));
+        assert_eq!(db.get_receipt_data(sale).await.unwrap().gift_cards.len(), 1);
+
+        let spend = db.create_order(&cashier(), &[], order(2, vec![
+            tender(TenderType::GiftCard, money(30.0), Some("GIFT0001AB")),
+            tender(TenderType::Cash, money(10.0), None),
+        ])).await.unwrap();
+        db.complete_order(&cashier(), spend).await.unwrap();
+        assert_eq!(balance("GIFT0001AB").await, money(20.0));
+        let err = db.create_order(&cashier(), &[], order(2, vec![tender(TenderType::GiftCard, money(25.0), Some("GIFT0001AB"))])).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+
+        // Refunds go back onto the card first; the cash part can become store credit
+        let item_id = db.get_order_items(spend).await.unwrap()[0].id;
+        let return_one = |store_credit: bool| CreateReturnRequest {
+            order_id: spend,
+            reason: ReturnReason::ChangedMind,
+            notes: None,
+            items: vec![ReturnItemRequest { order_item_id: item_id, quantity: 1, disposition: ReturnDisposition::Restock }],
+            store_credit,
+        };
+        db.create_return(&cashier(), &return_one(false)).await.unwrap();
+        assert_eq!(balance("GIFT0001AB").await, money(40.0));
+        let return_id = db.create_return(&cashier(), &return_one(true)).await.unwrap();
+        assert_eq!(balance("GIFT0001AB").await, money(50.0));
+        let details = db.get_return(return_id).await.unwrap();
+        let refunds: Vec<(TenderType, Money)> = details.refunds.iter().map(|r| (r.tender_type, r.amount)).collect();
+        assert_eq!(refunds, vec![(TenderType::GiftCard, money(10.0)), (TenderType::StoreCredit, money(10.0))]);
+        let credit = details.store_credit.unwrap();
+        assert_eq!((credit.kind, credit.balance), (GiftCardKind::StoreCredit, money(10.0)));
+
+        // Voiding gives the card back what it paid and keeps the cash as store credit
+        let voided = db.create_order(&cashier(), &[], order(1, vec![
+            tender(TenderType::StoreCredit, money(4.0), Some(&credit.code)),
+            tender(TenderType::Cash, money(6.0), None),
+        ])).await.unwrap();
+        assert_eq!(db.get_gift_card(credit.id).await.unwrap().balance, money(6.0));
+        let issued = db.cancel_order(&cashier(), voided, true).await.unwrap().unwrap();
+        assert_eq!(db.get_gift_card(credit.id).await.unwrap().balance, money(10.0));
+        assert_eq!(db.get_gift_card(issued).await.unwrap().balance, money(6.0));
+        let kept: Vec<(TenderType, Money)> = db.get_order_payments(voided).await.unwrap().iter().map(|p| (p.tender_type, p.amount)).collect();
+        assert_eq!(kept, vec![(TenderType::Cash, money(6.0))]);
+
+        // Without store credit the cash is handed back and nothing stays paid
+        let handed_back = db.create_order(&cashier(), &[], order(1, vec![tender(TenderType::Cash, money(5.0), None)])).await.unwrap();
+        assert_eq!(db.cancel_order(&cashier(), handed_back, false).await.unwrap(), None);
+        assert!(db.get_order_payments(handed_back).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lines_are_priced_from_the_catalogue_and_overrides_logged() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("BREAD", 10, None)).await.unwrap();
+        sqlx::query(
+            "INSERT INTO users (id, created_at, updated_at, email, hashed_password, full_name, role, is_active, is_superuser) VALUES (4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, 'manager@example.com', '', 'Floor Manager', 'manager', TRUE, FALSE)"
+        )
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .unwrap();
+        let manager = db.get_user_by_email("manager@example.com").await.unwrap().unwrap();
+        let approve = |token_hash: &'static str, price: f64, minutes: i64| {
+            let (db, manager) = (&db, &manager);
+            async move {
+                db.create_price_override_approval(token_hash, &cashier(), manager, product_id, money(price), Utc::now() + chrono::Duration::minutes(minutes)).await.unwrap();
+                vec![token_hash.to_string()]
+            }
+        };
+        let order = |price: f64, reason: Option<&str>| CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 2, price_at_sale: money(price), override_reason: reason.map(str::to_string) }],
+        };
+
+        // A client can't pick its own price
+        let err = db.create_order(&cashier(), &[], order(0.5, Some("Friend"))).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Forbidden { .. }));
+        let err = db.create_order(&cashier(), &approve("other-price", 1.0, 5).await, order(2.0, Some("Day old"))).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Forbidden { .. }));
+        let err = db.create_order(&cashier(), &approve("stale", 2.0, -1).await, order(2.0, Some("Day old"))).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+        assert_eq!(db.get_product_by_sku("BREAD").await.unwrap().unwrap().quantity, 10);
+
+        // An approval is for one line on one order
+        let approval = approve("approved", 2.0, 5).await;
+        let shelf = db.create_order(&cashier(), &[], order(2.5, None)).await.unwrap();
+        let marked_down = db.create_order(&cashier(), &approval, order(2.0, Some("Day old"))).await.unwrap();
+        let err = db.create_order(&cashier(), &approval, order(2.0, Some("Day old"))).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+        assert_eq!(db.get_order(shelf).await.unwrap().total_amount, money(5.0));
+        assert_eq!(db.get_order(marked_down).await.unwrap().total_amount, money(4.0));
+
+        let today = super::super::reports::ReportRange::today(Some(0)).unwrap();
+        let overrides = db.get_price_overrides(&today).await.unwrap();
+        let overrides: Vec<(i64, Money, Money, &str, &str)> = overrides.iter()
+            .map(|o| (o.order_id, o.shelf_price, o.override_price, o.reason.as_str(), o.approver_name.as_str()))
+            .collect();
+        assert_eq!(overrides, vec![(marked_down, money(2.5), money(2.0), "Day old", "Floor Manager")]);
+    }
+
+    #[tokio::test]
+    async fn test_negative_lines_at_the_shelf_price_are_refused() {
+        let db = test_db().await;
+        let product_id = db.create_product(&cashier(), product_request("BREAD", 10, None)).await.unwrap();
+
+        // Would be a cash refund without a reason or an approver
+        let err = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: -1, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+        assert_eq!(db.get_product_by_sku("BREAD").await.unwrap().unwrap().quantity, 10);
+        assert!(db.get_recent_orders(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_overselling_follows_product_and_category_policy() {
+        let db = test_db().await;
+        let milk = db.create_product(&cashier(), product_request("MILK", 2, None)).await.unwrap();
+        let eggs = db.create_product(&cashier(), CreateProductRequest { stock_policy: Some(StockPolicy::Backorder), ..product_request("EGGS", 1, None) }).await.unwrap();
+        let order = |items: Vec<(i64, i32)>| CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: items.into_iter()
+                .map(|(product_id, quantity)| OrderItemRequest { product_id, quantity, price_at_sale: money(2.5), override_reason: None })
+                .collect(),
+        };
+
+        // Blocked by default, counting every line of the product
+        let err = db.create_order(&cashier(), &[], order(vec![(milk, 2), (eggs, 3), (milk, 1)])).await.unwrap_err();
+        match AppError::from(err) {
+            AppError::InsufficientStock { lines, .. } => {
+                let lines: Vec<(&str, i32, i32)> = lines.iter().map(|l| (l.product_name.as_str(), l.requested, l.available)).collect();
+                assert_eq!(lines, vec![("Product MILK", 3, 2)]);
+            }
+            other => panic!("expected insufficient stock, got {:?}", other),
+        }
+        assert_eq!(db.get_product_by_sku("MILK").await.unwrap().unwrap().quantity, 2);
+
+        // Empty lines and empty orders never reach the stock check
+        for items in [vec![(milk, 0)], vec![]] {
+            let err = db.create_order(&cashier(), &[], order(items)).await.unwrap_err();
+            assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+        }
+
+        db.set_setting(STOCK_POLICY_SETTINGS_KEY, &StockPolicySettings {
+            categories: std::collections::BTreeMap::from([("grocery".to_string(), StockPolicy::Warn)]),
+            ..StockPolicySettings::default()
+        }).await.unwrap();
+        let order_id = db.create_order(&cashier(), &[], order(vec![(milk, 3), (eggs, 3)])).await.unwrap();
+        assert_eq!(db.get_product_by_sku("MILK").await.unwrap().unwrap().quantity, -1);
+
+        let flagged = db.get_oversold_lines(Some(order_id), false).await.unwrap();
+        let summary: Vec<(i64, StockPolicy, i32, i32)> = flagged.iter().map(|l| (l.product_id, l.policy, l.requested, l.available)).collect();
+        assert_eq!(summary, vec![(milk, StockPolicy::Warn, 3, 2), (eggs, StockPolicy::Backorder, 3, 1)]);
+
+        db.review_oversold_line(&cashier(), flagged[0].id, "Recounted").await.unwrap();
+        let err = db.review_oversold_line(&cashier(), flagged[0].id, "").await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+        assert_eq!(db.get_oversold_lines(None, false).await.unwrap().len(), 1);
+        let all = db.get_oversold_lines(None, true).await.unwrap();
+        assert_eq!((all[0].reviewer_name.as_str(), all[0].review_notes.as_str()), ("Till Operator", "Recounted"));
+    }
+
+    #[tokio::test]
+    async fn test_purchase_orders_are_sent_received_in_parts_and_closed() {
+        let db = test_db().await;
+        let supplier_id = db.create_supplier("Acme Foods", Some("Bola"), None, None, None).await.unwrap();
+        let rice = db.create_product(&cashier(), product_request("RICE", 10, None)).await.unwrap();
+        let beans = db.create_product(&cashier(), product_request("BEANS", 0, None)).await.unwrap();
+        let request = |lines: Vec<(i64, i32, f64)>| PurchaseOrderRequest {
+            supplier_id,
+            expected_date: None,
+            notes: Some("Deliver to back door".to_string()),
+            lines: lines.into_iter()
+                .map(|(product_id, quantity, cost)| PurchaseOrderLineRequest { product_id, quantity, unit_cost: money(cost) })
+                .collect(),
+        };
+
+        let po = db.create_purchase_order(&cashier(), &request(vec![(rice, 5, 1.0)])).await.unwrap();
+        db.update_purchase_order(po, &request(vec![(rice, 10, 2.0), (beans, 4, 3.0)])).await.unwrap();
+        let err = db.receive_purchase_order(&cashier(), po, &ReceivePurchaseOrderRequest { lines: vec![] }).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::InvalidTransition { .. }));
+        db.send_purchase_order(po).await.unwrap();
+        let err = db.update_purchase_order(po, &request(vec![(rice, 1, 1.0)])).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+
+        let details = db.get_purchase_order(po).await.unwrap();
+        assert_eq!((details.purchase_order.supplier_name.as_str(), details.purchase_order.total_cost), ("Acme Foods", money(32.0)));
+        let (rice_line, beans_line) = (details.lines[0].id, details.lines[1].id);
+        let receive = |line_id: i64, quantity: i32, unit_cost: Option<f64>| ReceiveLineRequest {
+            line_id,
+            quantity,
+            unit_cost: unit_cost.map(money),
+            batch_number: None,
+            expiry_date: None,
+        };
+
+        // Part of the rice arrives dearer than expected, so its cost is averaged
+        db.receive_purchase_order(&cashier(), po, &ReceivePurchaseOrderRequest { lines: vec![receive(rice_line, 6, Some(2.5))] }).await.unwrap();
+        let stocked = db.get_product_by_sku("RICE").await.unwrap().unwrap();
+        assert_eq!((stocked.quantity, stocked.cost), (16, money(1.56)));
+        assert_eq!(remaining(&db.get_product_batches(rice, false).await.unwrap()), vec![("OPENING", 10), ("PO-00001", 6)]);
+        let movement = &db.get_inventory_movements(Some(rice)).await.unwrap()[0];
+        assert_eq!((movement.quantity, movement.movement_type.as_str()), (6, "receipt"));
+        assert_eq!(db.get_purchase_order(po).await.unwrap().purchase_order.status, PurchaseOrderStatus::PartiallyReceived);
+
+        let err = db.receive_purchase_order(&cashier(), po, &ReceivePurchaseOrderRequest { lines: vec![receive(rice_line, 5, None)] }).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+
+        db.receive_purchase_order(&cashier(), po, &ReceivePurchaseOrderRequest {
+            lines: vec![receive(rice_line, 4, None), receive(beans_line, 4, None)],
+        }).await.unwrap();
+        let details = db.get_purchase_order(po).await.unwrap();
+        assert_eq!(details.purchase_order.status, PurchaseOrderStatus::Received);
+        assert_eq!(details.receipts.len(), 3);
+        assert_eq!(db.get_product_by_sku("BEANS").await.unwrap().unwrap().cost, money(3.0));
+
+        db.close_purchase_order(po).await.unwrap();
+        assert_eq!(db.get_purchase_orders(Some(PurchaseOrderStatus::Closed), Some(supplier_id)).await.unwrap().len(), 1);
+
+        let csv = crate::purchasing::render_csv(&db.get_purchase_order(po).await.unwrap());
+        assert_eq!(csv.lines().nth(2), Some("PO-00001,Acme Foods,,BEANS,Product BEANS,4,USD,3.00,12.00"));
+    }
+
+    #[tokio::test]
+    async fn test_reorders_are_suggested_from_sales_and_drafted_per_supplier() {
+        use crate::reorder::{draft_suggested_order, plan, ReorderSettings};
+
+        let db = test_db().await;
+        let supplier_id = db.create_supplier("Acme Foods", None, None, None, None).await.unwrap();
+        db.set_supplier_lead_time(supplier_id, Some(2)).await.unwrap();
+        let tea = db.create_product(&cashier(), CreateProductRequest { supplier_id: Some(supplier_id), reorder_level: 5, ..product_request("TEA", 10, None) }).await.unwrap();
+        db.create_product(&cashier(), product_request("SALT", 10, None)).await.unwrap();
+
+        let sale = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(20.0), reference: None }],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id: tea, quantity: 8, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        db.complete_order(&cashier(), sale).await.unwrap();
+
+        // 8 sold over 28 days: the level of 5 beats 2 days' lead time plus
+        // 3 of safety stock, and the order adds 14 days of sales on top
+        let candidates = db.get_reorder_candidates(Utc::now() - chrono::Duration::days(28)).await.unwrap();
+        let groups = plan(&candidates, &ReorderSettings::default());
+        assert_eq!(groups.len(), 1);
+        assert_eq!((groups[0].supplier_id, groups[0].lead_time_days), (Some(supplier_id), 2));
+        let line = &groups[0].lines[0];
+        assert_eq!((line.product_id, line.on_hand, line.reorder_point, line.suggested_quantity), (tea, 2, 5, 7));
+
+        let po = draft_suggested_order(&db, &cashier(), supplier_id).await.unwrap();
+        let details = db.get_purchase_order(po).await.unwrap();
+        assert_eq!(details.purchase_order.status, PurchaseOrderStatus::Draft);
+        assert_eq!(details.purchase_order.expected_date, Some(chrono::Local::now().date_naive() + chrono::Duration::days(2)));
+        let lines: Vec<(i64, i32, Money)> = details.lines.iter().map(|l| (l.product_id, l.quantity_ordered, l.unit_cost)).collect();
+        assert_eq!(lines, vec![(tea, 7, money(1.0))]);
+
+        // What is on order counts as stock, so nothing more is suggested
+        let err = draft_suggested_order(&db, &cashier(), supplier_id).await.unwrap_err();
+        assert!(matches!(err, AppError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_stocktake_counts_from_several_devices_post_variances_against_the_snapshot() {
+        let db = test_db().await;
+        let aisle = |sku: &str, quantity: i32, location: &str| CreateProductRequest { location: Some(location.to_string()), ..product_request(sku, quantity, None) };
+        let rice = db.create_product(&cashier(), aisle("RICE", 10, "Aisle 1")).await.unwrap();
+        let salt = db.create_product(&cashier(), aisle("SALT", 5, "Aisle 1")).await.unwrap();
+        let oil = db.create_product(&cashier(), aisle("OIL", 8, "Aisle 2")).await.unwrap();
+
+        let request = |category: Option<&str>, location: Option<&str>| StocktakeRequest {
+            category: category.map(str::to_string),
+            location: location.map(str::to_string),
+            notes: None,
+        };
+        let stocktake = db.create_stocktake(&cashier(), &request(None, Some("Aisle 1"))).await.unwrap();
+        let err = db.create_stocktake(&cashier(), &request(Some("grocery"), None)).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+
+        // A sale while counting is booked as usual; the count is held against
+        // the quantity frozen when the stocktake started
+        let sale = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(5.0), reference: None }],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id: rice, quantity: 2, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        db.complete_order(&cashier(), sale).await.unwrap();
+
+        db.record_stocktake_count(&cashier(), stocktake, rice, 3, CountMethod::Scan, "scanner-a").await.unwrap();
+        db.record_stocktake_count(&cashier(), stocktake, rice, 6, CountMethod::Manual, "tablet-b").await.unwrap();
+        let line = db.record_stocktake_count(&cashier(), stocktake, rice, -1, CountMethod::Manual, "tablet-b").await.unwrap();
+        assert_eq!((line.expected_quantity, line.counted_quantity, line.variance, line.variance_value), (10, Some(8), Some(-2), Some(money(-2.0))));
+        assert!(db.record_stocktake_count(&cashier(), stocktake, rice, -9, CountMethod::Manual, "").await.is_err());
+        let err = db.record_stocktake_count(&cashier(), stocktake, oil, 1, CountMethod::Scan, "").await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+        assert_eq!(db.get_stocktake_counts(stocktake, Some(rice)).await.unwrap().len(), 3);
+
+        let details = db.get_stocktake(stocktake).await.unwrap();
+        assert_eq!(details.lines.len(), 2);
+        assert_eq!((details.summary.lines_counted, details.summary.lines_uncounted), (1, 1));
+        assert_eq!((details.summary.units_short, details.summary.net_value), (2, money(-2.0)));
+        let report = crate::stocktake::render_pdf(&details, "Corner Shop");
+        assert!(report.windows(8).any(|window| window == b"ST-00001"));
+
+        db.post_stocktake(&cashier(), stocktake).await.unwrap();
+        for (sku, quantity) in [("RICE", 6), ("SALT", 5), ("OIL", 8)] {
+            assert_eq!(db.get_product_by_sku(sku).await.unwrap().unwrap().quantity, quantity);
+        }
+        let movements = db.get_inventory_movements(Some(rice)).await.unwrap();
+        assert!(movements.iter().any(|m| m.document_type == Some(StockDocument::Stocktake) && m.quantity == -2 && m.notes == "ST-00001: counted 8, expected 10"));
+        assert!(db.get_inventory_movements(Some(salt)).await.unwrap().iter().all(|m| m.document_type != Some(StockDocument::Stocktake)));
+
+        let err = db.record_stocktake_count(&cashier(), stocktake, salt, 5, CountMethod::Manual, "").await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::Conflict { .. }));
+        let err = db.post_stocktake(&cashier(), stocktake).await.unwrap_err();
+        assert!(matches!(AppError::from(err), AppError::InvalidTransition { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_every_stock_change_is_in_the_ledger_with_a_running_balance() {
+        let db = test_db().await;
+        let tea = db.create_product(&cashier(), product_request("TEA", 5, None)).await.unwrap();
+
+        let sale = db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![OrderPaymentRequest { tender_type: TenderType::Cash, amount: money(5.0), reference: None }],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id: tea, quantity: 2, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        db.update_stock(&cashier(), tea, UpdateStockRequest {
+            quantity_change: -1,
+            movement_type: StockMovementKind::WriteOff,
+            notes: Some("Crushed box".to_string()),
+        }).await.unwrap();
+        db.update_product(&cashier(), tea, CreateProductRequest { quantity: 4, ..product_request("TEA", 0, None) }).await.unwrap();
+
+        let ledger: Vec<(StockMovementKind, i32, i32, Option<StockDocument>)> = db.get_inventory_movements(Some(tea)).await.unwrap()
+            .iter()
+            .rev()
+            .map(|m| (m.movement_type, m.quantity, m.balance_after, m.document_type))
+            .collect();
+        assert_eq!(ledger, vec![
+            (StockMovementKind::Adjustment, 5, 5, Some(StockDocument::Batch)),
+            (StockMovementKind::Sale, -2, 3, Some(StockDocument::Order)),
+            (StockMovementKind::WriteOff, -1, 2, None),
+            (StockMovementKind::Adjustment, 2, 4, None),
+        ]);
+        let sold = db.get_inventory_movements(Some(tea)).await.unwrap().into_iter().find(|m| m.movement_type == StockMovementKind::Sale).unwrap();
+        assert_eq!((sold.document_id, sold.user_id, sold.user_name.as_str()), (Some(sale), Some(3), "Till Operator"));
+        assert!(db.get_ledger_discrepancies().await.unwrap().is_empty());
+
+        // Every writer is held to the kind's direction, not just manual changes
+        let mut conn = db.pool.as_ref().unwrap().acquire().await.unwrap();
+        for (kind, quantity) in [(StockMovementKind::Sale, 1), (StockMovementKind::Receipt, -1), (StockMovementKind::Adjustment, 0)] {
+            let entry = StockEntry { product_id: tea, kind, quantity, document: None, notes: "" };
+            let err = Database::move_stock(&mut conn, &cashier(), entry).await.unwrap_err();
+            assert!(matches!(AppError::from(err), AppError::Validation { .. }));
+        }
+        drop(conn);
+        assert_eq!(db.get_product_by_sku("TEA").await.unwrap().unwrap().quantity, 4);
+
+        // A quantity changed behind the ledger's back is found and put back
+        sqlx::query("UPDATE products SET quantity = 9 WHERE id = ?")
+            .bind(tea)
+            .execute(db.pool.as_ref().unwrap())
+            .await
+            .unwrap();
+        let found = db.get_ledger_discrepancies().await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].quantity, found[0].ledger_quantity, found[0].last_balance), (9, 4, Some(4)));
+
+        assert_eq!(db.repair_stock_ledger().await.unwrap(), found);
+        assert_eq!(db.get_product_by_sku("TEA").await.unwrap().unwrap().quantity, 4);
+        assert!(db.get_ledger_discrepancies().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_products_with_stock_or_sales_cannot_be_deleted() {
+        let db = test_db().await;
+        let stocked = db.create_product(&cashier(), product_request("JAM", 3, None)).await.unwrap();
+        let unused = db.create_product(&cashier(), product_request("HONEY", 0, None)).await.unwrap();
+
+        let err = db.delete_product(stocked).await.unwrap_err();
+        assert_eq!(AppError::from(err), AppError::conflict("Product has stock in the ledger and cannot be deleted"));
+        assert!(db.get_product_by_sku("JAM").await.unwrap().is_some());
+
+        db.create_order(&cashier(), &[], CreateOrderRequest {
+            customer_name: None,
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id: stocked, quantity: 1, price_at_sale: money(2.5), override_reason: None }],
+        }).await.unwrap();
+        let err = db.delete_product(stocked).await.unwrap_err();
+        assert_eq!(AppError::from(err), AppError::conflict("Product has been sold and cannot be deleted"));
+
+        db.delete_product(unused).await.unwrap();
+        assert!(db.get_product_by_sku("HONEY").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A single invalid input field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// An order line that cannot be fulfilled from stock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StockShortage {
+    pub product_id: i64,
+    pub product_name: String,
+    pub requested: i32,
+    pub available: i32,
+}
+
+/// Error returned by every Tauri command.
+///
+/// Serializes as `{ "code": "NOT_FOUND", "message": "...", ... }`. The codes
+/// are a stable contract with the frontend, which switches on `code` and
+/// only shows `message` as a fallback; never rename a variant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AppError {
+    /// Missing, invalid or expired session
+    Unauthorized { message: String },
+    /// Authenticated, but lacking the required permission
+    Forbidden { message: String, permission: Option<String> },
+    NotFound { message: String },
+    /// The request clashes with existing data (duplicate SKU, referenced row, ...)
+    Conflict { message: String },
+    Validation { message: String, fields: Vec<FieldError> },
+    InsufficientStock { message: String, lines: Vec<StockShortage> },
+    /// An order cannot move from its current status to the requested one
+    InvalidTransition { message: String, from: String, to: String },
+    Database { message: String },
+    Network { message: String },
+    /// A receipt printer, cash drawer or other peripheral could not be used
+    Device { message: String },
+    Internal { message: String },
+}
+
+impl AppError {
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        AppError::Unauthorized { message: message.into() }
+    }
+
+    pub fn forbidden(permission: &str) -> Self {
+        AppError::Forbidden {
+            message: "Insufficient permissions".to_string(),
+            permission: Some(permission.to_string()),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound { message: message.into() }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict { message: message.into() }
+    }
+
+    /// A validation error about a single field
+    pub fn validation(field: &str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        AppError::Validation {
+            fields: vec![FieldError { field: field.to_string(), message: message.clone() }],
+            message,
+        }
+    }
+
+    /// Lines that cannot be fulfilled, named in the message
+    pub fn insufficient_stock(lines: Vec<StockShortage>) -> Self {
+        let names: Vec<&str> = lines.iter().map(|line| line.product_name.as_str()).collect();
+        AppError::InsufficientStock {
+            message: format!("Not enough stock for {}", names.join(", ")),
+            lines,
+        }
+    }
+
+    pub fn invalid_transition(message: impl Into<String>, from: &str, to: &str) -> Self {
+        AppError::InvalidTransition {
+            message: message.into(),
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    pub fn database(message: impl Into<String>) -> Self {
+        AppError::Database { message: message.into() }
+    }
+
+    pub fn device(message: impl Into<String>) -> Self {
+        AppError::Device { message: message.into() }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::Internal { message: message.into() }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::Unauthorized { message }
+            | AppError::Forbidden { message, .. }
+            | AppError::NotFound { message }
+            | AppError::Conflict { message }
+            | AppError::Validation { message, .. }
+            | AppError::InsufficientStock { message, .. }
+            | AppError::InvalidTransition { message, .. }
+            | AppError::Database { message }
+            | AppError::Network { message }
+            | AppError::Device { message }
+            | AppError::Internal { message } => message,
+        }
+    }
+
+    /// Prefix the human-readable message, keeping the code and details
+    pub fn context(mut self, context: &str) -> Self {
+        let prefixed = format!("{}: {}", context, self.message());
+        match &mut self {
+            AppError::Unauthorized { message }
+            | AppError::Forbidden { message, .. }
+            | AppError::NotFound { message }
+            | AppError::Conflict { message }
+            | AppError::Validation { message, .. }
+            | AppError::InsufficientStock { message, .. }
+            | AppError::InvalidTransition { message, .. }
+            | AppError::Database { message }
+            | AppError::Network { message }
+            | AppError::Device { message }
+            | AppError::Internal { message } => *message = prefixed,
+        }
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        use sqlx::error::ErrorKind;
+
+        match &error {
+            sqlx::Error::RowNotFound => AppError::not_found("Record not found"),
+            sqlx::Error::Database(db_error) => match db_error.kind() {
+                ErrorKind::UniqueViolation => AppError::conflict(format!("A record with the same value already exists ({})", db_error.message())),
+                ErrorKind::ForeignKeyViolation => AppError::conflict(format!("The record is referenced by or refers to other data ({})", db_error.message())),
+                ErrorKind::NotNullViolation | ErrorKind::CheckViolation => AppError::Validation {
+                    message: db_error.message().to_string(),
+                    fields: Vec::new(),
+                },
+                _ => AppError::database(error.to_string()),
+            },
+            _ => AppError::database(error.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        AppError::Network { message: error.to_string() }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    /// Recover the typed error from anywhere in the `anyhow` chain, so
+    /// database methods can keep returning `anyhow::Result`
+    fn from(error: anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(app_error) = cause.downcast_ref::<AppError>() {
+                return app_error.clone();
+            }
+            if cause.downcast_ref::<sqlx::Error>().is_some() {
+                return match error.downcast::<sqlx::Error>() {
+                    Ok(sqlx_error) => sqlx_error.into(),
+                    Err(error) => AppError::database(error.to_string()),
+                };
+            }
+            if cause.downcast_ref::<reqwest::Error>().is_some() {
+                return AppError::Network { message: error.to_string() };
+            }
+        }
+
+        AppError::internal(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_serializes_with_stable_code() {
+        let error = AppError::validation("quantity", "Batch quantity must be positive");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "VALIDATION",
+                "message": "Batch quantity must be positive",
+                "fields": [{ "field": "quantity", "message": "Batch quantity must be positive" }]
+            })
+        );
+        assert_eq!(serde_json::to_value(AppError::unauthorized("x")).unwrap()["code"], "UNAUTHORIZED");
+        assert_eq!(serde_json::to_value(AppError::forbidden("reporting")).unwrap()["code"], "FORBIDDEN");
+    }
+
+    #[test]
+    fn test_typed_errors_survive_anyhow() {
+        let error: anyhow::Error = AppError::not_found("Order #4 not found").into();
+        assert_eq!(AppError::from(error), AppError::not_found("Order #4 not found"));
+
+        let error: anyhow::Error = sqlx::Error::RowNotFound.into();
+        assert!(matches!(AppError::from(error), AppError::NotFound { .. }));
+
+        let error = anyhow::anyhow!("something odd");
+        assert!(matches!(AppError::from(error), AppError::Internal { .. }));
+    }
+
+    #[test]
+    fn test_context_keeps_code() {
+        let error = AppError::conflict("duplicate SKU").context("Failed to create product");
+        assert_eq!(error, AppError::conflict("Failed to create product: duplicate SKU"));
+    }
+
+    #[tokio::test]
+    async fn test_constraint_violations_are_classified() {
+        let db = crate::database::Database::in_memory().await.unwrap();
+        let pool = db.pool.as_ref().unwrap();
+        let insert = "INSERT INTO roles (name) VALUES ('cashier')";
+        sqlx::query(insert).execute(pool).await.unwrap();
+
+        let error = sqlx::query(insert).execute(pool).await.unwrap_err();
+        assert!(matches!(AppError::from(error), AppError::Conflict { .. }));
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::NaiveDate;
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// Codes are kept as upper-case letters and digits, so "abcd-1234 ef56"
+/// and "ABCD1234EF56" are the same card
+pub fn normalize_card_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// A random 16 character code for a card without a pre-printed one
+pub fn generate_card_code() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..16].to_uppercase()
+}
+
+/// A change to a card's balance and the documents behind it
+#[derive(Debug, Clone, Copy)]
+pub struct CardMovement<'a> {
+    pub gift_card_id: i64,
+    pub kind: GiftCardEntryKind,
+    pub amount: Money,
+    pub order_id: Option<i64>,
+    pub order_payment_id: Option<i64>,
+    pub return_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub notes: &'a str,
+}
+
+pub fn validate_card_sale(sale: &GiftCardSaleRequest) -> Result<(), AppError> {
+    if !sale.amount.is_positive() {
+        return Err(AppError::validation("gift_cards", "Gift card amounts must be positive"));
+    }
+    if let Some(code) = &sale.code {
+        if normalize_card_code(code).len() < 6 {
+            return Err(AppError::validation("gift_cards", "Gift card codes need at least 6 letters or digits"));
+        }
+    }
+    Ok(())
+}
+
+/// Fails unless `amount` can be paid from `card` with `tender_type` on `today`
+pub fn check_redeemable(card: &GiftCard, tender_type: TenderType, amount: Money, today: NaiveDate) -> Result<(), AppError> {
+    match card.status {
+        GiftCardStatus::Active => {}
+        GiftCardStatus::Pending => return Err(AppError::conflict("This gift card's sale has not been completed yet")),
+        GiftCardStatus::Void => return Err(AppError::conflict("This gift card was voided")),
+    }
+    if card.kind.tender_type() != tender_type {
+        return Err(AppError::validation("tender_type", "This code belongs to a different kind of card"));
+    }
+    if let Some(expires_on) = card.expires_on {
+        if expires_on < today {
+            return Err(AppError::validation("reference", format!("This card expired on {}", expires_on)));
+        }
+    }
+    if amount > card.balance {
+        return Err(AppError::validation("amount", format!("The card only has {} left", card.balance)));
+    }
+    Ok(())
+}
+
+// Gift card commands
+/// A card's balance and history, found by the code on it
+#[tauri::command]
+pub async fn lookup_gift_card(
+    token: String,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<GiftCardDetails, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.lookup_gift_card(&code).await
+        .map_err(|e| AppError::from(e).context("Failed to look up gift card"))
+}
+
+#[tauri::command]
+pub async fn get_customer_gift_cards(
+    token: String,
+    customer_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<GiftCard>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_customer_gift_cards(customer_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get gift cards"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    fn card(status: GiftCardStatus, balance: Money, expires_on: Option<NaiveDate>) -> GiftCard {
+        GiftCard {
+            id: 1,
+            created_at: chrono::Utc::now(),
+            code: "ABCD1234".to_string(),
+            kind: GiftCardKind::GiftCard,
+            status,
+            customer_id: None,
+            initial_amount: money(50.0),
+            expires_on,
+            issued_order_id: Some(3),
+            issued_return_id: None,
+            balance,
+        }
+    }
+
+    #[test]
+    fn test_codes_are_normalized_and_generated_unique() {
+        assert_eq!(normalize_card_code(" abcd-1234 ef56"), "ABCD1234EF56");
+        let code = generate_card_code();
+        assert_eq!(code.len(), 16);
+        assert_eq!(normalize_card_code(&code), code);
+        assert_ne!(code, generate_card_code());
+    }
+
+    #[test]
+    fn test_only_active_unexpired_cards_with_funds_are_redeemable() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let active = card(GiftCardStatus::Active, money(20.0), Some(today));
+        assert!(check_redeemable(&active, TenderType::GiftCard, money(20.0), today).is_ok());
+        assert!(matches!(check_redeemable(&active, TenderType::GiftCard, money(20.01), today), Err(AppError::Validation { .. })));
+        assert!(check_redeemable(&active, TenderType::StoreCredit, money(5.0), today).is_err());
+        assert!(check_redeemable(&active, TenderType::GiftCard, money(5.0), today.succ_opt().unwrap()).is_err());
+
+        let pending = card(GiftCardStatus::Pending, Money::ZERO, None);
+        assert!(matches!(check_redeemable(&pending, TenderType::GiftCard, money(5.0), today), Err(AppError::Conflict { .. })));
+    }
+
+    #[test]
+    fn test_card_sales_are_validated() {
+        let sale = |amount: f64, code: Option<&str>| GiftCardSaleRequest { amount: money(amount), code: code.map(str::to_string), expires_on: None };
+        assert!(validate_card_sale(&sale(25.0, None)).is_ok());
+        assert!(validate_card_sale(&sale(0.0, None)).is_err());
+        assert!(validate_card_sale(&sale(25.0, Some("ab-12"))).is_err());
+    }
+}
@@ -1,18 +1,20 @@
 use tauri::State;
 use crate::{AppState, models::*};
 use crate::auth::check_permission;
+use crate::error::AppError;
+use crate::ledger::validate_manual_movement;
 
 // Product management commands
 #[tauri::command]
 pub async fn get_products(
     token: String,
     state: State<'_, AppState>,
-) -> Result<Vec<Product>, String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<Vec<Product>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.get_all_products().await
-        .map_err(|e| format!("Failed to get products: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get products"))
 }
 
 #[tauri::command]
@@ -20,12 +22,12 @@ pub async fn create_product(
     token: String,
     product_data: CreateProductRequest,
     state: State<'_, AppState>,
-) -> Result<i64, String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<i64, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
-    db.create_product(product_data).await
-        .map_err(|e| format!("Failed to create product: {}", e))
+    db.create_product(&user, product_data).await
+        .map_err(|e| AppError::from(e).context("Failed to create product"))
 }
 
 #[tauri::command]
@@ -34,12 +36,12 @@ pub async fn update_product(
     product_id: i64,
     product_data: CreateProductRequest,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<(), AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
-    db.update_product(product_id, product_data).await
-        .map_err(|e| format!("Failed to update product: {}", e))
+    db.update_product(&user, product_id, product_data).await
+        .map_err(|e| AppError::from(e).context("Failed to update product"))
 }
 
 #[tauri::command]
@@ -47,12 +49,12 @@ pub async fn delete_product(
     token: String,
     product_id: i64,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.delete_product(product_id).await
-        .map_err(|e| format!("Failed to delete product: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to delete product"))
 }
 
 #[tauri::command]
@@ -61,24 +63,54 @@ pub async fn update_stock(
     product_id: i64,
     stock_data: UpdateStockRequest,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<(), AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+    validate_manual_movement(&stock_data)?;
     
     let db = state.db.lock().await;
-    db.update_stock(product_id, stock_data).await
-        .map_err(|e| format!("Failed to update stock: {}", e))
+    db.update_stock(&user, product_id, stock_data).await
+        .map_err(|e| AppError::from(e).context("Failed to update stock"))
+}
+
+// Batch (lot) tracking commands
+#[tauri::command]
+pub async fn get_product_batches(
+    token: String,
+    product_id: i64,
+    include_empty: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ProductBatch>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_product_batches(product_id, include_empty.unwrap_or(false)).await
+        .map_err(|e| AppError::from(e).context("Failed to get product batches"))
+}
+
+#[tauri::command]
+pub async fn receive_batch(
+    token: String,
+    product_id: i64,
+    batch_data: ReceiveBatchRequest,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.receive_batch(&user, product_id, batch_data).await
+        .map_err(|e| AppError::from(e).context("Failed to receive batch"))
 }
 
 #[tauri::command]
 pub async fn get_low_stock_products(
     token: String,
     state: State<'_, AppState>,
-) -> Result<Vec<Product>, String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<Vec<Product>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.get_low_stock_products().await
-        .map_err(|e| format!("Failed to get low stock products: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get low stock products"))
 }
 
 // Supplier management commands
@@ -86,12 +118,12 @@ pub async fn get_low_stock_products(
 pub async fn get_suppliers(
     token: String,
     state: State<'_, AppState>,
-) -> Result<Vec<Supplier>, String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<Vec<Supplier>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.get_all_suppliers().await
-        .map_err(|e| format!("Failed to get suppliers: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get suppliers"))
 }
 
 #[tauri::command]
@@ -103,12 +135,12 @@ pub async fn create_supplier(
     phone: Option<String>,
     address: Option<String>,
     state: State<'_, AppState>,
-) -> Result<i64, String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<i64, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.create_supplier(&name, contact_name.as_deref(), email.as_deref(), phone.as_deref(), address.as_deref()).await
-        .map_err(|e| format!("Failed to create supplier: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to create supplier"))
 }
 
 #[tauri::command]
@@ -121,12 +153,27 @@ pub async fn update_supplier(
     phone: Option<String>,
     address: Option<String>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.update_supplier(supplier_id, &name, contact_name.as_deref(), email.as_deref(), phone.as_deref(), address.as_deref()).await
-        .map_err(|e| format!("Failed to update supplier: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to update supplier"))
+}
+
+/// How many days the supplier takes to deliver; `None` uses the reorder default
+#[tauri::command]
+pub async fn set_supplier_lead_time(
+    token: String,
+    supplier_id: i64,
+    lead_time_days: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.set_supplier_lead_time(supplier_id, lead_time_days).await
+        .map_err(|e| AppError::from(e).context("Failed to set supplier lead time"))
 }
 
 #[tauri::command]
@@ -134,12 +181,12 @@ pub async fn delete_supplier(
     token: String,
     supplier_id: i64,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.delete_supplier(supplier_id).await
-        .map_err(|e| format!("Failed to delete supplier: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to delete supplier"))
 }
 
 // Inventory movements
@@ -148,10 +195,10 @@ pub async fn get_inventory_movements(
     token: String,
     product_id: Option<i64>,
     state: State<'_, AppState>,
-) -> Result<Vec<InventoryMovement>, String> {
-    check_permission(&token, "inventory_management").await?;
+) -> Result<Vec<InventoryMovement>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.get_inventory_movements(product_id).await
-        .map_err(|e| format!("Failed to get inventory movements: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get inventory movements"))
 }
@@ -0,0 +1,109 @@
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// A change to a product's stock and the document behind it, written to the
+/// ledger by `Database::move_stock`
+#[derive(Debug, Clone, Copy)]
+pub struct StockEntry<'a> {
+    pub product_id: i64,
+    pub kind: StockMovementKind,
+    pub quantity: i32,
+    pub document: Option<(StockDocument, i64)>,
+    pub notes: &'a str,
+}
+
+impl StockMovementKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StockMovementKind::Sale => "sale",
+            StockMovementKind::Return => "return",
+            StockMovementKind::Receipt => "receipt",
+            StockMovementKind::Adjustment => "adjustment",
+            StockMovementKind::Transfer => "transfer",
+            StockMovementKind::WriteOff => "write_off",
+        }
+    }
+
+    /// Sales and write-offs take stock out, returns and receipts bring it in;
+    /// adjustments and transfers go either way
+    pub fn allows(self, quantity: i32) -> bool {
+        match self {
+            StockMovementKind::Sale | StockMovementKind::WriteOff => quantity < 0,
+            StockMovementKind::Return | StockMovementKind::Receipt => quantity > 0,
+            StockMovementKind::Adjustment | StockMovementKind::Transfer => quantity != 0,
+        }
+    }
+}
+
+/// Stock changed by hand: sales, returns and receipts are only made by the
+/// orders, returns and deliveries they belong to
+pub fn validate_manual_movement(update: &UpdateStockRequest) -> Result<(), AppError> {
+    if !matches!(update.movement_type, StockMovementKind::Adjustment | StockMovementKind::Transfer | StockMovementKind::WriteOff) {
+        return Err(AppError::validation("movement_type", format!("A {} can only be recorded through its document", update.movement_type.as_str())));
+    }
+    if !update.movement_type.allows(update.quantity_change) {
+        return Err(AppError::validation("quantity_change", format!("That quantity does not fit a {}", update.movement_type.as_str())));
+    }
+    Ok(())
+}
+
+// Stock ledger commands
+/// Products whose quantity or latest running balance disagrees with the sum
+/// of their ledger
+#[tauri::command]
+pub async fn check_stock_ledger(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<LedgerDiscrepancy>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_ledger_discrepancies().await
+        .map_err(|e| AppError::from(e).context("Failed to check stock ledger"))
+}
+
+/// Set each product's quantity and running balances back to what the ledger
+/// adds up to, returning what was out of line
+#[tauri::command]
+pub async fn repair_stock_ledger(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<LedgerDiscrepancy>, AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+
+    let db = state.db.lock().await;
+    db.repair_stock_ledger().await
+        .map_err(|e| AppError::from(e).context("Failed to repair stock ledger"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(movement_type: StockMovementKind, quantity_change: i32) -> UpdateStockRequest {
+        UpdateStockRequest { quantity_change, movement_type, notes: None }
+    }
+
+    #[test]
+    fn test_kinds_fix_the_direction_of_stock() {
+        assert!(StockMovementKind::Sale.allows(-1));
+        assert!(!StockMovementKind::Sale.allows(1));
+        assert!(StockMovementKind::Receipt.allows(5));
+        assert!(!StockMovementKind::WriteOff.allows(2));
+        assert!(StockMovementKind::Transfer.allows(-4));
+        assert!(!StockMovementKind::Adjustment.allows(0));
+    }
+
+    #[test]
+    fn test_only_adjustments_transfers_and_write_offs_are_made_by_hand() {
+        assert!(validate_manual_movement(&update(StockMovementKind::Adjustment, 3)).is_ok());
+        assert!(validate_manual_movement(&update(StockMovementKind::Transfer, -3)).is_ok());
+        assert!(validate_manual_movement(&update(StockMovementKind::WriteOff, -1)).is_ok());
+        assert!(matches!(validate_manual_movement(&update(StockMovementKind::WriteOff, 1)), Err(AppError::Validation { .. })));
+        assert!(validate_manual_movement(&update(StockMovementKind::Sale, -1)).is_err());
+        assert!(validate_manual_movement(&update(StockMovementKind::Receipt, 5)).is_err());
+    }
+}
@@ -7,13 +7,35 @@ mod auth;
 mod models;
 mod users;
 mod inventory;
+mod stock;
+mod purchasing;
+mod reorder;
+mod stocktake;
+mod ledger;
+mod pdf;
 mod pos;
 mod notifications;
 mod reports;
+mod promotions;
+mod payments;
+mod returns;
+mod orders;
+mod shifts;
+mod carts;
+mod customers;
+mod loyalty;
+mod giftcards;
+mod printer;
+mod receipt;
+mod tax;
+mod money;
 mod api_proxy;
 mod sync_service;
+mod migrations;
+mod error;
 
 use database::Database;
+use error::AppError;
 
 // Application state
 pub struct AppState {
@@ -28,7 +50,7 @@ fn greet(name: &str) -> String {
 
 // Open external URL command
 #[tauri::command]
-async fn open_url(url: String) -> Result<(), String> {
+async fn open_url(url: String) -> Result<(), AppError> {
     use tauri_plugin_opener::OpenerExt;
 
     // For development, we'll use a simple approach
@@ -37,7 +59,7 @@ async fn open_url(url: String) -> Result<(), String> {
         std::process::Command::new("xdg-open")
             .arg(&url)
             .spawn()
-            .map_err(|e| format!("Failed to open URL: {}", e))?;
+            .map_err(|e| AppError::internal(format!("Failed to open URL: {}", e)))?;
     }
 
     #[cfg(target_os = "windows")]
@@ -45,7 +67,7 @@ async fn open_url(url: String) -> Result<(), String> {
         std::process::Command::new("cmd")
             .args(["/C", "start", &url])
             .spawn()
-            .map_err(|e| format!("Failed to open URL: {}", e))?;
+            .map_err(|e| AppError::internal(format!("Failed to open URL: {}", e)))?;
     }
 
     #[cfg(target_os = "macos")]
@@ -53,7 +75,7 @@ async fn open_url(url: String) -> Result<(), String> {
         std::process::Command::new("open")
             .arg(&url)
             .spawn()
-            .map_err(|e| format!("Failed to open URL: {}", e))?;
+            .map_err(|e| AppError::internal(format!("Failed to open URL: {}", e)))?;
     }
 
     Ok(())
@@ -61,7 +83,7 @@ async fn open_url(url: String) -> Result<(), String> {
 
 // Database initialization command
 #[tauri::command]
-async fn init_database(app_handle: tauri::AppHandle) -> Result<String, String> {
+async fn init_database(app_handle: tauri::AppHandle) -> Result<String, AppError> {
     // Check if database is already initialized
     let state = app_handle.state::<AppState>();
     {
@@ -84,14 +106,14 @@ async fn init_database(app_handle: tauri::AppHandle) -> Result<String, String> {
     let mut database = Database::new(&db_url).await
         .map_err(|e| {
             println!("Failed to initialize database: {}", e);
-            format!("Failed to initialize database: {}", e)
+            AppError::from(e).context("Failed to initialize database")
         })?;
 
     println!("Running database migrations...");
     database.migrate().await
         .map_err(|e| {
             println!("Failed to run migrations: {}", e);
-            format!("Failed to run migrations: {}", e)
+            AppError::from(e).context("Failed to run migrations")
         })?;
 
     println!("Database tables created successfully - no mock data seeded");
@@ -123,12 +145,16 @@ pub fn run() {
             greet,
             open_url,
             init_database,
+            migrations::get_schema_version,
             auth::login,
             auth::logout,
             auth::get_current_user,
             auth::validate_user_session,
             auth::check_user_permission,
             auth::save_user_to_local,
+            auth::get_active_sessions,
+            auth::revoke_session,
+            auth::revoke_user_sessions,
             users::get_users,
             users::create_user,
             users::update_user,
@@ -140,22 +166,102 @@ pub fn run() {
             inventory::update_product,
             inventory::delete_product,
             inventory::update_stock,
+            inventory::get_product_batches,
+            inventory::receive_batch,
             inventory::get_low_stock_products,
             inventory::get_suppliers,
             inventory::create_supplier,
             inventory::update_supplier,
             inventory::delete_supplier,
+            inventory::set_supplier_lead_time,
             inventory::get_inventory_movements,
+            stock::get_stock_policy_settings,
+            stock::update_stock_policy_settings,
+            stock::get_oversold_lines,
+            stock::review_oversold_line,
+            purchasing::get_purchase_orders,
+            purchasing::get_purchase_order,
+            purchasing::create_purchase_order,
+            purchasing::update_purchase_order,
+            purchasing::send_purchase_order,
+            purchasing::receive_purchase_order,
+            purchasing::close_purchase_order,
+            purchasing::export_purchase_order_csv,
+            purchasing::export_purchase_order_pdf,
+            reorder::get_reorder_settings,
+            reorder::update_reorder_settings,
+            reorder::get_reorder_suggestions,
+            reorder::create_suggested_purchase_order,
+            stocktake::get_stocktakes,
+            stocktake::get_stocktake,
+            stocktake::start_stocktake,
+            stocktake::scan_stocktake_item,
+            stocktake::count_stocktake_item,
+            stocktake::get_stocktake_counts,
+            stocktake::post_stocktake,
+            stocktake::cancel_stocktake,
+            stocktake::export_stocktake_report_pdf,
+            ledger::check_stock_ledger,
+            ledger::repair_stock_ledger,
+            tax::get_tax_rates,
+            tax::create_tax_rate,
+            tax::update_tax_rate,
+            tax::get_tax_classes,
+            tax::create_tax_class,
+            tax::update_tax_class,
+            promotions::get_promotions,
+            promotions::create_promotion,
+            promotions::update_promotion,
             pos::search_products_by_sku,
             pos::search_products_by_name,
+            pos::approve_price_override,
             pos::create_order,
             pos::complete_order,
             pos::cancel_order,
+            orders::get_order_events,
             pos::get_recent_orders,
             pos::get_order_items,
+            pos::get_order_tax_summary,
+            pos::get_order_promotions,
+            payments::add_order_payment,
+            payments::get_order_payments,
+            payments::remove_order_payment,
+            returns::create_return,
+            returns::get_order_returns,
+            returns::print_return,
+            shifts::open_shift,
+            shifts::get_current_shift,
+            shifts::record_cash_movement,
+            shifts::get_shift_cash_movements,
+            shifts::take_x_report,
+            shifts::close_shift,
+            shifts::get_shift_reports,
+            shifts::print_shift,
+            customers::get_customers,
+            customers::get_customer,
+            customers::lookup_customer,
+            customers::create_customer,
+            customers::update_customer,
+            customers::delete_customer,
+            customers::get_customer_history,
+            loyalty::get_loyalty_settings,
+            loyalty::update_loyalty_settings,
+            loyalty::get_loyalty_account,
+            giftcards::lookup_gift_card,
+            giftcards::get_customer_gift_cards,
+            money::get_currency_settings,
+            money::update_currency_settings,
+            carts::park_cart,
+            carts::get_parked_carts,
+            carts::resume_parked_cart,
+            carts::discard_parked_cart,
+            carts::get_parked_cart_settings,
+            carts::update_parked_cart_settings,
             pos::process_barcode_scan,
             pos::print_receipt,
             pos::open_cash_drawer,
+            printer::get_printer_settings,
+            printer::update_printer_settings,
             notifications::get_notifications,
             notifications::mark_notification_read,
             notifications::get_expiring_products,
@@ -163,6 +269,11 @@ pub fn run() {
             notifications::create_notification,
             reports::get_sales_report,
             reports::get_product_sales_report,
+            reports::get_promotion_report,
+            reports::get_tender_report,
+            reports::get_cashier_sales_report,
+            reports::get_cash_drawer_events,
+            reports::get_price_overrides,
             reports::get_inventory_report,
             reports::get_dashboard_stats,
             reports::export_sales_report,
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// Key of the loyalty section in `app_settings`
+pub const LOYALTY_SETTINGS_KEY: &str = "loyalty";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoyaltySettings {
+    pub enabled: bool,
+    /// Points earned for each currency unit a customer spends
+    pub points_per_unit: f64,
+    /// What one point is worth when redeemed
+    pub point_value: f64,
+    /// Days before earned points lapse; `None` keeps them forever
+    pub expiry_days: Option<u32>,
+}
+
+impl Default for LoyaltySettings {
+    fn default() -> Self {
+        LoyaltySettings {
+            enabled: false,
+            points_per_unit: 1.0,
+            point_value: 0.01,
+            expiry_days: Some(365),
+        }
+    }
+}
+
+impl LoyaltySettings {
+    /// Points earned on `amount` of spend, rounded down
+    pub fn points_earned(&self, amount: Money) -> i64 {
+        if !amount.is_positive() {
+            return 0;
+        }
+        (amount.to_major() * self.points_per_unit + 1e-9).floor() as i64
+    }
+
+    /// Points needed to pay `amount`, rounded up so a redemption never
+    /// pays more than the points are worth
+    pub fn points_for(&self, amount: Money) -> i64 {
+        (amount.to_major() / self.point_value - 1e-9).ceil().max(0.0) as i64
+    }
+
+    pub fn value_of(&self, points: i64) -> Money {
+        Money::from_major(points as f64 * self.point_value)
+    }
+
+    /// When points credited at `now` lapse
+    pub fn expires_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.expiry_days.map(|days| now + Duration::days(days as i64))
+    }
+}
+
+/// A change to a customer's points and the documents behind it
+#[derive(Debug, Clone, Copy)]
+pub struct PointsMovement<'a> {
+    pub customer_id: i64,
+    pub kind: LoyaltyKind,
+    pub points: i64,
+    pub order_id: Option<i64>,
+    pub order_payment_id: Option<i64>,
+    pub return_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub notes: &'a str,
+}
+
+/// The earned points to take back when `refunded` more of a sale whose
+/// points were earned on `earn_base` is refunded, `refunded_before` having
+/// been refunded already. Shares are taken cumulatively so refunding the
+/// whole sale, in any number of returns, takes back exactly what it earned.
+pub fn earn_reversal(earned: i64, earn_base: Money, refunded_before: Money, refunded: Money) -> i64 {
+    if earned <= 0 || !earn_base.is_positive() {
+        return 0;
+    }
+    let share = |amount: Money| (earned as f64 * (amount.minor() as f64 / earn_base.minor() as f64).min(1.0)).round() as i64;
+    share(refunded_before + refunded) - share(refunded_before)
+}
+
+fn validate_settings(settings: &LoyaltySettings) -> Result<(), AppError> {
+    if !settings.points_per_unit.is_finite() || settings.points_per_unit < 0.0 {
+        return Err(AppError::validation("points_per_unit", "Earn rate cannot be negative"));
+    }
+    if !settings.point_value.is_finite() || settings.point_value <= 0.0 {
+        return Err(AppError::validation("point_value", "Point value must be positive"));
+    }
+    if settings.expiry_days == Some(0) {
+        return Err(AppError::validation("expiry_days", "Points must last at least one day"));
+    }
+    Ok(())
+}
+
+// Loyalty commands
+#[tauri::command]
+pub async fn get_loyalty_settings(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<LoyaltySettings, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    let settings = db.get_setting::<LoyaltySettings>(LOYALTY_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get loyalty settings"))?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn update_loyalty_settings(
+    token: String,
+    settings: LoyaltySettings,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_settings(&settings)?;
+
+    let db = state.db.lock().await;
+    db.set_setting(LOYALTY_SETTINGS_KEY, &settings).await
+        .map_err(|e| AppError::from(e).context("Failed to save loyalty settings"))
+}
+
+/// A customer's points balance and every movement behind it
+#[tauri::command]
+pub async fn get_loyalty_account(
+    token: String,
+    customer_id: i64,
+    state: State<'_, AppState>,
+) -> Result<LoyaltyAccount, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_loyalty_account(customer_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get loyalty account"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    #[test]
+    fn test_points_are_earned_per_whole_point_and_redeemed_rounding_up() {
+        let settings = LoyaltySettings { enabled: true, points_per_unit: 2.0, point_value: 0.05, expiry_days: None };
+        assert_eq!(settings.points_earned(money(12.49)), 24);
+        assert_eq!(settings.points_earned(money(-3.0)), 0);
+        assert_eq!(settings.points_for(money(1.0)), 20);
+        assert_eq!(settings.points_for(money(1.01)), 21);
+        assert_eq!(settings.value_of(21), money(1.05));
+    }
+
+    #[test]
+    fn test_earn_reversal_takes_back_exactly_what_was_earned() {
+        let first = earn_reversal(10, money(30.0), money(0.0), money(10.0));
+        let second = earn_reversal(10, money(30.0), money(10.0), money(10.0));
+        let third = earn_reversal(10, money(30.0), money(20.0), money(10.0));
+        assert_eq!((first, second, third), (3, 4, 3));
+        assert_eq!(earn_reversal(10, money(30.0), money(30.0), money(5.0)), 0);
+        assert_eq!(earn_reversal(0, money(30.0), money(0.0), money(30.0)), 0);
+    }
+
+    #[test]
+    fn test_settings_are_validated() {
+        assert!(validate_settings(&LoyaltySettings::default()).is_ok());
+        let free = LoyaltySettings { point_value: 0.0, ..LoyaltySettings::default() };
+        assert!(matches!(validate_settings(&free), Err(AppError::Validation { .. })));
+        let instant = LoyaltySettings { expiry_days: Some(0), ..LoyaltySettings::default() };
+        assert!(validate_settings(&instant).is_err());
+    }
+}
@@ -0,0 +1,422 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::AppState;
+use crate::error::AppError;
+
+/// A single numbered, forward-only schema migration
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// All known migrations, in the order they must be applied.
+///
+/// Applied migrations must never be edited: their checksum is recorded in
+/// `schema_version` and verified on every start. Add a new entry instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline",
+        sql: include_str!("../migrations/0001_baseline.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "product batches",
+        sql: include_str!("../migrations/0002_product_batches.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "sessions",
+        sql: include_str!("../migrations/0003_sessions.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "taxes",
+        sql: include_str!("../migrations/0004_taxes.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "promotions",
+        sql: include_str!("../migrations/0005_promotions.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "settings",
+        sql: include_str!("../migrations/0006_settings.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "cash drawer events",
+        sql: include_str!("../migrations/0007_cash_drawer_events.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "order payments",
+        sql: include_str!("../migrations/0008_order_payments.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "order returns",
+        sql: include_str!("../migrations/0009_order_returns.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "order events",
+        sql: include_str!("../migrations/0010_order_events.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "cashier tracking",
+        sql: include_str!("../migrations/0011_cashier_tracking.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "shifts",
+        sql: include_str!("../migrations/0012_shifts.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "parked carts",
+        sql: include_str!("../migrations/0013_parked_carts.sql"),
+    },
+    Migration {
+        version: 14,
+        description: "customers",
+        sql: include_str!("../migrations/0014_customers.sql"),
+    },
+    Migration {
+        version: 15,
+        description: "loyalty",
+        sql: include_str!("../migrations/0015_loyalty.sql"),
+    },
+    Migration {
+        version: 16,
+        description: "gift cards",
+        sql: include_str!("../migrations/0016_gift_cards.sql"),
+    },
+    Migration {
+        version: 17,
+        description: "money minor units",
+        sql: include_str!("../migrations/0017_money_minor_units.sql"),
+    },
+    Migration {
+        version: 18,
+        description: "price overrides",
+        sql: include_str!("../migrations/0018_price_overrides.sql"),
+    },
+    Migration {
+        version: 19,
+        description: "oversell policy",
+        sql: include_str!("../migrations/0019_oversell_policy.sql"),
+    },
+    Migration {
+        version: 20,
+        description: "purchase orders",
+        sql: include_str!("../migrations/0020_purchase_orders.sql"),
+    },
+    Migration {
+        version: 21,
+        description: "supplier lead times",
+        sql: include_str!("../migrations/0021_supplier_lead_times.sql"),
+    },
+    Migration {
+        version: 22,
+        description: "stocktakes",
+        sql: include_str!("../migrations/0022_stocktakes.sql"),
+    },
+    Migration {
+        version: 23,
+        description: "stock ledger",
+        sql: include_str!("../migrations/0023_stock_ledger.sql"),
+    },
+    Migration {
+        version: 24,
+        description: "price override approvals",
+        sql: include_str!("../migrations/0024_price_override_approvals.sql"),
+    },
+    Migration {
+        version: 25,
+        description: "promotion amounts",
+        sql: include_str!("../migrations/0025_promotion_amounts.sql"),
+    },
+];
+
+/// A row of the `schema_version` table
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub checksum: String,
+    pub applied_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaVersion {
+    pub current_version: i64,
+    pub latest_version: i64,
+    pub applied: Vec<AppliedMigration>,
+}
+
+/// SHA-256 of the migration SQL, ignoring carriage returns so that a
+/// Windows checkout with CRLF line endings yields the same checksum
+pub fn checksum(sql: &str) -> String {
+    let normalized = sql.replace('\r', "");
+    Sha256::digest(normalized.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+async fn ensure_version_table(pool: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER NOT NULL PRIMARY KEY,
+            description TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at DATETIME NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn applied_migrations(pool: &SqlitePool) -> Result<Vec<AppliedMigration>> {
+    ensure_version_table(pool).await?;
+
+    let applied = sqlx::query_as::<_, AppliedMigration>(
+        "SELECT * FROM schema_version ORDER BY version ASC"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(applied)
+}
+
+/// Apply every pending migration in `migrations`, each in its own transaction.
+///
+/// Fails without touching the schema if an applied migration has been edited,
+/// or if the database was migrated by a newer build than this one.
+pub async fn run(pool: &SqlitePool, migrations: &[Migration]) -> Result<i64> {
+    let applied = applied_migrations(pool).await?;
+
+    for record in &applied {
+        match migrations.iter().find(|m| m.version == record.version) {
+            Some(migration) => {
+                let expected = checksum(migration.sql);
+                if expected != record.checksum {
+                    return Err(anyhow::anyhow!(
+                        "Migration {} ({}) was modified after it was applied: recorded checksum {}, current checksum {}",
+                        record.version, record.description, record.checksum, expected
+                    ));
+                }
+            }
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Database is at schema version {} which this build does not know about; please update the application",
+                    record.version
+                ));
+            }
+        }
+    }
+
+    let applied_version = applied.last().map(|m| m.version).unwrap_or(0);
+    let mut current_version = applied_version;
+
+    for migration in migrations.iter().filter(|m| m.version > applied_version) {
+        let mut tx = pool.begin().await?;
+
+        sqlx::raw_sql(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| anyhow::anyhow!("Migration {} ({}) failed: {}", migration.version, migration.description, e))?;
+
+        sqlx::query(
+            "INSERT INTO schema_version (version, description, checksum, applied_at) VALUES (?, ?, ?, ?)"
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .bind(checksum(migration.sql))
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        current_version = migration.version;
+    }
+
+    Ok(current_version)
+}
+
+#[tauri::command]
+pub async fn get_schema_version(
+    state: State<'_, AppState>,
+) -> Result<SchemaVersion, AppError> {
+    let db = state.db.lock().await;
+    let pool = db.pool.as_ref().ok_or_else(|| AppError::database("Database not initialized"))?;
+
+    let applied = applied_migrations(pool).await
+        .map_err(|e| AppError::from(e).context("Failed to get schema version"))?;
+
+    Ok(SchemaVersion {
+        current_version: applied.last().map(|m| m.version).unwrap_or(0),
+        latest_version: latest_version(),
+        applied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn memory_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_checksum_ignores_line_endings() {
+        assert_eq!(checksum("SELECT 1;\nSELECT 2;\n"), checksum("SELECT 1;\r\nSELECT 2;\r\n"));
+        assert_ne!(checksum("SELECT 1;"), checksum("SELECT 2;"));
+    }
+
+    #[test]
+    fn test_migrations_are_strictly_increasing() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_is_idempotent() {
+        let pool = memory_pool().await;
+
+        assert_eq!(run(&pool, MIGRATIONS).await.unwrap(), latest_version());
+        assert_eq!(run(&pool, MIGRATIONS).await.unwrap(), latest_version());
+
+        let applied = applied_migrations(&pool).await.unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+    }
+
+    #[tokio::test]
+    async fn test_edited_migration_is_rejected() {
+        let pool = memory_pool().await;
+        let original = [Migration { version: 1, description: "one", sql: "CREATE TABLE t (id INTEGER);" }];
+        run(&pool, &original).await.unwrap();
+
+        let edited = [Migration { version: 1, description: "one", sql: "CREATE TABLE t (id TEXT);" }];
+        let err = run(&pool, &edited).await.unwrap_err();
+        assert!(err.to_string().contains("was modified"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_version_is_rejected() {
+        let pool = memory_pool().await;
+        let newer = [
+            Migration { version: 1, description: "one", sql: "CREATE TABLE t (id INTEGER);" },
+            Migration { version: 2, description: "two", sql: "ALTER TABLE t ADD COLUMN name TEXT;" },
+        ];
+        run(&pool, &newer).await.unwrap();
+
+        let err = run(&pool, &newer[..1]).await.unwrap_err();
+        assert!(err.to_string().contains("does not know about"));
+    }
+
+    #[tokio::test]
+    async fn test_float_amounts_become_whole_cents() {
+        let pool = memory_pool().await;
+        run(&pool, &MIGRATIONS[..16]).await.unwrap();
+        sqlx::query(
+            "INSERT INTO products (created_at, updated_at, name, description, sku, category, price, cost, quantity, reorder_level) VALUES (CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, 'Tea', '', 'TEA', 'grocery', 2.675, 0.1, 1, 0)"
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run(&pool, MIGRATIONS).await.unwrap();
+
+        let (price, cost): (i64, i64) = sqlx::query_as("SELECT price, cost FROM products WHERE sku = 'TEA'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((price, cost), (268, 10));
+    }
+
+    #[tokio::test]
+    async fn test_promotion_amounts_become_whole_cents() {
+        let pool = memory_pool().await;
+        run(&pool, &MIGRATIONS[..24]).await.unwrap();
+        for (promotion_type, value) in [("amount_off", 1.99), ("multi_buy", 5.0), ("percent_off", 12.5)] {
+            sqlx::query(
+                "INSERT INTO promotions (created_at, updated_at, name, promotion_type, scope, value) VALUES (CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, ?, 'line', ?)"
+            )
+            .bind(promotion_type)
+            .bind(promotion_type)
+            .bind(value)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        run(&pool, MIGRATIONS).await.unwrap();
+
+        let promotions: Vec<(f64, i64)> = sqlx::query_as("SELECT value, amount FROM promotions ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(promotions, vec![(0.0, 199), (0.0, 500), (12.5, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_movements_become_a_ledger_brought_up_to_stock() {
+        let pool = memory_pool().await;
+        run(&pool, &MIGRATIONS[..22]).await.unwrap();
+        sqlx::query(
+            "INSERT INTO products (created_at, updated_at, name, description, sku, category, price, cost, quantity, reorder_level) VALUES (CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, 'Tea', '', 'TEA', 'grocery', 250, 100, 7, 0)"
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        for (quantity, movement_type, notes) in [(-2, "sale", ""), (-1, "Damaged", ""), (3, "restock", "back room")] {
+            sqlx::query(
+                "INSERT INTO stock_movements (created_at, updated_at, product_id, quantity, movement_type, notes) VALUES (CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, 1, ?, ?, ?)"
+            )
+            .bind(quantity)
+            .bind(movement_type)
+            .bind(notes)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        run(&pool, MIGRATIONS).await.unwrap();
+
+        let ledger: Vec<(i32, String, String, i32)> = sqlx::query_as("SELECT quantity, movement_type, notes, balance_after FROM stock_movements ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(ledger, vec![
+            (-2, "sale".to_string(), "".to_string(), -2),
+            (-1, "write_off".to_string(), "".to_string(), -3),
+            (3, "adjustment".to_string(), "restock: back room".to_string(), 0),
+            (7, "adjustment".to_string(), "Balance brought forward into the stock ledger".to_string(), 7),
+        ]);
+    }
+}
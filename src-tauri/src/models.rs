@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+
+pub use crate::money::Money;
 
 // Updated User model to match online API schema exactly
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -49,11 +51,57 @@ pub struct Product {
     pub description: String,
     pub sku: String,
     pub category: String,
-    pub price: f64,
-    pub cost: f64,
+    pub price: Money,
+    pub cost: Money,
     pub quantity: i32,
     pub reorder_level: i32,
     pub supplier_id: Option<i64>,
+    pub tax_class_id: Option<i64>,
+    /// Overrides the category and store oversell policy when set
+    pub stock_policy: Option<StockPolicy>,
+    /// Where the product is kept, e.g. an aisle or back room shelf
+    pub location: Option<String>,
+}
+
+/// What a sale may do when it asks for more than is free to sell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum StockPolicy {
+    /// Refuse the sale
+    #[default]
+    Block,
+    /// Sell anyway and flag the sale for review
+    Warn,
+    /// Sell what is not there yet, to be filled from the next delivery
+    Backorder,
+}
+
+// A delivered lot of a product; stock is sold first-expired, first-out
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProductBatch {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub product_id: i64,
+    pub batch_number: String,
+    pub received_date: NaiveDate,
+    pub expiry_date: Option<NaiveDate>,
+    pub initial_quantity: i32,
+    pub quantity: i32,
+}
+
+// A batch with stock left that expires within the requested window
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExpiringBatch {
+    pub batch_id: i64,
+    pub batch_number: String,
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    pub expiry_date: NaiveDate,
+    pub quantity: i32,
+    pub days_until_expiry: i64,
 }
 
 // Updated Supplier model to match online API schema exactly
@@ -67,6 +115,194 @@ pub struct Supplier {
     pub email: String,
     pub phone: String,
     pub address: String,
+    /// Days from order to delivery; the reorder default applies when unset
+    pub lead_time_days: Option<i32>,
+}
+
+// Where a purchase order is in its lifecycle; the legal moves are in `purchasing.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum PurchaseOrderStatus {
+    Draft,
+    Sent,
+    PartiallyReceived,
+    Received,
+    Closed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PurchaseOrder {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    pub status: PurchaseOrderStatus,
+    pub expected_date: Option<NaiveDate>,
+    pub notes: String,
+    pub currency: String,
+    pub created_by: i64,
+    pub created_by_name: String,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Expected cost of everything ordered
+    pub total_cost: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PurchaseOrderLine {
+    pub id: i64,
+    pub purchase_order_id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    pub quantity_ordered: i32,
+    pub quantity_received: i32,
+    /// Expected cost per unit
+    pub unit_cost: Money,
+}
+
+// A delivery against a purchase order line and the batch it became
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PurchaseOrderReceipt {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub purchase_order_id: i64,
+    pub line_id: i64,
+    pub product_id: i64,
+    pub batch_id: i64,
+    pub quantity: i32,
+    /// Actual cost per unit of this delivery
+    pub unit_cost: Money,
+    pub user_id: i64,
+    pub user_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseOrderDetails {
+    pub purchase_order: PurchaseOrder,
+    pub supplier: Supplier,
+    pub lines: Vec<PurchaseOrderLine>,
+    pub receipts: Vec<PurchaseOrderReceipt>,
+}
+
+// Where a stocktake is; the legal moves are in `stocktake.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum StocktakeStatus {
+    Counting,
+    Posted,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum CountMethod {
+    Scan,
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Stocktake {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub status: StocktakeStatus,
+    /// Only products in this category were frozen; all of them when unset
+    pub category: Option<String>,
+    /// Only products kept here were frozen; all of them when unset
+    pub location: Option<String>,
+    pub notes: String,
+    pub started_by: i64,
+    pub started_by_name: String,
+    pub posted_at: Option<DateTime<Utc>>,
+    pub posted_by: Option<i64>,
+}
+
+// A product frozen into a stocktake with its tally so far
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StocktakeLine {
+    pub id: i64,
+    pub stocktake_id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    pub category: String,
+    pub location: Option<String>,
+    /// On hand when the stocktake started
+    pub expected_quantity: i32,
+    /// Cost per unit when the stocktake started
+    pub unit_cost: Money,
+    /// Empty until someone counts the product
+    pub counted_quantity: Option<i32>,
+    pub variance: Option<i32>,
+    pub variance_value: Option<Money>,
+}
+
+// One entry towards a line's tally
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StocktakeCount {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub stocktake_id: i64,
+    pub line_id: i64,
+    pub product_id: i64,
+    pub quantity: i32,
+    pub method: CountMethod,
+    pub device_id: String,
+    pub user_id: i64,
+    pub user_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VarianceSummary {
+    pub lines_counted: i32,
+    pub lines_uncounted: i32,
+    /// Units found beyond what was expected, and their cost
+    pub units_over: i32,
+    pub value_over: Money,
+    /// Units missing, and their cost
+    pub units_short: i32,
+    pub value_short: Money,
+    pub net_value: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeDetails {
+    pub stocktake: Stocktake,
+    pub lines: Vec<StocktakeLine>,
+    pub summary: VarianceSummary,
+}
+
+// A registered customer; purchases are linked through orders.customer_id
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Customer {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub email: String,
+    pub phone: String,
+    pub address: String,
+    pub notes: String,
+}
+
+// Where an order is in its lifecycle; the legal moves are in `orders.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum OrderStatus {
+    Draft,
+    Pending,
+    /// Tenders cover the total, not yet completed
+    Paid,
+    Completed,
+    PartiallyRefunded,
+    Refunded,
+    Voided,
 }
 
 // Updated Order model to match online API schema exactly
@@ -76,10 +312,17 @@ pub struct Order {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub customer_name: String,
-    pub total_amount: f64,
+    pub total_amount: Money,
     pub payment_method: String,
-    pub status: String,
+    pub status: OrderStatus,
     pub cashier_id: Option<i64>,
+    pub customer_id: Option<i64>,
+    /// Sum of the lines net of tax and discounts
+    pub subtotal: Money,
+    pub tax_total: Money,
+    pub discount_total: Money,
+    /// ISO code of the currency the amounts are in
+    pub currency: String,
 }
 
 // Updated OrderItem model to match online API schema exactly
@@ -91,10 +334,547 @@ pub struct OrderItem {
     pub order_id: i64,
     pub product_id: i64,
     pub quantity: i32,
-    pub unit_price: f64,
+    pub unit_price: Money,
+    /// Line and order-level promotions taken off this line
+    pub discount_amount: Money,
+    pub net_amount: Money,
+    pub tax_amount: Money,
+    pub line_total: Money,
+}
+
+// A configurable tax rate, as a percentage
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaxRate {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub rate: f64,
+    pub is_compound: bool,
+    pub is_active: bool,
+}
+
+// A set of stacked tax rates assigned to products
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaxClass {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub description: String,
+    pub prices_include_tax: bool,
+    /// Rates in the order they are stacked
+    #[sqlx(skip)]
+    pub rates: Vec<TaxRate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum PromotionType {
+    PercentOff,
+    AmountOff,
+    /// Buy `buy_quantity`, get `get_quantity` at `value` percent off
+    BuyXGetY,
+    /// `buy_quantity` units for a bundle price of `value`
+    MultiBuy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum PromotionScope {
+    Line,
+    Order,
+}
+
+// A discount rule evaluated when an order is priced
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Promotion {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub description: String,
+    pub promotion_type: PromotionType,
+    pub scope: PromotionScope,
+    pub value: f64,
+    pub amount: Money,
+    pub product_id: Option<i64>,
+    pub category: Option<String>,
+    pub buy_quantity: Option<i32>,
+    pub get_quantity: Option<i32>,
+    pub min_order_amount: Option<Money>,
+    pub coupon_code: Option<String>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub priority: i32,
+    pub is_active: bool,
+}
+
+// A promotion applied to an order, with the discount it gave
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderPromotion {
+    pub id: i64,
+    pub order_id: i64,
+    pub order_item_id: Option<i64>,
+    pub promotion_id: i64,
+    pub name: String,
+    pub coupon_code: Option<String>,
+    pub discount_amount: Money,
+}
+
+// One step in an order's lifecycle; `from_status` is empty when the order was created
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderEvent {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub order_id: i64,
+    pub from_status: Option<OrderStatus>,
+    pub to_status: OrderStatus,
+    pub user_id: Option<i64>,
+    pub user_name: String,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum TenderType {
+    Cash,
+    CreditCard,
+    DebitCard,
+    MobilePayment,
+    /// Paid with the customer's loyalty points
+    LoyaltyPoints,
+    GiftCard,
+    /// Store credit spent, or refunds given as store credit instead of cash
+    StoreCredit,
+    Other,
+}
+
+// One tender taken against an order
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderPayment {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub order_id: i64,
+    pub tender_type: TenderType,
+    /// Paid toward the order total
+    pub amount: Money,
+    /// Handed over by the customer
+    pub tendered: Money,
+    pub change_due: Money,
+    pub reference: String,
+    /// The shift open when the tender was taken
+    pub shift_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ReturnReason {
+    Damaged,
+    Defective,
+    WrongItem,
+    ChangedMind,
+    Expired,
+    Other,
+}
+
+// What happens to returned goods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ReturnDisposition {
+    /// Back on the shelf and into stock
+    Restock,
+    /// Not sellable; stays out of stock
+    WriteOff,
+}
+
+// A return against a completed order
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderReturn {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub order_id: i64,
+    pub user_id: i64,
+    pub user_name: String,
+    pub reason: ReturnReason,
+    pub notes: String,
+    pub refund_total: Money,
+    pub tax_total: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderReturnItem {
+    pub id: i64,
+    pub return_id: i64,
+    pub order_item_id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub quantity: i32,
+    pub disposition: ReturnDisposition,
+    /// Share of the line total refunded, tax included
+    pub refund_amount: Money,
+    pub tax_amount: Money,
+}
+
+// Money paid back for a return, to one of the order's original tenders
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderRefund {
+    pub id: i64,
+    pub return_id: i64,
+    pub order_payment_id: Option<i64>,
+    pub tender_type: TenderType,
+    pub amount: Money,
+    pub shift_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnDetails {
+    pub order_return: OrderReturn,
+    pub items: Vec<OrderReturnItem>,
+    pub refunds: Vec<OrderRefund>,
+    /// Issued when the refund was taken as store credit
+    pub store_credit: Option<GiftCard>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ShiftStatus {
+    Open,
+    Closed,
+}
+
+// A till session from opening float to counted close
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Shift {
+    pub id: i64,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub user_id: i64,
+    pub user_name: String,
+    pub opening_float: Money,
+    pub status: ShiftStatus,
+    pub closed_by_user_id: Option<i64>,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum CashMovementKind {
+    /// Cash paid out of the drawer for an expense
+    Payout,
+    /// Surplus cash moved from the drawer to the safe
+    Drop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShiftCashMovement {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub shift_id: i64,
+    pub user_id: i64,
+    pub user_name: String,
+    pub kind: CashMovementKind,
+    pub amount: Money,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ShiftReportType {
+    /// Mid-shift reading; the shift stays open
+    X,
+    /// End-of-day reading taken when the shift is closed
+    Z,
+}
+
+// What one tender should and did come to over a shift
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShiftTenderLine {
+    pub tender_type: TenderType,
+    pub payments: Money,
+    pub refunds: Money,
+    pub expected: Money,
+    pub counted: Option<Money>,
+    /// Counted minus expected; negative when the till is short
+    pub variance: Option<Money>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShiftReport {
+    pub report_type: ShiftReportType,
+    pub shift_id: i64,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub opened_by: String,
+    pub opening_float: Money,
+    pub orders: i64,
+    pub gross_sales: Money,
+    pub refunds: Money,
+    pub payouts: Money,
+    pub drops: Money,
+    pub tenders: Vec<ShiftTenderLine>,
+}
+
+// A stored X or Z report, reprintable as produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShiftReportRecord {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub shift_id: i64,
+    pub user_id: i64,
+    pub user_name: String,
+    pub report: ShiftReport,
+}
+
+// A cart set aside at the till, with its line count and value
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ParkedCart {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub label: String,
+    pub customer_name: String,
+    pub payment_method: String,
+    pub user_id: i64,
+    pub user_name: String,
+    /// Stock is held for the cart until then; `None` when nothing is reserved
+    pub reserved_until: Option<DateTime<Utc>>,
+    pub item_count: i64,
+    pub subtotal: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ParkedCartItem {
+    pub id: i64,
+    pub cart_id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    pub quantity: i32,
+    pub price_at_sale: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParkedCartDetails {
+    pub cart: ParkedCart,
+    pub items: Vec<ParkedCartItem>,
+}
+
+// What a customer has bought and returned over time
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CustomerSummary {
+    pub orders: i64,
+    /// Sales less everything refunded on them
+    pub lifetime_spend: Money,
+    pub refunded: Money,
+    pub first_purchase_at: Option<DateTime<Utc>>,
+    pub last_purchase_at: Option<DateTime<Utc>>,
+}
+
+// One line of a customer's past sales, as needed to settle a warranty claim
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CustomerPurchaseLine {
+    pub order_id: i64,
+    pub created_at: DateTime<Utc>,
+    pub status: OrderStatus,
+    pub order_item_id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    pub quantity: i32,
+    pub returned_quantity: i32,
+    pub line_total: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerHistory {
+    pub customer: Customer,
+    pub summary: CustomerSummary,
+    pub purchases: Vec<CustomerPurchaseLine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum LoyaltyKind {
+    /// Awarded for a completed sale
+    Earn,
+    /// Spent as a tender
+    Redeem,
+    /// Given back after a redemption was cancelled or refunded
+    Restore,
+    /// Taken back when the sale that earned them is refunded
+    Reverse,
+    /// Lapsed unused
+    Expire,
+}
+
+// One change to a customer's points; positive rows are lots drawn down
+// oldest expiry first
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LoyaltyEntry {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub customer_id: i64,
+    pub kind: LoyaltyKind,
+    pub points: i64,
+    /// What is left of a positive lot
+    pub remaining: i64,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub order_id: Option<i64>,
+    pub order_payment_id: Option<i64>,
+    pub return_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub notes: String,
 }
 
-// Updated StockMovement model to match online API schema exactly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoyaltyAccount {
+    pub customer_id: i64,
+    pub balance: i64,
+    /// What the balance is worth as a tender
+    pub balance_value: Money,
+    pub next_expiry: Option<DateTime<Utc>>,
+    pub entries: Vec<LoyaltyEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum GiftCardKind {
+    /// Sold on an order
+    GiftCard,
+    /// Issued in place of a cash refund
+    StoreCredit,
+}
+
+impl GiftCardKind {
+    /// The tender a card of this kind is spent with
+    pub fn tender_type(self) -> TenderType {
+        match self {
+            GiftCardKind::GiftCard => TenderType::GiftCard,
+            GiftCardKind::StoreCredit => TenderType::StoreCredit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum GiftCardStatus {
+    /// Sold on an order that has not completed yet
+    Pending,
+    Active,
+    /// The order that sold it was voided
+    Void,
+}
+
+// A gift card or store credit; the balance is the sum of its ledger
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GiftCard {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub code: String,
+    pub kind: GiftCardKind,
+    pub status: GiftCardStatus,
+    pub customer_id: Option<i64>,
+    pub initial_amount: Money,
+    /// Last day the card can be spent
+    pub expires_on: Option<NaiveDate>,
+    pub issued_order_id: Option<i64>,
+    pub issued_return_id: Option<i64>,
+    pub balance: Money,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum GiftCardEntryKind {
+    Issue,
+    Redeem,
+    /// Put back after a redemption was taken back or refunded
+    Refund,
+}
+
+// One change to a card's balance
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GiftCardEntry {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub gift_card_id: i64,
+    pub kind: GiftCardEntryKind,
+    pub amount: Money,
+    pub order_id: Option<i64>,
+    pub order_payment_id: Option<i64>,
+    pub return_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftCardDetails {
+    pub card: GiftCard,
+    pub entries: Vec<GiftCardEntry>,
+}
+
+// Tax charged on an order, one line per rate, as printed on the receipt
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderTaxLine {
+    pub tax_rate_id: Option<i64>,
+    pub name: String,
+    pub rate: f64,
+    pub taxable_amount: Money,
+    pub tax_amount: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderTaxSummary {
+    pub order_id: i64,
+    pub subtotal: Money,
+    pub tax_total: Money,
+    pub total_amount: Money,
+    pub taxes: Vec<OrderTaxLine>,
+}
+
+// Why stock moved; the sign of the quantity says which way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum StockMovementKind {
+    Sale,
+    /// Goods coming back from a customer, including cancelled sales
+    Return,
+    /// Goods delivered into stock
+    Receipt,
+    /// Corrections, opening stock and stocktake variances
+    Adjustment,
+    /// Stock sent to or received from another site
+    Transfer,
+    /// Damaged, expired or lost goods taken out of stock
+    WriteOff,
+}
+
+// The kind of document a stock movement was made by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum StockDocument {
+    Order,
+    Return,
+    PurchaseOrder,
+    Stocktake,
+    Batch,
+}
+
+// An entry in the stock ledger, the record of every change to a product's
+// quantity. The fields up to `user_id` match the online API schema.
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct StockMovement {
     pub id: i64,
@@ -102,8 +882,29 @@ pub struct StockMovement {
     pub updated_at: DateTime<Utc>,
     pub product_id: i64,
     pub quantity: i32,
-    pub movement_type: String,
+    pub movement_type: StockMovementKind,
     pub notes: String,
+    /// Who made the change; empty for movements recorded before users were tracked
+    pub user_id: Option<i64>,
+    pub user_name: String,
+    /// The product's quantity once this movement was made
+    pub balance_after: i32,
+    pub document_type: Option<StockDocument>,
+    pub document_id: Option<i64>,
+}
+
+// A product whose quantity does not agree with its ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LedgerDiscrepancy {
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    /// What `products.quantity` says
+    pub quantity: i32,
+    /// The sum of every movement in the ledger
+    pub ledger_quantity: i32,
+    /// The running balance of the latest movement
+    pub last_balance: Option<i32>,
 }
 
 // Keep InventoryMovement as alias for backward compatibility
@@ -123,6 +924,91 @@ pub struct SyncQueue {
     pub error_message: Option<String>,
 }
 
+// A login session; `user_info` is the JSON snapshot taken at login
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Session {
+    pub id: i64,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub user_id: i64,
+    #[serde(skip_serializing)]
+    pub user_info: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum CashDrawerReason {
+    Sale,
+    NoSale,
+    Payout,
+}
+
+// An audited opening of the cash drawer
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CashDrawerEvent {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub user_id: i64,
+    pub user_name: String,
+    pub reason: CashDrawerReason,
+    pub order_id: Option<i64>,
+    pub notes: String,
+    /// False if the drawer could not be kicked
+    pub opened: bool,
+}
+
+// A product sold beyond its stock under a warn or backorder policy
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OversoldLine {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub order_id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub policy: StockPolicy,
+    pub requested: i32,
+    /// Stock free to sell when the order was taken
+    pub available: i32,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub reviewed_by: Option<i64>,
+    pub reviewer_name: String,
+    pub review_notes: String,
+}
+
+// A manager's sign-off to sell one product at one price; the token is
+// handed over once and used up by the order that sells the line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceOverrideApproval {
+    pub approval_token: String,
+    pub product_id: i64,
+    pub price: Money,
+    pub approver_name: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// A line sold away from its shelf price, and who approved it
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PriceOverride {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub order_id: i64,
+    pub order_item_id: i64,
+    pub product_id: i64,
+    pub quantity: i32,
+    pub shelf_price: Money,
+    pub override_price: Money,
+    pub reason: String,
+    pub cashier_id: i64,
+    pub cashier_name: String,
+    pub approved_by: i64,
+    pub approver_name: String,
+}
+
 // DTOs for API requests/responses
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
@@ -145,39 +1031,262 @@ pub struct UserInfo {
     pub permissions: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: i64,
+    pub user_id: i64,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub idle_expires_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub is_current: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateProductRequest {
     pub name: String,
     pub description: Option<String>,
     pub sku: String,
     pub category: String,
-    pub price: f64,
-    pub cost: f64,
+    pub price: Money,
+    pub cost: Money,
     pub quantity: i32,
     pub reorder_level: i32,
-    pub expiry_date: Option<String>,
+    /// Expiry of the opening batch created for the initial quantity
+    pub expiry_date: Option<NaiveDate>,
     pub supplier_id: Option<i64>,
+    pub tax_class_id: Option<i64>,
+    #[serde(default)]
+    pub stock_policy: Option<StockPolicy>,
+    #[serde(default)]
+    pub location: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateStockRequest {
     pub quantity_change: i32,
-    pub movement_type: String,
+    /// Adjustment, transfer or write-off; the other kinds come from their documents
+    pub movement_type: StockMovementKind,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveBatchRequest {
+    pub batch_number: Option<String>,
+    pub received_date: Option<NaiveDate>,
+    pub expiry_date: Option<NaiveDate>,
+    pub quantity: i32,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderLineRequest {
+    pub product_id: i64,
+    pub quantity: i32,
+    /// Expected cost per unit
+    pub unit_cost: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderRequest {
+    pub supplier_id: i64,
+    pub expected_date: Option<NaiveDate>,
     pub notes: Option<String>,
+    pub lines: Vec<PurchaseOrderLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveLineRequest {
+    pub line_id: i64,
+    pub quantity: i32,
+    /// What the delivery actually cost per unit; the line's expected cost if unset
+    pub unit_cost: Option<Money>,
+    pub batch_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceivePurchaseOrderRequest {
+    pub lines: Vec<ReceiveLineRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StocktakeRequest {
+    /// Count one category only
+    pub category: Option<String>,
+    /// Count one location only
+    pub location: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxRateRequest {
+    pub name: String,
+    /// Percentage, e.g. 7.5 for 7.5%
+    pub rate: f64,
+    #[serde(default)]
+    pub is_compound: bool,
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxClassRequest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub prices_include_tax: bool,
+    /// Rates in stacking order; compound rates apply on top of earlier ones
+    pub rate_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromotionRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub promotion_type: PromotionType,
+    pub scope: PromotionScope,
+    /// Percent off, for percent_off and buy_x_get_y
+    #[serde(default)]
+    pub value: f64,
+    /// Amount off, or the bundle price of a multi_buy
+    #[serde(default)]
+    pub amount: Money,
+    pub product_id: Option<i64>,
+    pub category: Option<String>,
+    pub buy_quantity: Option<i32>,
+    pub get_quantity: Option<i32>,
+    pub min_order_amount: Option<Money>,
+    pub coupon_code: Option<String>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateOrderRequest {
     pub customer_name: Option<String>,
+    /// Registered customer the sale is for; their name is used when no
+    /// customer_name is given
+    #[serde(default)]
+    pub customer_id: Option<i64>,
     pub payment_method: String,
     pub items: Vec<OrderItemRequest>,
+    #[serde(default)]
+    pub coupon_codes: Vec<String>,
+    /// Tenders taken at checkout; more can be added before completing
+    #[serde(default)]
+    pub payments: Vec<OrderPaymentRequest>,
+    /// Gift cards sold on the order, usable once it completes
+    #[serde(default)]
+    pub gift_cards: Vec<GiftCardSaleRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GiftCardSaleRequest {
+    pub amount: Money,
+    /// A pre-printed card's code; one is generated when left out
+    pub code: Option<String>,
+    pub expires_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderPaymentRequest {
+    pub tender_type: TenderType,
+    /// Amount handed over; only cash may exceed the balance due
+    pub amount: Money,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderPaymentResult {
+    pub payment_id: i64,
+    pub amount_paid: Money,
+    pub balance_due: Money,
+    pub change_due: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerRequest {
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParkCartRequest {
+    pub label: String,
+    pub customer_name: Option<String>,
+    pub payment_method: Option<String>,
+    pub items: Vec<OrderItemRequest>,
+    /// Hold the cart's stock for the configured reservation time
+    #[serde(default)]
+    pub reserve_stock: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenShiftRequest {
+    pub opening_float: Money,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CashMovementRequest {
+    pub kind: CashMovementKind,
+    pub amount: Money,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenderCount {
+    pub tender_type: TenderType,
+    pub counted: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CloseShiftRequest {
+    /// Counted takings per tender; cash must be counted
+    pub counts: Vec<TenderCount>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReturnItemRequest {
+    pub order_item_id: i64,
+    pub quantity: i32,
+    pub disposition: ReturnDisposition,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateReturnRequest {
+    pub order_id: i64,
+    pub reason: ReturnReason,
+    pub notes: Option<String>,
+    pub items: Vec<ReturnItemRequest>,
+    /// Give what would be refunded in cash as store credit instead
+    #[serde(default)]
+    pub store_credit: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrderItemRequest {
     pub product_id: i64,
     pub quantity: i32,
-    pub price_at_sale: f64,
+    /// Must match the shelf price unless the line is a price override
+    pub price_at_sale: Money,
+    /// Why the line is sold away from its shelf price; an override also
+    /// needs a manager's approval
+    #[serde(default)]
+    pub override_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
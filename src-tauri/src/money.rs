@@ -0,0 +1,274 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use tauri::State;
+
+use crate::AppState;
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// Key of the currency section in `app_settings`
+pub const CURRENCY_SETTINGS_KEY: &str = "currency";
+
+const MINOR_PER_MAJOR: i64 = 100;
+
+/// ISO 4217 currencies with two decimal places, the only ones `Money` can
+/// hold; yen or dinar amounts would be mis-scaled by a factor of 100 or 10
+const TWO_DECIMAL_CURRENCIES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD", "CDF", "CHF",
+    "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR",
+    "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF",
+    "IDR", "ILS", "INR", "IRR", "JMD", "KES", "KGS", "KHR", "KPW", "KYD", "KZT", "LAK", "LBP",
+    "LKR", "LRD", "LSL", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "PAB", "PEN", "PGK",
+    "PHP", "PKR", "PLN", "QAR", "RON", "RSD", "RUB", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD",
+    "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TOP",
+    "TRY", "TTD", "TWD", "TZS", "UAH", "USD", "UYU", "UZS", "VES", "WST", "XCD", "YER", "ZAR",
+    "ZMW", "ZWL",
+];
+
+/// An amount in the store currency, held as a whole number of minor units
+/// (cents) so sums and differences are exact. The store currency is always
+/// one with two decimal places.
+///
+/// It is stored as an INTEGER column and crosses the API as a decimal number
+/// of major units (12.34); fractions of a cent only ever appear in rates and
+/// percentages, and are rounded half away from zero where they are applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_minor(minor: i64) -> Self {
+        Money(minor)
+    }
+
+    pub const fn minor(self) -> i64 {
+        self.0
+    }
+
+    /// The nearest amount to a fractional number of minor units
+    pub fn nearest(minor: f64) -> Self {
+        // Absorb binary representation error so 100.5 rounds like the decimal it stands for
+        Money((minor + minor.signum() * 1e-7).round() as i64)
+    }
+
+    /// The nearest amount to a decimal in major units, e.g. 12.345 -> 12.35
+    pub fn from_major(amount: f64) -> Self {
+        Money::nearest(amount * MINOR_PER_MAJOR as f64)
+    }
+
+    pub fn to_major(self) -> f64 {
+        self.0 as f64 / MINOR_PER_MAJOR as f64
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    /// This amount times a rate, e.g. `price.scale(0.2)` for 20% of it
+    pub fn scale(self, factor: f64) -> Self {
+        Money::nearest(self.0 as f64 * factor)
+    }
+
+    /// `part / whole` of this amount, computed in integers
+    pub fn share(self, part: i64, whole: i64) -> Self {
+        if whole == 0 {
+            return Money::ZERO;
+        }
+        let scaled = self.0 as i128 * part as i128;
+        let whole = whole as i128;
+        // Round half away from zero
+        let rounded = (2 * scaled + scaled.signum() * whole.abs()) / (2 * whole);
+        Money(rounded as i64)
+    }
+}
+
+impl fmt::Display for Money {
+    /// Major units with two decimals, e.g. "-12.05"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let minor = self.0.unsigned_abs();
+        let amount = format!("{}{}.{:02}", sign, minor / MINOR_PER_MAJOR as u64, minor % MINOR_PER_MAJOR as u64);
+        f.pad(&amount)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_major())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let amount = f64::deserialize(deserializer)?;
+        if !amount.is_finite() {
+            return Err(serde::de::Error::custom("amount must be a finite number"));
+        }
+        Ok(Money::from_major(amount))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+/// A unit price times a quantity
+impl Mul<i32> for Money {
+    type Output = Money;
+
+    fn mul(self, quantity: i32) -> Money {
+        Money(self.0 * quantity as i64)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurrencySettings {
+    /// ISO 4217 code stamped on every new order
+    pub code: String,
+}
+
+impl Default for CurrencySettings {
+    fn default() -> Self {
+        CurrencySettings { code: "USD".to_string() }
+    }
+}
+
+fn validate_settings(settings: &CurrencySettings) -> Result<(), AppError> {
+    if TWO_DECIMAL_CURRENCIES.binary_search(&settings.code.as_str()).is_err() {
+        return Err(AppError::validation("code", "Currency must be an ISO code with two decimal places, such as USD"));
+    }
+    Ok(())
+}
+
+// Currency commands
+#[tauri::command]
+pub async fn get_currency_settings(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<CurrencySettings, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    let settings = db.get_setting::<CurrencySettings>(CURRENCY_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get currency settings"))?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+/// Changing the currency only affects orders created afterwards; amounts
+/// already recorded are not converted
+#[tauri::command]
+pub async fn update_currency_settings(
+    token: String,
+    settings: CurrencySettings,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_settings(&settings)?;
+
+    let db = state.db.lock().await;
+    db.set_setting(CURRENCY_SETTINGS_KEY, &settings).await
+        .map_err(|e| AppError::from(e).context("Failed to save currency settings"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amounts_round_to_the_cent_and_sum_exactly() {
+        assert_eq!(Money::from_major(1.005), Money::from_minor(101));
+        assert_eq!(Money::from_major(-2.675), Money::from_minor(-268));
+        let total: Money = std::iter::repeat_n(Money::from_major(0.1), 10).sum();
+        assert_eq!(total, Money::from_major(1.0));
+        assert_eq!(Money::from_major(4.99) * 3, Money::from_minor(1497));
+        assert_eq!(Money::from_major(14.97).scale(0.2), Money::from_minor(299));
+    }
+
+    #[test]
+    fn test_shares_round_half_away_from_zero() {
+        let amount = Money::from_minor(1000);
+        assert_eq!(amount.share(1, 3), Money::from_minor(333));
+        assert_eq!(amount.share(2, 3), Money::from_minor(667));
+        assert_eq!((-amount).share(1, 8), Money::from_minor(-125));
+        assert_eq!(amount.share(1, 0), Money::ZERO);
+    }
+
+    #[test]
+    fn test_amounts_cross_the_api_as_major_units() {
+        assert_eq!(Money::from_minor(-1205).to_string(), "-12.05");
+        assert_eq!(format!("{:>7}", Money::from_minor(5)), "   0.05");
+        assert_eq!(serde_json::to_string(&Money::from_minor(1234)).unwrap(), "12.34");
+        assert_eq!(serde_json::from_str::<Money>("19.999").unwrap(), Money::from_minor(2000));
+        assert!(validate_settings(&CurrencySettings { code: "usd".to_string() }).is_err());
+    }
+
+    #[test]
+    fn test_only_currencies_in_hundredths_are_accepted() {
+        let currency = |code: &str| validate_settings(&CurrencySettings { code: code.to_string() });
+        assert!(currency("USD").is_ok());
+        assert!(currency("NGN").is_ok());
+        assert!(matches!(currency("JPY"), Err(AppError::Validation { .. })));
+        assert!(currency("KWD").is_err());
+        assert!(currency("ABC").is_err());
+    }
+}
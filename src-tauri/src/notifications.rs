@@ -1,6 +1,7 @@
 use tauri::State;
 use crate::{AppState, models::*};
 use crate::auth::check_permission;
+use crate::error::AppError;
 
 #[tauri::command]
 pub async fn get_notifications(
@@ -8,13 +9,13 @@ pub async fn get_notifications(
     user_id: Option<i64>,
     unread_only: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<Vec<Notification>, String> {
+) -> Result<Vec<Notification>, AppError> {
     // Check if user has permission to view notifications (basic dashboard access)
-    check_permission(&token, "dashboard_access").await?;
+    check_permission(&state, &token, "dashboard_access").await?;
 
     let db = state.db.lock().await;
     db.get_notifications(user_id, unread_only.unwrap_or(false)).await
-        .map_err(|e| format!("Failed to get notifications: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get notifications"))
 }
 
 #[tauri::command]
@@ -22,13 +23,13 @@ pub async fn mark_notification_read(
     token: String,
     notificationId: i64,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     // Check if user has permission to manage notifications
-    check_permission(&token, "dashboard_access").await?;
+    check_permission(&state, &token, "dashboard_access").await?;
 
     let db = state.db.lock().await;
     db.mark_notification_read(notificationId).await
-        .map_err(|e| format!("Failed to mark notification as read: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to mark notification as read"))
 }
 
 #[tauri::command]
@@ -36,26 +37,26 @@ pub async fn get_expiring_products(
     token: String,
     days_ahead: Option<i32>,
     state: State<'_, AppState>,
-) -> Result<Vec<Product>, String> {
+) -> Result<Vec<ExpiringBatch>, AppError> {
     // Check if user has permission to view inventory
-    check_permission(&token, "inventory_management").await?;
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.get_expiring_products(days_ahead.unwrap_or(7)).await
-        .map_err(|e| format!("Failed to get expiring products: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get expiring products"))
 }
 
 #[tauri::command]
 pub async fn check_alerts(
     token: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     // Check if user has permission to manage inventory
-    check_permission(&token, "inventory_management").await?;
+    check_permission(&state, &token, "inventory_management").await?;
     
     let db = state.db.lock().await;
     db.check_and_create_alerts().await
-        .map_err(|e| format!("Failed to check alerts: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to check alerts"))
 }
 
 #[tauri::command]
@@ -68,11 +69,11 @@ pub async fn create_notification(
     priority: String,
     product_id: Option<i64>,
     state: State<'_, AppState>,
-) -> Result<i64, String> {
+) -> Result<i64, AppError> {
     // Check if user has permission to create notifications (admin only)
-    check_permission(&token, "user_management").await?;
+    check_permission(&state, &token, "user_management").await?;
     
     let db = state.db.lock().await;
     db.create_notification(user_id, &title, &message, &notification_type, &priority, product_id).await
-        .map_err(|e| format!("Failed to create notification: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to create notification"))
 }
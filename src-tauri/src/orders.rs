@@ -0,0 +1,109 @@
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+impl OrderStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrderStatus::Draft => "draft",
+            OrderStatus::Pending => "pending",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Completed => "completed",
+            OrderStatus::PartiallyRefunded => "partially_refunded",
+            OrderStatus::Refunded => "refunded",
+            OrderStatus::Voided => "voided",
+        }
+    }
+
+    /// The order lifecycle: draft -> pending <-> paid -> completed ->
+    /// partially_refunded -> refunded, where a completed order can also be
+    /// refunded in one go and draft or pending orders can be voided.
+    ///
+    /// A pending order with nothing to pay can complete directly, and each
+    /// further partial return keeps an order partially refunded. Refunded and
+    /// voided orders are final. Paid orders cannot be voided until a tender is
+    /// removed; voiding a pending order hands back the tenders it has and
+    /// drops their payments, keeping only cash taken for store credit.
+    pub fn can_transition_to(self, to: OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        matches!(
+            (self, to),
+            (Draft, Pending)
+                | (Draft, Voided)
+                | (Pending, Paid)
+                | (Pending, Completed)
+                | (Pending, Voided)
+                | (Paid, Pending)
+                | (Paid, Completed)
+                | (Completed, PartiallyRefunded)
+                | (Completed, Refunded)
+                | (PartiallyRefunded, PartiallyRefunded)
+                | (PartiallyRefunded, Refunded)
+        )
+    }
+
+    pub fn check_transition(self, order_id: i64, to: OrderStatus) -> Result<(), AppError> {
+        if self.can_transition_to(to) {
+            return Ok(());
+        }
+
+        Err(AppError::invalid_transition(
+            format!("Order #{} cannot go from {} to {}", order_id, self.as_str(), to.as_str()),
+            self.as_str(),
+            to.as_str(),
+        ))
+    }
+}
+
+/// An order's status history, oldest first
+#[tauri::command]
+pub async fn get_order_events(
+    token: String,
+    order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<OrderEvent>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_order_events(order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get order history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_final_statuses_go_nowhere() {
+        let all = [
+            OrderStatus::Draft,
+            OrderStatus::Pending,
+            OrderStatus::Paid,
+            OrderStatus::Completed,
+            OrderStatus::PartiallyRefunded,
+            OrderStatus::Refunded,
+            OrderStatus::Voided,
+        ];
+        for to in all {
+            assert!(!OrderStatus::Refunded.can_transition_to(to));
+            assert!(!OrderStatus::Voided.can_transition_to(to));
+            assert!(!to.can_transition_to(OrderStatus::Draft));
+        }
+    }
+
+    #[test]
+    fn test_completed_orders_cannot_be_voided() {
+        let error = OrderStatus::Completed.check_transition(9, OrderStatus::Voided).unwrap_err();
+        assert_eq!(error, AppError::invalid_transition("Order #9 cannot go from completed to voided", "completed", "voided"));
+        assert!(OrderStatus::Pending.check_transition(9, OrderStatus::Voided).is_ok());
+        assert!(OrderStatus::Paid.check_transition(9, OrderStatus::Voided).is_err());
+    }
+
+    #[test]
+    fn test_status_names_match_storage() {
+        assert_eq!(serde_json::to_value(OrderStatus::PartiallyRefunded).unwrap(), "partially_refunded");
+    }
+}
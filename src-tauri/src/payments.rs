@@ -0,0 +1,94 @@
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// Split a tender into the part paid toward `balance_due` and the change
+/// given back. Only cash can be over-tendered; any other tender must not
+/// exceed what is still owed.
+pub fn apply_tender(balance_due: Money, tender_type: TenderType, tendered: Money) -> Result<(Money, Money), AppError> {
+    if !tendered.is_positive() {
+        return Err(AppError::validation("amount", "Payment amount must be positive"));
+    }
+    if !balance_due.is_positive() {
+        return Err(AppError::conflict("The order is already fully paid"));
+    }
+
+    if tendered <= balance_due {
+        return Ok((tendered, Money::ZERO));
+    }
+
+    match tender_type {
+        TenderType::Cash => Ok((balance_due, tendered - balance_due)),
+        _ => Err(AppError::validation(
+            "amount",
+            format!("Only cash can exceed the balance due of {}", balance_due),
+        )),
+    }
+}
+
+// Payment commands
+#[tauri::command]
+pub async fn add_order_payment(
+    token: String,
+    order_id: i64,
+    payment: OrderPaymentRequest,
+    state: State<'_, AppState>,
+) -> Result<OrderPaymentResult, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.add_order_payment(&user, order_id, &payment).await
+        .map_err(|e| AppError::from(e).context("Failed to add payment"))
+}
+
+#[tauri::command]
+pub async fn get_order_payments(
+    token: String,
+    order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<OrderPayment>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_order_payments(order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get payments"))
+}
+
+/// Take back a tender entered by mistake on an order that is not yet completed
+#[tauri::command]
+pub async fn remove_order_payment(
+    token: String,
+    payment_id: i64,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.remove_order_payment(&user, payment_id).await
+        .map_err(|e| AppError::from(e).context("Failed to remove payment"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    #[test]
+    fn test_cash_gives_change() {
+        assert_eq!(apply_tender(money(13.6), TenderType::Cash, money(20.0)).unwrap(), (money(13.6), money(6.4)));
+        assert_eq!(apply_tender(money(13.6), TenderType::Cash, money(10.0)).unwrap(), (money(10.0), Money::ZERO));
+    }
+
+    #[test]
+    fn test_other_tenders_cannot_overpay() {
+        assert_eq!(apply_tender(money(13.6), TenderType::CreditCard, money(13.6)).unwrap(), (money(13.6), Money::ZERO));
+        assert!(matches!(apply_tender(money(13.6), TenderType::CreditCard, money(20.0)), Err(AppError::Validation { .. })));
+        assert!(matches!(apply_tender(Money::ZERO, TenderType::Cash, money(5.0)), Err(AppError::Conflict { .. })));
+        assert!(apply_tender(money(5.0), TenderType::Cash, Money::ZERO).is_err());
+    }
+}
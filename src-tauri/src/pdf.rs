@@ -0,0 +1,147 @@
+/// Characters that fit on a line of a `PdfDocument` page
+pub const COLUMNS: usize = 80;
+
+// A4 in points, with 10pt Courier (6pt per character) on 12pt lines
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+const MARGIN: u32 = 50;
+const FONT_SIZE: u32 = 10;
+const LEADING: u32 = 12;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LEADING) as usize;
+
+/// Builder for plain text PDF documents: monospaced lines on A4 pages,
+/// broken onto a new page when one fills up
+#[derive(Debug, Default)]
+pub struct PdfDocument {
+    /// Each line and whether it is bold
+    lines: Vec<(String, bool)>,
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(&mut self, text: &str) -> &mut Self {
+        self.lines.push((text.to_string(), false));
+        self
+    }
+
+    pub fn bold_line(&mut self, text: &str) -> &mut Self {
+        self.lines.push((text.to_string(), true));
+        self
+    }
+
+    pub fn blank(&mut self) -> &mut Self {
+        self.line("")
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        let pages: Vec<&[(String, bool)]> = if self.lines.is_empty() {
+            vec![&[]]
+        } else {
+            self.lines.chunks(LINES_PER_PAGE).collect()
+        };
+
+        // Objects 1-4 are the catalog, page tree and fonts; each page is
+        // followed by its content stream
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 5 + 2 * i)).collect();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes());
+        objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec());
+        objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier-Bold /Encoding /WinAnsiEncoding >>".to_vec());
+
+        for (i, lines) in pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH, PAGE_HEIGHT, 6 + 2 * i
+            ).into_bytes());
+
+            let mut content = format!("BT {} TL {} {} Td\n", LEADING, MARGIN, PAGE_HEIGHT - MARGIN - FONT_SIZE).into_bytes();
+            for (text, bold) in lines.iter() {
+                content.extend_from_slice(format!("/F{} {} Tf (", if *bold { 2 } else { 1 }, FONT_SIZE).as_bytes());
+                content.extend(escape(text));
+                content.extend_from_slice(b") Tj T*\n");
+            }
+            content.extend_from_slice(b"ET");
+
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend(content);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes());
+        pdf
+    }
+}
+
+/// A line as a PDF string body: Latin-1 bytes with the delimiters escaped,
+/// anything the standard fonts cannot show as '?'
+fn escape(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                bytes.push(b'\\');
+                bytes.push(c as u8);
+            }
+            ' '..='~' | '\u{a0}'..='\u{ff}' => bytes.push(c as u32 as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_long_documents_break_onto_pages_with_a_valid_xref() {
+        let mut document = PdfDocument::new();
+        for i in 0..(LINES_PER_PAGE * 2 + 1) {
+            document.line(&format!("Line {}", i));
+        }
+        let pdf = document.into_bytes();
+
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        assert!(contains(&pdf, b"/Count 3"));
+
+        let text = String::from_utf8(pdf.clone()).unwrap();
+        let startxref: usize = text.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref\n0 11\n"));
+    }
+
+    #[test]
+    fn test_text_is_escaped_for_pdf_strings() {
+        assert_eq!(escape("(a\\b)"), b"\\(a\\\\b\\)".to_vec());
+        assert_eq!(escape("Café €5"), b"Caf\xe9 ?5".to_vec());
+
+        let mut document = PdfDocument::new();
+        document.bold_line("PO-00001").blank();
+        let pdf = document.into_bytes();
+        assert!(contains(&pdf, b"/F2 10 Tf (PO-00001) Tj T*"));
+        assert!(contains(&pdf, b"/F1 10 Tf () Tj T*"));
+    }
+}
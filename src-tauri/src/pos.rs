@@ -1,18 +1,58 @@
 use tauri::State;
 use crate::{AppState, models::*};
-use crate::auth::check_permission;
+use crate::auth::{check_permission, hash_token};
+use crate::error::AppError;
+use crate::database::Database;
+use crate::printer::{kick_cash_drawer, PrinterSettings, PRINTER_SETTINGS_KEY};
+use crate::receipt::print_order_receipt;
+
+/// Permission a manager needs to approve selling a line away from its shelf price
+pub const PRICE_OVERRIDE_PERMISSION: &str = "price_override";
+
+/// How long a manager's approval can wait for the order that uses it
+const PRICE_OVERRIDE_APPROVAL_MINUTES: i64 = 5;
+
+/// An unused approval the cashier holds for one product at one price
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OverrideApproval {
+    pub id: i64,
+    pub product_id: i64,
+    pub price: Money,
+    pub approved_by: i64,
+    pub approver_name: String,
+}
+
+/// Checks a line's price against the product's `shelf_price`. Returns the
+/// reason to log when the line is a price override, which must give one and
+/// come with a manager's approval for that price.
+pub fn check_line_price(product_name: &str, shelf_price: Money, item: &OrderItemRequest, approval: Option<&OverrideApproval>) -> Result<Option<String>, AppError> {
+    if item.price_at_sale == shelf_price {
+        return Ok(None);
+    }
+    if item.price_at_sale.is_negative() {
+        return Err(AppError::validation("items", format!("{} cannot be sold at a negative price", product_name)));
+    }
+    let reason = item.override_reason.as_deref().unwrap_or("").trim();
+    if reason.is_empty() {
+        return Err(AppError::validation("items", format!("{} is priced at {}; give a reason to sell it at {}", product_name, shelf_price, item.price_at_sale)));
+    }
+    match approval {
+        Some(_) => Ok(Some(reason.to_string())),
+        None => Err(AppError::forbidden(PRICE_OVERRIDE_PERMISSION)),
+    }
+}
 
 #[tauri::command]
 pub async fn search_products_by_sku(
     token: String,
     sku: String,
     state: State<'_, AppState>,
-) -> Result<Option<Product>, String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<Option<Product>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
     
     let db = state.db.lock().await;
     db.get_product_by_sku(&sku).await
-        .map_err(|e| format!("Failed to search product: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to search product"))
 }
 
 #[tauri::command]
@@ -20,25 +60,71 @@ pub async fn search_products_by_name(
     token: String,
     query: String,
     state: State<'_, AppState>,
-) -> Result<Vec<Product>, String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<Vec<Product>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
     
     let db = state.db.lock().await;
     db.search_products_by_name(&query).await
-        .map_err(|e| format!("Failed to search products: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to search products"))
+}
+
+/// A manager signs off selling a product at another price by entering their
+/// own credentials at the till. The token returned is good for one line on
+/// one of this cashier's orders, for a few minutes; it grants nothing else.
+#[tauri::command]
+pub async fn approve_price_override(
+    token: String,
+    email: String,
+    password: String,
+    product_id: i64,
+    price: Money,
+    state: State<'_, AppState>,
+) -> Result<PriceOverrideApproval, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+    if price.is_negative() {
+        return Err(AppError::validation("price", "Price cannot be negative"));
+    }
+
+    let db = state.db.lock().await;
+    let manager = db.get_user_by_email(&email).await
+        .map_err(|e| AppError::from(e).context("Failed to approve price override"))?
+        .filter(|manager| manager.is_active && bcrypt::verify(&password, &manager.password_hash).unwrap_or(false))
+        .ok_or_else(|| AppError::unauthorized("Invalid email or password"))?;
+    let permissions = db.get_user_permissions(manager.id).await
+        .map_err(|e| AppError::from(e).context("Failed to approve price override"))?;
+    if !permissions.iter().any(|p| p == PRICE_OVERRIDE_PERMISSION) {
+        return Err(AppError::forbidden(PRICE_OVERRIDE_PERMISSION));
+    }
+
+    let approval_token = uuid::Uuid::new_v4().to_string();
+    let expires_at = chrono::Utc::now() + chrono::Duration::minutes(PRICE_OVERRIDE_APPROVAL_MINUTES);
+    db.create_price_override_approval(&hash_token(&approval_token), &user, &manager, product_id, price, expires_at).await
+        .map_err(|e| AppError::from(e).context("Failed to approve price override"))?;
+
+    Ok(PriceOverrideApproval {
+        approval_token,
+        product_id,
+        price,
+        approver_name: manager.full_name,
+        expires_at,
+    })
 }
 
+/// Lines are priced from the catalogue. Each line sold at a different price
+/// uses up one of `approval_tokens` from `approve_price_override`.
 #[tauri::command]
 pub async fn create_order(
     token: String,
     order_data: CreateOrderRequest,
+    approval_tokens: Option<Vec<String>>,
     state: State<'_, AppState>,
-) -> Result<i64, String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<i64, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+    let approvals: Vec<String> = approval_tokens.unwrap_or_default().iter().map(|t| hash_token(t)).collect();
     
     let db = state.db.lock().await;
-    db.create_order(order_data).await
-        .map_err(|e| format!("Failed to create order: {}", e))
+    db.create_order(&user, &approvals, order_data).await
+        .map_err(|e| AppError::from(e).context("Failed to create order"))
 }
 
 #[tauri::command]
@@ -46,25 +132,35 @@ pub async fn complete_order(
     token: String,
     order_id: i64,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<(), AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
     
     let db = state.db.lock().await;
-    db.complete_order(order_id).await
-        .map_err(|e| format!("Failed to complete order: {}", e))
+    db.complete_order(&user, order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to complete order"))
 }
 
+/// Void an unpaid order. With `store_credit` the cash already taken is kept
+/// as store credit, whose card is returned.
 #[tauri::command]
 pub async fn cancel_order(
     token: String,
     order_id: i64,
+    store_credit: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<Option<GiftCard>, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
     
     let db = state.db.lock().await;
-    db.cancel_order(order_id).await
-        .map_err(|e| format!("Failed to cancel order: {}", e))
+    let store_credit_id = db.cancel_order(&user, order_id, store_credit.unwrap_or(false)).await
+        .map_err(|e| AppError::from(e).context("Failed to cancel order"))?;
+
+    match store_credit_id {
+        Some(gift_card_id) => db.get_gift_card(gift_card_id).await
+            .map(Some)
+            .map_err(|e| AppError::from(e).context("Failed to get store credit")),
+        None => Ok(None),
+    }
 }
 
 #[tauri::command]
@@ -72,12 +168,12 @@ pub async fn get_recent_orders(
     token: String,
     limit: Option<i64>,
     state: State<'_, AppState>,
-) -> Result<Vec<Order>, String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<Vec<Order>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
     
     let db = state.db.lock().await;
     db.get_recent_orders(limit.unwrap_or(10)).await
-        .map_err(|e| format!("Failed to get recent orders: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get recent orders"))
 }
 
 #[tauri::command]
@@ -85,12 +181,38 @@ pub async fn get_order_items(
     token: String,
     order_id: i64,
     state: State<'_, AppState>,
-) -> Result<Vec<OrderItem>, String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<Vec<OrderItem>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
     
     let db = state.db.lock().await;
     db.get_order_items(order_id).await
-        .map_err(|e| format!("Failed to get order items: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get order items"))
+}
+
+#[tauri::command]
+pub async fn get_order_tax_summary(
+    token: String,
+    order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<OrderTaxSummary, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+    
+    let db = state.db.lock().await;
+    db.get_order_tax_summary(order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get order tax summary"))
+}
+
+#[tauri::command]
+pub async fn get_order_promotions(
+    token: String,
+    order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<OrderPromotion>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+    
+    let db = state.db.lock().await;
+    db.get_order_promotions(order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get order promotions"))
 }
 
 // Barcode scanning simulation (in a real implementation, this would interface with hardware)
@@ -99,49 +221,142 @@ pub async fn process_barcode_scan(
     token: String,
     barcode: String,
     state: State<'_, AppState>,
-) -> Result<Option<Product>, String> {
-    check_permission(&token, "sales_management").await?;
+) -> Result<Option<Product>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
     
     println!("Processing barcode scan: {}", barcode);
     
     // For now, treat barcode as SKU
     let db = state.db.lock().await;
     db.get_product_by_sku(&barcode).await
-        .map_err(|e| format!("Failed to process barcode: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to process barcode"))
 }
 
-// Print receipt simulation (in a real implementation, this would interface with printer hardware)
+/// Print an order's receipt on the configured receipt printer
 #[tauri::command]
 pub async fn print_receipt(
     token: String,
     order_id: i64,
-    _state: State<'_, AppState>,
-) -> Result<String, String> {
-    check_permission(&token, "sales_management").await?;
-    
-    println!("Printing receipt for order: {}", order_id);
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
     
-    // In a real implementation, this would:
-    // 1. Get order details from database
-    // 2. Format receipt content
-    // 3. Send to thermal printer via ESC/POS commands
-    // 4. Return success/failure status
+    let db = state.db.lock().await;
+    print_order_receipt(&db, order_id).await
+        .map_err(|e| e.context("Failed to print receipt"))?;
     
     Ok("Receipt printed successfully".to_string())
 }
 
-// Cash drawer simulation (in a real implementation, this would interface with cash drawer hardware)
+/// Record the drawer open, then kick the drawer. The event is written
+/// first so that an open can never happen without an audit record.
+async fn open_drawer(db: &Database, user: &UserInfo, reason: CashDrawerReason, order_id: Option<i64>, notes: &str) -> Result<i64, AppError> {
+    if reason == CashDrawerReason::Sale && order_id.is_none() {
+        return Err(AppError::validation("order_id", "A sale drawer open must reference its order"));
+    }
+    if reason == CashDrawerReason::Payout && notes.trim().is_empty() {
+        return Err(AppError::validation("notes", "Describe what the payout was for"));
+    }
+    if let Some(order_id) = order_id {
+        db.get_order(order_id).await?;
+    }
+
+    let settings = db.get_setting::<PrinterSettings>(PRINTER_SETTINGS_KEY).await?
+        .unwrap_or_default();
+    let event_id = db.create_cash_drawer_event(user, reason, order_id, notes.trim()).await?;
+
+    kick_cash_drawer(&settings).await?;
+    db.mark_cash_drawer_opened(event_id).await?;
+
+    Ok(event_id)
+}
+
 #[tauri::command]
 pub async fn open_cash_drawer(
     token: String,
-) -> Result<String, String> {
-    check_permission(&token, "sales_management").await?;
-    
-    println!("Opening cash drawer");
+    reason: CashDrawerReason,
+    order_id: Option<i64>,
+    notes: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
     
-    // In a real implementation, this would:
-    // 1. Send signal to cash drawer (usually via printer port)
-    // 2. Return success/failure status
+    let db = state.db.lock().await;
+    open_drawer(&db, &user, reason, order_id, notes.as_deref().unwrap_or("")).await
+        .map_err(|e| e.context("Failed to open cash drawer"))?;
     
     Ok("Cash drawer opened successfully".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::PrinterConnection;
+
+    fn cashier() -> UserInfo {
+        UserInfo {
+            id: 7,
+            email: "cashier@example.com".to_string(),
+            full_name: "Till Operator".to_string(),
+            role: "cashier".to_string(),
+            permissions: vec!["sales_management".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_prices_away_from_the_shelf_need_a_reason_and_approval() {
+        let shelf = Money::from_major(4.0);
+        let item = |price: f64, reason: Option<&str>| OrderItemRequest {
+            product_id: 1,
+            quantity: 1,
+            price_at_sale: Money::from_major(price),
+            override_reason: reason.map(str::to_string),
+        };
+        let approval = OverrideApproval {
+            id: 1,
+            product_id: 1,
+            price: Money::from_major(3.0),
+            approved_by: 2,
+            approver_name: "Floor Manager".to_string(),
+        };
+
+        assert_eq!(check_line_price("Milk", shelf, &item(4.0, None), None).unwrap(), None);
+        assert!(matches!(check_line_price("Milk", shelf, &item(3.0, Some(" ")), Some(&approval)), Err(AppError::Validation { .. })));
+        assert!(matches!(check_line_price("Milk", shelf, &item(3.0, Some("Dented")), None), Err(AppError::Forbidden { .. })));
+        assert!(check_line_price("Milk", shelf, &item(-1.0, Some("Dented")), Some(&approval)).is_err());
+        assert_eq!(check_line_price("Milk", shelf, &item(3.0, Some(" Dented ")), Some(&approval)).unwrap(), Some("Dented".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_drawer_opens_are_audited() {
+        let db = Database::in_memory().await.unwrap();
+        let today = crate::reports::ReportRange::today(Some(0)).unwrap();
+
+        // Without a printer the attempt is still on record, but not as opened
+        assert!(matches!(open_drawer(&db, &cashier(), CashDrawerReason::NoSale, None, "").await, Err(AppError::Device { .. })));
+
+        let path = std::env::temp_dir().join(format!("isms-{}-drawer.bin", std::process::id()));
+        db.set_setting(PRINTER_SETTINGS_KEY, &PrinterSettings {
+            connection: Some(PrinterConnection::File { path: path.to_string_lossy().to_string() }),
+            ..PrinterSettings::default()
+        }).await.unwrap();
+
+        assert!(open_drawer(&db, &cashier(), CashDrawerReason::Payout, None, " ").await.is_err());
+        open_drawer(&db, &cashier(), CashDrawerReason::Payout, None, "Window cleaner").await.unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), vec![0x1b, b'@', 0x1b, b'p', 0, 50, 100]);
+        std::fs::remove_file(&path).unwrap();
+
+        let events = db.get_cash_drawer_events(&today, None).await.unwrap();
+        let events: Vec<(CashDrawerReason, &str, &str, bool)> = events.iter()
+            .map(|e| (e.reason, e.user_name.as_str(), e.notes.as_str(), e.opened))
+            .collect();
+        assert_eq!(events, vec![
+            (CashDrawerReason::Payout, "Till Operator", "Window cleaner", true),
+            (CashDrawerReason::NoSale, "Till Operator", "", false),
+        ]);
+
+        let no_sales = db.get_cash_drawer_events(&today, Some(CashDrawerReason::NoSale)).await.unwrap();
+        assert_eq!(no_sales.len(), 1);
+    }
+}
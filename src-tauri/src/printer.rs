@@ -0,0 +1,460 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::State;
+use tokio::io::AsyncWriteExt;
+
+use crate::AppState;
+use crate::auth::check_permission;
+use crate::error::AppError;
+use crate::receipt::ReceiptTemplate;
+
+/// Key of the printer section in `app_settings`
+pub const PRINTER_SETTINGS_KEY: &str = "printer";
+
+const ESC: u8 = 0x1b;
+const GS: u8 = 0x1d;
+const LF: u8 = 0x0a;
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaperWidth {
+    Mm58,
+    #[default]
+    Mm80,
+}
+
+impl PaperWidth {
+    /// Characters per line in the printer's default font A
+    pub fn columns(&self) -> usize {
+        match self {
+            PaperWidth::Mm58 => 32,
+            PaperWidth::Mm80 => 48,
+        }
+    }
+
+    /// Printable width in dots at 203 dpi
+    pub fn dots(&self) -> u32 {
+        match self {
+            PaperWidth::Mm58 => 384,
+            PaperWidth::Mm80 => 576,
+        }
+    }
+}
+
+/// Where ESC/POS bytes are sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PrinterConnection {
+    /// A local device such as `/dev/usb/lp0` or a Windows printer port
+    Device { path: String },
+    /// A network printer speaking raw TCP, normally on port 9100
+    Network {
+        host: String,
+        #[serde(default = "default_network_port")]
+        port: u16,
+    },
+    /// Append to a file; useful for spooling and tests
+    File { path: String },
+}
+
+fn default_network_port() -> u16 {
+    9100
+}
+
+/// Drawer connector pin driven by the kick pulse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DrawerPin {
+    #[default]
+    Pin2,
+    Pin5,
+}
+
+/// Drawer kick pulse; ESC/POS counts time in 2 ms steps up to 510 ms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CashDrawerSettings {
+    pub pin: DrawerPin,
+    pub on_ms: u16,
+    pub off_ms: u16,
+}
+
+impl Default for CashDrawerSettings {
+    fn default() -> Self {
+        CashDrawerSettings { pin: DrawerPin::Pin2, on_ms: 100, off_ms: 200 }
+    }
+}
+
+const MAX_PULSE_MS: u16 = 510;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrinterSettings {
+    #[serde(default)]
+    pub paper_width: PaperWidth,
+    /// No receipts are printed until a connection is configured
+    pub connection: Option<PrinterConnection>,
+    #[serde(default)]
+    pub template: ReceiptTemplate,
+    /// The drawer is wired to this printer's drawer kick port
+    #[serde(default)]
+    pub cash_drawer: CashDrawerSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+}
+
+/// A 1-bit image, rows packed MSB first and padded to whole bytes, 1 = black
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Bitmap {
+    /// Parse a binary (P4) portable bitmap, which uses the same bit layout
+    /// as ESC/POS raster images
+    pub fn from_pbm(bytes: &[u8]) -> Result<Self, String> {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+
+        while fields.len() < 3 {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos < bytes.len() && bytes[pos] == b'#' {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err("Truncated PBM header".to_string());
+            }
+            fields.push(String::from_utf8_lossy(&bytes[start..pos]).to_string());
+        }
+
+        if fields[0] != "P4" {
+            return Err("Logo must be a binary (P4) PBM image".to_string());
+        }
+        let width: u32 = fields[1].parse().map_err(|_| "Invalid PBM width".to_string())?;
+        let height: u32 = fields[2].parse().map_err(|_| "Invalid PBM height".to_string())?;
+
+        // Exactly one whitespace byte separates the header from the raster
+        let data = bytes.get(pos + 1..).unwrap_or_default();
+        let expected = width.div_ceil(8) as usize * height as usize;
+        if width == 0 || height == 0 || data.len() < expected {
+            return Err("PBM image data is truncated".to_string());
+        }
+
+        Ok(Bitmap { width, height, data: data[..expected].to_vec() })
+    }
+}
+
+/// Builder for ESC/POS command streams
+pub struct EscPos {
+    bytes: Vec<u8>,
+}
+
+impl Default for EscPos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EscPos {
+    /// Start with a printer reset so earlier jobs cannot leak formatting
+    pub fn new() -> Self {
+        EscPos { bytes: vec![ESC, b'@'] }
+    }
+
+    pub fn align(&mut self, align: Align) -> &mut Self {
+        let n = match align {
+            Align::Left => 0,
+            Align::Center => 1,
+        };
+        self.bytes.extend_from_slice(&[ESC, b'a', n]);
+        self
+    }
+
+    pub fn bold(&mut self, on: bool) -> &mut Self {
+        self.bytes.extend_from_slice(&[ESC, b'E', on as u8]);
+        self
+    }
+
+    /// Double width and height
+    pub fn double(&mut self, on: bool) -> &mut Self {
+        self.bytes.extend_from_slice(&[GS, b'!', if on { 0x11 } else { 0x00 }]);
+        self
+    }
+
+    /// Printers default to code page 437; anything outside ASCII prints as `?`
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.bytes.extend(text.chars().map(|c| if c.is_ascii() && !c.is_ascii_control() { c as u8 } else { b'?' }));
+        self
+    }
+
+    pub fn line(&mut self, text: &str) -> &mut Self {
+        self.text(text);
+        self.bytes.push(LF);
+        self
+    }
+
+    pub fn feed(&mut self, lines: u8) -> &mut Self {
+        self.bytes.extend_from_slice(&[ESC, b'd', lines]);
+        self
+    }
+
+    /// Feed past the cutter and make a partial cut
+    pub fn cut(&mut self) -> &mut Self {
+        self.bytes.extend_from_slice(&[GS, b'V', 66, 3]);
+        self
+    }
+
+    pub fn raster(&mut self, bitmap: &Bitmap) -> &mut Self {
+        let width_bytes = bitmap.width.div_ceil(8) as u16;
+        let height = bitmap.height as u16;
+        self.bytes.extend_from_slice(&[GS, b'v', b'0', 0]);
+        self.bytes.extend_from_slice(&width_bytes.to_le_bytes());
+        self.bytes.extend_from_slice(&height.to_le_bytes());
+        self.bytes.extend_from_slice(&bitmap.data);
+        self.bytes.push(LF);
+        self
+    }
+
+    /// CODE128 (code set B) with the human-readable text printed below
+    pub fn code128(&mut self, data: &str) -> &mut Self {
+        let payload: Vec<u8> = b"{B".iter().copied().chain(data.bytes().filter(|b| (32..127).contains(b))).collect();
+        self.bytes.extend_from_slice(&[GS, b'h', 80, GS, b'w', 2, GS, b'H', 2]);
+        self.bytes.extend_from_slice(&[GS, b'k', 73, payload.len() as u8]);
+        self.bytes.extend_from_slice(&payload);
+        self.bytes.push(LF);
+        self
+    }
+
+    /// QR code, model 2, error correction M
+    pub fn qr(&mut self, data: &str, module_size: u8) -> &mut Self {
+        let store_len = (data.len() + 3) as u16;
+        self.bytes.extend_from_slice(&[GS, b'(', b'k', 4, 0, 49, 65, 50, 0]);
+        self.bytes.extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 67, module_size]);
+        self.bytes.extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 69, 49]);
+        self.bytes.extend_from_slice(&[GS, b'(', b'k']);
+        self.bytes.extend_from_slice(&store_len.to_le_bytes());
+        self.bytes.extend_from_slice(&[49, 80, 48]);
+        self.bytes.extend_from_slice(data.as_bytes());
+        self.bytes.extend_from_slice(&[GS, b'(', b'k', 3, 0, 49, 81, 48]);
+        self.bytes.push(LF);
+        self
+    }
+
+    /// Drawer kick pulse (`ESC p m t1 t2`)
+    pub fn pulse(&mut self, settings: &CashDrawerSettings) -> &mut Self {
+        let pin = match settings.pin {
+            DrawerPin::Pin2 => 0,
+            DrawerPin::Pin5 => 1,
+        };
+        let steps = |ms: u16| (ms.min(MAX_PULSE_MS) / 2) as u8;
+        self.bytes.extend_from_slice(&[ESC, b'p', pin, steps(settings.on_ms), steps(settings.off_ms)]);
+        self
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Send a finished job to the printer
+pub async fn send(connection: &PrinterConnection, bytes: &[u8]) -> Result<(), AppError> {
+    match connection {
+        PrinterConnection::Device { path } => {
+            let mut device = tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(path)
+                .await
+                .map_err(|e| AppError::device(format!("Cannot open printer device {}: {}", path, e)))?;
+            device.write_all(bytes).await
+                .map_err(|e| AppError::device(format!("Failed to write to printer device {}: {}", path, e)))?;
+            device.flush().await
+                .map_err(|e| AppError::device(format!("Failed to write to printer device {}: {}", path, e)))?;
+        }
+        PrinterConnection::Network { host, port } => {
+            let address = format!("{}:{}", host, port);
+            let mut stream = tokio::time::timeout(NETWORK_TIMEOUT, tokio::net::TcpStream::connect(&address))
+                .await
+                .map_err(|_| AppError::device(format!("Timed out connecting to printer at {}", address)))?
+                .map_err(|e| AppError::device(format!("Cannot connect to printer at {}: {}", address, e)))?;
+            tokio::time::timeout(NETWORK_TIMEOUT, async {
+                stream.write_all(bytes).await?;
+                stream.shutdown().await
+            })
+            .await
+            .map_err(|_| AppError::device(format!("Timed out sending to printer at {}", address)))?
+            .map_err(|e| AppError::device(format!("Failed to send to printer at {}: {}", address, e)))?;
+        }
+        PrinterConnection::File { path } => {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await
+                .map_err(|e| AppError::device(format!("Cannot open print file {}: {}", path, e)))?;
+            file.write_all(bytes).await
+                .map_err(|e| AppError::device(format!("Failed to write print file {}: {}", path, e)))?;
+            file.flush().await
+                .map_err(|e| AppError::device(format!("Failed to write print file {}: {}", path, e)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Kick the cash drawer attached to the configured printer
+pub async fn kick_cash_drawer(settings: &PrinterSettings) -> Result<(), AppError> {
+    let connection = settings.connection.as_ref()
+        .ok_or_else(|| AppError::device("No receipt printer is configured for the cash drawer"))?;
+
+    let mut escpos = EscPos::new();
+    escpos.pulse(&settings.cash_drawer);
+    send(connection, &escpos.into_bytes()).await
+}
+
+/// Load the configured receipt logo, checking that it fits the paper
+pub async fn load_logo(path: &str, paper_width: PaperWidth) -> Result<Bitmap, AppError> {
+    let bytes = tokio::fs::read(path).await
+        .map_err(|e| AppError::validation("template.logo_path", format!("Cannot read logo {}: {}", path, e)))?;
+    let bitmap = Bitmap::from_pbm(&bytes)
+        .map_err(|e| AppError::validation("template.logo_path", e))?;
+
+    if bitmap.width > paper_width.dots() {
+        return Err(AppError::validation(
+            "template.logo_path",
+            format!("Logo is {} dots wide but the paper only fits {}", bitmap.width, paper_width.dots()),
+        ));
+    }
+    Ok(bitmap)
+}
+
+// Printer configuration commands
+#[tauri::command]
+pub async fn get_printer_settings(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<PrinterSettings, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    let settings = db.get_setting::<PrinterSettings>(PRINTER_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get printer settings"))?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn update_printer_settings(
+    token: String,
+    settings: PrinterSettings,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+
+    if settings.cash_drawer.on_ms == 0 || settings.cash_drawer.on_ms > MAX_PULSE_MS {
+        return Err(AppError::validation("cash_drawer.on_ms", format!("Pulse time must be between 2 and {} ms", MAX_PULSE_MS)));
+    }
+    if settings.cash_drawer.off_ms > MAX_PULSE_MS {
+        return Err(AppError::validation("cash_drawer.off_ms", format!("Pulse off time cannot exceed {} ms", MAX_PULSE_MS)));
+    }
+
+    if let Some(path) = &settings.template.logo_path {
+        load_logo(path, settings.paper_width).await?;
+    }
+
+    let db = state.db.lock().await;
+    db.set_setting(PRINTER_SETTINGS_KEY, &settings).await
+        .map_err(|e| AppError::from(e).context("Failed to save printer settings"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("isms-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn test_pbm_logo_is_parsed() {
+        let mut pbm = b"P4\n# logo\n10 2\n".to_vec();
+        pbm.extend_from_slice(&[0xff, 0xc0, 0x80, 0x40]);
+        let bitmap = Bitmap::from_pbm(&pbm).unwrap();
+        assert_eq!((bitmap.width, bitmap.height), (10, 2));
+        assert_eq!(bitmap.data, vec![0xff, 0xc0, 0x80, 0x40]);
+
+        let mut escpos = EscPos::new();
+        escpos.raster(&bitmap);
+        assert_eq!(&escpos.into_bytes()[2..10], &[GS, b'v', b'0', 0, 2, 0, 2, 0]);
+
+        assert!(Bitmap::from_pbm(b"P1\n1 1\n1").is_err());
+        assert!(Bitmap::from_pbm(b"P4\n16 4\n\x00").is_err());
+    }
+
+    #[test]
+    fn test_drawer_pulse() {
+        let mut escpos = EscPos::new();
+        escpos.pulse(&CashDrawerSettings { pin: DrawerPin::Pin5, on_ms: 120, off_ms: 1000 });
+        assert_eq!(escpos.into_bytes(), vec![ESC, b'@', ESC, b'p', 1, 60, 255]);
+    }
+
+    #[test]
+    fn test_text_is_limited_to_ascii() {
+        let mut escpos = EscPos::new();
+        escpos.line("Café\t1");
+        assert_eq!(escpos.into_bytes(), b"\x1b@Caf??1\n".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_file_connection_appends() {
+        let path = temp_path("spool.bin");
+        let connection = PrinterConnection::File { path: path.clone() };
+        send(&connection, b"one").await.unwrap();
+        send(&connection, b"two").await.unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"onetwo");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_network_connection_delivers_bytes() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut bytes = Vec::new();
+            socket.read_to_end(&mut bytes).await.unwrap();
+            bytes
+        });
+
+        let connection = PrinterConnection::Network { host: "127.0.0.1".to_string(), port };
+        send(&connection, b"\x1b@receipt").await.unwrap();
+        assert_eq!(received.await.unwrap(), b"\x1b@receipt");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_device_is_a_device_error() {
+        let connection = PrinterConnection::Device { path: temp_path("missing/lp0") };
+        assert!(matches!(send(&connection, b"x").await, Err(AppError::Device { .. })));
+    }
+}
@@ -0,0 +1,403 @@
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// An order line as the promotions engine sees it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromotionLine {
+    pub product_id: i64,
+    pub category: String,
+    pub quantity: i32,
+    pub unit_price: Money,
+}
+
+/// A promotion that gave a discount; `line_index` is `None` for order-level ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedPromotion {
+    pub promotion_id: i64,
+    pub name: String,
+    pub coupon_code: Option<String>,
+    pub line_index: Option<usize>,
+    pub discount_amount: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PromotionResult {
+    /// Total discount per line, including its share of order-level discounts
+    pub line_discounts: Vec<Money>,
+    pub applied: Vec<AppliedPromotion>,
+}
+
+pub fn normalize_coupon_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+fn is_running(promotion: &Promotion, now: DateTime<Utc>) -> bool {
+    promotion.is_active
+        && promotion.starts_at.is_none_or(|start| start <= now)
+        && promotion.ends_at.is_none_or(|end| now < end)
+}
+
+fn targets(promotion: &Promotion, line: &PromotionLine) -> bool {
+    promotion.product_id.is_none_or(|id| id == line.product_id)
+        && promotion.category.as_deref().is_none_or(|category| category == line.category)
+}
+
+/// Amount-off and multi-buy promotions use their amount, the others their
+/// percentage
+fn line_discount(promotion: &Promotion, line: &PromotionLine) -> Money {
+    let amount = line.unit_price * line.quantity;
+    let buy = promotion.buy_quantity.unwrap_or(0);
+    let get = promotion.get_quantity.unwrap_or(0);
+
+    let discount = match promotion.promotion_type {
+        PromotionType::PercentOff => amount.scale(promotion.value / 100.0),
+        PromotionType::AmountOff => promotion.amount * line.quantity,
+        PromotionType::BuyXGetY if buy > 0 && get > 0 => {
+            let discounted_units = line.quantity / (buy + get) * get;
+            (line.unit_price * discounted_units).scale(promotion.value / 100.0)
+        }
+        PromotionType::MultiBuy if buy > 0 => {
+            let bundles = line.quantity / buy;
+            (line.unit_price * buy - promotion.amount).max(Money::ZERO) * bundles
+        }
+        _ => Money::ZERO,
+    };
+
+    discount.clamp(Money::ZERO, amount)
+}
+
+fn order_discount(promotion: &Promotion, subtotal: Money) -> Money {
+    if promotion.min_order_amount.is_some_and(|minimum| subtotal < minimum) {
+        return Money::ZERO;
+    }
+
+    let discount = match promotion.promotion_type {
+        PromotionType::PercentOff => subtotal.scale(promotion.value / 100.0),
+        PromotionType::AmountOff => promotion.amount,
+        _ => Money::ZERO,
+    };
+
+    discount.clamp(Money::ZERO, subtotal)
+}
+
+/// Price an order's promotions.
+///
+/// Promotions are tried by priority (highest first), then oldest first. Each
+/// line gets the first line promotion that discounts it, then the first
+/// order promotion that discounts the remaining subtotal is spread over the
+/// lines in proportion to what is left of each, so tax is charged on what
+/// the customer actually pays. Coupon promotions only run when their code
+/// was entered, and an entered code that is unknown, out of its time window
+/// or gives no discount is rejected rather than silently ignored.
+pub fn apply_promotions(
+    lines: &[PromotionLine],
+    promotions: &[Promotion],
+    coupon_codes: &[String],
+    now: DateTime<Utc>,
+) -> Result<PromotionResult, AppError> {
+    let mut entered: Vec<String> = Vec::new();
+    for code in coupon_codes.iter().map(|code| normalize_coupon_code(code)) {
+        if code.is_empty() || entered.contains(&code) {
+            continue;
+        }
+        let known = promotions.iter()
+            .any(|p| p.coupon_code.as_deref() == Some(code.as_str()) && is_running(p, now));
+        if !known {
+            return Err(AppError::validation("coupon_codes", format!("Coupon code '{}' is not valid", code)));
+        }
+        entered.push(code);
+    }
+
+    let mut eligible: Vec<&Promotion> = promotions.iter()
+        .filter(|p| is_running(p, now))
+        .filter(|p| p.coupon_code.as_ref().is_none_or(|code| entered.contains(code)))
+        .collect();
+    eligible.sort_by_key(|p| (Reverse(p.priority), p.id));
+
+    let mut result = PromotionResult {
+        line_discounts: vec![Money::ZERO; lines.len()],
+        applied: Vec::new(),
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        let best = eligible.iter()
+            .filter(|p| p.scope == PromotionScope::Line && targets(p, line))
+            .map(|p| (p, line_discount(p, line)))
+            .find(|(_, discount)| discount.is_positive());
+
+        if let Some((promotion, discount)) = best {
+            result.line_discounts[index] = discount;
+            result.applied.push(AppliedPromotion {
+                promotion_id: promotion.id,
+                name: promotion.name.clone(),
+                coupon_code: promotion.coupon_code.clone(),
+                line_index: Some(index),
+                discount_amount: discount,
+            });
+        }
+    }
+
+    let remaining: Vec<Money> = lines.iter()
+        .zip(&result.line_discounts)
+        .map(|(line, discount)| line.unit_price * line.quantity - *discount)
+        .collect();
+    let subtotal: Money = remaining.iter().sum();
+
+    let order_promotion = eligible.iter()
+        .filter(|p| p.scope == PromotionScope::Order)
+        .map(|p| (p, order_discount(p, subtotal)))
+        .find(|(_, discount)| discount.is_positive());
+
+    if let Some((promotion, discount)) = order_promotion {
+        let last = remaining.iter().rposition(|amount| amount.is_positive());
+        let mut left = discount;
+        for (index, amount) in remaining.iter().enumerate() {
+            if !amount.is_positive() {
+                continue;
+            }
+            // The last line takes the rounding remainder
+            let share = if Some(index) == last { left } else { discount.share(amount.minor(), subtotal.minor()) };
+            result.line_discounts[index] += share;
+            left -= share;
+        }
+
+        result.applied.push(AppliedPromotion {
+            promotion_id: promotion.id,
+            name: promotion.name.clone(),
+            coupon_code: promotion.coupon_code.clone(),
+            line_index: None,
+            discount_amount: discount,
+        });
+    }
+
+    if let Some(unused) = entered.iter().find(|code| !result.applied.iter().any(|a| a.coupon_code.as_ref() == Some(*code))) {
+        return Err(AppError::validation("coupon_codes", format!("Coupon code '{}' does not apply to this order", unused)));
+    }
+
+    Ok(result)
+}
+
+fn validate_promotion(promotion: &PromotionRequest) -> Result<(), AppError> {
+    if promotion.name.trim().is_empty() {
+        return Err(AppError::validation("name", "Promotion name is required"));
+    }
+    if promotion.value < 0.0 {
+        return Err(AppError::validation("value", "Promotion value cannot be negative"));
+    }
+    if promotion.amount.is_negative() {
+        return Err(AppError::validation("amount", "Promotion amount cannot be negative"));
+    }
+
+    match promotion.promotion_type {
+        PromotionType::PercentOff | PromotionType::BuyXGetY if promotion.value > 100.0 => {
+            return Err(AppError::validation("value", "Percentage cannot exceed 100"));
+        }
+        PromotionType::BuyXGetY if promotion.get_quantity.unwrap_or(0) <= 0 => {
+            return Err(AppError::validation("get_quantity", "Buy X get Y promotions need a positive get quantity"));
+        }
+        _ => {}
+    }
+
+    let bundled = matches!(promotion.promotion_type, PromotionType::BuyXGetY | PromotionType::MultiBuy);
+    if bundled && promotion.buy_quantity.unwrap_or(0) <= 0 {
+        return Err(AppError::validation("buy_quantity", "Buy quantity must be positive"));
+    }
+
+    if promotion.scope == PromotionScope::Order {
+        if bundled {
+            return Err(AppError::validation("scope", "Buy X get Y and multi-buy promotions apply to lines"));
+        }
+        if promotion.product_id.is_some() || promotion.category.is_some() {
+            return Err(AppError::validation("scope", "Order promotions cannot target a product or category"));
+        }
+    }
+
+    if let (Some(start), Some(end)) = (promotion.starts_at, promotion.ends_at) {
+        if start >= end {
+            return Err(AppError::validation("ends_at", "Promotion must end after it starts"));
+        }
+    }
+
+    if promotion.coupon_code.as_deref().is_some_and(|code| normalize_coupon_code(code).is_empty()) {
+        return Err(AppError::validation("coupon_code", "Coupon code cannot be blank"));
+    }
+
+    Ok(())
+}
+
+// Promotion management commands
+#[tauri::command]
+pub async fn get_promotions(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<Promotion>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_promotions().await
+        .map_err(|e| AppError::from(e).context("Failed to get promotions"))
+}
+
+#[tauri::command]
+pub async fn create_promotion(
+    token: String,
+    promotion_data: PromotionRequest,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_promotion(&promotion_data)?;
+
+    let db = state.db.lock().await;
+    db.create_promotion(&promotion_data).await
+        .map_err(|e| AppError::from(e).context("Failed to create promotion"))
+}
+
+#[tauri::command]
+pub async fn update_promotion(
+    token: String,
+    promotion_id: i64,
+    promotion_data: PromotionRequest,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_promotion(&promotion_data)?;
+
+    let db = state.db.lock().await;
+    db.update_promotion(promotion_id, &promotion_data).await
+        .map_err(|e| AppError::from(e).context("Failed to update promotion"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `value` is in major units for amount-off and multi-buy promotions
+    fn promotion(id: i64, promotion_type: PromotionType, scope: PromotionScope, value: f64) -> Promotion {
+        let in_money = matches!(promotion_type, PromotionType::AmountOff | PromotionType::MultiBuy);
+        Promotion {
+            id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            name: format!("Promo {}", id),
+            description: String::new(),
+            promotion_type,
+            scope,
+            value: if in_money { 0.0 } else { value },
+            amount: if in_money { Money::from_major(value) } else { Money::ZERO },
+            product_id: None,
+            category: None,
+            buy_quantity: None,
+            get_quantity: None,
+            min_order_amount: None,
+            coupon_code: None,
+            starts_at: None,
+            ends_at: None,
+            priority: 0,
+            is_active: true,
+        }
+    }
+
+    fn line(product_id: i64, quantity: i32, unit_price: f64) -> PromotionLine {
+        PromotionLine { product_id, category: "grocery".to_string(), quantity, unit_price: Money::from_major(unit_price) }
+    }
+
+    fn amounts(values: &[f64]) -> Vec<Money> {
+        values.iter().map(|v| Money::from_major(*v)).collect()
+    }
+
+    #[test]
+    fn test_line_promotion_types() {
+        let lines = [line(1, 3, 2.0)];
+
+        let percent = promotion(1, PromotionType::PercentOff, PromotionScope::Line, 10.0);
+        assert_eq!(apply_promotions(&lines, &[percent], &[], Utc::now()).unwrap().line_discounts, amounts(&[0.6]));
+
+        let fixed = promotion(1, PromotionType::AmountOff, PromotionScope::Line, 0.25);
+        assert_eq!(apply_promotions(&lines, &[fixed], &[], Utc::now()).unwrap().line_discounts, amounts(&[0.75]));
+
+        // Buy 2 get 1 free: 7 units hold two full groups
+        let bogof = Promotion { buy_quantity: Some(2), get_quantity: Some(1), ..promotion(1, PromotionType::BuyXGetY, PromotionScope::Line, 100.0) };
+        assert_eq!(apply_promotions(&[line(1, 7, 2.0)], &[bogof], &[], Utc::now()).unwrap().line_discounts, amounts(&[4.0]));
+
+        // 3 for 5.00
+        let bundle = Promotion { buy_quantity: Some(3), ..promotion(1, PromotionType::MultiBuy, PromotionScope::Line, 5.0) };
+        assert_eq!(apply_promotions(&[line(1, 7, 2.0)], &[bundle], &[], Utc::now()).unwrap().line_discounts, amounts(&[2.0]));
+    }
+
+    #[test]
+    fn test_priority_then_age_picks_one_promotion_per_line() {
+        let lines = [line(1, 1, 10.0), line(2, 1, 10.0)];
+        let older = promotion(1, PromotionType::PercentOff, PromotionScope::Line, 10.0);
+        let newer = promotion(2, PromotionType::PercentOff, PromotionScope::Line, 50.0);
+        let urgent = Promotion { product_id: Some(2), priority: 5, ..promotion(3, PromotionType::PercentOff, PromotionScope::Line, 20.0) };
+
+        let result = apply_promotions(&lines, &[newer, urgent, older], &[], Utc::now()).unwrap();
+        assert_eq!(result.line_discounts, amounts(&[1.0, 2.0]));
+        let applied: Vec<i64> = result.applied.iter().map(|a| a.promotion_id).collect();
+        assert_eq!(applied, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_order_discount_is_spread_over_lines() {
+        let lines = [line(1, 1, 10.0), line(2, 2, 10.0)];
+        let order = Promotion { min_order_amount: Some(Money::from_major(25.0)), ..promotion(1, PromotionType::AmountOff, PromotionScope::Order, 10.0) };
+
+        let result = apply_promotions(&lines, std::slice::from_ref(&order), &[], Utc::now()).unwrap();
+        assert_eq!(result.line_discounts, amounts(&[3.33, 6.67]));
+        assert_eq!(result.applied[0].line_index, None);
+        assert_eq!(result.applied[0].discount_amount, Money::from_major(10.0));
+
+        let small = apply_promotions(&lines[..1], &[order], &[], Utc::now()).unwrap();
+        assert!(small.applied.is_empty());
+    }
+
+    #[test]
+    fn test_time_windows_and_coupons() {
+        let now = Utc::now();
+        let lines = [line(1, 1, 10.0)];
+        let expired = Promotion { ends_at: Some(now), ..promotion(1, PromotionType::PercentOff, PromotionScope::Line, 50.0) };
+        let coupon = Promotion { coupon_code: Some("SAVE5".to_string()), ..promotion(2, PromotionType::AmountOff, PromotionScope::Order, 5.0) };
+        let promotions = [expired, coupon];
+
+        assert!(apply_promotions(&lines, &promotions, &[], now).unwrap().applied.is_empty());
+
+        let result = apply_promotions(&lines, &promotions, &[" save5 ".to_string()], now).unwrap();
+        assert_eq!(result.applied[0].coupon_code.as_deref(), Some("SAVE5"));
+        assert_eq!(result.line_discounts, amounts(&[5.0]));
+
+        let err = apply_promotions(&lines, &promotions, &["NOPE".to_string()], now).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }));
+    }
+
+    #[test]
+    fn test_invalid_promotions_are_rejected() {
+        let request = PromotionRequest {
+            name: "Half off".to_string(),
+            description: None,
+            promotion_type: PromotionType::BuyXGetY,
+            scope: PromotionScope::Order,
+            value: 50.0,
+            amount: Money::ZERO,
+            product_id: None,
+            category: None,
+            buy_quantity: Some(1),
+            get_quantity: Some(1),
+            min_order_amount: None,
+            coupon_code: None,
+            starts_at: None,
+            ends_at: None,
+            priority: 0,
+            is_active: true,
+        };
+        assert!(validate_promotion(&request).is_err());
+        let mut request = PromotionRequest { scope: PromotionScope::Line, ..request };
+        assert!(validate_promotion(&request).is_ok());
+        request.amount = Money::from_minor(-1);
+        assert!(validate_promotion(&request).is_err());
+    }
+}
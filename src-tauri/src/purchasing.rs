@@ -0,0 +1,380 @@
+use chrono::Local;
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+use crate::pdf::{PdfDocument, COLUMNS};
+use crate::printer::{PrinterSettings, PRINTER_SETTINGS_KEY};
+
+impl PurchaseOrderStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PurchaseOrderStatus::Draft => "draft",
+            PurchaseOrderStatus::Sent => "sent",
+            PurchaseOrderStatus::PartiallyReceived => "partially_received",
+            PurchaseOrderStatus::Received => "received",
+            PurchaseOrderStatus::Closed => "closed",
+        }
+    }
+
+    /// The purchase order lifecycle: draft -> sent -> partially_received ->
+    /// received -> closed, where a delivery can also fill the order in one go.
+    ///
+    /// Only drafts can be edited. Any open order can be closed, which for a
+    /// draft abandons it and for a partly received order gives up on the rest.
+    pub fn can_transition_to(self, to: PurchaseOrderStatus) -> bool {
+        use PurchaseOrderStatus::*;
+
+        matches!(
+            (self, to),
+            (Draft, Sent)
+                | (Draft, Closed)
+                | (Sent, PartiallyReceived)
+                | (Sent, Received)
+                | (Sent, Closed)
+                | (PartiallyReceived, PartiallyReceived)
+                | (PartiallyReceived, Received)
+                | (PartiallyReceived, Closed)
+                | (Received, Closed)
+        )
+    }
+
+    pub fn check_transition(self, purchase_order_id: i64, to: PurchaseOrderStatus) -> Result<(), AppError> {
+        if self.can_transition_to(to) {
+            return Ok(());
+        }
+
+        Err(AppError::invalid_transition(
+            format!("{} cannot go from {} to {}", po_number(purchase_order_id), self.as_str(), to.as_str()),
+            self.as_str(),
+            to.as_str(),
+        ))
+    }
+}
+
+/// The number a purchase order is known by to the supplier, e.g. PO-00042
+pub fn po_number(purchase_order_id: i64) -> String {
+    format!("PO-{:05}", purchase_order_id)
+}
+
+/// The cost of stock after `received` units at `unit_cost` join `on_hand`
+/// units at `cost`, weighted by quantity. Stock that is not there (none or
+/// oversold) does not count, so the delivery sets the cost.
+pub fn average_cost(on_hand: i32, cost: Money, received: i32, unit_cost: Money) -> Money {
+    if on_hand <= 0 {
+        return unit_cost;
+    }
+    (cost * on_hand + unit_cost * received).share(1, (on_hand + received) as i64)
+}
+
+pub fn validate_purchase_order(order: &PurchaseOrderRequest) -> Result<(), AppError> {
+    if order.lines.is_empty() {
+        return Err(AppError::validation("lines", "A purchase order needs at least one line"));
+    }
+    if order.lines.iter().any(|line| line.quantity <= 0) {
+        return Err(AppError::validation("lines", "Quantities must be positive"));
+    }
+    if order.lines.iter().any(|line| line.unit_cost.is_negative()) {
+        return Err(AppError::validation("lines", "Costs cannot be negative"));
+    }
+    for (i, line) in order.lines.iter().enumerate() {
+        if order.lines[..i].iter().any(|other| other.product_id == line.product_id) {
+            return Err(AppError::validation("lines", "Each product can only be on one line"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_receipt(receipt: &ReceivePurchaseOrderRequest) -> Result<(), AppError> {
+    if receipt.lines.is_empty() {
+        return Err(AppError::validation("lines", "Nothing was received"));
+    }
+    if receipt.lines.iter().any(|line| line.quantity <= 0) {
+        return Err(AppError::validation("lines", "Quantities must be positive"));
+    }
+    if receipt.lines.iter().any(|line| line.unit_cost.is_some_and(Money::is_negative)) {
+        return Err(AppError::validation("lines", "Costs cannot be negative"));
+    }
+    Ok(())
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One row per line, each repeating the order so the file can be imported as is
+pub fn render_csv(details: &PurchaseOrderDetails) -> String {
+    let order = &details.purchase_order;
+    let expected_date = order.expected_date.map(|date| date.to_string()).unwrap_or_default();
+
+    let mut csv = String::from("PO Number,Supplier,Expected Date,SKU,Product,Quantity,Currency,Unit Cost,Line Total\n");
+    for line in &details.lines {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            po_number(order.id),
+            csv_field(&order.supplier_name),
+            expected_date,
+            csv_field(&line.sku),
+            csv_field(&line.product_name),
+            line.quantity_ordered,
+            order.currency,
+            line.unit_cost,
+            line.unit_cost * line.quantity_ordered,
+        ));
+    }
+    csv
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// The purchase order as a document for the supplier, from `store_name`
+pub fn render_pdf(details: &PurchaseOrderDetails, store_name: &str) -> Vec<u8> {
+    let order = &details.purchase_order;
+    let supplier = &details.supplier;
+    let mut pdf = PdfDocument::new();
+
+    pdf.bold_line(&truncate(store_name, COLUMNS));
+    pdf.bold_line(&format!("PURCHASE ORDER {}", po_number(order.id)));
+    pdf.line(&format!("Date: {}", order.created_at.with_timezone(&Local).format("%Y-%m-%d")));
+    if let Some(expected_date) = order.expected_date {
+        pdf.line(&format!("Expected delivery: {}", expected_date));
+    }
+    pdf.blank();
+
+    pdf.bold_line("Supplier");
+    for detail in [&supplier.name, &supplier.contact_name, &supplier.address, &supplier.phone, &supplier.email] {
+        if !detail.trim().is_empty() {
+            pdf.line(&truncate(detail, COLUMNS));
+        }
+    }
+    pdf.blank();
+
+    let row = |sku: &str, product: &str, quantity: &str, unit_cost: &str, total: &str| {
+        format!("{:<12} {:<34} {:>6} {:>12} {:>12}", truncate(sku, 12), truncate(product, 34), quantity, unit_cost, total)
+    };
+    pdf.bold_line(&row("SKU", "Product", "Qty", "Unit cost", "Total"));
+    pdf.line(&"-".repeat(COLUMNS));
+    for line in &details.lines {
+        pdf.line(&row(
+            &line.sku,
+            &line.product_name,
+            &line.quantity_ordered.to_string(),
+            &line.unit_cost.to_string(),
+            &(line.unit_cost * line.quantity_ordered).to_string(),
+        ));
+    }
+    pdf.line(&"-".repeat(COLUMNS));
+    pdf.bold_line(&format!("{:>width$}", format!("Total {} {}", order.currency, order.total_cost), width = COLUMNS));
+
+    if !order.notes.trim().is_empty() {
+        pdf.blank();
+        pdf.line(&truncate(&format!("Notes: {}", order.notes.trim()), COLUMNS));
+    }
+
+    pdf.into_bytes()
+}
+
+// Purchase order commands
+#[tauri::command]
+pub async fn get_purchase_orders(
+    token: String,
+    status: Option<PurchaseOrderStatus>,
+    supplier_id: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<PurchaseOrder>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_purchase_orders(status, supplier_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase orders"))
+}
+
+#[tauri::command]
+pub async fn get_purchase_order(
+    token: String,
+    purchase_order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<PurchaseOrderDetails, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))
+}
+
+/// Start a purchase order as a draft
+#[tauri::command]
+pub async fn create_purchase_order(
+    token: String,
+    order_data: PurchaseOrderRequest,
+    state: State<'_, AppState>,
+) -> Result<PurchaseOrderDetails, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+    validate_purchase_order(&order_data)?;
+
+    let db = state.db.lock().await;
+    let purchase_order_id = db.create_purchase_order(&user, &order_data).await
+        .map_err(|e| AppError::from(e).context("Failed to create purchase order"))?;
+
+    db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))
+}
+
+/// Replace a draft's supplier, dates, notes and lines
+#[tauri::command]
+pub async fn update_purchase_order(
+    token: String,
+    purchase_order_id: i64,
+    order_data: PurchaseOrderRequest,
+    state: State<'_, AppState>,
+) -> Result<PurchaseOrderDetails, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+    validate_purchase_order(&order_data)?;
+
+    let db = state.db.lock().await;
+    db.update_purchase_order(purchase_order_id, &order_data).await
+        .map_err(|e| AppError::from(e).context("Failed to update purchase order"))?;
+
+    db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))
+}
+
+/// Mark a draft as sent to its supplier, after which it can be received
+#[tauri::command]
+pub async fn send_purchase_order(
+    token: String,
+    purchase_order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<PurchaseOrderDetails, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.send_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to send purchase order"))?;
+
+    db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))
+}
+
+/// Book a delivery into stock, in full or in part
+#[tauri::command]
+pub async fn receive_purchase_order(
+    token: String,
+    purchase_order_id: i64,
+    receipt: ReceivePurchaseOrderRequest,
+    state: State<'_, AppState>,
+) -> Result<PurchaseOrderDetails, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+    validate_receipt(&receipt)?;
+
+    let db = state.db.lock().await;
+    db.receive_purchase_order(&user, purchase_order_id, &receipt).await
+        .map_err(|e| AppError::from(e).context("Failed to receive purchase order"))?;
+
+    db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))
+}
+
+#[tauri::command]
+pub async fn close_purchase_order(
+    token: String,
+    purchase_order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<PurchaseOrderDetails, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.close_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to close purchase order"))?;
+
+    db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))
+}
+
+#[tauri::command]
+pub async fn export_purchase_order_csv(
+    token: String,
+    purchase_order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    let details = db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))?;
+
+    Ok(render_csv(&details))
+}
+
+/// The PDF's bytes, headed with the store name from the receipt template
+#[tauri::command]
+pub async fn export_purchase_order_pdf(
+    token: String,
+    purchase_order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    let details = db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))?;
+    let printer = db.get_setting::<PrinterSettings>(PRINTER_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get printer settings"))?
+        .unwrap_or_default();
+    let store_name = printer.template.header_lines.first().cloned().unwrap_or_default();
+
+    Ok(render_pdf(&details, &store_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    #[test]
+    fn test_orders_are_received_before_they_close() {
+        use PurchaseOrderStatus::*;
+
+        assert!(Draft.can_transition_to(Sent));
+        assert!(!Draft.can_transition_to(PartiallyReceived));
+        assert!(PartiallyReceived.can_transition_to(PartiallyReceived));
+        assert!(Sent.can_transition_to(Closed));
+        for to in [Draft, Sent, PartiallyReceived, Received, Closed] {
+            assert!(!Closed.can_transition_to(to));
+        }
+        assert!(matches!(Received.check_transition(7, Sent), Err(AppError::InvalidTransition { .. })));
+    }
+
+    #[test]
+    fn test_receiving_averages_cost_over_stock_on_hand() {
+        assert_eq!(average_cost(10, money(2.0), 10, money(3.0)), money(2.5));
+        assert_eq!(average_cost(2, money(1.0), 1, money(2.0)), money(1.33));
+        assert_eq!(average_cost(0, money(1.0), 5, money(4.0)), money(4.0));
+        assert_eq!(average_cost(-3, money(1.0), 5, money(4.0)), money(4.0));
+    }
+
+    #[test]
+    fn test_orders_and_exports_are_validated_and_quoted() {
+        let line = |product_id: i64, quantity: i32| PurchaseOrderLineRequest { product_id, quantity, unit_cost: money(1.0) };
+        let order = |lines| PurchaseOrderRequest { supplier_id: 1, expected_date: None, notes: None, lines };
+        assert!(validate_purchase_order(&order(vec![line(1, 5), line(2, 1)])).is_ok());
+        assert!(validate_purchase_order(&order(vec![])).is_err());
+        assert!(validate_purchase_order(&order(vec![line(1, 0)])).is_err());
+        assert!(matches!(validate_purchase_order(&order(vec![line(1, 5), line(1, 2)])), Err(AppError::Validation { .. })));
+
+        assert_eq!(csv_field("Rice, 5kg"), "\"Rice, 5kg\"");
+        assert_eq!(csv_field("12\" pan"), "\"12\"\" pan\"");
+        assert_eq!(po_number(42), "PO-00042");
+    }
+}
@@ -0,0 +1,509 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::database::Database;
+use crate::error::AppError;
+use crate::models::*;
+use crate::printer::{self, Align, Bitmap, EscPos, PaperWidth, PrinterConnection, PrinterSettings, PRINTER_SETTINGS_KEY};
+
+/// Machine-readable code printed at the bottom of the receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptCode {
+    None,
+    #[default]
+    Code128,
+    Qr,
+}
+
+/// Store-specific parts of the receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReceiptTemplate {
+    /// Printed centered at the top; the first line is the store name
+    pub header_lines: Vec<String>,
+    /// A binary PBM image no wider than the paper
+    pub logo_path: Option<String>,
+    pub footer_lines: Vec<String>,
+    pub currency_symbol: String,
+    pub code: ReceiptCode,
+}
+
+impl Default for ReceiptTemplate {
+    fn default() -> Self {
+        ReceiptTemplate {
+            header_lines: vec!["ISMS SUPERMARKET".to_string()],
+            logo_path: None,
+            footer_lines: vec![
+                "THANK YOU FOR YOUR BUSINESS!".to_string(),
+                "Please keep this receipt for your records".to_string(),
+            ],
+            currency_symbol: "$".to_string(),
+            code: ReceiptCode::Code128,
+        }
+    }
+}
+
+/// An order line as printed
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReceiptLine {
+    pub order_item_id: i64,
+    pub product_name: String,
+    pub quantity: i32,
+    pub unit_price: Money,
+    pub discount_amount: Money,
+    pub line_total: Money,
+}
+
+/// Points printed for a loyalty customer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptLoyalty {
+    pub earned: i64,
+    pub redeemed: i64,
+    /// The customer's balance when the receipt was printed
+    pub balance: i64,
+}
+
+/// Everything a receipt shows about one order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptData {
+    pub order: Order,
+    pub cashier_name: Option<String>,
+    pub lines: Vec<ReceiptLine>,
+    /// Gift cards sold on the order
+    pub gift_cards: Vec<GiftCard>,
+    pub taxes: Vec<OrderTaxLine>,
+    pub promotions: Vec<OrderPromotion>,
+    pub payments: Vec<OrderPayment>,
+    pub loyalty: Option<ReceiptLoyalty>,
+}
+
+/// `left` and `right` on one line, truncating `left` if they do not fit
+fn columns(left: &str, right: &str, width: usize) -> String {
+    let room = width.saturating_sub(right.chars().count() + 1);
+    let left: String = left.chars().take(room).collect();
+    let padding = width.saturating_sub(left.chars().count() + right.chars().count());
+    format!("{}{}{}", left, " ".repeat(padding), right)
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Centered logo and header lines, with the store name emphasised
+fn header(escpos: &mut EscPos, template: &ReceiptTemplate, width: usize, logo: Option<&Bitmap>) {
+    escpos.align(Align::Center);
+    if let Some(logo) = logo {
+        escpos.raster(logo);
+    }
+    for (index, line) in template.header_lines.iter().enumerate() {
+        if index == 0 {
+            escpos.bold(true).double(true).line(&truncate(line, width / 2)).double(false).bold(false);
+        } else {
+            escpos.line(&truncate(line, width));
+        }
+    }
+}
+
+/// Footer lines, the machine-readable code and the paper cut
+fn footer(mut escpos: EscPos, template: &ReceiptTemplate, paper_width: PaperWidth, code: &str) -> Vec<u8> {
+    let width = paper_width.columns();
+    escpos.align(Align::Center);
+    for line in &template.footer_lines {
+        escpos.line(&truncate(line, width));
+    }
+
+    match template.code {
+        ReceiptCode::None => {}
+        ReceiptCode::Code128 => {
+            escpos.code128(code);
+        }
+        ReceiptCode::Qr => {
+            escpos.qr(code, if paper_width == PaperWidth::Mm58 { 5 } else { 6 });
+        }
+    }
+
+    escpos.feed(3).cut();
+    escpos.into_bytes()
+}
+
+/// Tender names as printed, e.g. "CREDIT CARD"
+fn tender_name(tender_type: TenderType) -> String {
+    serde_json::to_value(tender_type).ok()
+        .and_then(|value| value.as_str().map(|name| name.replace('_', " ").to_uppercase()))
+        .unwrap_or_default()
+}
+
+/// Render a receipt to ESC/POS bytes
+pub fn render(data: &ReceiptData, template: &ReceiptTemplate, paper_width: PaperWidth, logo: Option<&Bitmap>) -> Vec<u8> {
+    let width = paper_width.columns();
+    let rule = "-".repeat(width);
+    let money = |amount: Money| format!("{}{}", template.currency_symbol, amount);
+    let order = &data.order;
+    let mut escpos = EscPos::new();
+
+    header(&mut escpos, template, width, logo);
+
+    escpos.align(Align::Left).line(&rule);
+    escpos.line(&columns("Receipt #:", &order.id.to_string(), width));
+    escpos.line(&columns("Date:", &order.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(), width));
+    if !order.customer_name.is_empty() {
+        escpos.line(&columns("Customer:", &order.customer_name, width));
+    }
+    if let Some(cashier_name) = &data.cashier_name {
+        escpos.line(&columns("Cashier:", cashier_name, width));
+    }
+    escpos.line(&rule);
+
+    for line in &data.lines {
+        escpos.line(&truncate(&line.product_name, width));
+        let quantity = format!("  {} x {}", line.quantity, money(line.unit_price));
+        escpos.line(&columns(&quantity, &money(line.unit_price * line.quantity), width));
+        for promotion in data.promotions.iter().filter(|p| p.order_item_id == Some(line.order_item_id)) {
+            escpos.line(&columns(&format!("  {}", promotion.name), &format!("-{}", money(promotion.discount_amount)), width));
+        }
+    }
+    for card in &data.gift_cards {
+        escpos.line(&columns("Gift card", &money(card.initial_amount), width));
+        escpos.line(&format!("  {}", card.code));
+        if let Some(expires_on) = card.expires_on {
+            escpos.line(&format!("  Valid until {}", expires_on));
+        }
+    }
+    escpos.line(&rule);
+
+    for promotion in data.promotions.iter().filter(|p| p.order_item_id.is_none()) {
+        let label = match &promotion.coupon_code {
+            Some(code) => format!("{} ({})", promotion.name, code),
+            None => promotion.name.clone(),
+        };
+        escpos.line(&columns(&label, &format!("-{}", money(promotion.discount_amount)), width));
+    }
+    if order.discount_total.is_positive() {
+        escpos.line(&columns("Total savings", &format!("-{}", money(order.discount_total)), width));
+    }
+    escpos.line(&columns("Subtotal", &money(order.subtotal), width));
+    for tax in &data.taxes {
+        escpos.line(&columns(&format!("{} {}%", tax.name, tax.rate), &money(tax.tax_amount), width));
+    }
+    escpos.bold(true).double(true)
+        .line(&columns("TOTAL", &money(order.total_amount), width / 2))
+        .double(false).bold(false);
+    if data.payments.is_empty() {
+        escpos.line(&columns("Paid by", &order.payment_method.to_uppercase(), width));
+    }
+    for payment in &data.payments {
+        escpos.line(&columns(&tender_name(payment.tender_type), &money(payment.tendered), width));
+        if payment.change_due.is_positive() {
+            escpos.line(&columns("Change", &money(payment.change_due), width));
+        }
+    }
+    if let Some(loyalty) = &data.loyalty {
+        escpos.line(&rule);
+        if loyalty.redeemed > 0 {
+            escpos.line(&columns("Points redeemed", &loyalty.redeemed.to_string(), width));
+        }
+        escpos.line(&columns("Points earned", &loyalty.earned.to_string(), width));
+        escpos.line(&columns("Points balance", &loyalty.balance.to_string(), width));
+    }
+    escpos.line(&rule);
+
+    footer(escpos, template, paper_width, &order.id.to_string())
+}
+
+/// Render a return receipt to ESC/POS bytes. Its code carries the return
+/// number prefixed with R so it cannot be mistaken for a sale.
+pub fn render_return(details: &ReturnDetails, template: &ReceiptTemplate, paper_width: PaperWidth, logo: Option<&Bitmap>) -> Vec<u8> {
+    let width = paper_width.columns();
+    let rule = "-".repeat(width);
+    let money = |amount: Money| format!("{}{}", template.currency_symbol, amount);
+    let order_return = &details.order_return;
+    let mut escpos = EscPos::new();
+
+    header(&mut escpos, template, width, logo);
+    escpos.bold(true).line("RETURN").bold(false);
+
+    escpos.align(Align::Left).line(&rule);
+    escpos.line(&columns("Return #:", &order_return.id.to_string(), width));
+    escpos.line(&columns("Original receipt #:", &order_return.order_id.to_string(), width));
+    escpos.line(&columns("Date:", &order_return.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(), width));
+    escpos.line(&columns("Processed by:", &order_return.user_name, width));
+    let reason = serde_json::to_value(order_return.reason).ok()
+        .and_then(|value| value.as_str().map(|name| name.replace('_', " ")))
+        .unwrap_or_default();
+    escpos.line(&columns("Reason:", &reason, width));
+    escpos.line(&rule);
+
+    for item in &details.items {
+        escpos.line(&truncate(&item.product_name, width));
+        let quantity = match item.disposition {
+            ReturnDisposition::Restock => format!("  {} returned", item.quantity),
+            ReturnDisposition::WriteOff => format!("  {} returned, written off", item.quantity),
+        };
+        escpos.line(&columns(&quantity, &format!("-{}", money(item.refund_amount)), width));
+    }
+    escpos.line(&rule);
+
+    escpos.line(&columns("Tax refunded", &money(order_return.tax_total), width));
+    escpos.bold(true).double(true)
+        .line(&columns("REFUND", &money(order_return.refund_total), width / 2))
+        .double(false).bold(false);
+    for refund in &details.refunds {
+        escpos.line(&columns(&format!("To {}", tender_name(refund.tender_type)), &money(refund.amount), width));
+    }
+    if let Some(card) = &details.store_credit {
+        escpos.line(&columns("Store credit code", &card.code, width));
+    }
+    escpos.line(&rule);
+
+    footer(escpos, template, paper_width, &format!("R{}", order_return.id))
+}
+
+/// Render a stored X or Z report to ESC/POS bytes
+pub fn render_shift_report(record: &ShiftReportRecord, template: &ReceiptTemplate, paper_width: PaperWidth) -> Vec<u8> {
+    let width = paper_width.columns();
+    let rule = "-".repeat(width);
+    let money = |amount: Money| format!("{}{}", template.currency_symbol, amount);
+    let time = |at: chrono::DateTime<chrono::Utc>| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string();
+    let report = &record.report;
+    let mut escpos = EscPos::new();
+
+    let title = match report.report_type {
+        ShiftReportType::X => "X REPORT",
+        ShiftReportType::Z => "Z REPORT",
+    };
+    escpos.align(Align::Center);
+    if let Some(store) = template.header_lines.first() {
+        escpos.line(&truncate(store, width));
+    }
+    escpos.bold(true).double(true).line(title).double(false).bold(false);
+
+    escpos.align(Align::Left).line(&rule);
+    escpos.line(&columns("Shift #:", &report.shift_id.to_string(), width));
+    escpos.line(&columns("Opened:", &time(report.opened_at), width));
+    escpos.line(&columns("Opened by:", &report.opened_by, width));
+    if let Some(closed_at) = report.closed_at {
+        escpos.line(&columns("Closed:", &time(closed_at), width));
+    }
+    escpos.line(&columns("Printed:", &time(record.created_at), width));
+    escpos.line(&columns("Taken by:", &record.user_name, width));
+    escpos.line(&rule);
+
+    escpos.line(&columns("Orders", &report.orders.to_string(), width));
+    escpos.line(&columns("Sales", &money(report.gross_sales), width));
+    escpos.line(&columns("Refunds", &format!("-{}", money(report.refunds)), width));
+    escpos.line(&columns("Opening float", &money(report.opening_float), width));
+    escpos.line(&columns("Payouts", &format!("-{}", money(report.payouts)), width));
+    escpos.line(&columns("Drops", &format!("-{}", money(report.drops)), width));
+
+    for tender in &report.tenders {
+        escpos.line(&rule);
+        escpos.bold(true).line(&tender_name(tender.tender_type)).bold(false);
+        escpos.line(&columns("  Taken", &money(tender.payments), width));
+        escpos.line(&columns("  Refunded", &format!("-{}", money(tender.refunds)), width));
+        escpos.line(&columns("  Expected", &money(tender.expected), width));
+        if let (Some(counted), Some(variance)) = (tender.counted, tender.variance) {
+            escpos.line(&columns("  Counted", &money(counted), width));
+            let variance = if variance.is_negative() { format!("-{}", money(-variance)) } else { money(variance) };
+            escpos.line(&columns("  Variance", &variance, width));
+        }
+    }
+    escpos.line(&rule);
+
+    escpos.feed(3).cut();
+    escpos.into_bytes()
+}
+
+/// The configured printer, its settings and logo
+async fn receipt_printer(db: &Database) -> Result<(PrinterConnection, PrinterSettings, Option<Bitmap>), AppError> {
+    let settings = db.get_setting::<PrinterSettings>(PRINTER_SETTINGS_KEY).await?
+        .unwrap_or_default();
+    let connection = settings.connection.clone()
+        .ok_or_else(|| AppError::device("No receipt printer is configured"))?;
+
+    let logo = match &settings.template.logo_path {
+        Some(path) => Some(printer::load_logo(path, settings.paper_width).await?),
+        None => None,
+    };
+
+    Ok((connection, settings, logo))
+}
+
+/// Render an order's receipt with the configured template and send it to
+/// the configured printer
+pub async fn print_order_receipt(db: &Database, order_id: i64) -> Result<(), AppError> {
+    let (connection, settings, logo) = receipt_printer(db).await?;
+    let data = db.get_receipt_data(order_id).await?;
+
+    let bytes = render(&data, &settings.template, settings.paper_width, logo.as_ref());
+    printer::send(&connection, &bytes).await
+}
+
+/// Reprint a stored X or Z report on the configured printer
+pub async fn print_shift_report(db: &Database, report_id: i64) -> Result<(), AppError> {
+    let (connection, settings, _) = receipt_printer(db).await?;
+    let record = db.get_shift_report(report_id).await?;
+
+    let bytes = render_shift_report(&record, &settings.template, settings.paper_width);
+    printer::send(&connection, &bytes).await
+}
+
+/// Render a return receipt and send it to the configured printer
+pub async fn print_return_receipt(db: &Database, return_id: i64) -> Result<(), AppError> {
+    let (connection, settings, logo) = receipt_printer(db).await?;
+    let details = db.get_return(return_id).await?;
+
+    let bytes = render_return(&details, &settings.template, settings.paper_width, logo.as_ref());
+    printer::send(&connection, &bytes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The printable text of a job with every ESC/POS command removed, one entry per line
+    fn text_lines(bytes: &[u8]) -> Vec<String> {
+        let le = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]) as usize;
+        let mut text = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            i += match (bytes[i], bytes.get(i + 1)) {
+                (0x1b, Some(b'@')) => 2,
+                (0x1b, _) => 3,
+                (0x1d, Some(b'V')) => 4,
+                (0x1d, Some(b'v')) => 8 + le(i + 4) * le(i + 6),
+                (0x1d, Some(b'k')) => 4 + bytes[i + 3] as usize,
+                (0x1d, Some(b'(')) => 5 + le(i + 3),
+                (0x1d, _) => 3,
+                (byte, _) => {
+                    text.push(byte);
+                    1
+                }
+            };
+        }
+        String::from_utf8(text).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_columns_fit_the_paper() {
+        assert_eq!(columns("Subtotal", "$3.00", 20), "Subtotal       $3.00");
+        assert_eq!(columns("A very long product name", "$10.00", 20), "A very long p $10.00");
+    }
+
+    #[test]
+    fn test_return_receipt_lists_refunds_per_tender() {
+        let details = ReturnDetails {
+            order_return: OrderReturn {
+                id: 4,
+                created_at: chrono::Utc::now(),
+                order_id: 12,
+                user_id: 1,
+                user_name: "Ada".to_string(),
+                reason: ReturnReason::WrongItem,
+                notes: String::new(),
+                refund_total: Money::from_major(5.0),
+                tax_total: Money::from_major(0.83),
+            },
+            items: vec![OrderReturnItem {
+                id: 1,
+                return_id: 4,
+                order_item_id: 30,
+                product_id: 2,
+                product_name: "Free Range Eggs".to_string(),
+                quantity: 2,
+                disposition: ReturnDisposition::WriteOff,
+                refund_amount: Money::from_major(5.0),
+                tax_amount: Money::from_major(0.83),
+            }],
+            refunds: vec![
+                OrderRefund { id: 1, return_id: 4, order_payment_id: Some(8), tender_type: TenderType::DebitCard, amount: Money::from_major(4.5), shift_id: None },
+                OrderRefund { id: 2, return_id: 4, order_payment_id: Some(7), tender_type: TenderType::Cash, amount: Money::from_major(0.5), shift_id: None },
+            ],
+            store_credit: None,
+        };
+
+        let bytes = render_return(&details, &ReceiptTemplate::default(), PaperWidth::Mm58, None);
+        let lines = text_lines(&bytes);
+        assert!(lines.contains(&"Original receipt #:           12".to_string()));
+        assert!(lines.contains(&"Reason:               wrong item".to_string()));
+        assert!(lines.contains(&"  2 returned, written off -$5.00".to_string()));
+        assert!(lines.contains(&"To DEBIT CARD              $4.50".to_string()));
+        assert!(lines.contains(&"To CASH                    $0.50".to_string()));
+        assert!(bytes.windows(4).any(|window| window == b"{BR4"));
+    }
+
+    #[tokio::test]
+    async fn test_receipt_is_printed_to_configured_file() {
+        let db = Database::in_memory().await.unwrap();
+        let cashier = UserInfo {
+            id: 1,
+            email: "cashier@example.com".to_string(),
+            full_name: "Till Operator".to_string(),
+            role: "cashier".to_string(),
+            permissions: vec![],
+        };
+        sqlx::query(
+            "INSERT INTO users (id, created_at, updated_at, email, hashed_password, full_name, role, is_active, is_superuser) VALUES (1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, ?, '', ?, ?, TRUE, FALSE)"
+        )
+        .bind(&cashier.email)
+        .bind(&cashier.full_name)
+        .bind(&cashier.role)
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .unwrap();
+        let product_id = db.create_product(&cashier, CreateProductRequest {
+            name: "Orange Juice 1L".to_string(),
+            description: None,
+            sku: "OJ1".to_string(),
+            category: "beverages".to_string(),
+            price: Money::from_major(2.4),
+            cost: Money::from_major(1.0),
+            quantity: 10,
+            reorder_level: 0,
+            expiry_date: None,
+            supplier_id: None,
+            tax_class_id: None,
+            stock_policy: None,
+            location: None,
+        }).await.unwrap();
+        let order_id = db.create_order(&cashier, &[], CreateOrderRequest {
+            customer_name: Some("Ada".to_string()),
+            customer_id: None,
+            payment_method: "cash".to_string(),
+            coupon_codes: vec![],
+            payments: vec![],
+            gift_cards: vec![],
+            items: vec![OrderItemRequest { product_id, quantity: 2, price_at_sale: Money::from_major(2.4), override_reason: None }],
+        }).await.unwrap();
+
+        assert!(matches!(print_order_receipt(&db, order_id).await, Err(AppError::Device { .. })));
+
+        let path = std::env::temp_dir().join(format!("isms-{}-receipt.bin", std::process::id()));
+        let settings = PrinterSettings {
+            paper_width: PaperWidth::Mm58,
+            connection: Some(PrinterConnection::File { path: path.to_string_lossy().to_string() }),
+            template: ReceiptTemplate { code: ReceiptCode::Qr, ..ReceiptTemplate::default() },
+            ..PrinterSettings::default()
+        };
+        db.set_setting(PRINTER_SETTINGS_KEY, &settings).await.unwrap();
+        print_order_receipt(&db, order_id).await.unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines = text_lines(&bytes);
+        assert!(lines.iter().all(|line| line.chars().count() <= 32));
+        assert!(lines.contains(&"Orange Juice 1L".to_string()));
+        assert!(lines.contains(&"  2 x $2.40                $4.80".to_string()));
+        assert!(lines.contains(&"Paid by                     CASH".to_string()));
+        assert!(lines.contains(&"Cashier:           Till Operator".to_string()));
+        assert!(lines.contains(&"TOTAL      $4.80".to_string()));
+        assert!(bytes.starts_with(b"\x1b@"));
+        assert!(bytes.ends_with(&[0x1d, b'V', 66, 3]));
+
+        // QR store command carrying the order id
+        let id = order_id.to_string();
+        let qr = [&[0x1d, b'(', b'k', id.len() as u8 + 3, 0, 49, 80, 48][..], id.as_bytes()].concat();
+        assert!(bytes.windows(qr.len()).any(|window| window == qr));
+    }
+}
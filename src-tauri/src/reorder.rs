@@ -0,0 +1,311 @@
+use chrono::{Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::database::Database;
+use crate::error::AppError;
+
+/// Key of the reorder section in `app_settings`
+pub const REORDER_SETTINGS_KEY: &str = "reorder";
+
+const MAX_DAYS: u32 = 365;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReorderSettings {
+    /// Days of sales the average daily rate is taken over
+    pub window_days: u32,
+    /// Lead time of suppliers without one of their own
+    pub default_lead_time_days: u32,
+    /// Days of sales kept in hand against late deliveries and busy days
+    pub safety_stock_days: u32,
+    /// Days of sales each order should last once it arrives
+    pub cover_days: u32,
+}
+
+impl Default for ReorderSettings {
+    fn default() -> Self {
+        ReorderSettings {
+            window_days: 28,
+            default_lead_time_days: 7,
+            safety_stock_days: 3,
+            cover_days: 14,
+        }
+    }
+}
+
+/// A product with its recent sales and what is already on order
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReorderCandidate {
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    pub supplier_id: Option<i64>,
+    pub supplier_name: Option<String>,
+    pub lead_time_days: Option<i32>,
+    pub quantity: i32,
+    pub reorder_level: i32,
+    pub cost: Money,
+    /// Units sold within the window
+    pub units_sold: i64,
+    /// Units on purchase orders not yet received
+    pub on_order: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReorderSuggestion {
+    pub product_id: i64,
+    pub product_name: String,
+    pub sku: String,
+    pub on_hand: i32,
+    pub on_order: i32,
+    pub reorder_level: i32,
+    pub average_daily_sales: f64,
+    /// Stock at or below which the product is reordered
+    pub reorder_point: i32,
+    pub suggested_quantity: i32,
+    pub unit_cost: Money,
+}
+
+/// What to order from one supplier; products without a supplier are
+/// grouped under `supplier_id: None`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplierReorder {
+    pub supplier_id: Option<i64>,
+    pub supplier_name: String,
+    pub lead_time_days: u32,
+    pub lines: Vec<ReorderSuggestion>,
+    pub total_cost: Money,
+}
+
+/// Whole units covering `days` of sales at `daily`, rounded up
+fn units_for(daily: f64, days: u32) -> i32 {
+    (daily * days as f64 - 1e-9).ceil().max(0.0) as i32
+}
+
+fn lead_time(candidate: &ReorderCandidate, settings: &ReorderSettings) -> u32 {
+    candidate.lead_time_days
+        .map(|days| days.max(0) as u32)
+        .unwrap_or(settings.default_lead_time_days)
+}
+
+/// A product is reordered once what it has and has on order falls to the
+/// larger of its reorder level and its sales over the lead time plus safety
+/// stock. The order brings it back up to that point plus `cover_days` of
+/// sales, or just above the point for a product that is not selling.
+pub fn suggest(candidate: &ReorderCandidate, settings: &ReorderSettings) -> Option<ReorderSuggestion> {
+    let daily = candidate.units_sold as f64 / settings.window_days.max(1) as f64;
+    if daily == 0.0 && candidate.reorder_level <= 0 {
+        return None;
+    }
+
+    let safety_stock = units_for(daily, settings.safety_stock_days);
+    let reorder_point = candidate.reorder_level.max(units_for(daily, lead_time(candidate, settings)) + safety_stock);
+    let position = candidate.quantity + candidate.on_order as i32;
+    if position > reorder_point {
+        return None;
+    }
+
+    let order_up_to = reorder_point + units_for(daily, settings.cover_days).max(1);
+    Some(ReorderSuggestion {
+        product_id: candidate.product_id,
+        product_name: candidate.product_name.clone(),
+        sku: candidate.sku.clone(),
+        on_hand: candidate.quantity,
+        on_order: candidate.on_order as i32,
+        reorder_level: candidate.reorder_level,
+        average_daily_sales: daily,
+        reorder_point,
+        suggested_quantity: order_up_to - position,
+        unit_cost: candidate.cost,
+    })
+}
+
+/// Suggestions grouped by supplier in name order, unassigned products last
+pub fn plan(candidates: &[ReorderCandidate], settings: &ReorderSettings) -> Vec<SupplierReorder> {
+    let mut groups: Vec<SupplierReorder> = Vec::new();
+    for candidate in candidates {
+        let Some(suggestion) = suggest(candidate, settings) else { continue };
+        let group = match groups.iter().position(|group| group.supplier_id == candidate.supplier_id) {
+            Some(index) => &mut groups[index],
+            None => {
+                groups.push(SupplierReorder {
+                    supplier_id: candidate.supplier_id,
+                    supplier_name: candidate.supplier_name.clone().unwrap_or_else(|| "No supplier".to_string()),
+                    lead_time_days: lead_time(candidate, settings),
+                    lines: Vec::new(),
+                    total_cost: Money::ZERO,
+                });
+                groups.last_mut().unwrap()
+            }
+        };
+        group.total_cost += suggestion.unit_cost * suggestion.suggested_quantity;
+        group.lines.push(suggestion);
+    }
+
+    groups.sort_by(|a, b| {
+        (a.supplier_id.is_none(), &a.supplier_name).cmp(&(b.supplier_id.is_none(), &b.supplier_name))
+    });
+    groups
+}
+
+async fn current_plan(db: &Database) -> Result<Vec<SupplierReorder>, AppError> {
+    let settings = db.get_setting::<ReorderSettings>(REORDER_SETTINGS_KEY).await?
+        .unwrap_or_default();
+    let since = Utc::now() - Duration::days(settings.window_days as i64);
+    let candidates = db.get_reorder_candidates(since).await?;
+
+    Ok(plan(&candidates, &settings))
+}
+
+/// Start a draft purchase order for everything suggested from `supplier_id`,
+/// expected after the supplier's lead time
+pub async fn draft_suggested_order(db: &Database, user: &UserInfo, supplier_id: i64) -> Result<i64, AppError> {
+    let suggestion = current_plan(db).await?
+        .into_iter()
+        .find(|group| group.supplier_id == Some(supplier_id))
+        .ok_or_else(|| AppError::not_found(format!("Nothing needs reordering from supplier #{}", supplier_id)))?;
+
+    let order = PurchaseOrderRequest {
+        supplier_id,
+        expected_date: Some(Local::now().date_naive() + Duration::days(suggestion.lead_time_days as i64)),
+        notes: Some("Suggested reorder".to_string()),
+        lines: suggestion.lines.iter()
+            .map(|line| PurchaseOrderLineRequest {
+                product_id: line.product_id,
+                quantity: line.suggested_quantity,
+                unit_cost: line.unit_cost,
+            })
+            .collect(),
+    };
+
+    Ok(db.create_purchase_order(user, &order).await?)
+}
+
+fn validate_settings(settings: &ReorderSettings) -> Result<(), AppError> {
+    if settings.window_days == 0 || settings.window_days > MAX_DAYS {
+        return Err(AppError::validation("window_days", format!("Sales window must be between 1 and {} days", MAX_DAYS)));
+    }
+    if settings.default_lead_time_days > MAX_DAYS || settings.safety_stock_days > MAX_DAYS || settings.cover_days > MAX_DAYS {
+        return Err(AppError::validation("cover_days", format!("Periods cannot be longer than {} days", MAX_DAYS)));
+    }
+    Ok(())
+}
+
+// Reorder commands
+#[tauri::command]
+pub async fn get_reorder_settings(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<ReorderSettings, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    let settings = db.get_setting::<ReorderSettings>(REORDER_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get reorder settings"))?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn update_reorder_settings(
+    token: String,
+    settings: ReorderSettings,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_settings(&settings)?;
+
+    let db = state.db.lock().await;
+    db.set_setting(REORDER_SETTINGS_KEY, &settings).await
+        .map_err(|e| AppError::from(e).context("Failed to save reorder settings"))
+}
+
+#[tauri::command]
+pub async fn get_reorder_suggestions(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<SupplierReorder>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    current_plan(&db).await
+        .map_err(|e| e.context("Failed to get reorder suggestions"))
+}
+
+/// Turn a supplier's suggestion into a draft purchase order to review and send
+#[tauri::command]
+pub async fn create_suggested_purchase_order(
+    token: String,
+    supplier_id: i64,
+    state: State<'_, AppState>,
+) -> Result<PurchaseOrderDetails, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    let purchase_order_id = draft_suggested_order(&db, &user, supplier_id).await
+        .map_err(|e| e.context("Failed to create suggested purchase order"))?;
+
+    db.get_purchase_order(purchase_order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get purchase order"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(supplier_id: Option<i64>, quantity: i32, reorder_level: i32, units_sold: i64, on_order: i64) -> ReorderCandidate {
+        ReorderCandidate {
+            product_id: 1,
+            product_name: "Rice 5kg".to_string(),
+            sku: "RICE".to_string(),
+            supplier_id,
+            supplier_name: supplier_id.map(|id| format!("Supplier {}", id)),
+            lead_time_days: None,
+            quantity,
+            reorder_level,
+            cost: Money::from_major(4.0),
+            units_sold,
+            on_order,
+        }
+    }
+
+    #[test]
+    fn test_reorder_point_covers_lead_time_and_safety_stock() {
+        let settings = ReorderSettings::default();
+
+        // 2 a day: 14 over the lead time, 6 safety stock, 28 to cover
+        let selling = suggest(&candidate(Some(1), 20, 5, 56, 0), &settings).unwrap();
+        assert_eq!((selling.reorder_point, selling.suggested_quantity), (20, 28));
+        assert!(suggest(&candidate(Some(1), 21, 5, 56, 0), &settings).is_none());
+        assert!(suggest(&candidate(Some(1), 10, 5, 56, 11), &settings).is_none());
+
+        let slow = ReorderCandidate { lead_time_days: Some(30), ..candidate(Some(1), 0, 0, 7, 0) };
+        assert_eq!(suggest(&slow, &settings).unwrap().reorder_point, 9);
+    }
+
+    #[test]
+    fn test_products_that_do_not_sell_reorder_only_at_their_level() {
+        let settings = ReorderSettings::default();
+        assert!(suggest(&candidate(Some(1), 0, 0, 0, 0), &settings).is_none());
+        let idle = suggest(&candidate(Some(1), 3, 3, 0, 0), &settings).unwrap();
+        assert_eq!((idle.reorder_point, idle.suggested_quantity), (3, 1));
+        assert!(validate_settings(&ReorderSettings { window_days: 0, ..settings }).is_err());
+    }
+
+    #[test]
+    fn test_suggestions_are_grouped_by_supplier() {
+        let candidates = vec![
+            candidate(None, 0, 2, 0, 0),
+            candidate(Some(2), 0, 2, 0, 0),
+            candidate(Some(1), 50, 2, 0, 0),
+            candidate(Some(2), 1, 2, 0, 0),
+        ];
+        let groups = plan(&candidates, &ReorderSettings::default());
+        let summary: Vec<(Option<i64>, usize, Money)> = groups.iter().map(|g| (g.supplier_id, g.lines.len(), g.total_cost)).collect();
+        assert_eq!(summary, vec![(Some(2), 2, Money::from_major(20.0)), (None, 1, Money::from_major(12.0))]);
+        assert_eq!(groups[1].supplier_name, "No supplier");
+    }
+}
@@ -1,14 +1,135 @@
 use tauri::State;
 use crate::{AppState, models::*};
 use crate::auth::check_permission;
+use crate::error::AppError;
+use crate::money::{CurrencySettings, CURRENCY_SETTINGS_KEY};
 use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// Largest UTC offset in use anywhere (UTC+14:00 / UTC-12:00, rounded out)
+const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+/// How sales are bucketed in a sales report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl ReportPeriod {
+    /// SQL expression for the start of the bucket containing `o.created_at`,
+    /// shifted into the report's local time by the bound offset modifier
+    pub fn bucket_sql(&self) -> &'static str {
+        match self {
+            ReportPeriod::Daily => "date(o.created_at, ?)",
+            // Weeks start on Monday: jump to the coming Sunday, then back six days
+            ReportPeriod::Weekly => "date(o.created_at, ?, 'weekday 0', '-6 days')",
+            ReportPeriod::Monthly => "strftime('%Y-%m-01', o.created_at, ?)",
+        }
+    }
+}
+
+/// A validated, half-open `[start, end)` reporting window in UTC, together
+/// with the local offset used to interpret dates and group results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub offset: FixedOffset,
+}
+
+impl ReportRange {
+    /// Parse report bounds sent by the frontend.
+    ///
+    /// Plain dates (`2024-01-31`) are whole local days, so the end date is
+    /// inclusive. Timestamps with an explicit offset (RFC 3339) are taken as
+    /// exact instants and timestamps without one as local time. When no offset
+    /// is given, the machine's local offset is used.
+    pub fn parse(start_date: &str, end_date: &str, utc_offset_minutes: Option<i32>) -> Result<Self, AppError> {
+        let offset = resolve_offset(utc_offset_minutes)?;
+        let start = parse_bound(start_date, offset, false)
+            .ok_or_else(|| AppError::validation("start_date", format!("Invalid start date '{}'", start_date)))?;
+        let end = parse_bound(end_date, offset, true)
+            .ok_or_else(|| AppError::validation("end_date", format!("Invalid end date '{}'", end_date)))?;
+
+        if start >= end {
+            return Err(AppError::validation("end_date", format!("Start date '{}' must be before end date '{}'", start_date, end_date)));
+        }
+
+        Ok(Self { start, end, offset })
+    }
+
+    /// The current local day
+    pub fn today(utc_offset_minutes: Option<i32>) -> Result<Self, AppError> {
+        let offset = resolve_offset(utc_offset_minutes)?;
+        let today = Utc::now().with_timezone(&offset).date_naive();
+        let start = local_midnight(today, offset)
+            .ok_or_else(|| AppError::internal("Invalid local date"))?;
+
+        Ok(Self { start, end: start + Duration::days(1), offset })
+    }
+
+    /// SQLite date modifier that shifts UTC timestamps into local time
+    pub fn offset_modifier(&self) -> String {
+        format!("{:+} minutes", self.offset.local_minus_utc() / 60)
+    }
+
+    /// Lower bound in the format SQLite's `datetime()` produces
+    pub fn start_sql(&self) -> String {
+        self.start.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+
+    /// Exclusive upper bound in the format SQLite's `datetime()` produces
+    pub fn end_sql(&self) -> String {
+        self.end.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+fn resolve_offset(utc_offset_minutes: Option<i32>) -> Result<FixedOffset, AppError> {
+    let minutes = utc_offset_minutes.unwrap_or_else(|| Local::now().offset().local_minus_utc() / 60);
+    let invalid = || AppError::validation("utc_offset_minutes", format!("Invalid UTC offset of {} minutes", minutes));
+    if minutes.abs() > MAX_UTC_OFFSET_MINUTES {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(minutes * 60).ok_or_else(invalid)
+}
+
+fn local_midnight(date: NaiveDate, offset: FixedOffset) -> Option<DateTime<Utc>> {
+    offset
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn parse_bound(value: &str, offset: FixedOffset, is_end: bool) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if is_end { date.succ_opt()? } else { date };
+        return local_midnight(date, offset);
+    }
+
+    if let Ok(instant) = DateTime::parse_from_rfc3339(value) {
+        return Some(instant.with_timezone(&Utc));
+    }
+
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .and_then(|local| offset.from_local_datetime(&local).single())
+        .map(|dt| dt.with_timezone(&Utc))
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SalesReport {
     pub date: String,
-    pub total_sales: f64,
+    pub total_sales: Money,
     pub total_orders: i64,
-    pub average_order_value: f64,
+    pub average_order_value: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,13 +137,40 @@ pub struct ProductSalesReport {
     pub product_id: i64,
     pub product_name: String,
     pub quantity_sold: i64,
-    pub total_revenue: f64,
+    pub total_revenue: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromotionReport {
+    pub promotion_id: i64,
+    pub promotion_name: String,
+    pub orders: i64,
+    pub total_discount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TenderReport {
+    pub tender_type: TenderType,
+    pub payments: i64,
+    pub total_amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CashierSalesReport {
+    pub cashier_id: i64,
+    pub cashier_name: String,
+    pub orders: i64,
+    pub total_sales: Money,
+    pub average_order: Money,
+    /// Returns this user processed, whoever rang up the sale
+    pub returns: i64,
+    pub total_refunds: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InventoryReport {
     pub total_products: i64,
-    pub total_value: f64,
+    pub total_value: Money,
     pub low_stock_count: i64,
     pub expiring_soon_count: i64,
     pub categories: Vec<CategoryReport>,
@@ -32,16 +180,16 @@ pub struct InventoryReport {
 pub struct CategoryReport {
     pub category: String,
     pub product_count: i64,
-    pub total_value: f64,
+    pub total_value: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DashboardStats {
-    pub today_sales: f64,
+    pub today_sales: Money,
     pub today_orders: i64,
     pub total_products: i64,
     pub low_stock_count: i64,
-    pub total_inventory_value: f64,
+    pub total_inventory_value: Money,
     pub recent_orders: Vec<Order>,
 }
 
@@ -50,16 +198,21 @@ pub async fn get_sales_report(
     token: String,
     start_date: String,
     end_date: String,
+    period: Option<ReportPeriod>,
+    utc_offset_minutes: Option<i32>,
     state: State<'_, AppState>,
-) -> Result<Vec<SalesReport>, String> {
+) -> Result<Vec<SalesReport>, AppError> {
     // Check if user has permission to view reports
-    check_permission(&token, "reporting").await?;
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
 
     let db = state.db.lock().await;
 
-    // Get sales data grouped by date
-    let sales_data = db.get_sales_by_date_range(&start_date, &end_date).await
-        .map_err(|e| format!("Failed to get sales report: {}", e))?;
+    // Get sales data grouped by period
+    let sales_data = db.get_sales_by_date_range(&range, period.unwrap_or_default()).await
+        .map_err(|e| AppError::from(e).context("Failed to get sales report"))?;
 
     Ok(sales_data)
 }
@@ -69,33 +222,153 @@ pub async fn get_product_sales_report(
     token: String,
     start_date: String,
     end_date: String,
+    utc_offset_minutes: Option<i32>,
     state: State<'_, AppState>,
-) -> Result<Vec<ProductSalesReport>, String> {
+) -> Result<Vec<ProductSalesReport>, AppError> {
     // Check if user has permission to view reports
-    check_permission(&token, "reporting").await?;
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
 
     let db = state.db.lock().await;
 
     // Get product sales data
-    let product_sales = db.get_product_sales_by_date_range(&start_date, &end_date).await
-        .map_err(|e| format!("Failed to get product sales report: {}", e))?;
+    let product_sales = db.get_product_sales_by_date_range(&range).await
+        .map_err(|e| AppError::from(e).context("Failed to get product sales report"))?;
 
     Ok(product_sales)
 }
 
+#[tauri::command]
+pub async fn get_promotion_report(
+    token: String,
+    start_date: String,
+    end_date: String,
+    utc_offset_minutes: Option<i32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<PromotionReport>, AppError> {
+    // Check if user has permission to view reports
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
+
+    let db = state.db.lock().await;
+
+    // Get discount given per promotion
+    let promotions = db.get_promotion_sales_by_date_range(&range).await
+        .map_err(|e| AppError::from(e).context("Failed to get promotion report"))?;
+
+    Ok(promotions)
+}
+
+#[tauri::command]
+pub async fn get_tender_report(
+    token: String,
+    start_date: String,
+    end_date: String,
+    utc_offset_minutes: Option<i32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<TenderReport>, AppError> {
+    // Check if user has permission to view reports
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
+
+    let db = state.db.lock().await;
+
+    // Get revenue per tender type
+    let tenders = db.get_tender_sales_by_date_range(&range).await
+        .map_err(|e| AppError::from(e).context("Failed to get tender report"))?;
+
+    Ok(tenders)
+}
+
+#[tauri::command]
+pub async fn get_cashier_sales_report(
+    token: String,
+    start_date: String,
+    end_date: String,
+    utc_offset_minutes: Option<i32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CashierSalesReport>, AppError> {
+    // Check if user has permission to view reports
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
+
+    let db = state.db.lock().await;
+
+    // Get sales and refunds per cashier
+    let cashiers = db.get_cashier_sales_by_date_range(&range).await
+        .map_err(|e| AppError::from(e).context("Failed to get cashier sales report"))?;
+
+    Ok(cashiers)
+}
+
+/// Audit log of cash drawer opens, newest first
+#[tauri::command]
+pub async fn get_cash_drawer_events(
+    token: String,
+    start_date: String,
+    end_date: String,
+    reason: Option<CashDrawerReason>,
+    utc_offset_minutes: Option<i32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CashDrawerEvent>, AppError> {
+    // Check if user has permission to view reports
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
+
+    let db = state.db.lock().await;
+
+    let events = db.get_cash_drawer_events(&range, reason).await
+        .map_err(|e| AppError::from(e).context("Failed to get cash drawer events"))?;
+
+    Ok(events)
+}
+
+/// Log of lines sold away from their shelf price, newest first
+#[tauri::command]
+pub async fn get_price_overrides(
+    token: String,
+    start_date: String,
+    end_date: String,
+    utc_offset_minutes: Option<i32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<PriceOverride>, AppError> {
+    // Check if user has permission to view reports
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
+
+    let db = state.db.lock().await;
+
+    let overrides = db.get_price_overrides(&range).await
+        .map_err(|e| AppError::from(e).context("Failed to get price overrides"))?;
+
+    Ok(overrides)
+}
+
 #[tauri::command]
 pub async fn get_inventory_report(
     token: String,
     state: State<'_, AppState>,
-) -> Result<InventoryReport, String> {
+) -> Result<InventoryReport, AppError> {
     // Check if user has permission to view reports
-    check_permission(&token, "reporting").await?;
+    check_permission(&state, &token, "reporting").await?;
     
     let db = state.db.lock().await;
     
     // Get inventory statistics
     let inventory_report = db.get_inventory_report().await
-        .map_err(|e| format!("Failed to get inventory report: {}", e))?;
+        .map_err(|e| AppError::from(e).context("Failed to get inventory report"))?;
     
     Ok(inventory_report)
 }
@@ -103,16 +376,20 @@ pub async fn get_inventory_report(
 #[tauri::command]
 pub async fn get_dashboard_stats(
     token: String,
+    utc_offset_minutes: Option<i32>,
     state: State<'_, AppState>,
-) -> Result<DashboardStats, String> {
+) -> Result<DashboardStats, AppError> {
     // Check if user has permission to view dashboard
-    check_permission(&token, "dashboard_access").await?;
-    
+    check_permission(&state, &token, "dashboard_access").await?;
+
+    let today = ReportRange::today(utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
+
     let db = state.db.lock().await;
     
     // Get dashboard statistics
-    let dashboard_stats = db.get_dashboard_stats().await
-        .map_err(|e| format!("Failed to get dashboard stats: {}", e))?;
+    let dashboard_stats = db.get_dashboard_stats(&today).await
+        .map_err(|e| AppError::from(e).context("Failed to get dashboard stats"))?;
     
     Ok(dashboard_stats)
 }
@@ -122,23 +399,31 @@ pub async fn export_sales_report(
     token: String,
     start_date: String,
     end_date: String,
+    period: Option<ReportPeriod>,
+    utc_offset_minutes: Option<i32>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     // Check if user has permission to export reports
-    check_permission(&token, "reporting").await?;
+    check_permission(&state, &token, "reporting").await?;
+
+    let range = ReportRange::parse(&start_date, &end_date, utc_offset_minutes)
+        .map_err(|e| e.context("Invalid report range"))?;
 
     let db = state.db.lock().await;
+    let currency = db.get_setting::<CurrencySettings>(CURRENCY_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get currency settings"))?
+        .unwrap_or_default();
 
     // Get sales data
-    let sales_data = db.get_sales_by_date_range(&start_date, &end_date).await
-        .map_err(|e| format!("Failed to get sales data: {}", e))?;
+    let sales_data = db.get_sales_by_date_range(&range, period.unwrap_or_default()).await
+        .map_err(|e| AppError::from(e).context("Failed to get sales data"))?;
 
-    // Convert to CSV
-    let mut csv_content = String::from("Date,Total Sales,Total Orders,Average Order Value\n");
+    // Convert to CSV; amounts are written to the cent, in the store currency
+    let mut csv_content = String::from("Date,Currency,Total Sales,Total Orders,Average Order Value\n");
     for sale in sales_data {
         csv_content.push_str(&format!(
-            "{},{},{},{}\n",
-            sale.date, sale.total_sales, sale.total_orders, sale.average_order_value
+            "{},{},{},{},{}\n",
+            sale.date, currency.code, sale.total_sales, sale.total_orders, sale.average_order_value
         ));
     }
 
@@ -149,26 +434,111 @@ pub async fn export_sales_report(
 pub async fn export_inventory_report(
     token: String,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     // Check if user has permission to export reports
-    check_permission(&token, "reporting").await?;
+    check_permission(&state, &token, "reporting").await?;
     
     let db = state.db.lock().await;
+    let currency = db.get_setting::<CurrencySettings>(CURRENCY_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get currency settings"))?
+        .unwrap_or_default();
     
     // Get all products for inventory report
     let products = db.get_all_products().await
-        .map_err(|e| format!("Failed to get products: {}", e))?;
+        .map_err(|e| AppError::from(e).context("Failed to get products"))?;
     
     // Convert to CSV (removed expiry date to match new schema)
-    let mut csv_content = String::from("Name,SKU,Category,Quantity,Price,Cost,Total Value\n");
+    let mut csv_content = String::from("Name,SKU,Category,Quantity,Currency,Price,Cost,Total Value\n");
     for product in products {
-        let total_value = product.price * product.quantity as f64;
+        let total_value = product.price * product.quantity;
         csv_content.push_str(&format!(
-            "{},{},{},{},{},{},{}\n",
+            "{},{},{},{},{},{},{},{}\n",
             product.name, product.sku, product.category,
-            product.quantity, product.price, product.cost, total_value
+            product.quantity, currency.code, product.price, product.cost, total_value
         ));
     }
     
     Ok(csv_content)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    async fn insert_order(db: &Database, created_at: &str, total: f64, status: &str) {
+        sqlx::query(
+            "INSERT INTO orders (created_at, updated_at, customer_name, total_amount, payment_method, status) VALUES (?, ?, 'Walk-in', ?, 'cash', ?)"
+        )
+        .bind(created_at)
+        .bind(created_at)
+        .bind(Money::from_major(total))
+        .bind(status)
+        .execute(db.pool.as_ref().unwrap())
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_plain_dates_cover_whole_local_days() {
+        let range = ReportRange::parse("2024-01-01", "2024-01-31", Some(60)).unwrap();
+        assert_eq!(range.start_sql(), "2023-12-31 23:00:00");
+        assert_eq!(range.end_sql(), "2024-01-31 23:00:00");
+        assert_eq!(range.offset_modifier(), "+60 minutes");
+    }
+
+    #[test]
+    fn test_rfc3339_bounds_are_exact_instants() {
+        let range = ReportRange::parse("2024-01-01T08:00:00Z", "2024-01-01T17:30:00-05:00", Some(60)).unwrap();
+        assert_eq!(range.start_sql(), "2024-01-01 08:00:00");
+        assert_eq!(range.end_sql(), "2024-01-01 22:30:00");
+    }
+
+    #[test]
+    fn test_invalid_ranges_are_rejected() {
+        assert!(ReportRange::parse("yesterday", "2024-01-31", Some(0)).is_err());
+        assert!(ReportRange::parse("2024-02-01", "2024-01-31", Some(0)).is_err());
+        assert!(ReportRange::parse("2024-01-01", "2024-01-31", Some(15 * 60)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sales_only_count_completed_orders() {
+        let db = Database::in_memory().await.unwrap();
+        insert_order(&db, "2024-01-01 10:00:00", 10.0, "completed").await;
+        insert_order(&db, "2024-01-01 11:00:00", 30.0, "completed").await;
+        insert_order(&db, "2024-01-01 12:00:00", 99.0, "cancelled").await;
+        insert_order(&db, "2024-01-01 13:00:00", 99.0, "pending").await;
+
+        let range = ReportRange::parse("2024-01-01", "2024-01-01", Some(0)).unwrap();
+        let report = db.get_sales_by_date_range(&range, ReportPeriod::Daily).await.unwrap();
+
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].date, "2024-01-01");
+        assert_eq!(report[0].total_orders, 2);
+        assert_eq!(report[0].total_sales, Money::from_major(40.0));
+        assert_eq!(report[0].average_order_value, Money::from_major(20.0));
+    }
+
+    #[tokio::test]
+    async fn test_sales_are_grouped_in_local_time() {
+        let db = Database::in_memory().await.unwrap();
+        // 23:30 UTC on Sunday 7 Jan is already Monday 8 Jan at UTC+1
+        insert_order(&db, "2024-01-07T23:30:00+00:00", 5.0, "completed").await;
+        insert_order(&db, "2024-01-09 09:00:00", 7.0, "completed").await;
+        insert_order(&db, "2024-02-01 09:00:00", 11.0, "completed").await;
+
+        let range = ReportRange::parse("2024-01-01", "2024-02-29", Some(60)).unwrap();
+
+        let daily = db.get_sales_by_date_range(&range, ReportPeriod::Daily).await.unwrap();
+        let days: Vec<&str> = daily.iter().map(|r| r.date.as_str()).collect();
+        assert_eq!(days, vec!["2024-01-08", "2024-01-09", "2024-02-01"]);
+
+        let weekly = db.get_sales_by_date_range(&range, ReportPeriod::Weekly).await.unwrap();
+        assert_eq!(weekly[0].date, "2024-01-08");
+        assert_eq!(weekly[0].total_orders, 2);
+
+        let monthly = db.get_sales_by_date_range(&range, ReportPeriod::Monthly).await.unwrap();
+        let months: Vec<(&str, Money)> = monthly.iter().map(|r| (r.date.as_str(), r.total_sales)).collect();
+        assert_eq!(months, vec![("2024-01-01", Money::from_major(12.0)), ("2024-02-01", Money::from_major(11.0))]);
+    }
+}
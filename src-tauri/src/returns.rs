@@ -0,0 +1,136 @@
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+use crate::receipt::print_return_receipt;
+
+/// The refund and tax for returning `quantity` of a line that sold `sold`
+/// units, `returned` of which have come back already. Shares are taken
+/// cumulatively so returning every unit, in any number of returns, refunds
+/// exactly the line total and its tax.
+pub fn refund_share(line_total: Money, tax_amount: Money, sold: i32, returned: i32, quantity: i32) -> (Money, Money) {
+    let share = |amount: Money, units: i32| amount.share(units as i64, sold as i64);
+    (
+        share(line_total, returned + quantity) - share(line_total, returned),
+        share(tax_amount, returned + quantity) - share(tax_amount, returned),
+    )
+}
+
+/// A tender on the original order and how much of it can still be refunded
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefundableTender {
+    pub payment_id: i64,
+    pub tender_type: TenderType,
+    pub refundable: Money,
+}
+
+/// Spread a refund over the order's original tenders. Cards and other
+/// non-cash tenders are refunded first so a card sale is never returned as
+/// cash; within each kind the latest tender goes first. Anything the tenders
+/// cannot cover is paid in cash.
+pub fn allocate_refund(amount: Money, tenders: &[RefundableTender]) -> Vec<(Option<i64>, TenderType, Money)> {
+    let mut order: Vec<&RefundableTender> = tenders.iter().collect();
+    order.sort_by_key(|t| (t.tender_type == TenderType::Cash, std::cmp::Reverse(t.payment_id)));
+
+    let mut remaining = amount;
+    let mut refunds = Vec::new();
+
+    for tender in order {
+        if !remaining.is_positive() {
+            break;
+        }
+        let take = remaining.min(tender.refundable);
+        if take.is_positive() {
+            refunds.push((Some(tender.payment_id), tender.tender_type, take));
+            remaining -= take;
+        }
+    }
+
+    if remaining.is_positive() {
+        refunds.push((None, TenderType::Cash, remaining));
+    }
+
+    refunds
+}
+
+// Returns commands
+#[tauri::command]
+pub async fn create_return(
+    token: String,
+    return_data: CreateReturnRequest,
+    state: State<'_, AppState>,
+) -> Result<ReturnDetails, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    let return_id = db.create_return(&user, &return_data).await
+        .map_err(|e| AppError::from(e).context("Failed to process return"))?;
+
+    db.get_return(return_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get return"))
+}
+
+#[tauri::command]
+pub async fn get_order_returns(
+    token: String,
+    order_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<ReturnDetails>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_order_returns(order_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get returns"))
+}
+
+/// Print the return receipt on the configured receipt printer
+#[tauri::command]
+pub async fn print_return(
+    token: String,
+    return_id: i64,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    print_return_receipt(&db, return_id).await
+        .map_err(|e| e.context("Failed to print return receipt"))?;
+
+    Ok("Return receipt printed successfully".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    #[test]
+    fn test_partial_returns_add_up_to_the_line() {
+        // 3 units for 10.00 including 1.67 tax
+        let first = refund_share(money(10.0), money(1.67), 3, 0, 1);
+        let second = refund_share(money(10.0), money(1.67), 3, 1, 1);
+        let third = refund_share(money(10.0), money(1.67), 3, 2, 1);
+        assert_eq!(first, (money(3.33), money(0.56)));
+        assert_eq!(second, (money(3.34), money(0.55)));
+        assert_eq!(first.0 + second.0 + third.0, money(10.0));
+        assert_eq!(first.1 + second.1 + third.1, money(1.67));
+    }
+
+    #[test]
+    fn test_refunds_go_to_cards_before_cash() {
+        let tenders = [
+            RefundableTender { payment_id: 1, tender_type: TenderType::Cash, refundable: money(4.0) },
+            RefundableTender { payment_id: 2, tender_type: TenderType::CreditCard, refundable: money(6.0) },
+        ];
+        assert_eq!(allocate_refund(money(5.0), &tenders), vec![(Some(2), TenderType::CreditCard, money(5.0))]);
+        assert_eq!(allocate_refund(money(8.0), &tenders), vec![
+            (Some(2), TenderType::CreditCard, money(6.0)),
+            (Some(1), TenderType::Cash, money(2.0)),
+        ]);
+        assert_eq!(allocate_refund(money(1.5), &[]), vec![(None, TenderType::Cash, money(1.5))]);
+    }
+}
@@ -0,0 +1,244 @@
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+use crate::receipt::print_shift_report;
+
+const TENDERS: [TenderType; 8] = [
+    TenderType::Cash,
+    TenderType::CreditCard,
+    TenderType::DebitCard,
+    TenderType::MobilePayment,
+    TenderType::LoyaltyPoints,
+    TenderType::GiftCard,
+    TenderType::StoreCredit,
+    TenderType::Other,
+];
+
+/// Expected takings per tender over a shift, compared with what was counted.
+///
+/// Cash is expected to hold the opening float plus cash taken, less cash
+/// refunded, paid out and dropped to the safe; every other tender is expected
+/// to come to what it took less what it refunded. Cash is always listed,
+/// other tenders only when they saw activity or were counted.
+pub fn tender_lines(
+    opening_float: Money,
+    payments: &[(TenderType, Money)],
+    refunds: &[(TenderType, Money)],
+    cash_out: Money,
+    counts: Option<&[TenderCount]>,
+) -> Vec<ShiftTenderLine> {
+    let total = |rows: &[(TenderType, Money)], tender: TenderType| {
+        rows.iter().filter(|(t, _)| *t == tender).map(|(_, amount)| *amount).sum::<Money>()
+    };
+
+    TENDERS
+        .iter()
+        .filter_map(|&tender_type| {
+            let paid = total(payments, tender_type);
+            let refunded = total(refunds, tender_type);
+            let counted = counts
+                .and_then(|counts| counts.iter().find(|c| c.tender_type == tender_type))
+                .map(|c| c.counted);
+
+            let is_cash = tender_type == TenderType::Cash;
+            if !is_cash && paid.is_zero() && refunded.is_zero() && counted.is_none() {
+                return None;
+            }
+
+            let expected = if is_cash {
+                opening_float + paid - refunded - cash_out
+            } else {
+                paid - refunded
+            };
+
+            Some(ShiftTenderLine {
+                tender_type,
+                payments: paid,
+                refunds: refunded,
+                expected,
+                counted,
+                variance: counted.map(|counted| counted - expected),
+            })
+        })
+        .collect()
+}
+
+fn validate_counts(counts: &[TenderCount]) -> Result<(), AppError> {
+    let mut seen = std::collections::HashSet::new();
+    if !counts.iter().all(|c| seen.insert(c.tender_type)) {
+        return Err(AppError::validation("counts", "Each tender can only be counted once"));
+    }
+    if counts.iter().any(|c| c.counted.is_negative()) {
+        return Err(AppError::validation("counts", "Counted amounts cannot be negative"));
+    }
+    if !counts.iter().any(|c| c.tender_type == TenderType::Cash) {
+        return Err(AppError::validation("counts", "Count the cash drawer before closing the shift"));
+    }
+    Ok(())
+}
+
+// Shift commands
+#[tauri::command]
+pub async fn open_shift(
+    token: String,
+    shift_data: OpenShiftRequest,
+    state: State<'_, AppState>,
+) -> Result<Shift, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+    if shift_data.opening_float.is_negative() {
+        return Err(AppError::validation("opening_float", "The opening float cannot be negative"));
+    }
+
+    let db = state.db.lock().await;
+    db.open_shift(&user, &shift_data).await
+        .map_err(|e| AppError::from(e).context("Failed to open shift"))
+}
+
+/// The open shift, if there is one
+#[tauri::command]
+pub async fn get_current_shift(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Option<Shift>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_current_shift().await
+        .map_err(|e| AppError::from(e).context("Failed to get current shift"))
+}
+
+#[tauri::command]
+pub async fn record_cash_movement(
+    token: String,
+    movement: CashMovementRequest,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+    if !movement.amount.is_positive() {
+        return Err(AppError::validation("amount", "Amount must be positive"));
+    }
+    if movement.kind == CashMovementKind::Payout && movement.notes.as_deref().unwrap_or("").trim().is_empty() {
+        return Err(AppError::validation("notes", "Describe what the payout was for"));
+    }
+
+    let db = state.db.lock().await;
+    db.record_cash_movement(&user, &movement).await
+        .map_err(|e| AppError::from(e).context("Failed to record cash movement"))
+}
+
+/// Payouts and drops recorded against a shift
+#[tauri::command]
+pub async fn get_shift_cash_movements(
+    token: String,
+    shift_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<ShiftCashMovement>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_shift_cash_movements(shift_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get cash movements"))
+}
+
+/// Take a mid-shift X reading of the open shift
+#[tauri::command]
+pub async fn take_x_report(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<ShiftReportRecord, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.take_x_report(&user).await
+        .map_err(|e| AppError::from(e).context("Failed to take X report"))
+}
+
+/// Close the open shift against the counted takings and produce its Z report
+#[tauri::command]
+pub async fn close_shift(
+    token: String,
+    close_data: CloseShiftRequest,
+    state: State<'_, AppState>,
+) -> Result<ShiftReportRecord, AppError> {
+    let user = check_permission(&state, &token, "sales_management").await?;
+    validate_counts(&close_data.counts)?;
+
+    let db = state.db.lock().await;
+    db.close_shift(&user, &close_data).await
+        .map_err(|e| AppError::from(e).context("Failed to close shift"))
+}
+
+#[tauri::command]
+pub async fn get_shift_reports(
+    token: String,
+    shift_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<ShiftReportRecord>, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_shift_reports(shift_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get shift reports"))
+}
+
+/// Reprint a stored X or Z report on the receipt printer
+#[tauri::command]
+pub async fn print_shift(
+    token: String,
+    report_id: i64,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    check_permission(&state, &token, "sales_management").await?;
+
+    let db = state.db.lock().await;
+    print_shift_report(&db, report_id).await
+        .map_err(|e| e.context("Failed to print shift report"))?;
+
+    Ok("Shift report printed successfully".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    #[test]
+    fn test_cash_expected_includes_float_and_cash_out() {
+        let payments = [(TenderType::Cash, money(40.0)), (TenderType::CreditCard, money(25.5)), (TenderType::Cash, money(12.25))];
+        let refunds = [(TenderType::Cash, money(2.25))];
+        let counts = [
+            TenderCount { tender_type: TenderType::Cash, counted: money(129.0) },
+            TenderCount { tender_type: TenderType::CreditCard, counted: money(25.5) },
+        ];
+
+        let lines = tender_lines(money(100.0), &payments, &refunds, money(20.0), Some(&counts));
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].expected, lines[0].counted, lines[0].variance), (money(130.0), Some(money(129.0)), Some(money(-1.0))));
+        assert_eq!((lines[1].tender_type, lines[1].expected, lines[1].variance), (TenderType::CreditCard, money(25.5), Some(Money::ZERO)));
+
+        // An X reading has nothing counted
+        let lines = tender_lines(money(100.0), &[], &[], Money::ZERO, None);
+        assert_eq!(lines, vec![ShiftTenderLine {
+            tender_type: TenderType::Cash,
+            payments: Money::ZERO,
+            refunds: Money::ZERO,
+            expected: money(100.0),
+            counted: None,
+            variance: None,
+        }]);
+    }
+
+    #[test]
+    fn test_close_requires_a_cash_count() {
+        let card = TenderCount { tender_type: TenderType::CreditCard, counted: money(10.0) };
+        let cash = TenderCount { tender_type: TenderType::Cash, counted: money(10.0) };
+        assert!(validate_counts(std::slice::from_ref(&card)).is_err());
+        assert!(validate_counts(&[cash.clone(), cash.clone()]).is_err());
+        assert!(validate_counts(&[cash, card]).is_ok());
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// Key of the oversell policy section in `app_settings`
+pub const STOCK_POLICY_SETTINGS_KEY: &str = "stock_policy";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StockPolicySettings {
+    /// Policy for products whose category has none of its own
+    pub default_policy: StockPolicy,
+    /// Policies by product category
+    pub categories: BTreeMap<String, StockPolicy>,
+}
+
+impl StockPolicySettings {
+    /// A product's own policy, else its category's, else the store default
+    pub fn policy_for(&self, product_policy: Option<StockPolicy>, category: &str) -> StockPolicy {
+        product_policy
+            .or_else(|| self.categories.get(category).copied())
+            .unwrap_or(self.default_policy)
+    }
+}
+
+/// How a sale of one product stands against the stock free to sell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StockCheck {
+    InStock,
+    /// The sale must be refused
+    Short,
+    /// The sale goes ahead but must be reviewed
+    Oversold,
+}
+
+pub fn check_stock(policy: StockPolicy, requested: i32, available: i32) -> StockCheck {
+    if requested <= available {
+        StockCheck::InStock
+    } else if policy == StockPolicy::Block {
+        StockCheck::Short
+    } else {
+        StockCheck::Oversold
+    }
+}
+
+fn validate_settings(settings: &StockPolicySettings) -> Result<(), AppError> {
+    if settings.categories.keys().any(|category| category.trim().is_empty()) {
+        return Err(AppError::validation("categories", "Category names cannot be blank"));
+    }
+    Ok(())
+}
+
+// Oversell policy commands
+#[tauri::command]
+pub async fn get_stock_policy_settings(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<StockPolicySettings, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    let settings = db.get_setting::<StockPolicySettings>(STOCK_POLICY_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get stock policy settings"))?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn update_stock_policy_settings(
+    token: String,
+    settings: StockPolicySettings,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_settings(&settings)?;
+
+    let db = state.db.lock().await;
+    db.set_setting(STOCK_POLICY_SETTINGS_KEY, &settings).await
+        .map_err(|e| AppError::from(e).context("Failed to save stock policy settings"))
+}
+
+/// Sales that went beyond stock, oldest first. Without `include_reviewed`
+/// only those still awaiting a manager are listed.
+#[tauri::command]
+pub async fn get_oversold_lines(
+    token: String,
+    order_id: Option<i64>,
+    include_reviewed: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<OversoldLine>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_oversold_lines(order_id, include_reviewed.unwrap_or(false)).await
+        .map_err(|e| AppError::from(e).context("Failed to get oversold lines"))
+}
+
+#[tauri::command]
+pub async fn review_oversold_line(
+    token: String,
+    line_id: i64,
+    notes: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.review_oversold_line(&user, line_id, notes.as_deref().unwrap_or("").trim()).await
+        .map_err(|e| AppError::from(e).context("Failed to review oversold line"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_product_policy_wins_over_category_and_default() {
+        let settings = StockPolicySettings {
+            default_policy: StockPolicy::Warn,
+            categories: BTreeMap::from([("bakery".to_string(), StockPolicy::Backorder)]),
+        };
+        assert_eq!(settings.policy_for(Some(StockPolicy::Block), "bakery"), StockPolicy::Block);
+        assert_eq!(settings.policy_for(None, "bakery"), StockPolicy::Backorder);
+        assert_eq!(settings.policy_for(None, "grocery"), StockPolicy::Warn);
+        assert_eq!(StockPolicySettings::default().policy_for(None, "grocery"), StockPolicy::Block);
+    }
+
+    #[test]
+    fn test_only_blocked_shortfalls_are_refused() {
+        assert_eq!(check_stock(StockPolicy::Block, 3, 3), StockCheck::InStock);
+        assert_eq!(check_stock(StockPolicy::Block, 4, 3), StockCheck::Short);
+        assert_eq!(check_stock(StockPolicy::Warn, 4, 3), StockCheck::Oversold);
+        assert_eq!(check_stock(StockPolicy::Backorder, 1, -2), StockCheck::Oversold);
+    }
+
+    #[test]
+    fn test_settings_are_validated() {
+        assert!(validate_settings(&StockPolicySettings::default()).is_ok());
+        let blank = StockPolicySettings {
+            categories: BTreeMap::from([(" ".to_string(), StockPolicy::Warn)]),
+            ..StockPolicySettings::default()
+        };
+        assert!(matches!(validate_settings(&blank), Err(AppError::Validation { .. })));
+    }
+}
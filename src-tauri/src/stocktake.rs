@@ -0,0 +1,379 @@
+use chrono::Local;
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+use crate::pdf::{PdfDocument, COLUMNS};
+use crate::printer::{PrinterSettings, PRINTER_SETTINGS_KEY};
+
+impl StocktakeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StocktakeStatus::Counting => "counting",
+            StocktakeStatus::Posted => "posted",
+            StocktakeStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// A stocktake is counted and then either posted or abandoned; both are final
+    pub fn can_transition_to(self, to: StocktakeStatus) -> bool {
+        use StocktakeStatus::*;
+
+        matches!((self, to), (Counting, Posted) | (Counting, Cancelled))
+    }
+
+    pub fn check_transition(self, stocktake_id: i64, to: StocktakeStatus) -> Result<(), AppError> {
+        if self.can_transition_to(to) {
+            return Ok(());
+        }
+
+        Err(AppError::invalid_transition(
+            format!("{} cannot go from {} to {}", stocktake_number(stocktake_id), self.as_str(), to.as_str()),
+            self.as_str(),
+            to.as_str(),
+        ))
+    }
+
+    /// Fails unless counts can still be entered
+    pub fn check_open(self, stocktake_id: i64) -> Result<(), AppError> {
+        if self == StocktakeStatus::Counting {
+            return Ok(());
+        }
+        Err(AppError::conflict(format!("{} is {} and can no longer be counted", stocktake_number(stocktake_id), self.as_str())))
+    }
+}
+
+/// The number a stocktake is known by on the shop floor, e.g. ST-00007
+pub fn stocktake_number(stocktake_id: i64) -> String {
+    format!("ST-{:05}", stocktake_id)
+}
+
+/// Totals over the lines counted so far
+pub fn summarize_variances(lines: &[StocktakeLine]) -> VarianceSummary {
+    let mut summary = VarianceSummary::default();
+    for line in lines {
+        let (Some(variance), Some(value)) = (line.variance, line.variance_value) else {
+            summary.lines_uncounted += 1;
+            continue;
+        };
+
+        summary.lines_counted += 1;
+        if variance > 0 {
+            summary.units_over += variance;
+            summary.value_over += value;
+        } else {
+            summary.units_short -= variance;
+            summary.value_short -= value;
+        }
+        summary.net_value += value;
+    }
+    summary
+}
+
+/// A scan counts what was scanned; a manual entry may be zero to record that
+/// none were found, or negative to correct an earlier entry
+fn validate_count(method: CountMethod, quantity: i32) -> Result<(), AppError> {
+    if method == CountMethod::Scan && quantity <= 0 {
+        return Err(AppError::validation("quantity", "A scan must count at least one unit"));
+    }
+    Ok(())
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// The variance report: every line that was off, the totals, and what was
+/// not counted, headed with `store_name`
+pub fn render_pdf(details: &StocktakeDetails, store_name: &str) -> Vec<u8> {
+    let stocktake = &details.stocktake;
+    let summary = &details.summary;
+    let mut pdf = PdfDocument::new();
+
+    pdf.bold_line(&truncate(store_name, COLUMNS));
+    pdf.bold_line(&format!("STOCKTAKE VARIANCE REPORT {}", stocktake_number(stocktake.id)));
+    pdf.line(&truncate(&format!(
+        "Started: {} by {}",
+        stocktake.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        stocktake.started_by_name,
+    ), COLUMNS));
+    match stocktake.posted_at {
+        Some(posted_at) => pdf.line(&format!("Posted: {}", posted_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))),
+        None => pdf.line(&format!("Status: {}", stocktake.status.as_str())),
+    };
+    let scope = match (&stocktake.category, &stocktake.location) {
+        (None, None) => "All products".to_string(),
+        (category, location) => [category.as_ref().map(|c| format!("Category: {}", c)), location.as_ref().map(|l| format!("Location: {}", l))]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("  "),
+    };
+    pdf.line(&truncate(&scope, COLUMNS));
+    pdf.blank();
+
+    let row = |sku: &str, product: &str, expected: &str, counted: &str, variance: &str, value: &str| {
+        format!("{:<12} {:<29} {:>8} {:>8} {:>8} {:>10}", truncate(sku, 12), truncate(product, 29), expected, counted, variance, value)
+    };
+    pdf.bold_line(&row("SKU", "Product", "Expected", "Counted", "Variance", "Value"));
+    pdf.line(&"-".repeat(COLUMNS));
+    let mut variances = 0;
+    for line in &details.lines {
+        let (Some(counted), Some(variance), Some(value)) = (line.counted_quantity, line.variance, line.variance_value) else { continue };
+        if variance == 0 {
+            continue;
+        }
+        variances += 1;
+        pdf.line(&row(
+            &line.sku,
+            &line.product_name,
+            &line.expected_quantity.to_string(),
+            &counted.to_string(),
+            &format!("{:+}", variance),
+            &value.to_string(),
+        ));
+    }
+    if variances == 0 {
+        pdf.line("No variances");
+    }
+    pdf.line(&"-".repeat(COLUMNS));
+
+    pdf.line(&format!("Products counted: {} of {}", summary.lines_counted, summary.lines_counted + summary.lines_uncounted));
+    pdf.line(&format!("Over:  {} units, {}", summary.units_over, summary.value_over));
+    pdf.line(&format!("Short: {} units, {}", summary.units_short, summary.value_short));
+    pdf.bold_line(&format!("Net variance: {}", summary.net_value));
+
+    if summary.lines_uncounted > 0 {
+        pdf.blank();
+        pdf.bold_line("Not counted");
+        for line in details.lines.iter().filter(|line| line.counted_quantity.is_none()) {
+            pdf.line(&row(&line.sku, &line.product_name, &line.expected_quantity.to_string(), "", "", ""));
+        }
+    }
+
+    if !stocktake.notes.trim().is_empty() {
+        pdf.blank();
+        pdf.line(&truncate(&format!("Notes: {}", stocktake.notes.trim()), COLUMNS));
+    }
+
+    pdf.into_bytes()
+}
+
+// Stocktake commands
+#[tauri::command]
+pub async fn get_stocktakes(
+    token: String,
+    status: Option<StocktakeStatus>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Stocktake>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_stocktakes(status).await
+        .map_err(|e| AppError::from(e).context("Failed to get stocktakes"))
+}
+
+#[tauri::command]
+pub async fn get_stocktake(
+    token: String,
+    stocktake_id: i64,
+    state: State<'_, AppState>,
+) -> Result<StocktakeDetails, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_stocktake(stocktake_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get stocktake"))
+}
+
+/// Freeze stock for a full count, or a partial one by category or location
+#[tauri::command]
+pub async fn start_stocktake(
+    token: String,
+    request: StocktakeRequest,
+    state: State<'_, AppState>,
+) -> Result<StocktakeDetails, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    let stocktake_id = db.create_stocktake(&user, &request).await
+        .map_err(|e| AppError::from(e).context("Failed to start stocktake"))?;
+
+    db.get_stocktake(stocktake_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get stocktake"))
+}
+
+/// Count a scanned barcode, once or `quantity` times
+#[tauri::command]
+pub async fn scan_stocktake_item(
+    token: String,
+    stocktake_id: i64,
+    barcode: String,
+    quantity: Option<i32>,
+    device_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<StocktakeLine, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+    let quantity = quantity.unwrap_or(1);
+    validate_count(CountMethod::Scan, quantity)?;
+
+    let db = state.db.lock().await;
+    // For now, treat barcode as SKU
+    let product = db.get_product_by_sku(barcode.trim()).await
+        .map_err(|e| AppError::from(e).context("Failed to process barcode"))?
+        .ok_or_else(|| AppError::not_found(format!("No product has the barcode {}", barcode.trim())))?;
+
+    db.record_stocktake_count(&user, stocktake_id, product.id, quantity, CountMethod::Scan, device_id.as_deref().unwrap_or("").trim()).await
+        .map_err(|e| AppError::from(e).context("Failed to record count"))
+}
+
+/// Add a hand count to a product's tally
+#[tauri::command]
+pub async fn count_stocktake_item(
+    token: String,
+    stocktake_id: i64,
+    product_id: i64,
+    quantity: i32,
+    device_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<StocktakeLine, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+    validate_count(CountMethod::Manual, quantity)?;
+
+    let db = state.db.lock().await;
+    db.record_stocktake_count(&user, stocktake_id, product_id, quantity, CountMethod::Manual, device_id.as_deref().unwrap_or("").trim()).await
+        .map_err(|e| AppError::from(e).context("Failed to record count"))
+}
+
+#[tauri::command]
+pub async fn get_stocktake_counts(
+    token: String,
+    stocktake_id: i64,
+    product_id: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<StocktakeCount>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_stocktake_counts(stocktake_id, product_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get stocktake counts"))
+}
+
+/// Adjust stock by the variances counted, in one go
+#[tauri::command]
+pub async fn post_stocktake(
+    token: String,
+    stocktake_id: i64,
+    state: State<'_, AppState>,
+) -> Result<StocktakeDetails, AppError> {
+    let user = check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.post_stocktake(&user, stocktake_id).await
+        .map_err(|e| AppError::from(e).context("Failed to post stocktake"))?;
+
+    db.get_stocktake(stocktake_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get stocktake"))
+}
+
+#[tauri::command]
+pub async fn cancel_stocktake(
+    token: String,
+    stocktake_id: i64,
+    state: State<'_, AppState>,
+) -> Result<StocktakeDetails, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.cancel_stocktake(stocktake_id).await
+        .map_err(|e| AppError::from(e).context("Failed to cancel stocktake"))?;
+
+    db.get_stocktake(stocktake_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get stocktake"))
+}
+
+/// The variance report PDF's bytes, headed with the store name from the receipt template
+#[tauri::command]
+pub async fn export_stocktake_report_pdf(
+    token: String,
+    stocktake_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    let details = db.get_stocktake(stocktake_id).await
+        .map_err(|e| AppError::from(e).context("Failed to get stocktake"))?;
+    let printer = db.get_setting::<PrinterSettings>(PRINTER_SETTINGS_KEY).await
+        .map_err(|e| AppError::from(e).context("Failed to get printer settings"))?
+        .unwrap_or_default();
+    let store_name = printer.template.header_lines.first().cloned().unwrap_or_default();
+
+    Ok(render_pdf(&details, &store_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    fn line(expected: i32, counted: Option<i32>, unit_cost: f64) -> StocktakeLine {
+        let variance = counted.map(|counted| counted - expected);
+        StocktakeLine {
+            id: 1,
+            stocktake_id: 1,
+            product_id: 1,
+            product_name: "Rice 5kg".to_string(),
+            sku: "RICE".to_string(),
+            category: "grocery".to_string(),
+            location: Some("Aisle 3".to_string()),
+            expected_quantity: expected,
+            unit_cost: money(unit_cost),
+            counted_quantity: counted,
+            variance,
+            variance_value: variance.map(|variance| money(unit_cost) * variance),
+        }
+    }
+
+    #[test]
+    fn test_stocktakes_are_posted_or_cancelled_once() {
+        use StocktakeStatus::*;
+
+        assert!(Counting.can_transition_to(Posted));
+        assert!(Counting.can_transition_to(Cancelled));
+        for to in [Counting, Posted, Cancelled] {
+            assert!(!Posted.can_transition_to(to));
+            assert!(!Cancelled.can_transition_to(to));
+        }
+        assert!(matches!(Posted.check_transition(3, Cancelled), Err(AppError::InvalidTransition { .. })));
+        assert!(matches!(Posted.check_open(3), Err(AppError::Conflict { .. })));
+        assert_eq!(stocktake_number(3), "ST-00003");
+    }
+
+    #[test]
+    fn test_variances_are_summed_by_direction() {
+        let lines = vec![line(10, Some(12), 1.5), line(10, Some(7), 2.0), line(4, Some(4), 9.0), line(5, None, 3.0)];
+        let summary = summarize_variances(&lines);
+        assert_eq!(summary, VarianceSummary {
+            lines_counted: 3,
+            lines_uncounted: 1,
+            units_over: 2,
+            value_over: money(3.0),
+            units_short: 3,
+            value_short: money(6.0),
+            net_value: money(-3.0),
+        });
+    }
+
+    #[test]
+    fn test_counts_are_validated() {
+        assert!(validate_count(CountMethod::Scan, 1).is_ok());
+        assert!(validate_count(CountMethod::Scan, 0).is_err());
+        assert!(validate_count(CountMethod::Manual, 0).is_ok());
+        assert!(validate_count(CountMethod::Manual, -2).is_ok());
+    }
+}
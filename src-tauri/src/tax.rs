@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{AppState, models::*};
+use crate::auth::check_permission;
+use crate::error::AppError;
+
+/// A rate as the engine applies it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxRule {
+    pub tax_rate_id: Option<i64>,
+    pub name: String,
+    /// Percentage, e.g. 20.0 for 20%
+    pub rate: f64,
+    pub is_compound: bool,
+}
+
+impl From<&TaxRate> for TaxRule {
+    fn from(rate: &TaxRate) -> Self {
+        TaxRule {
+            tax_rate_id: Some(rate.id),
+            name: rate.name.clone(),
+            rate: rate.rate,
+            is_compound: rate.is_compound,
+        }
+    }
+}
+
+/// Tax charged by one rule on one line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineTax {
+    pub tax_rate_id: Option<i64>,
+    pub name: String,
+    pub rate: f64,
+    pub is_compound: bool,
+    pub taxable_amount: Money,
+    pub tax_amount: Money,
+}
+
+/// A taxed order line; `net_amount + tax_amount == line_total` exactly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineTotals {
+    pub net_amount: Money,
+    pub tax_amount: Money,
+    pub line_total: Money,
+    pub taxes: Vec<LineTax>,
+}
+
+/// Stack the rules on a net amount in minor units, without rounding
+fn stacked_taxes(net: f64, rules: &[TaxRule]) -> Vec<(f64, f64)> {
+    let mut charged = 0.0;
+    rules
+        .iter()
+        .map(|rule| {
+            let taxable = if rule.is_compound { net + charged } else { net };
+            let tax = taxable * rule.rate / 100.0;
+            charged += tax;
+            (taxable, tax)
+        })
+        .collect()
+}
+
+/// Tax one line of `amount` (unit price times quantity).
+///
+/// With `prices_include_tax` the amount is the gross the customer pays and
+/// the net is backed out of it; otherwise the amount is net and tax is added.
+/// Every tax is rounded per line; for inclusive prices any rounding cent is
+/// absorbed by the last rate so the printed total never drifts from the shelf price.
+pub fn calculate_line(amount: Money, rules: &[TaxRule], prices_include_tax: bool) -> LineTotals {
+    let net_amount = if prices_include_tax {
+        let multiplier = 1.0 + stacked_taxes(1.0, rules).iter().map(|(_, tax)| tax).sum::<f64>();
+        amount.scale(1.0 / multiplier)
+    } else {
+        amount
+    };
+
+    let mut taxes: Vec<LineTax> = rules
+        .iter()
+        .zip(stacked_taxes(net_amount.minor() as f64, rules))
+        .map(|(rule, (taxable, tax))| LineTax {
+            tax_rate_id: rule.tax_rate_id,
+            name: rule.name.clone(),
+            rate: rule.rate,
+            is_compound: rule.is_compound,
+            taxable_amount: Money::nearest(taxable),
+            tax_amount: Money::nearest(tax),
+        })
+        .collect();
+
+    let mut tax_amount: Money = taxes.iter().map(|t| t.tax_amount).sum();
+
+    if prices_include_tax {
+        let difference = amount - net_amount - tax_amount;
+        if let Some(last) = taxes.last_mut() {
+            last.tax_amount += difference;
+            tax_amount += difference;
+        }
+    }
+
+    LineTotals {
+        net_amount,
+        tax_amount,
+        line_total: net_amount + tax_amount,
+        taxes,
+    }
+}
+
+fn validate_rate(rate: &TaxRateRequest) -> Result<(), AppError> {
+    if rate.name.trim().is_empty() {
+        return Err(AppError::validation("name", "Tax rate name is required"));
+    }
+    if !(0.0..=100.0).contains(&rate.rate) {
+        return Err(AppError::validation("rate", "Tax rate must be between 0 and 100 percent"));
+    }
+    Ok(())
+}
+
+fn validate_class(class: &TaxClassRequest) -> Result<(), AppError> {
+    if class.name.trim().is_empty() {
+        return Err(AppError::validation("name", "Tax class name is required"));
+    }
+    let mut seen = std::collections::HashSet::new();
+    if !class.rate_ids.iter().all(|id| seen.insert(*id)) {
+        return Err(AppError::validation("rate_ids", "A tax rate can only appear once in a class"));
+    }
+    Ok(())
+}
+
+// Tax configuration commands
+#[tauri::command]
+pub async fn get_tax_rates(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<TaxRate>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_tax_rates().await
+        .map_err(|e| AppError::from(e).context("Failed to get tax rates"))
+}
+
+#[tauri::command]
+pub async fn create_tax_rate(
+    token: String,
+    rate_data: TaxRateRequest,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_rate(&rate_data)?;
+
+    let db = state.db.lock().await;
+    db.create_tax_rate(&rate_data).await
+        .map_err(|e| AppError::from(e).context("Failed to create tax rate"))
+}
+
+#[tauri::command]
+pub async fn update_tax_rate(
+    token: String,
+    rate_id: i64,
+    rate_data: TaxRateRequest,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_rate(&rate_data)?;
+
+    let db = state.db.lock().await;
+    db.update_tax_rate(rate_id, &rate_data).await
+        .map_err(|e| AppError::from(e).context("Failed to update tax rate"))
+}
+
+#[tauri::command]
+pub async fn get_tax_classes(
+    token: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<TaxClass>, AppError> {
+    check_permission(&state, &token, "inventory_management").await?;
+
+    let db = state.db.lock().await;
+    db.get_tax_classes().await
+        .map_err(|e| AppError::from(e).context("Failed to get tax classes"))
+}
+
+#[tauri::command]
+pub async fn create_tax_class(
+    token: String,
+    class_data: TaxClassRequest,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_class(&class_data)?;
+
+    let db = state.db.lock().await;
+    db.create_tax_class(&class_data).await
+        .map_err(|e| AppError::from(e).context("Failed to create tax class"))
+}
+
+#[tauri::command]
+pub async fn update_tax_class(
+    token: String,
+    class_id: i64,
+    class_data: TaxClassRequest,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    check_permission(&state, &token, "system_settings").await?;
+    validate_class(&class_data)?;
+
+    let db = state.db.lock().await;
+    db.update_tax_class(class_id, &class_data).await
+        .map_err(|e| AppError::from(e).context("Failed to update tax class"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, rate: f64, is_compound: bool) -> TaxRule {
+        TaxRule { tax_rate_id: None, name: name.to_string(), rate, is_compound }
+    }
+
+    fn money(amount: f64) -> Money {
+        Money::from_major(amount)
+    }
+
+    #[test]
+    fn test_exclusive_prices_add_tax() {
+        let line = calculate_line(money(4.99) * 3, &[rule("VAT", 20.0, false)], false);
+        assert_eq!(line.net_amount, money(14.97));
+        assert_eq!(line.tax_amount, money(2.99));
+        assert_eq!(line.line_total, money(17.96));
+    }
+
+    #[test]
+    fn test_inclusive_prices_back_out_tax() {
+        let line = calculate_line(money(10.0), &[rule("VAT", 20.0, false)], true);
+        assert_eq!(line.net_amount, money(8.33));
+        assert_eq!(line.tax_amount, money(1.67));
+        assert_eq!(line.line_total, money(10.0));
+    }
+
+    #[test]
+    fn test_compound_rates_stack_on_earlier_taxes() {
+        let rules = [rule("GST", 5.0, false), rule("QST", 9.975, true)];
+        let line = calculate_line(money(100.0), &rules, false);
+        assert_eq!(line.taxes[0].tax_amount, money(5.0));
+        assert_eq!(line.taxes[1].taxable_amount, money(105.0));
+        assert_eq!(line.taxes[1].tax_amount, money(10.47));
+        assert_eq!(line.line_total, money(115.47));
+
+        // The same gross, inclusive, must come back to the same split
+        let line = calculate_line(money(115.47), &rules, true);
+        assert_eq!(line.net_amount, money(100.0));
+        assert_eq!(line.tax_amount, money(15.47));
+    }
+
+    #[test]
+    fn test_inclusive_rounding_never_changes_the_total() {
+        let rules = [rule("A", 7.5, false), rule("B", 2.5, false)];
+        for cents in 1..2000 {
+            let amount = Money::from_minor(cents);
+            let line = calculate_line(amount, &rules, true);
+            assert_eq!(line.line_total, amount);
+            assert_eq!(line.taxes.iter().map(|t| t.tax_amount).sum::<Money>(), line.tax_amount);
+        }
+    }
+
+    #[test]
+    fn test_untaxed_lines() {
+        let line = calculate_line(money(2.5), &[], true);
+        assert_eq!((line.net_amount, line.tax_amount, line.line_total), (money(2.5), Money::ZERO, money(2.5)));
+        assert!(line.taxes.is_empty());
+    }
+}
@@ -1,18 +1,19 @@
 use tauri::State;
 use crate::{AppState, models::*};
 use crate::auth::check_permission;
+use crate::error::AppError;
 
 #[tauri::command]
 pub async fn get_users(
     token: String,
     state: State<'_, AppState>,
-) -> Result<Vec<User>, String> {
+) -> Result<Vec<User>, AppError> {
     // Check if user has permission to manage users
-    check_permission(&token, "user_management").await?;
+    check_permission(&state, &token, "user_management").await?;
     
     let db = state.db.lock().await;
     db.get_all_users().await
-        .map_err(|e| format!("Failed to get users: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get users"))
 }
 
 #[tauri::command]
@@ -23,13 +24,13 @@ pub async fn create_user(
     role: String,
     password: String,
     state: State<'_, AppState>,
-) -> Result<i64, String> {
+) -> Result<i64, AppError> {
     // Check if user has permission to manage users
-    check_permission(&token, "user_management").await?;
+    check_permission(&state, &token, "user_management").await?;
     
     let db = state.db.lock().await;
     db.create_user(&email, &full_name, &role, &password).await
-        .map_err(|e| format!("Failed to create user: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to create user"))
 }
 
 #[tauri::command]
@@ -40,13 +41,13 @@ pub async fn update_user(
     full_name: String,
     role: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     // Check if user has permission to manage users
-    check_permission(&token, "user_management").await?;
+    check_permission(&state, &token, "user_management").await?;
     
     let db = state.db.lock().await;
     db.update_user(user_id, &email, &full_name, &role).await
-        .map_err(|e| format!("Failed to update user: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to update user"))
 }
 
 #[tauri::command]
@@ -54,37 +55,37 @@ pub async fn delete_user(
     token: String,
     user_id: i64,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     // Check if user has permission to manage users
-    check_permission(&token, "user_management").await?;
+    check_permission(&state, &token, "user_management").await?;
     
     let db = state.db.lock().await;
     db.delete_user(user_id).await
-        .map_err(|e| format!("Failed to delete user: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to delete user"))
 }
 
 #[tauri::command]
 pub async fn get_roles(
     token: String,
     state: State<'_, AppState>,
-) -> Result<Vec<Role>, String> {
+) -> Result<Vec<Role>, AppError> {
     // Check if user has permission to manage users
-    check_permission(&token, "user_management").await?;
+    check_permission(&state, &token, "user_management").await?;
     
     let db = state.db.lock().await;
     db.get_all_roles().await
-        .map_err(|e| format!("Failed to get roles: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get roles"))
 }
 
 #[tauri::command]
 pub async fn get_permissions(
     token: String,
     state: State<'_, AppState>,
-) -> Result<Vec<Permission>, String> {
+) -> Result<Vec<Permission>, AppError> {
     // Check if user has permission to manage users
-    check_permission(&token, "user_management").await?;
+    check_permission(&state, &token, "user_management").await?;
     
     let db = state.db.lock().await;
     db.get_all_permissions().await
-        .map_err(|e| format!("Failed to get permissions: {}", e))
+        .map_err(|e| AppError::from(e).context("Failed to get permissions"))
 }
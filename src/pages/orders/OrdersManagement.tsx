@@ -63,6 +63,10 @@ const OrdersManagement: React.FC = () => {
       const quantity = item.quantity || 0;
       return sum + (price * quantity);
     }, 0);
+    // Tax is computed by the backend; orders created before it have none
+    const taxTotal = orderData.tax_total || 0;
+    const discountTotal = orderData.discount_total || 0;
+    const subtotal = orderData.subtotal ?? total - taxTotal;
 
     return `
       <div style="font-family: 'Courier New', monospace; width: 320px; margin: 0 auto; padding: 20px; background: white;">
@@ -133,13 +137,18 @@ const OrdersManagement: React.FC = () => {
         </div>
 
         <div style="border-top: 2px solid #000; padding-top: 10px;">
+          ${discountTotal > 0 ? `
+          <div style="display: flex; justify-content: space-between; font-size: 12px; margin: 3px 0;">
+            <span>Discounts:</span>
+            <span>-$${discountTotal.toFixed(2)}</span>
+          </div>` : ''}
           <div style="display: flex; justify-content: space-between; font-size: 12px; margin: 3px 0;">
             <span>Subtotal:</span>
-            <span>$${total.toFixed(2)}</span>
+            <span>$${subtotal.toFixed(2)}</span>
           </div>
           <div style="display: flex; justify-content: space-between; font-size: 12px; margin: 3px 0;">
             <span>Tax:</span>
-            <span>$0.00</span>
+            <span>$${taxTotal.toFixed(2)}</span>
           </div>
           <div style="display: flex; justify-content: space-between; font-size: 16px; font-weight: bold; margin: 8px 0; border-top: 1px solid #000; padding-top: 5px;">
             <span>TOTAL:</span>
@@ -397,8 +406,10 @@ const OrdersManagement: React.FC = () => {
       case 'pending':
         return 'text-yellow-600 bg-yellow-100 dark:text-yellow-400 dark:bg-yellow-900/20';
       case 'cancelled':
+      case 'voided':
         return 'text-red-600 bg-red-100 dark:text-red-400 dark:bg-red-900/20';
       case 'refunded':
+      case 'partially_refunded':
         return 'text-purple-600 bg-purple-100 dark:text-purple-400 dark:bg-purple-900/20';
       default:
         return 'text-gray-600 bg-gray-100 dark:text-gray-400 dark:bg-gray-900/20';
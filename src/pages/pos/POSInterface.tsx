@@ -5,7 +5,7 @@ import { posService } from '../../services/posService';
 import { useToast } from '../../hooks/useToast';
 import Button from '../../components/ui/Button';
 import Card from '../../components/ui/Card';
-import { Product } from '../../types';
+import { Product, TenderType } from '../../types';
 import toast from 'react-hot-toast';
 
 const POSInterface: React.FC = () => {
@@ -58,6 +58,10 @@ const POSInterface: React.FC = () => {
       const quantity = item.quantity || 0;
       return sum + (price * quantity);
     }, 0);
+    // Tax is computed by the backend; orders created before it have none
+    const taxTotal = orderData.tax_total || 0;
+    const discountTotal = orderData.discount_total || 0;
+    const subtotal = orderData.subtotal ?? total - taxTotal;
 
     return `
       <div style="font-family: 'Courier New', monospace; width: 320px; margin: 0 auto; padding: 20px; background: white;">
@@ -130,13 +134,18 @@ const POSInterface: React.FC = () => {
         </div>
 
         <div style="border-top: 2px solid #000; padding-top: 10px;">
+          ${discountTotal > 0 ? `
+          <div style="display: flex; justify-content: space-between; font-size: 12px; margin: 3px 0;">
+            <span>Discounts:</span>
+            <span>-$${discountTotal.toFixed(2)}</span>
+          </div>` : ''}
           <div style="display: flex; justify-content: space-between; font-size: 12px; margin: 3px 0;">
             <span>Subtotal:</span>
-            <span>$${total.toFixed(2)}</span>
+            <span>$${subtotal.toFixed(2)}</span>
           </div>
           <div style="display: flex; justify-content: space-between; font-size: 12px; margin: 3px 0;">
             <span>Tax:</span>
-            <span>$0.00</span>
+            <span>$${taxTotal.toFixed(2)}</span>
           </div>
           <div style="display: flex; justify-content: space-between; font-size: 16px; font-weight: bold; margin: 8px 0; border-top: 1px solid #000; padding-top: 5px;">
             <span>TOTAL:</span>
@@ -426,6 +435,16 @@ const POSInterface: React.FC = () => {
     try {
       setProcessing(true);
 
+      // Tender whatever is still owed with the selected payment method
+      const payments = await posService.getOrderPayments(currentOrder.id);
+      const balanceDue = currentOrder.total_amount - payments.reduce((sum, p) => sum + p.amount, 0);
+      if (balanceDue > 0.005) {
+        await posService.addOrderPayment(currentOrder.id, {
+          tender_type: paymentMethod as TenderType,
+          amount: Math.round(balanceDue * 100) / 100,
+        });
+      }
+
       const completedOrder = await posService.completeOrder(currentOrder.id);
 
       setLastOrderId(completedOrder.id || currentOrder.id);
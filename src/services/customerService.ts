@@ -0,0 +1,46 @@
+import { secureInvoke } from '../utils/apiInterceptor';
+import { Customer, CustomerHistory, CustomerRequest, LoyaltyAccount, LoyaltySettings } from '../types';
+
+export const customerService = {
+  getCustomers: async (search?: string): Promise<Customer[]> => {
+    return await secureInvoke('get_customers', { search });
+  },
+
+  getCustomer: async (customerId: number): Promise<Customer> => {
+    return await secureInvoke('get_customer', { customerId });
+  },
+
+  // Find a customer at the counter by phone number or email address
+  lookupCustomer: async (contact: string): Promise<Customer | null> => {
+    return await secureInvoke('lookup_customer', { contact });
+  },
+
+  createCustomer: async (customer: CustomerRequest): Promise<Customer> => {
+    return await secureInvoke('create_customer', { customer });
+  },
+
+  updateCustomer: async (customerId: number, customer: CustomerRequest): Promise<void> => {
+    return await secureInvoke('update_customer', { customerId, customer });
+  },
+
+  deleteCustomer: async (customerId: number): Promise<void> => {
+    return await secureInvoke('delete_customer', { customerId });
+  },
+
+  getCustomerHistory: async (customerId: number): Promise<CustomerHistory> => {
+    return await secureInvoke('get_customer_history', { customerId });
+  },
+
+  // Points balance and ledger; points are spent with the 'loyalty_points' tender
+  getLoyaltyAccount: async (customerId: number): Promise<LoyaltyAccount> => {
+    return await secureInvoke('get_loyalty_account', { customerId });
+  },
+
+  getLoyaltySettings: async (): Promise<LoyaltySettings> => {
+    return await secureInvoke('get_loyalty_settings');
+  },
+
+  updateLoyaltySettings: async (settings: LoyaltySettings): Promise<void> => {
+    return await secureInvoke('update_loyalty_settings', { settings });
+  },
+};
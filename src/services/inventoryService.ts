@@ -1,6 +1,6 @@
 import { secureInvoke } from '../utils/apiInterceptor';
 import { onlineFirstService } from './onlineFirstService';
-import { Product, Supplier, InventoryMovement, CreateProductRequest, UpdateStockRequest } from '../types';
+import { Product, Supplier, InventoryMovement, CreateProductRequest, UpdateStockRequest, StockPolicySettings, OversoldLine, PurchaseOrder, PurchaseOrderDetails, PurchaseOrderRequest, PurchaseOrderStatus, ReceivePurchaseOrderRequest, ReorderSettings, SupplierReorder, Stocktake, StocktakeCount, StocktakeDetails, StocktakeLine, StocktakeRequest, StocktakeStatus, LedgerDiscrepancy } from '../types';
 
 export const inventoryService = {
   // Product management - Online-first operations
@@ -69,4 +69,132 @@ export const inventoryService = {
       return [];
     }
   },
+
+  // Oversell policy (local only)
+  getStockPolicySettings: async (): Promise<StockPolicySettings> => {
+    return await secureInvoke('get_stock_policy_settings');
+  },
+
+  updateStockPolicySettings: async (settings: StockPolicySettings): Promise<void> => {
+    return await secureInvoke('update_stock_policy_settings', { settings });
+  },
+
+  // Sales that went beyond stock; only those awaiting review unless includeReviewed
+  getOversoldLines: async (orderId?: number, includeReviewed?: boolean): Promise<OversoldLine[]> => {
+    return await secureInvoke('get_oversold_lines', { orderId, includeReviewed });
+  },
+
+  reviewOversoldLine: async (lineId: number, notes?: string): Promise<void> => {
+    return await secureInvoke('review_oversold_line', { lineId, notes });
+  },
+
+  // Purchase orders (local only)
+  getPurchaseOrders: async (status?: PurchaseOrderStatus, supplierId?: number): Promise<PurchaseOrder[]> => {
+    return await secureInvoke('get_purchase_orders', { status, supplierId });
+  },
+
+  getPurchaseOrder: async (purchaseOrderId: number): Promise<PurchaseOrderDetails> => {
+    return await secureInvoke('get_purchase_order', { purchaseOrderId });
+  },
+
+  createPurchaseOrder: async (orderData: PurchaseOrderRequest): Promise<PurchaseOrderDetails> => {
+    return await secureInvoke('create_purchase_order', { orderData });
+  },
+
+  updatePurchaseOrder: async (purchaseOrderId: number, orderData: PurchaseOrderRequest): Promise<PurchaseOrderDetails> => {
+    return await secureInvoke('update_purchase_order', { purchaseOrderId, orderData });
+  },
+
+  sendPurchaseOrder: async (purchaseOrderId: number): Promise<PurchaseOrderDetails> => {
+    return await secureInvoke('send_purchase_order', { purchaseOrderId });
+  },
+
+  receivePurchaseOrder: async (purchaseOrderId: number, receipt: ReceivePurchaseOrderRequest): Promise<PurchaseOrderDetails> => {
+    return await secureInvoke('receive_purchase_order', { purchaseOrderId, receipt });
+  },
+
+  closePurchaseOrder: async (purchaseOrderId: number): Promise<PurchaseOrderDetails> => {
+    return await secureInvoke('close_purchase_order', { purchaseOrderId });
+  },
+
+  exportPurchaseOrderCsv: async (purchaseOrderId: number): Promise<string> => {
+    return await secureInvoke('export_purchase_order_csv', { purchaseOrderId });
+  },
+
+  // The PDF arrives as an array of bytes
+  exportPurchaseOrderPdf: async (purchaseOrderId: number): Promise<Blob> => {
+    const bytes: number[] = await secureInvoke('export_purchase_order_pdf', { purchaseOrderId });
+    return new Blob([new Uint8Array(bytes)], { type: 'application/pdf' });
+  },
+
+  // Reorder suggestions
+  setSupplierLeadTime: async (supplierId: number, leadTimeDays?: number): Promise<void> => {
+    return await secureInvoke('set_supplier_lead_time', { supplierId, leadTimeDays });
+  },
+
+  getReorderSettings: async (): Promise<ReorderSettings> => {
+    return await secureInvoke('get_reorder_settings');
+  },
+
+  updateReorderSettings: async (settings: ReorderSettings): Promise<void> => {
+    return await secureInvoke('update_reorder_settings', { settings });
+  },
+
+  getReorderSuggestions: async (): Promise<SupplierReorder[]> => {
+    return await secureInvoke('get_reorder_suggestions');
+  },
+
+  createSuggestedPurchaseOrder: async (supplierId: number): Promise<PurchaseOrderDetails> => {
+    return await secureInvoke('create_suggested_purchase_order', { supplierId });
+  },
+
+  // Stocktakes
+  getStocktakes: async (status?: StocktakeStatus): Promise<Stocktake[]> => {
+    return await secureInvoke('get_stocktakes', { status });
+  },
+
+  getStocktake: async (stocktakeId: number): Promise<StocktakeDetails> => {
+    return await secureInvoke('get_stocktake', { stocktakeId });
+  },
+
+  startStocktake: async (request: StocktakeRequest): Promise<StocktakeDetails> => {
+    return await secureInvoke('start_stocktake', { request });
+  },
+
+  scanStocktakeItem: async (stocktakeId: number, barcode: string, quantity?: number, deviceId?: string): Promise<StocktakeLine> => {
+    return await secureInvoke('scan_stocktake_item', { stocktakeId, barcode, quantity, deviceId });
+  },
+
+  // Adds to the product's tally; negative to correct a miscount
+  countStocktakeItem: async (stocktakeId: number, productId: number, quantity: number, deviceId?: string): Promise<StocktakeLine> => {
+    return await secureInvoke('count_stocktake_item', { stocktakeId, productId, quantity, deviceId });
+  },
+
+  getStocktakeCounts: async (stocktakeId: number, productId?: number): Promise<StocktakeCount[]> => {
+    return await secureInvoke('get_stocktake_counts', { stocktakeId, productId });
+  },
+
+  postStocktake: async (stocktakeId: number): Promise<StocktakeDetails> => {
+    return await secureInvoke('post_stocktake', { stocktakeId });
+  },
+
+  cancelStocktake: async (stocktakeId: number): Promise<StocktakeDetails> => {
+    return await secureInvoke('cancel_stocktake', { stocktakeId });
+  },
+
+  // The PDF arrives as an array of bytes
+  exportStocktakeReportPdf: async (stocktakeId: number): Promise<Blob> => {
+    const bytes: number[] = await secureInvoke('export_stocktake_report_pdf', { stocktakeId });
+    return new Blob([new Uint8Array(bytes)], { type: 'application/pdf' });
+  },
+
+  // Stock ledger
+  checkStockLedger: async (): Promise<LedgerDiscrepancy[]> => {
+    return await secureInvoke('check_stock_ledger');
+  },
+
+  // Sets quantities back to what the ledger adds up to
+  repairStockLedger: async (): Promise<LedgerDiscrepancy[]> => {
+    return await secureInvoke('repair_stock_ledger');
+  },
 };
@@ -1,6 +1,6 @@
 import { secureInvoke } from '../utils/apiInterceptor';
 import { onlineFirstService } from './onlineFirstService';
-import { Product, Order, OrderItem, CreateOrderRequest } from '../types';
+import { Product, Order, OrderItem, CreateOrderRequest, CashDrawerReason, OrderPayment, OrderPaymentRequest, OrderPaymentResult, CreateReturnRequest, ReturnDetails, OrderEvent, Shift, ShiftCashMovement, CashMovementKind, ShiftReportRecord, CloseShiftRequest, ParkCartRequest, ParkedCart, ParkedCartDetails, ParkedCartSettings, CurrencySettings, GiftCard, GiftCardDetails, PriceOverrideApproval } from '../types';
 
 export const posService = {
   // Product search - use online-first
@@ -38,6 +38,18 @@ export const posService = {
     return response as Order;
   },
 
+  // A manager signs off one product at one price with their own credentials;
+  // the approval token is good for one line, for a few minutes
+  approvePriceOverride: async (email: string, password: string, productId: number, price: number): Promise<PriceOverrideApproval> => {
+    return await secureInvoke('approve_price_override', { email, password, productId, price });
+  },
+
+  // Each line sold away from the shelf price uses up one approval token
+  createOrderWithOverrides: async (orderData: CreateOrderRequest, approvalTokens: string[]): Promise<Order> => {
+    const orderId: number = await secureInvoke('create_order', { orderData, approvalTokens });
+    return await onlineFirstService.orders.getById(orderId) as Order;
+  },
+
   completeOrder: async (orderId: number): Promise<Order> => {
     return await onlineFirstService.orders.complete(orderId) as Order;
   },
@@ -46,6 +58,11 @@ export const posService = {
     await onlineFirstService.orders.cancel(orderId);
   },
 
+  // Void an unpaid order, keeping the cash taken as store credit
+  cancelOrderForStoreCredit: async (orderId: number): Promise<GiftCard | null> => {
+    return await secureInvoke('cancel_order', { orderId, storeCredit: true });
+  },
+
   refundOrder: async (orderId: number): Promise<void> => {
     await onlineFirstService.orders.refund(orderId);
   },
@@ -58,6 +75,112 @@ export const posService = {
     return await secureInvoke('get_order_items', { orderId });
   },
 
+  getOrderEvents: async (orderId: number): Promise<OrderEvent[]> => {
+    return await secureInvoke('get_order_events', { orderId });
+  },
+
+  // Payments
+  addOrderPayment: async (orderId: number, payment: OrderPaymentRequest): Promise<OrderPaymentResult> => {
+    return await secureInvoke('add_order_payment', { orderId, payment });
+  },
+
+  getOrderPayments: async (orderId: number): Promise<OrderPayment[]> => {
+    return await secureInvoke('get_order_payments', { orderId });
+  },
+
+  removeOrderPayment: async (paymentId: number): Promise<void> => {
+    return await secureInvoke('remove_order_payment', { paymentId });
+  },
+
+  // Returns
+  createReturn: async (returnData: CreateReturnRequest): Promise<ReturnDetails> => {
+    return await secureInvoke('create_return', { returnData });
+  },
+
+  getOrderReturns: async (orderId: number): Promise<ReturnDetails[]> => {
+    return await secureInvoke('get_order_returns', { orderId });
+  },
+
+  printReturn: async (returnId: number): Promise<string> => {
+    return await secureInvoke('print_return', { returnId });
+  },
+
+  // Parked carts
+  parkCart: async (cartData: ParkCartRequest): Promise<ParkedCartDetails> => {
+    return await secureInvoke('park_cart', { cartData });
+  },
+
+  getParkedCarts: async (): Promise<ParkedCart[]> => {
+    return await secureInvoke('get_parked_carts');
+  },
+
+  resumeParkedCart: async (cartId: number): Promise<ParkedCartDetails> => {
+    return await secureInvoke('resume_parked_cart', { cartId });
+  },
+
+  discardParkedCart: async (cartId: number): Promise<void> => {
+    return await secureInvoke('discard_parked_cart', { cartId });
+  },
+
+  // Gift cards and store credit; spend them with the 'gift_card' or
+  // 'store_credit' tender, quoting the card's code as the reference
+  lookupGiftCard: async (code: string): Promise<GiftCardDetails> => {
+    return await secureInvoke('lookup_gift_card', { code });
+  },
+
+  getCustomerGiftCards: async (customerId: number): Promise<GiftCard[]> => {
+    return await secureInvoke('get_customer_gift_cards', { customerId });
+  },
+
+  getParkedCartSettings: async (): Promise<ParkedCartSettings> => {
+    return await secureInvoke('get_parked_cart_settings');
+  },
+
+  updateParkedCartSettings: async (settings: ParkedCartSettings): Promise<void> => {
+    return await secureInvoke('update_parked_cart_settings', { settings });
+  },
+
+  getCurrencySettings: async (): Promise<CurrencySettings> => {
+    return await secureInvoke('get_currency_settings');
+  },
+
+  updateCurrencySettings: async (settings: CurrencySettings): Promise<void> => {
+    return await secureInvoke('update_currency_settings', { settings });
+  },
+
+  // Shifts
+  openShift: async (openingFloat: number, notes?: string): Promise<Shift> => {
+    return await secureInvoke('open_shift', { shiftData: { opening_float: openingFloat, notes } });
+  },
+
+  getCurrentShift: async (): Promise<Shift | null> => {
+    return await secureInvoke('get_current_shift');
+  },
+
+  recordCashMovement: async (kind: CashMovementKind, amount: number, notes?: string): Promise<number> => {
+    return await secureInvoke('record_cash_movement', { movement: { kind, amount, notes } });
+  },
+
+  getShiftCashMovements: async (shiftId: number): Promise<ShiftCashMovement[]> => {
+    return await secureInvoke('get_shift_cash_movements', { shiftId });
+  },
+
+  takeXReport: async (): Promise<ShiftReportRecord> => {
+    return await secureInvoke('take_x_report');
+  },
+
+  closeShift: async (closeData: CloseShiftRequest): Promise<ShiftReportRecord> => {
+    return await secureInvoke('close_shift', { closeData });
+  },
+
+  getShiftReports: async (shiftId: number): Promise<ShiftReportRecord[]> => {
+    return await secureInvoke('get_shift_reports', { shiftId });
+  },
+
+  printShift: async (reportId: number): Promise<string> => {
+    return await secureInvoke('print_shift', { reportId });
+  },
+
   // Hardware integration
   processBarcodeSccan: async (barcode: string): Promise<Product | null> => {
     return await secureInvoke('process_barcode_scan', { barcode });
@@ -67,7 +190,7 @@ export const posService = {
     return await secureInvoke('print_receipt', { orderId });
   },
 
-  openCashDrawer: async (): Promise<string> => {
-    return await secureInvoke('open_cash_drawer');
+  openCashDrawer: async (reason: CashDrawerReason, orderId?: number, notes?: string): Promise<string> => {
+    return await secureInvoke('open_cash_drawer', { reason, orderId, notes });
   },
 };
@@ -1,6 +1,7 @@
 import { secureInvoke } from '../utils/apiInterceptor';
 import { onlineFirstService } from './onlineFirstService';
 import { apiClient } from './api';
+import { PriceOverride } from '../types';
 
 export interface SalesReport {
   date: string;
@@ -181,4 +182,9 @@ export const reportService = {
   exportInventoryReport: async (): Promise<string> => {
     return await secureInvoke('export_inventory_report');
   },
+
+  // Lines sold away from their shelf price, with who approved them
+  getPriceOverrides: async (startDate: string, endDate: string): Promise<PriceOverride[]> => {
+    return await secureInvoke('get_price_overrides', { startDate, endDate });
+  },
 };
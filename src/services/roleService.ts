@@ -18,8 +18,8 @@ export const roleService = {
       console.error('Failed to fetch roles:', error);
       // Return default roles if API fails
       return {
-        admin: ['user_management', 'inventory_management', 'sales_management', 'reporting', 'system_settings', 'dashboard_access'],
-        manager: ['inventory_management', 'sales_management', 'reporting', 'dashboard_access'],
+        admin: ['user_management', 'inventory_management', 'sales_management', 'reporting', 'system_settings', 'dashboard_access', 'price_override'],
+        manager: ['inventory_management', 'sales_management', 'reporting', 'dashboard_access', 'price_override'],
         cashier: ['sales_management', 'dashboard_access'],
       };
     }
@@ -34,7 +34,8 @@ export const roleService = {
       'sales_management',
       'reporting',
       'system_settings',
-      'dashboard_access'
+      'dashboard_access',
+      'price_override'
     ];
   },
 
@@ -42,8 +43,8 @@ export const roleService = {
   getRolePermissions: async (roleName: string): Promise<string[]> => {
     // Return default permissions based on role (no API call to avoid 401 errors)
     const defaultPermissions: Record<string, string[]> = {
-      admin: ['user_management', 'inventory_management', 'sales_management', 'reporting', 'system_settings', 'dashboard_access'],
-      manager: ['inventory_management', 'sales_management', 'reporting', 'dashboard_access'],
+      admin: ['user_management', 'inventory_management', 'sales_management', 'reporting', 'system_settings', 'dashboard_access', 'price_override'],
+      manager: ['inventory_management', 'sales_management', 'reporting', 'dashboard_access', 'price_override'],
       cashier: ['sales_management', 'dashboard_access'],
     };
     return defaultPermissions[roleName] || ['dashboard_access'];
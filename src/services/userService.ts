@@ -136,7 +136,8 @@ export const userService = {
           { id: 3, name: 'sales_management', description: 'Sales Management', created_at: new Date().toISOString() },
           { id: 4, name: 'reporting', description: 'Reporting', created_at: new Date().toISOString() },
           { id: 5, name: 'system_settings', description: 'System Settings', created_at: new Date().toISOString() },
-          { id: 6, name: 'dashboard_access', description: 'Dashboard Access', created_at: new Date().toISOString() }
+          { id: 6, name: 'dashboard_access', description: 'Dashboard Access', created_at: new Date().toISOString() },
+          { id: 7, name: 'price_override', description: 'Approve price overrides', created_at: new Date().toISOString() }
         ];
       }
     }
@@ -70,6 +70,11 @@ export interface Product {
   reorder_level: number;
   expiry_date?: string;
   supplier_id?: number;
+  tax_class_id?: number;
+  // Overrides the category and store oversell policy when set
+  stock_policy?: StockPolicy;
+  // Where the product is kept, e.g. an aisle or back room shelf
+  location?: string;
   last_synced_at?: string;
   created_at: string;
   updated_at: string;
@@ -86,14 +91,40 @@ export interface CreateProductRequest {
   reorder_level: number;
   expiry_date?: string;
   supplier_id?: number;
+  stock_policy?: StockPolicy;
+  location?: string;
 }
 
 export interface UpdateStockRequest {
   quantity_change: number;
-  movement_type: string;
+  // Sales, returns and receipts are only recorded through their documents
+  movement_type: 'adjustment' | 'transfer' | 'write_off';
   notes?: string;
 }
 
+// What a sale may do when it asks for more than is in stock
+export type StockPolicy = 'block' | 'warn' | 'backorder';
+
+export interface StockPolicySettings {
+  default_policy: StockPolicy;
+  categories: Record<string, StockPolicy>;
+}
+
+export interface OversoldLine {
+  id: number;
+  created_at: string;
+  order_id: number;
+  product_id: number;
+  product_name: string;
+  policy: StockPolicy;
+  requested: number;
+  available: number;
+  reviewed_at?: string;
+  reviewed_by?: number;
+  reviewer_name: string;
+  review_notes: string;
+}
+
 // Supplier Types
 export interface Supplier {
   id: number;
@@ -102,19 +133,283 @@ export interface Supplier {
   email?: string;
   phone?: string;
   address?: string;
+  lead_time_days?: number;
   last_synced_at?: string;
   created_at: string;
   updated_at: string;
 }
 
+// Purchase Order Types
+export type PurchaseOrderStatus = 'draft' | 'sent' | 'partially_received' | 'received' | 'closed';
+
+export interface PurchaseOrder {
+  id: number;
+  created_at: string;
+  updated_at: string;
+  supplier_id: number;
+  supplier_name: string;
+  status: PurchaseOrderStatus;
+  expected_date?: string;
+  notes: string;
+  currency: string;
+  created_by: number;
+  created_by_name: string;
+  sent_at?: string;
+  closed_at?: string;
+  total_cost: number;
+}
+
+export interface PurchaseOrderLine {
+  id: number;
+  purchase_order_id: number;
+  product_id: number;
+  product_name: string;
+  sku: string;
+  quantity_ordered: number;
+  quantity_received: number;
+  unit_cost: number;
+}
+
+export interface PurchaseOrderReceipt {
+  id: number;
+  created_at: string;
+  purchase_order_id: number;
+  line_id: number;
+  product_id: number;
+  batch_id: number;
+  quantity: number;
+  unit_cost: number;
+  user_id: number;
+  user_name: string;
+}
+
+export interface PurchaseOrderDetails {
+  purchase_order: PurchaseOrder;
+  supplier: Supplier;
+  lines: PurchaseOrderLine[];
+  receipts: PurchaseOrderReceipt[];
+}
+
+export interface PurchaseOrderRequest {
+  supplier_id: number;
+  expected_date?: string;
+  notes?: string;
+  lines: {
+    product_id: number;
+    quantity: number;
+    unit_cost: number;
+  }[];
+}
+
+export interface ReceivePurchaseOrderRequest {
+  lines: {
+    line_id: number;
+    quantity: number;
+    // Actual cost per unit; the line's expected cost if left out
+    unit_cost?: number;
+    batch_number?: string;
+    expiry_date?: string;
+  }[];
+}
+
+// Reorder Types
+export interface ReorderSettings {
+  window_days: number;
+  default_lead_time_days: number;
+  safety_stock_days: number;
+  cover_days: number;
+}
+
+export interface ReorderSuggestion {
+  product_id: number;
+  product_name: string;
+  sku: string;
+  on_hand: number;
+  on_order: number;
+  reorder_level: number;
+  average_daily_sales: number;
+  reorder_point: number;
+  suggested_quantity: number;
+  unit_cost: number;
+}
+
+export interface SupplierReorder {
+  // Absent for products without a supplier
+  supplier_id?: number;
+  supplier_name: string;
+  lead_time_days: number;
+  lines: ReorderSuggestion[];
+  total_cost: number;
+}
+
+// Stocktake Types
+export type StocktakeStatus = 'counting' | 'posted' | 'cancelled';
+
+export type CountMethod = 'scan' | 'manual';
+
+export interface Stocktake {
+  id: number;
+  created_at: string;
+  updated_at: string;
+  status: StocktakeStatus;
+  // Scope of a partial count; absent covers everything
+  category?: string;
+  location?: string;
+  notes: string;
+  started_by: number;
+  started_by_name: string;
+  posted_at?: string;
+  posted_by?: number;
+}
+
+export interface StocktakeLine {
+  id: number;
+  stocktake_id: number;
+  product_id: number;
+  product_name: string;
+  sku: string;
+  category: string;
+  location?: string;
+  // Frozen when the stocktake started
+  expected_quantity: number;
+  unit_cost: number;
+  // Absent until someone counts the product
+  counted_quantity?: number;
+  variance?: number;
+  variance_value?: number;
+}
+
+export interface StocktakeCount {
+  id: number;
+  created_at: string;
+  stocktake_id: number;
+  line_id: number;
+  product_id: number;
+  quantity: number;
+  method: CountMethod;
+  device_id: string;
+  user_id: number;
+  user_name: string;
+}
+
+export interface VarianceSummary {
+  lines_counted: number;
+  lines_uncounted: number;
+  units_over: number;
+  value_over: number;
+  units_short: number;
+  value_short: number;
+  net_value: number;
+}
+
+export interface StocktakeDetails {
+  stocktake: Stocktake;
+  lines: StocktakeLine[];
+  summary: VarianceSummary;
+}
+
+export interface StocktakeRequest {
+  category?: string;
+  location?: string;
+  notes?: string;
+}
+
+// Customer Types
+export interface Customer {
+  id: number;
+  created_at: string;
+  updated_at: string;
+  name: string;
+  email: string;
+  phone: string;
+  address: string;
+  notes: string;
+}
+
+export interface CustomerRequest {
+  name: string;
+  email?: string;
+  phone?: string;
+  address?: string;
+  notes?: string;
+}
+
+export interface CustomerSummary {
+  orders: number;
+  lifetime_spend: number;
+  refunded: number;
+  first_purchase_at?: string;
+  last_purchase_at?: string;
+}
+
+export interface CustomerPurchaseLine {
+  order_id: number;
+  created_at: string;
+  status: OrderStatus;
+  order_item_id: number;
+  product_id: number;
+  product_name: string;
+  sku: string;
+  quantity: number;
+  returned_quantity: number;
+  line_total: number;
+}
+
+export interface CustomerHistory {
+  customer: Customer;
+  summary: CustomerSummary;
+  purchases: CustomerPurchaseLine[];
+}
+
+// Loyalty Types
+export interface LoyaltySettings {
+  enabled: boolean;
+  points_per_unit: number;
+  point_value: number;
+  expiry_days?: number | null;
+}
+
+export type LoyaltyKind = 'earn' | 'redeem' | 'restore' | 'reverse' | 'expire';
+
+export interface LoyaltyEntry {
+  id: number;
+  created_at: string;
+  customer_id: number;
+  kind: LoyaltyKind;
+  points: number;
+  remaining: number;
+  expires_at?: string;
+  order_id?: number;
+  order_payment_id?: number;
+  return_id?: number;
+  user_id?: number;
+  notes: string;
+}
+
+export interface LoyaltyAccount {
+  customer_id: number;
+  balance: number;
+  balance_value: number;
+  next_expiry?: string;
+  entries: LoyaltyEntry[];
+}
+
 // Order and Sales Types
+export type OrderStatus = 'draft' | 'pending' | 'paid' | 'completed' | 'partially_refunded' | 'refunded' | 'voided';
+
 export interface Order {
   id: number;
   customer_name?: string;
+  customer_id?: number;
   payment_method: string;
   cashier_id: number;
+  subtotal?: number;
+  tax_total?: number;
+  discount_total?: number;
   total_amount: number;
-  status: string;
+  /** ISO 4217 code, e.g. "USD"; amounts are in major units rounded to the cent */
+  currency?: string;
+  status: OrderStatus;
   order_date: string;
   last_synced_at?: string;
   created_at: string;
@@ -127,6 +422,10 @@ export interface OrderItem {
   product_id: number;
   quantity: number;
   price_at_sale: number;
+  discount_amount?: number;
+  net_amount?: number;
+  tax_amount?: number;
+  line_total?: number;
   created_at: string;
 }
 
@@ -134,24 +433,334 @@ export interface OrderItemRequest {
   product_id: number;
   quantity: number;
   price_at_sale: number;
+  // Required, with a manager's approval, when price_at_sale is not the shelf price
+  override_reason?: string;
 }
 
 export interface CreateOrderRequest {
   customer_name?: string;
+  customer_id?: number;
   payment_method: string;
   items: OrderItemRequest[];
+  coupon_codes?: string[];
+  payments?: OrderPaymentRequest[];
+  gift_cards?: GiftCardSaleRequest[];
+}
+
+// Gift Card Types
+export type GiftCardKind = 'gift_card' | 'store_credit';
+export type GiftCardStatus = 'pending' | 'active' | 'void';
+
+export interface GiftCard {
+  id: number;
+  created_at: string;
+  code: string;
+  kind: GiftCardKind;
+  status: GiftCardStatus;
+  customer_id?: number;
+  initial_amount: number;
+  expires_on?: string;
+  issued_order_id?: number;
+  issued_return_id?: number;
+  balance: number;
+}
+
+export type GiftCardEntryKind = 'issue' | 'redeem' | 'refund';
+
+export interface GiftCardEntry {
+  id: number;
+  created_at: string;
+  gift_card_id: number;
+  kind: GiftCardEntryKind;
+  amount: number;
+  order_id?: number;
+  order_payment_id?: number;
+  return_id?: number;
+  user_id?: number;
+  notes: string;
+}
+
+export interface GiftCardDetails {
+  card: GiftCard;
+  entries: GiftCardEntry[];
+}
+
+export interface GiftCardSaleRequest {
+  amount: number;
+  code?: string;
+  expires_on?: string;
+}
+
+export interface OrderEvent {
+  id: number;
+  created_at: string;
+  order_id: number;
+  from_status?: OrderStatus;
+  to_status: OrderStatus;
+  user_id?: number;
+  user_name: string;
+  notes: string;
+}
+
+export type TenderType = 'cash' | 'credit_card' | 'debit_card' | 'mobile_payment' | 'loyalty_points' | 'gift_card' | 'store_credit' | 'other';
+
+export interface OrderPayment {
+  id: number;
+  created_at: string;
+  order_id: number;
+  tender_type: TenderType;
+  amount: number;
+  tendered: number;
+  change_due: number;
+  reference: string;
+  shift_id?: number;
+}
+
+export interface OrderPaymentRequest {
+  tender_type: TenderType;
+  amount: number;
+  reference?: string;
+}
+
+export interface OrderPaymentResult {
+  payment_id: number;
+  amount_paid: number;
+  balance_due: number;
+  change_due: number;
+}
+
+export type ReturnReason = 'damaged' | 'defective' | 'wrong_item' | 'changed_mind' | 'expired' | 'other';
+export type ReturnDisposition = 'restock' | 'write_off';
+
+export interface OrderReturn {
+  id: number;
+  created_at: string;
+  order_id: number;
+  user_id: number;
+  user_name: string;
+  reason: ReturnReason;
+  notes: string;
+  refund_total: number;
+  tax_total: number;
+}
+
+export interface OrderReturnItem {
+  id: number;
+  return_id: number;
+  order_item_id: number;
+  product_id: number;
+  product_name: string;
+  quantity: number;
+  disposition: ReturnDisposition;
+  refund_amount: number;
+  tax_amount: number;
+}
+
+export interface OrderRefund {
+  id: number;
+  return_id: number;
+  order_payment_id?: number;
+  tender_type: TenderType;
+  amount: number;
+  shift_id?: number;
+}
+
+export interface ReturnDetails {
+  order_return: OrderReturn;
+  items: OrderReturnItem[];
+  refunds: OrderRefund[];
+  store_credit?: GiftCard;
+}
+
+export interface CreateReturnRequest {
+  order_id: number;
+  reason: ReturnReason;
+  notes?: string;
+  items: {
+    order_item_id: number;
+    quantity: number;
+    disposition: ReturnDisposition;
+  }[];
+  store_credit?: boolean;
+}
+
+export type CashDrawerReason = 'sale' | 'no_sale' | 'payout';
+
+export interface PriceOverrideApproval {
+  approval_token: string;
+  product_id: number;
+  price: number;
+  approver_name: string;
+  expires_at: string;
+}
+
+export interface PriceOverride {
+  id: number;
+  created_at: string;
+  order_id: number;
+  order_item_id: number;
+  product_id: number;
+  quantity: number;
+  shelf_price: number;
+  override_price: number;
+  reason: string;
+  cashier_id: number;
+  cashier_name: string;
+  approved_by: number;
+  approver_name: string;
+}
+
+// Parked Cart Types
+export interface ParkedCart {
+  id: number;
+  created_at: string;
+  label: string;
+  customer_name: string;
+  payment_method: string;
+  user_id: number;
+  user_name: string;
+  reserved_until?: string;
+  item_count: number;
+  subtotal: number;
+}
+
+export interface ParkedCartItem {
+  id: number;
+  cart_id: number;
+  product_id: number;
+  product_name: string;
+  sku: string;
+  quantity: number;
+  price_at_sale: number;
+}
+
+export interface ParkedCartDetails {
+  cart: ParkedCart;
+  items: ParkedCartItem[];
+}
+
+export interface ParkCartRequest {
+  label: string;
+  customer_name?: string;
+  payment_method?: string;
+  items: {
+    product_id: number;
+    quantity: number;
+    price_at_sale: number;
+  }[];
+  reserve_stock?: boolean;
+}
+
+export interface ParkedCartSettings {
+  reservation_minutes: number;
+}
+
+export interface CurrencySettings {
+  code: string;
+}
+
+// Shift Types
+export type ShiftStatus = 'open' | 'closed';
+export type CashMovementKind = 'payout' | 'drop';
+export type ShiftReportType = 'x' | 'z';
+
+export interface Shift {
+  id: number;
+  opened_at: string;
+  closed_at?: string;
+  user_id: number;
+  user_name: string;
+  opening_float: number;
+  status: ShiftStatus;
+  closed_by_user_id?: number;
+  notes: string;
+}
+
+export interface ShiftCashMovement {
+  id: number;
+  created_at: string;
+  shift_id: number;
+  user_id: number;
+  user_name: string;
+  kind: CashMovementKind;
+  amount: number;
+  notes: string;
+}
+
+export interface ShiftTenderLine {
+  tender_type: TenderType;
+  payments: number;
+  refunds: number;
+  expected: number;
+  counted?: number;
+  variance?: number;
+}
+
+export interface ShiftReport {
+  report_type: ShiftReportType;
+  shift_id: number;
+  opened_at: string;
+  closed_at?: string;
+  opened_by: string;
+  opening_float: number;
+  orders: number;
+  gross_sales: number;
+  refunds: number;
+  payouts: number;
+  drops: number;
+  tenders: ShiftTenderLine[];
+}
+
+export interface ShiftReportRecord {
+  id: number;
+  created_at: string;
+  shift_id: number;
+  user_id: number;
+  user_name: string;
+  report: ShiftReport;
+}
+
+export interface TenderCount {
+  tender_type: TenderType;
+  counted: number;
+}
+
+export interface CloseShiftRequest {
+  counts: TenderCount[];
+  notes?: string;
 }
 
 // Inventory Movement Types
+export type StockMovementKind = 'sale' | 'return' | 'receipt' | 'adjustment' | 'transfer' | 'write_off';
+
+export type StockDocument = 'order' | 'return' | 'purchase_order' | 'stocktake' | 'batch';
+
+// An entry in the stock ledger; negative quantities take stock out
 export interface InventoryMovement {
   id: number;
   product_id: number;
-  quantity_change: number;
-  movement_type: string;
-  notes?: string;
-  timestamp: string;
+  quantity: number;
+  movement_type: StockMovementKind;
+  notes: string;
+  user_id?: number;
+  user_name: string;
+  // The product's quantity once this movement was made
+  balance_after: number;
+  document_type?: StockDocument;
+  document_id?: number;
   last_synced_at?: string;
   created_at: string;
+  updated_at: string;
+}
+
+// A product whose quantity does not agree with its ledger
+export interface LedgerDiscrepancy {
+  product_id: number;
+  product_name: string;
+  sku: string;
+  quantity: number;
+  ledger_quantity: number;
+  last_balance?: number;
 }
 
 // Role and Permission Types
@@ -311,3 +920,27 @@ export interface RouteConfig {
   permission?: string;
   layout?: React.ComponentType<{ children: React.ReactNode }>;
 }
+
+// Error returned by Tauri commands; switch on `code`, `message` is a fallback
+export type AppErrorCode =
+  | 'UNAUTHORIZED'
+  | 'FORBIDDEN'
+  | 'NOT_FOUND'
+  | 'CONFLICT'
+  | 'VALIDATION'
+  | 'INSUFFICIENT_STOCK'
+  | 'INVALID_TRANSITION'
+  | 'DATABASE'
+  | 'NETWORK'
+  | 'DEVICE'
+  | 'INTERNAL';
+
+export interface AppError {
+  code: AppErrorCode;
+  message: string;
+  permission?: string | null;
+  fields?: { field: string; message: string }[];
+  lines?: { product_id: number; product_name: string; requested: number; available: number }[];
+  from?: OrderStatus;
+  to?: OrderStatus;
+}
@@ -25,6 +25,7 @@ export const secureInvoke = async <T>(
   } catch (error: any) {
     // Check for authentication-related errors
     if (
+      error?.code === 'UNAUTHORIZED' ||
       error.message?.includes('Invalid token') ||
       error.message?.includes('Token expired') ||
       error.message?.includes('Authentication failed') ||
@@ -64,6 +65,7 @@ export const publicInvoke = async <T>(
 export const isAuthError = (error: any): boolean => {
   const errorMessage = error?.message?.toLowerCase() || '';
   return (
+    error?.code === 'UNAUTHORIZED' ||
     errorMessage.includes('invalid token') ||
     errorMessage.includes('token expired') ||
     errorMessage.includes('authentication failed') ||